@@ -0,0 +1,36 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Compare `Scanner` and `Pretokenizer::count()` with the `Pretokenizer`
+//! iterator on a large C-like input.
+//!
+//! Run with `cargo bench --bench scanner`.
+
+use pretok::{Pretokenizer, Scanner};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// The best time of a few runs of `f`.
+fn best<F: FnMut() -> usize>(mut f: F) -> (Duration, usize) {
+    let mut best = Duration::MAX;
+    let mut n = 0;
+    for _ in 0..5 {
+        let start = Instant::now();
+        n = f();
+        best = best.min(start.elapsed());
+    }
+    (best, n)
+}
+
+fn main() {
+    let unit = "int main(int argc, char **argv) {\n    /* count the\n       arguments */\n    \
+                printf(\"%d args\\n\", argc); // done\n    return 0;\n}\n\n";
+    let src = unit.repeat(200_000);
+    let mb = src.len() as f64 / 1e6;
+
+    let (t, n) = best(|| Pretokenizer::new(&src).inspect(|t| { black_box(t.s); }).count());
+    println!("Pretokenizer: {} pretokens, {:.0} MB/s", n, mb / t.as_secs_f64());
+    let (t, n) = best(|| Pretokenizer::new(&src).count());
+    println!("count():      {} pretokens, {:.0} MB/s", n, mb / t.as_secs_f64());
+    let (t, n) = best(|| Scanner::new(&src).inspect(|&span| { black_box(span); }).count());
+    println!("Scanner:      {} spans,     {:.0} MB/s", n, mb / t.as_secs_f64());
+}
@@ -0,0 +1,10 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+// The pretokens must match the reference pretokenizer's and keep their
+// invariants.  Built with `panic = "abort"`, so a mismatch is a crash.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(s) = std::str::from_utf8(data) {
+        pretok::verify(s);
+    }
+});
@@ -0,0 +1,101 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use pretok::{Bom, Config, Gaps, Pretokenizer, RawPretokenizer, Shebang};
+
+// Configuration values chosen to collide with the input.
+const MARKERS: [&str; 6] = ["", "#", "#if 0", "#endif", "//", "\n"];
+
+/// Build a configuration from the first bytes of `data`, returning it with
+/// the rest of `data`.
+fn config(data: &[u8]) -> Option<(Config, &[u8])> {
+    let (head, rest) = data.split_at(data.len().min(6));
+    if head.len() < 6 {
+        return None;
+    }
+    let flag = |bit: u32| u32::from_le_bytes([head[0], head[1], head[2], head[3]]) & (1 << bit) != 0;
+    let marker = |n: u8| MARKERS[n as usize % MARKERS.len()];
+    let mut cfg = Config::new()
+        .trigraphs(flag(0))
+        .directives(flag(1))
+        .nested_comments(flag(2))
+        .detect_bidi(flag(3))
+        .detect_non_ascii(flag(4))
+        .detect_control_chars(flag(5))
+        .lossless(flag(6))
+        .lazy_lines(flag(7))
+        .offsets_only(flag(8))
+        .shebang([Shebang::Text, Shebang::Skip, Shebang::Token][head[4] as usize % 3])
+        .bom([Bom::Skip, Bom::Text, Bom::Token][head[5] as usize % 3]);
+    if flag(9) {
+        cfg = cfg.skip_region(marker(head[4] >> 2), marker(head[5]));
+    }
+    if flag(10) {
+        cfg = cfg.pragma(marker(head[5] >> 3));
+    }
+    if flag(11) {
+        cfg = cfg.tool_directives(marker(head[5]), marker(head[4] >> 2));
+    }
+    if flag(12) {
+        cfg = cfg.max_token_len(head[5] as usize % 8);
+    }
+    if flag(13) {
+        cfg = cfg.max_line_len(head[5] as usize % 8);
+    }
+    if flag(14) {
+        cfg = cfg.max_tokens(head[4] as usize % 8);
+    }
+    if flag(15) {
+        cfg = cfg.max_bytes(head[5] as usize);
+    }
+    if flag(16) {
+        cfg = cfg.max_comment_depth(head[4] as usize % 3);
+    }
+    Some((cfg, rest))
+}
+
+// Every entry point must return normally on any input and configuration.
+// Built with `panic = "abort"`, so a panic anywhere is reported as a crash.
+fuzz_target!(|data: &[u8]| {
+    let (cfg, rest) = match config(data) {
+        Some(split) => split,
+        None => return,
+    };
+    let s = String::from_utf8_lossy(rest);
+    let s = s.as_ref();
+    let end = s.len() + 1;
+
+    let mut pt = Pretokenizer::with_config(s, cfg.clone());
+    for (i, &b) in rest.iter().enumerate() {
+        let at = (b as usize * 7 + i) % end;
+        match b % 8 {
+            0 => {
+                pt.peek();
+            }
+            1 => {
+                let _ = pt.seek(at);
+            }
+            2 => {
+                pt.skip_tokens(b as usize % 4);
+            }
+            3 => {
+                pt.line_at(at);
+            }
+            4 => {
+                let _ = pt.skip_to_line(b as usize % 8);
+            }
+            _ => {
+                pt.next();
+            }
+        }
+    }
+    while pt.next().is_some() {}
+    let _ = (pt.stats(), pt.remainder(), pt.line());
+
+    RawPretokenizer::with_config(s, cfg.clone()).for_each(drop);
+    Gaps::with_config(s, cfg.clone()).for_each(drop);
+    Pretokenizer::with_config(s, cfg.clone()).strict().for_each(drop);
+    Pretokenizer::before_with_config(s, rest.len() % end, cfg.clone()).for_each(drop);
+    pretok::fingerprint_with(s, cfg);
+    <pretok::FixedPretokenizer>::new(s).for_each(drop);
+});
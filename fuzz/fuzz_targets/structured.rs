@@ -0,0 +1,22 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use pretok::{Config, FuzzSource, Pretokenizer};
+
+// Inputs built from words, strings and comments must give the pretokens
+// they describe, each at its place in the input.
+fuzz_target!(|src: FuzzSource| {
+    let text = src.text();
+    let want = src.expected();
+
+    let mut got = Vec::new();
+    for tok in Pretokenizer::with_config(&text, Config::new().lossless(true)) {
+        assert_eq!(&text[tok.offset..tok.offset + tok.s.len()], tok.s);
+        got.push((tok.kind, tok.s.to_string()));
+    }
+    assert_eq!(got, want);
+
+    let got: Vec<_> = Pretokenizer::new(&text).map(|t| (t.kind, t.s.to_string())).collect();
+    let want: Vec<_> = want.into_iter().filter(|(kind, _)| !kind.is_trivia()).collect();
+    assert_eq!(got, want);
+});
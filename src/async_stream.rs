@@ -0,0 +1,135 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Pretokenizing input read from an `AsyncRead`.
+
+use crate::stream::Chunks;
+use crate::{Config, OwnedPretoken};
+use futures_core::Stream;
+use futures_io::AsyncRead;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Like [StreamingPretokenizer](struct.StreamingPretokenizer.html), reading
+/// from an `AsyncRead` and yielding pretokens as a `Stream`.  Pretokens are
+/// yielded as soon as they are complete, without waiting for the rest of the
+/// input.
+/// ```
+/// use futures::executor::block_on;
+/// use futures::stream::TryStreamExt;
+/// use pretok::AsyncPretokenizer;
+/// let input = futures::io::Cursor::new("a \"b c\"\nd");
+/// let toks: Vec<_> = block_on(AsyncPretokenizer::new(input).try_collect())?;
+/// let toks: Vec<_> = toks.iter().map(|t| t.s.as_str()).collect();
+/// assert_eq!(toks, vec!["a", "\"b c\"", "d"]);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct AsyncPretokenizer<R> {
+    reader: R,
+    chunks: Chunks,
+    /// Bytes from the last read
+    scratch: Vec<u8>,
+    /// An error to return once the pretokens before it are returned
+    error: Option<io::Error>,
+    /// True once every pretoken is returned, or after an error
+    done: bool,
+}
+
+impl<R: AsyncRead + Unpin> AsyncPretokenizer<R> {
+    /// Create an async pretokenizer reading from `reader`.
+    pub fn new(reader: R) -> AsyncPretokenizer<R> {
+        AsyncPretokenizer::with_config(reader, Config::default())
+    }
+
+    /// Create an async pretokenizer reading from `reader`, with `cfg`.
+    pub fn with_config(reader: R, cfg: Config) -> AsyncPretokenizer<R> {
+        AsyncPretokenizer{
+            reader,
+            chunks: Chunks::new(cfg),
+            scratch: Vec::new(),
+            error: None,
+            done: false,
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for AsyncPretokenizer<R> {
+    type Item = io::Result<OwnedPretoken>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(tok) = this.chunks.ready.pop_front() {
+                return Poll::Ready(Some(Ok(tok)));
+            }
+            if let Some(e) = this.error.take() {
+                this.done = true;
+                return Poll::Ready(Some(Err(e)));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+            this.scratch.resize(this.chunks.want(), 0);
+            let read = match Pin::new(&mut this.reader).poll_read(cx, &mut this.scratch) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Poll::Ready(Err(e)) => Err(e),
+                Poll::Ready(Ok(0)) => this.chunks.end(),
+                Poll::Ready(Ok(n)) => this.chunks.push_bytes(&this.scratch[..n]),
+            };
+            if let Err(e) = read {
+                this.error = Some(e);
+            }
+            this.chunks.pretokenize();
+            this.done = this.chunks.eof;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::stream::StreamExt;
+
+    /// Reads `step` bytes at a time, returning `Pending` before each read.
+    struct Trickle<'b> {
+        data: &'b [u8],
+        step: usize,
+        ready: bool,
+    }
+
+    impl<'b> AsyncRead for Trickle<'b> {
+        fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8])
+            -> Poll<io::Result<usize>> {
+            if !std::mem::replace(&mut self.ready, false) {
+                self.ready = true;
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            let n = self.step.min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Poll::Ready(Ok(n))
+        }
+    }
+
+    #[test]
+    fn async_stream_test_0() {
+        let src = "#!/bin/x\n#define A \"a\\\n b\"\n  x/**/y // z\n\"\u{e9}\" /* \n*/ w";
+        let cfg = Config::new().lossless(true).directives(true);
+        let want = crate::tokenize_with(src, cfg.clone());
+        for step in 1..6 {
+            let reader = Trickle{ data: src.as_bytes(), step, ready: false };
+            let got: Vec<_> = block_on(AsyncPretokenizer::with_config(reader, cfg.clone())
+                .map(Result::unwrap).collect());
+            assert_eq!(got, want, "by {}", step);
+        }
+
+        let mut bad = AsyncPretokenizer::new(futures::io::Cursor::new(b"ok\nok \xff ok"));
+        assert_eq!(block_on(bad.next()).unwrap().unwrap().s, "ok");
+        assert_eq!(block_on(bad.next()).unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(block_on(bad.next()).is_none());
+    }
+}
@@ -0,0 +1,110 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Checking that strings, comments and brackets are closed.
+
+use crate::{Config, Kind, PretokError, Pretokenizer};
+
+/// Check that every string, block comment, region and bracket of `s` is
+/// closed, returning a diagnostic for each that isn't, in input order.  Each
+/// names what is open and where it opened: an
+/// [UnterminatedString](enum.PretokError.html#variant.UnterminatedString),
+/// [UnterminatedBlockComment](enum.PretokError.html#variant.UnterminatedBlockComment),
+/// [UnterminatedRegion](enum.PretokError.html#variant.UnterminatedRegion) or
+/// [UnclosedBracket](enum.PretokError.html#variant.UnclosedBracket), or an
+/// [UnmatchedBracket](enum.PretokError.html#variant.UnmatchedBracket) for a
+/// closing bracket with nothing to close.
+///
+/// Brackets are `()`, `[]` and `{}`, and those in strings and comments are
+/// ignored.  A closing bracket closes the innermost open bracket of its
+/// kind, leaving those opened since unclosed.
+/// ```
+/// use pretok::PretokError;
+/// let errs = pretok::check_balance("f(a[1);\n}");
+/// let v: Vec<_> = errs.iter().map(|e| e.to_string()).collect();
+/// assert_eq!(v, vec!["unclosed '[' opened at line 1", "unmatched '}' at line 2"]);
+/// assert!(pretok::check_balance("f(\")\") /* ( */").is_empty());
+/// ```
+pub fn check_balance(s: &str) -> Vec<PretokError> {
+    check_balance_with(s, Config::default())
+}
+
+/// Like [check_balance()](fn.check_balance.html), with `cfg`.  Only the
+/// diagnostics listed there are returned, whatever checks `cfg` enables.
+pub fn check_balance_with(s: &str, cfg: Config) -> Vec<PretokError> {
+    let (open, mut errs, pt) = brackets(s, cfg);
+    for (ch, at, line) in open {
+        errs.push(PretokError::UnclosedBracket{ ch, line, span: at..at + 1 });
+    }
+    errs.extend(pt.diagnostics().iter().filter(|e| matches!(e,
+        PretokError::UnterminatedString { .. }
+        | PretokError::UnterminatedBlockComment { .. }
+        | PretokError::UnterminatedRegion { .. })).cloned());
+    errs.sort_by_key(|e| e.span().start);
+    errs
+}
+
+/// Pretokenize all of `s`, returning the kind, offset and line of each
+/// bracket still open at the end, diagnostics for the brackets closed out of
+/// order, and the pretokenizer.
+pub(crate) fn brackets(s: &str, cfg: Config)
+    -> (Vec<(char, usize, usize)>, Vec<PretokError>, Pretokenizer<'_>) {
+    let mut errs = Vec::new();
+    // The kind, offset and line of each open bracket.
+    let mut open: Vec<(char, usize, usize)> = Vec::new();
+    let (mut pos, mut line) = (0, cfg.first_line());
+    let mut pt = Pretokenizer::with_config(s, cfg);
+    for tok in pt.by_ref() {
+        if tok.kind != Kind::Word {
+            continue;
+        }
+        for (i, c) in tok.s.char_indices() {
+            let opener = match c {
+                '(' | '[' | '{' => None,
+                ')' => Some('('),
+                ']' => Some('['),
+                '}' => Some('{'),
+                _ => continue,
+            };
+            let at = tok.offset + i;
+            line += memchr::memchr_iter(b'\n', &s.as_bytes()[pos..at]).count();
+            pos = at;
+            match opener {
+                None => open.push((c, at, line)),
+                Some(opener) => match open.iter().rposition(|&(o, _, _)| o == opener) {
+                    Some(k) => {
+                        for (ch, at, line) in open.drain(k..).skip(1) {
+                            errs.push(PretokError::UnclosedBracket{ ch, line, span: at..at + 1 });
+                        }
+                    }
+                    None => errs.push(PretokError::UnmatchedBracket{ ch: c, line,
+                                                                   span: at..at + 1 }),
+                },
+            }
+        }
+    }
+    (open, errs, pt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn balance_test_1() {
+        let src = "{ ( [ ) ]\n} ) \"]\" x[\n/* {";
+        let errs = check_balance(src);
+        assert_eq!(errs, vec![
+            PretokError::UnclosedBracket{ ch: '[', line: 1, span: 4..5 },
+            PretokError::UnmatchedBracket{ ch: ']', line: 1, span: 8..9 },
+            PretokError::UnmatchedBracket{ ch: ')', line: 2, span: 12..13 },
+            PretokError::UnclosedBracket{ ch: '[', line: 2, span: 19..20 },
+            PretokError::UnterminatedBlockComment{ line: 3, span: 21..25 },
+        ]);
+        assert_eq!(errs[4].to_string(), "unterminated block comment starting at line 3");
+        let cfg = Config::new().max_line_len(1).detect_bidi(true);
+        assert_eq!(check_balance_with("\"\u{202e}", cfg), vec![
+            PretokError::UnterminatedString{ line: 1, span: 0..4 },
+        ]);
+        assert!(check_balance("").is_empty());
+    }
+}
@@ -0,0 +1,866 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Command line tool for seeing how pretok splits input.
+
+use pretok::{Config, Kind, LineIndex, Pretokenizer, TreeWalk};
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+const USAGE: &str = "\
+usage: pretok [options] [FILE...]
+       pretok strip [--blank] [options] [FILE...]
+       pretok stats [options] [FILE...]
+       pretok check [options] [FILE...]
+       pretok annotate [options] [FILE...]
+       pretok trace [options] [FILE...]
+
+Print each pretoken of each FILE, or of standard input if FILE is - or
+missing, as line:column, byte offset, kind and text.  A FILE that is a
+directory stands for the files under it, and one containing * or ? for the
+files matching it, where ** matches any number of directories.  Files are
+processed in parallel and printed in path order, each under a ==> FILE <==
+header when there are several.
+
+commands:
+  strip              print the input without its comments, leaving all else
+  stats              print counts of lines, pretokens and comments per file
+  check              report unterminated strings, comments and regions,
+                     bidirectional and other control characters as
+                     FILE:LINE:COLUMN, exiting with status 1 if any are found
+  annotate           print each line of the input with its pretokens
+                     underlined and their kinds below
+  trace              print each pretoken after the scanner state changes
+                     made while scanning it, as line:column, byte offset
+                     and FROM -> TO
+
+options:
+  --format FORMAT    text, the default, jsonl for one JSON object per line,
+                     or csv for comma separated values under a header
+  --ext EXT          only take files ending in .EXT from directories; may be
+                     given more than once
+  --jobs N           use N threads, or one per CPU if 0, the default
+  --blank            with strip, replace comments with spaces, keeping line
+                     breaks, so lines and columns don't move
+  --lossless         include whitespace and comments
+  --directives       return # lines as preprocessor directives
+  --trigraphs        recognize C trigraphs
+  --nested-comments  allow block comments to nest
+  -h, --help         print this help
+";
+
+/// How pretokens are printed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    /// Tab separated fields
+    Text,
+    /// A JSON object per line
+    JsonLines,
+    /// Comma separated values, under a header
+    Csv,
+}
+
+/// What to do with the input.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Command {
+    /// Print each pretoken
+    Tokens,
+    /// Print the input without comments, or with them blanked
+    Strip { blank: bool },
+    /// Print counts for each file
+    Stats,
+    /// Print diagnostics for each file
+    Check,
+    /// Print the input with pretokens underlined
+    Annotate,
+    /// Print the scanner state changes with the pretokens
+    Trace,
+}
+
+/// What the command line asks for.
+#[derive(Debug)]
+struct Args {
+    command: Command,
+    cfg: Config,
+    format: Format,
+    /// The input files, directories and globs, with - for standard input
+    paths: Vec<String>,
+    /// The extensions of files wanted from directories
+    exts: Vec<String>,
+    /// The number of threads, or 0 for one per CPU
+    jobs: usize,
+}
+
+/// Parse the arguments after the program name.  Returns None to print the
+/// usage.
+fn parse_args(args: &[String]) -> Result<Option<Args>, String> {
+    let mut args = args.iter().peekable();
+    let mut command = match args.peek().map(|a| &a[..]) {
+        Some("strip") => Command::Strip{ blank: false },
+        Some("stats") => Command::Stats,
+        Some("check") => Command::Check,
+        Some("annotate") => Command::Annotate,
+        Some("trace") => Command::Trace,
+        _ => Command::Tokens,
+    };
+    if command != Command::Tokens {
+        args.next();
+    }
+    let mut cfg = Config::new();
+    let mut format = Format::Text;
+    let mut paths = Vec::new();
+    let mut exts = Vec::new();
+    let mut jobs = 0;
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "-h" | "--help" => return Ok(None),
+            "--blank" => match &mut command {
+                Command::Strip{ blank } => *blank = true,
+                _ => return Err("--blank is only for strip".to_string()),
+            },
+            "--format" => {
+                format = match args.next().map(|f| &f[..]) {
+                    Some("text") => Format::Text,
+                    Some("jsonl") => Format::JsonLines,
+                    Some("csv") => Format::Csv,
+                    Some(f) => return Err(format!("unknown format {}", f)),
+                    None => return Err("--format needs a value".to_string()),
+                }
+            }
+            "--ext" => match args.next() {
+                Some(ext) => exts.push(ext.trim_start_matches('.').to_string()),
+                None => return Err("--ext needs a value".to_string()),
+            },
+            "--jobs" => {
+                jobs = match args.next().map(|n| n.parse()) {
+                    Some(Ok(n)) => n,
+                    Some(Err(_)) => return Err("--jobs needs a number".to_string()),
+                    None => return Err("--jobs needs a value".to_string()),
+                }
+            }
+            "--lossless" => cfg = cfg.lossless(true),
+            "--directives" => cfg = cfg.directives(true),
+            "--trigraphs" => cfg = cfg.trigraphs(true),
+            "--nested-comments" => cfg = cfg.nested_comments(true),
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("unknown option {}", arg));
+            }
+            _ => paths.push(arg.clone()),
+        }
+    }
+    if paths.is_empty() {
+        paths.push("-".to_string());
+    }
+    Ok(Some(Args{ command, cfg, format, paths, exts, jobs }))
+}
+
+/// The name of input `path` for messages.
+fn display_name(path: &str) -> &str {
+    match path {
+        "-" => "<stdin>",
+        path => path,
+    }
+}
+
+/// The files named by `paths`, in order, with directories and globs
+/// replaced by the files they match in path order.  Directories give only
+/// files with one of `exts`, if any.  A directory that can't be listed or a
+/// glob matching nothing gives an error.
+fn expand(paths: &[String], exts: &[String]) -> Vec<Result<String, String>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.contains(&['*', '?'][..]) {
+            let n = files.len();
+            // Walk from the directories before the first wildcard.
+            let wild = path.find(&['*', '?'][..]).unwrap();
+            let root = path[..wild].rfind('/').map_or("", |i| &path[..=i]);
+            for found in TreeWalk::new(if root.is_empty() { "." } else { root }).paths() {
+                match found {
+                    Ok(found) => {
+                        let found = found.to_string_lossy();
+                        let found = if root.is_empty() {
+                            found.trim_start_matches("./")
+                        } else {
+                            &found
+                        };
+                        if glob_match(path, found) {
+                            files.push(Ok(found.to_string()));
+                        }
+                    }
+                    Err(e) => files.push(Err(e.to_string())),
+                }
+            }
+            if files.len() == n {
+                files.push(Err(format!("{}: no files match", path)));
+            }
+        } else if std::path::Path::new(path).is_dir() {
+            let walk = exts.iter().fold(TreeWalk::new(path), |walk, ext| walk.extension(ext));
+            files.extend(walk.paths().into_iter()
+                .map(|found| found.map(|p| p.to_string_lossy().into_owned())
+                     .map_err(|e| e.to_string())));
+        } else {
+            files.push(Ok(path.clone()));
+        }
+    }
+    files
+}
+
+/// True if `path` matches `glob`, where `*` matches any characters but `/`,
+/// `?` matches one, and `**/` matches any number of directories.
+fn glob_match(glob: &str, path: &str) -> bool {
+    if let Some(rest) = glob.strip_prefix("**/") {
+        return glob_match(rest, path) || path.match_indices('/')
+            .any(|(i, _)| glob_match(rest, &path[i + 1..]));
+    }
+    if glob == "**" {
+        return true;
+    }
+    let mut chars = glob.chars();
+    match chars.next() {
+        None => path.is_empty(),
+        Some('*') => {
+            let rest = chars.as_str();
+            path.char_indices().map(|(i, _)| i).chain(Some(path.len()))
+                .take_while(|&i| !path[..i].contains('/'))
+                .any(|i| glob_match(rest, &path[i..]))
+        }
+        Some(g) => {
+            let mut path_chars = path.chars();
+            match path_chars.next() {
+                Some(c) if c == g || (g == '?' && c != '/') => {
+                    glob_match(chars.as_str(), path_chars.as_str())
+                }
+                _ => false,
+            }
+        }
+    }
+}
+
+/// Read all of the input named by `path`, or `stdin` for -.
+fn read_input(path: &str, stdin: &mut dyn Read) -> Result<String, String> {
+    let name = display_name(path);
+    let mut bytes = Vec::new();
+    let read = match path {
+        "-" => stdin.read_to_end(&mut bytes),
+        path => std::fs::File::open(path).and_then(|mut f| f.read_to_end(&mut bytes)),
+    };
+    read.map_err(|e| format!("{}: {}", name, e))?;
+    String::from_utf8(bytes).map_err(|e| {
+        let e = pretok::validate_utf8(e.as_bytes()).unwrap_err();
+        format!("{}: {}", name, e)
+    })
+}
+
+/// Print each pretoken of `src` on its own line, naming file `name` in each
+/// JSON object or CSV record if given.
+fn dump(src: &str, name: Option<&str>, cfg: Config, format: Format, out: &mut dyn Write)
+    -> io::Result<()> {
+    let file = match (name, format) {
+        (Some(name), Format::JsonLines) => format!("\"file\":{},", json_string(name)),
+        (Some(name), Format::Csv) => format!("{},", csv_field(name)),
+        _ => String::new(),
+    };
+    let lines = LineIndex::new(src);
+    for tok in Pretokenizer::with_config(src, cfg) {
+        let (line, col) = lines.line_col(tok.offset);
+        match format {
+            Format::Text => writeln!(out, "{}:{}\t{}\t{:?}\t{:?}",
+                                     line, col, tok.offset, tok.kind, tok.s)?,
+            Format::JsonLines => writeln!(out,
+                "{{{}\"line\":{},\"col\":{},\"offset\":{},\"kind\":\"{:?}\",\"text\":{}}}",
+                file, line, col, tok.offset, tok.kind, json_string(tok.s))?,
+            Format::Csv => writeln!(out, "{}{},{},{},{},{:?},{}", file, tok.offset,
+                                    tok.offset + tok.s.len(), line, col, tok.kind,
+                                    csv_field(tok.s))?,
+        }
+    }
+    Ok(())
+}
+
+/// Print `src` without its comments, or with each character of them but
+/// line breaks replaced by a space if `blank`.
+fn strip(src: &str, cfg: Config, blank: bool, out: &mut dyn Write) -> io::Result<()> {
+    for tok in Pretokenizer::with_config(src, cfg.lossless(true)) {
+        match tok.kind {
+            Kind::Comment if blank => {
+                let spaces: String = tok.s.chars()
+                    .map(|c| if c == '\n' || c == '\r' { c } else { ' ' })
+                    .collect();
+                out.write_all(spaces.as_bytes())?;
+            }
+            Kind::Comment => {}
+            _ => out.write_all(tok.s.as_bytes())?,
+        }
+    }
+    Ok(())
+}
+
+/// Print each pretoken of `src` as dump() does, after the scanner state
+/// changes made while scanning it.
+fn trace(src: &str, cfg: Config, out: &mut dyn Write) -> io::Result<()> {
+    let lines = LineIndex::new(src);
+    let mut pt = Pretokenizer::with_config(src, cfg.trace(true));
+    let mut shown = 0;
+    loop {
+        let tok = pt.next();
+        for step in &pt.trace()[shown..] {
+            let (line, col) = lines.line_col(step.offset);
+            writeln!(out, "{}:{}\t{}\t{} -> {}", line, col, step.offset, step.from, step.to)?;
+        }
+        shown = pt.trace().len();
+        match tok {
+            Some(tok) => {
+                let (line, col) = lines.line_col(tok.offset);
+                writeln!(out, "{}:{}\t{}\t{:?}\t{:?}", line, col, tok.offset, tok.kind, tok.s)?;
+            }
+            None => return Ok(()),
+        }
+    }
+}
+
+/// Print each line of `src` followed by a line underlining each pretoken on
+/// it, with `^` under its first character and `~` under the rest, and lines
+/// with the kind of each pretoken starting on it, under that first
+/// character.  Whitespace and line breaks are not underlined, and tabs are
+/// expanded to stops every 8 columns to keep the marks aligned.
+fn annotate(src: &str, cfg: Config, out: &mut dyn Write) -> io::Result<()> {
+    let toks: Vec<_> = Pretokenizer::with_config(src, cfg)
+        .filter(|t| !matches!(t.kind, Kind::Whitespace | Kind::Newline))
+        .collect();
+    let mut first = 0;
+    let mut start = 0;
+    for line in src.split_inclusive('\n') {
+        let end = start + line.len();
+        let text = line.trim_end_matches(&['\n', '\r'][..]);
+        // The display column of each byte offset in the line.
+        let mut cols = vec![0; text.len() + 1];
+        let mut shown = String::new();
+        for (i, c) in text.char_indices() {
+            let width = if c == '\t' { 8 - cols[i] % 8 } else { 1 };
+            if c == '\t' {
+                shown.push_str(&" ".repeat(width));
+            } else {
+                shown.push(c);
+            }
+            let next = cols[i] + width;
+            for col in &mut cols[i + 1..=i + c.len_utf8()] {
+                *col = next;
+            }
+        }
+        let col = |offset: usize| cols[offset - start];
+        let mut under = vec![' '; col(start + text.len())];
+        let mut labels = Vec::new();
+        while first < toks.len() && toks[first].offset + toks[first].s.len() <= start {
+            first += 1;
+        }
+        for tok in toks[first..].iter().take_while(|t| t.offset < end) {
+            let from = tok.offset.max(start);
+            let to = (tok.offset + tok.s.len()).min(start + text.len());
+            for mark in &mut under[col(from)..col(to)] {
+                *mark = '~';
+            }
+            if tok.offset >= start && from < to {
+                under[col(from)] = '^';
+            }
+            if tok.offset >= start {
+                labels.push((col(tok.offset), format!("{:?}", tok.kind)));
+            }
+        }
+        writeln!(out, "{}", shown)?;
+        if under.iter().any(|&c| c != ' ') {
+            let under: String = under.into_iter().collect();
+            writeln!(out, "{}", under.trim_end())?;
+        }
+        // Each label goes on the first row with room for it.
+        let mut rows: Vec<String> = Vec::new();
+        for (col, label) in labels {
+            let row = match rows.iter().position(|r| r.chars().count() < col) {
+                Some(i) => &mut rows[i],
+                None => {
+                    rows.push(String::new());
+                    rows.last_mut().unwrap()
+                }
+            };
+            let pad = col - row.chars().count().min(col);
+            row.push_str(&" ".repeat(pad));
+            row.push_str(&label);
+        }
+        for row in rows {
+            writeln!(out, "{}", row)?;
+        }
+        start = end;
+    }
+    Ok(())
+}
+
+/// Print the diagnostics for `src`, read from `name`, returning how many
+/// there were.
+fn check(src: &str, name: &str, cfg: Config, format: Format, out: &mut dyn Write)
+    -> io::Result<usize> {
+    let cfg = cfg.detect_bidi(true).detect_control_chars(true);
+    let mut pt = Pretokenizer::with_config(src, cfg);
+    pt.by_ref().count();
+    let lines = LineIndex::new(src);
+    let mut found: Vec<_> = pt.diagnostics().iter().collect();
+    found.sort_by_key(|e| e.span().start);
+    for e in &found {
+        let offset = e.span().start;
+        let (line, col) = lines.line_col(offset);
+        match format {
+            Format::Text => writeln!(out, "{}:{}:{}: {}", name, line, col, e)?,
+            Format::JsonLines => writeln!(out,
+                "{{\"file\":{},\"line\":{},\"col\":{},\"offset\":{},\"message\":{}}}",
+                json_string(name), line, col, offset, json_string(&e.to_string()))?,
+            Format::Csv => writeln!(out, "{},{},{},{},{}", csv_field(name), line, col, offset,
+                                    csv_field(&e.to_string()))?,
+        }
+    }
+    Ok(found.len())
+}
+
+/// Counts for the stats command.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct FileStats {
+    lines: usize,
+    blank_lines: usize,
+    tokens: usize,
+    comments: usize,
+    comment_bytes: usize,
+    /// Bytes in the longest pretoken
+    longest: usize,
+}
+
+impl FileStats {
+    /// Count the lines, pretokens and comments of `src`.
+    fn new(src: &str, cfg: Config) -> FileStats {
+        let mut stats = FileStats::default();
+        let mut pt = Pretokenizer::with_config(src, cfg.lossless(true));
+        for tok in pt.by_ref() {
+            match tok.kind {
+                Kind::Comment => {
+                    stats.comments += 1;
+                    stats.comment_bytes += tok.s.len();
+                }
+                kind if kind.is_trivia() => {}
+                _ => {
+                    stats.tokens += 1;
+                    stats.longest = stats.longest.max(tok.s.len());
+                }
+            }
+        }
+        stats.lines = pt.stats().lines;
+        stats.blank_lines = pt.stats().blank_lines;
+        stats
+    }
+
+    /// Add the counts of `other`.
+    fn add(&mut self, other: &FileStats) {
+        self.lines += other.lines;
+        self.blank_lines += other.blank_lines;
+        self.tokens += other.tokens;
+        self.comments += other.comments;
+        self.comment_bytes += other.comment_bytes;
+        self.longest = self.longest.max(other.longest);
+    }
+
+    /// Print the counts for the file named `name`.
+    fn print(&self, name: &str, format: Format, out: &mut dyn Write) -> io::Result<()> {
+        match format {
+            Format::Text => writeln!(out, "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                                     self.lines, self.blank_lines, self.tokens, self.comments,
+                                     self.comment_bytes, self.longest, name),
+            Format::JsonLines => writeln!(out,
+                "{{\"file\":{},\"lines\":{},\"blank_lines\":{},\"tokens\":{},\
+                 \"comments\":{},\"comment_bytes\":{},\"longest_token\":{}}}",
+                json_string(name), self.lines, self.blank_lines, self.tokens, self.comments,
+                self.comment_bytes, self.longest),
+            Format::Csv => writeln!(out, "{},{},{},{},{},{},{}", csv_field(name), self.lines,
+                                    self.blank_lines, self.tokens, self.comments,
+                                    self.comment_bytes, self.longest),
+        }
+    }
+}
+
+/// `s` as a JSON string literal.
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// What running the command on one input gave.
+#[derive(Debug, Default)]
+struct FileOutput {
+    out: Vec<u8>,
+    /// Why the input couldn't be read
+    error: Option<String>,
+    /// True if check found problems
+    found: bool,
+    stats: FileStats,
+}
+
+/// Run the command of `args` on `src`, read from `path` or found in error,
+/// printing a header first if `many`.
+fn run_file(args: &Args, path: &str, src: Result<String, String>, many: bool) -> FileOutput {
+    let mut file = FileOutput::default();
+    let src = match src {
+        Ok(src) => src,
+        Err(e) => {
+            file.error = Some(e);
+            return file;
+        }
+    };
+    let (name, cfg, out) = (display_name(path), args.cfg.clone(), &mut file.out);
+    // Stats and check name the file on each line, as does jsonl.
+    let header = many && match args.command {
+        Command::Tokens => args.format == Format::Text,
+        Command::Strip{..} | Command::Annotate | Command::Trace => true,
+        Command::Stats | Command::Check => false,
+    };
+    if header {
+        let _ = writeln!(out, "==> {} <==", name);
+    }
+    let result = match args.command {
+        Command::Tokens => dump(&src, Some(name).filter(|_| many), cfg, args.format, out),
+        Command::Strip{ blank } => strip(&src, cfg, blank, out),
+        Command::Stats => {
+            file.stats = FileStats::new(&src, cfg);
+            file.stats.print(name, args.format, out)
+        }
+        Command::Annotate => annotate(&src, cfg, out),
+        Command::Trace => trace(&src, cfg, out),
+        Command::Check => check(&src, name, cfg, args.format, out)
+            .map(|found| file.found = found > 0),
+    };
+    // Writing to a buffer can't fail.
+    result.expect("write to buffer");
+    file
+}
+
+/// Run the command of `args` on each of `files` on several threads, handing
+/// the results to `done` in order.  Standard input is read up front.
+fn run_files(args: &Args, files: Vec<Result<String, String>>, stdin: &mut dyn Read,
+             mut done: impl FnMut(FileOutput)) {
+    let stdin = if files.iter().any(|f| f.as_deref() == Ok("-")) {
+        Some(read_input("-", stdin))
+    } else {
+        None
+    };
+    let threads = match args.jobs {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    };
+    let many = files.len() > 1;
+    let next = AtomicUsize::new(0);
+    let (files, stdin, next) = (&files, &stdin, &next);
+    thread::scope(|scope| {
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..threads.min(files.len()) {
+            let tx = tx.clone();
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let output = match files.get(i) {
+                    Some(Ok(path)) => {
+                        let src = match (&path[..], stdin) {
+                            ("-", Some(src)) => src.clone(),
+                            _ => read_input(path, &mut io::empty()),
+                        };
+                        run_file(args, path, src, many)
+                    }
+                    Some(Err(e)) => run_file(args, "", Err(e.clone()), many),
+                    None => break,
+                };
+                if tx.send((i, output)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+        // Outputs that finished ahead of their turn
+        let mut pending = BTreeMap::new();
+        let mut turn = 0;
+        for (i, output) in rx {
+            pending.insert(i, output);
+            while let Some(output) = pending.remove(&turn) {
+                done(output);
+                turn += 1;
+            }
+        }
+    });
+}
+
+/// `s` as a CSV field, quoted if it holds a comma, quote or line break.
+fn csv_field(s: &str) -> String {
+    if s.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// The header line printed before the output of all files, if any.
+fn header(command: Command, format: Format, many: bool) -> Option<&'static str> {
+    match (command, format) {
+        (Command::Tokens, Format::Csv) if many => Some("file,offset,end,line,col,kind,text"),
+        (Command::Tokens, Format::Csv) => Some("offset,end,line,col,kind,text"),
+        (Command::Stats, Format::Text) => {
+            Some("lines\tblank\ttokens\tcomments\tcomment_bytes\tlongest\tfile")
+        }
+        (Command::Stats, Format::Csv) => {
+            Some("file,lines,blank_lines,tokens,comments,comment_bytes,longest_token")
+        }
+        (Command::Check, Format::Csv) => Some("file,line,col,offset,message"),
+        _ => None,
+    }
+}
+
+/// Run with the arguments after the program name, returning the exit
+/// status.
+fn run(args: &[String], stdin: &mut dyn Read, out: &mut dyn Write, err: &mut dyn Write)
+    -> i32 {
+    let args = match parse_args(args) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            let _ = out.write_all(USAGE.as_bytes());
+            return 0;
+        }
+        Err(e) => {
+            let _ = writeln!(err, "pretok: {}\n\n{}", e, USAGE);
+            return 2;
+        }
+    };
+    let mut status = 0;
+    let mut total = FileStats::default();
+    let files = expand(&args.paths, &args.exts);
+    let many = files.len() > 1;
+    if let Some(header) = header(args.command, args.format, many) {
+        let _ = writeln!(out, "{}", header);
+    }
+    run_files(&args, files, stdin, |file| {
+        let _ = out.write_all(&file.out);
+        if let Some(e) = file.error {
+            let _ = writeln!(err, "pretok: {}", e);
+            status = 1;
+        }
+        if file.found {
+            status = 1;
+        }
+        total.add(&file.stats);
+    });
+    if args.command == Command::Stats && many && args.format == Format::Text {
+        let _ = total.print("total", args.format, out);
+    }
+    status
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let stdout = io::stdout();
+    let status = run(&args, &mut io::stdin().lock(), &mut stdout.lock(), &mut io::stderr());
+    std::process::exit(status);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run with `args` and `input`, returning the status, output and errors.
+    fn run_with(args: &[&str], input: &[u8]) -> (i32, String, String) {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let status = run(&args, &mut &input[..], &mut out, &mut err);
+        (status, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    #[test]
+    fn pretok_test_1() {
+        let (status, out, _) = run_with(&[], "a \"b\tc\"\n  /* x */ \u{e9}d".as_bytes());
+        assert_eq!(status, 0);
+        assert_eq!(out, "1:1\t0\tWord\t\"a\"\n1:3\t2\tQuoted\t\"\\\"b\\tc\\\"\"\n\
+                         2:11\t18\tWord\t\"\u{e9}d\"\n");
+        let (_, out, _) = run_with(&["--lossless", "-"], b"a //b");
+        assert_eq!(out.lines().count(), 3);
+        let (_, out, _) = run_with(&["--directives"], b"#x\n");
+        assert!(out.contains("Directive"));
+
+        let (status, out, _) = run_with(&["--help"], b"");
+        assert_eq!((status, out.starts_with("usage:")), (0, true));
+        let (status, _, err) = run_with(&["--bogus"], b"");
+        assert_eq!(status, 2);
+        assert!(err.starts_with("pretok: unknown option --bogus"));
+        let (status, _, err) = run_with(&[], b"ok \xff");
+        assert_eq!(status, 1);
+        assert_eq!(err, "pretok: <stdin>: invalid UTF-8 at line 1, byte offset 3\n");
+        let (status, _, err) = run_with(&["/no/such/file"], b"");
+        assert_eq!(status, 1);
+        assert!(err.starts_with("pretok: /no/such/file: "));
+    }
+
+    #[test]
+    fn pretok_test_2() {
+        let (_, out, _) = run_with(&["--format", "jsonl"], b"x \"\\\x01\"\n");
+        assert_eq!(out, "{\"line\":1,\"col\":1,\"offset\":0,\"kind\":\"Word\",\"text\":\"x\"}\n\
+                         {\"line\":1,\"col\":3,\"offset\":2,\"kind\":\"Quoted\",\
+                         \"text\":\"\\\"\\\\\\u0001\\\"\"}\n");
+        let (status, _, err) = run_with(&["--format", "xml"], b"");
+        assert_eq!(status, 2);
+        assert!(err.starts_with("pretok: unknown format xml"));
+        assert!(run_with(&["--format"], b"").2.contains("needs a value"));
+        let (_, out, _) = run_with(&["--format", "text"], b"x");
+        assert_eq!(out, "1:1\t0\tWord\t\"x\"\n");
+    }
+
+    #[test]
+    fn pretok_test_3() {
+        let src = "a /* b\r\n \u{e9} */ \"//\" // c\nd/**/e\n";
+        let (status, out, _) = run_with(&["strip"], src.as_bytes());
+        assert_eq!(status, 0);
+        assert_eq!(out, "a  \"//\" \nde\n");
+        let (_, out, _) = run_with(&["strip", "--blank"], src.as_bytes());
+        assert_eq!(out, "a     \r\n      \"//\"     \nd    e\n");
+        assert_eq!(out.len(), src.len() - 1);
+        let (status, _, err) = run_with(&["--blank"], b"");
+        assert_eq!(status, 2);
+        assert!(err.starts_with("pretok: --blank is only for strip"));
+    }
+
+    #[test]
+    fn pretok_test_4() {
+        let dir = std::env::temp_dir().join(format!("pretok_test_4.{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.c");
+        std::fs::write(&path, "int x; // one\n\n/* two */\nlonger\n").unwrap();
+        let path = path.to_str().unwrap();
+        let (status, out, _) = run_with(&["stats", path, "-"], b"a\n");
+        assert_eq!(status, 0);
+        assert_eq!(out, format!("lines\tblank\ttokens\tcomments\tcomment_bytes\tlongest\tfile\n\
+                                 4\t1\t3\t2\t15\t6\t{}\n\
+                                 1\t0\t1\t0\t0\t1\t<stdin>\n\
+                                 5\t1\t4\t2\t15\t6\ttotal\n", path));
+        let (_, out, _) = run_with(&["stats", "--format", "jsonl"], b"a\n");
+        assert_eq!(out, "{\"file\":\"<stdin>\",\"lines\":1,\"blank_lines\":0,\"tokens\":1,\
+                         \"comments\":0,\"comment_bytes\":0,\"longest_token\":1}\n");
+        // A missing file is reported, and the others still counted.
+        let (status, out, err) = run_with(&["stats", "/no/such/file", "-"], b"");
+        assert_eq!(status, 1);
+        assert!(err.starts_with("pretok: /no/such/file: "));
+        assert!(out.contains("<stdin>"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pretok_test_5() {
+        let (status, out, _) = run_with(&["check"], b"a \"b\nc\n");
+        assert_eq!(status, 1);
+        assert_eq!(out, "<stdin>:1:3: unterminated string literal starting at line 1\n");
+        let (status, out, _) = run_with(&["check", "--format", "jsonl"],
+                                        "x\n\u{e9} // \u{202e}\n/*".as_bytes());
+        assert_eq!(status, 1);
+        assert_eq!(out, "{\"file\":\"<stdin>\",\"line\":2,\"col\":6,\"offset\":8,\
+                         \"message\":\"bidirectional control character U+202E at line 2\"}\n\
+                         {\"file\":\"<stdin>\",\"line\":3,\"col\":1,\"offset\":12,\
+                         \"message\":\"unterminated block comment starting at line 3\"}\n");
+        assert_eq!(run_with(&["check", "-"], b"a \"b\" /* c */\n"),
+                   (0, String::new(), String::new()));
+    }
+
+    #[test]
+    fn pretok_test_6() {
+        let src = "f(\"a\tb\"); /* c\nd */ e\n\nx";
+        let (status, out, _) = run_with(&["annotate", "--lossless"], src.as_bytes());
+        assert_eq!(status, 0);
+        assert_eq!(out, "f(\"a    b\"); /* c\n\
+                         ^~^~~~~~~~^~ ^~~~\n\
+                         Word      Word\n  \
+                           Quoted     Comment\n\
+                         d */ e\n\
+                         ~~~~ ^\n     \
+                              Word\n\
+                         \n\
+                         x\n\
+                         ^\n\
+                         Word\n");
+        assert_eq!(run_with(&["annotate"], b"").1, "");
+    }
+
+    #[test]
+    fn pretok_test_7() {
+        assert!(glob_match("src/**/*.c", "src/a.c"));
+        assert!(glob_match("src/**/*.c", "src/x/y/a.c"));
+        assert!(!glob_match("src/*.c", "src/x/a.c"));
+        assert!(glob_match("?.[ch]", "?.[ch]"));
+        assert!(!glob_match("?.c", "ab.c"));
+        assert!(glob_match("a/**", "a/b/c"));
+
+        let root = std::env::temp_dir().join(format!("pretok_test_7.{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("a.c"), "a /* x").unwrap();
+        std::fs::write(root.join("b.txt"), "b").unwrap();
+        std::fs::write(root.join("sub/c.c"), "\"c").unwrap();
+        let dir = root.to_str().unwrap();
+        let (status, out, _) = run_with(&["--ext", "c", "--jobs", "2", dir], b"");
+        assert_eq!(status, 0);
+        assert_eq!(out, format!("==> {0}/a.c <==\n1:1\t0\tWord\t\"a\"\n\
+                                 ==> {0}/sub/c.c <==\n1:1\t0\tQuoted\t\"\\\"c\"\n", dir));
+        let (status, out, _) = run_with(&["check", &format!("{}/**/*.c", dir), "-"], b"\"");
+        assert_eq!(status, 1);
+        assert_eq!(out, format!("{0}/a.c:1:3: unterminated block comment starting at line 1\n\
+                                 {0}/sub/c.c:1:1: unterminated string literal starting at line 1\n\
+                                 <stdin>:1:1: unterminated string literal starting at line 1\n",
+                                dir));
+        let (_, out, _) = run_with(&["--format", "jsonl", &format!("{}/*.txt", dir), "-"], b"d");
+        assert_eq!(out, format!("{{\"file\":\"{}/b.txt\",\"line\":1,\"col\":1,\"offset\":0,\
+                                 \"kind\":\"Word\",\"text\":\"b\"}}\n\
+                                 {{\"file\":\"<stdin>\",\"line\":1,\"col\":1,\"offset\":0,\
+                                 \"kind\":\"Word\",\"text\":\"d\"}}\n", dir));
+        let (status, _, err) = run_with(&[&format!("{}/*.h", dir)], b"");
+        assert_eq!(status, 1);
+        assert!(err.ends_with("*.h: no files match\n"));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn pretok_test_8() {
+        let (status, out, _) = run_with(&["--format", "csv"], b"a \"b,\"\"c\"\n\"x\ny\"");
+        assert_eq!(status, 0);
+        assert_eq!(out, "offset,end,line,col,kind,text\n\
+                         0,1,1,1,Word,a\n\
+                         2,6,1,3,Quoted,\"\"\"b,\"\"\"\n\
+                         6,9,1,7,Quoted,\"\"\"c\"\"\"\n\
+                         10,15,2,1,Quoted,\"\"\"x\ny\"\"\"\n");
+        let (_, out, _) = run_with(&["stats", "--format", "csv"], b"a\n");
+        assert_eq!(out, "file,lines,blank_lines,tokens,comments,comment_bytes,longest_token\n\
+                         <stdin>,1,0,1,0,0,1\n");
+        let (_, out, _) = run_with(&["check", "--format", "csv"], b"/*");
+        assert_eq!(out, "file,line,col,offset,message\n\
+                         <stdin>,1,1,0,unterminated block comment starting at line 1\n");
+        assert_eq!(csv_field("a b"), "a b");
+    }
+
+    #[test]
+    fn pretok_test_9() {
+        let (status, out, _) = run_with(&["trace"], b"a /*\n*/");
+        assert_eq!(status, 0);
+        assert_eq!(out, "1:1\t0\tWS -> StartTok\n\
+                         1:2\t1\tStartTok -> NormalTok\n\
+                         1:1\t0\tWord\t\"a\"\n\
+                         1:4\t3\tWS -> MaybeComment\n\
+                         1:5\t4\tMaybeComment -> BlockComment\n\
+                         2:2\t6\tBlockComment -> MaybeBlockCommentDone\n");
+    }
+}
@@ -0,0 +1,146 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! An iterator that gathers runs of blank lines.
+
+use crate::{Kind, Pretoken, Pretokenizer};
+use std::collections::VecDeque;
+
+/// An iterator over the pieces of a
+/// [lossless](struct.Config.html#method.lossless) pretokenizer with each
+/// run of blank lines as one [Kind::BlankLines](enum.Kind.html#variant.BlankLines)
+/// piece, created by
+/// [Pretokenizer::blank_lines()](struct.Pretokenizer.html#method.blank_lines).
+///
+/// A blank line holds nothing but spaces and tabs.  The piece for a run
+/// spans the whole of each line, including its line break, and its line is
+/// the last line of the run, so the number of blank lines in it is the
+/// number of line breaks, plus one for a blank last line of the input
+/// without one.  The line break ending the line before the run is still a
+/// [Kind::Newline](enum.Kind.html#variant.Newline).  Without lossless mode
+/// there is nothing to gather.
+/// ```
+/// use pretok::{Config, Kind, Pretokenizer};
+/// let src = "a\n\n  \nb\n";
+/// let v: Vec<_> = Pretokenizer::with_config(src, Config::new().lossless(true))
+///     .blank_lines()
+///     .map(|t| (t.kind, t.s, t.line))
+///     .collect();
+/// assert_eq!(v, vec![(Kind::Word, "a", 1), (Kind::Newline, "\n", 1),
+///                    (Kind::BlankLines, "\n  \n", 3), (Kind::Word, "b", 4),
+///                    (Kind::Newline, "\n", 4)]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BlankLines<'a> {
+    inner: Pretokenizer<'a>,
+    /// The input of `inner`
+    src: &'a str,
+    /// Pieces read ahead that are not blank lines
+    queue: VecDeque<Pretoken<'a>>,
+    /// True at the start of a line
+    bol: bool,
+}
+
+impl<'a> BlankLines<'a> {
+    pub(crate) fn new(src: &'a str, inner: Pretokenizer<'a>) -> BlankLines<'a> {
+        BlankLines{ inner, src, queue: VecDeque::new(), bol: true }
+    }
+
+    /// The underlying pretokenizer.  Pieces read ahead are lost.
+    pub fn into_inner(self) -> Pretokenizer<'a> {
+        self.inner
+    }
+
+    /// Gather the blank lines starting with `tok` at the start of a line,
+    /// queueing the pieces after them.
+    fn gather(&mut self, tok: Pretoken<'a>) -> Option<Pretoken<'a>> {
+        let (mut first, mut last): (Option<Pretoken<'a>>, Option<Pretoken<'a>>) = (None, None);
+        // Whitespace starting a line that may be blank
+        let mut indent = None;
+        let mut next = Some(tok);
+        loop {
+            match next {
+                Some(t) if t.kind == Kind::Whitespace && indent.is_none() => indent = Some(t),
+                Some(t) if t.kind == Kind::Newline => {
+                    let start = indent.take().unwrap_or_else(|| t.clone());
+                    first.get_or_insert(start);
+                    last = Some(t);
+                }
+                Some(t) => {
+                    self.queue.extend(indent);
+                    self.queue.push_back(t);
+                    break;
+                }
+                None => {
+                    // A blank last line without a line break.
+                    if let Some(t) = indent {
+                        first.get_or_insert(t.clone());
+                        last = Some(t);
+                    }
+                    break;
+                }
+            }
+            next = self.inner.next();
+        }
+        match (first, last) {
+            (Some(first), Some(last)) => {
+                let end = last.offset + last.s.len();
+                Some(Pretoken{ s: &self.src[first.offset..end], line: last.line,
+                               offset: first.offset, kind: Kind::BlankLines })
+            }
+            _ => self.queue.pop_front(),
+        }
+    }
+}
+
+impl<'a> Iterator for BlankLines<'a> {
+    type Item = Pretoken<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tok = match self.queue.pop_front() {
+            Some(tok) => tok,
+            None => {
+                let tok = self.inner.next()?;
+                if self.bol && matches!(tok.kind, Kind::Whitespace | Kind::Newline) {
+                    self.gather(tok)?
+                } else {
+                    tok
+                }
+            }
+        };
+        if tok.kind != Kind::Bom {
+            self.bol = matches!(tok.kind, Kind::Newline | Kind::BlankLines);
+        }
+        Some(tok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    #[test]
+    fn blank_test_1() {
+        let pieces = |src| -> Vec<_> {
+            Pretokenizer::with_config(src, Config::new().lossless(true)).blank_lines()
+                .map(|t| (t.kind, t.s)).collect()
+        };
+        use Kind::*;
+        assert_eq!(pieces("\n \t\n x \n\t"),
+                   vec![(BlankLines, "\n \t\n"), (Whitespace, " "), (Word, "x"),
+                        (Whitespace, " "), (Newline, "\n"), (BlankLines, "\t")]);
+        assert_eq!(pieces("a /* b */\n\n// c\n  d"),
+                   vec![(Word, "a"), (Whitespace, " "), (Comment, "/* b */"), (Newline, "\n"),
+                        (BlankLines, "\n"), (Comment, "// c"), (Newline, "\n"),
+                        (Whitespace, "  "), (Word, "d")]);
+        assert_eq!(pieces("a \"b\n\n\" \n"),
+                   vec![(Word, "a"), (Whitespace, " "), (Quoted, "\"b\n\n\""),
+                        (Whitespace, " "), (Newline, "\n")]);
+        let src = "\r\n\n";
+        assert_eq!(pieces(src).iter().map(|p| p.1).collect::<String>(), src);
+        assert_eq!(pieces(""), vec![]);
+        let mut blank = Pretokenizer::new("a\n\nb").blank_lines();
+        assert_eq!(blank.next().unwrap().s, "a");
+        assert_eq!(blank.into_inner().next().unwrap().s, "b");
+    }
+}
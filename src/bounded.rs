@@ -0,0 +1,64 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! An iterator over the pretokens before an offset.
+
+use crate::{Pretoken, Pretokenizer};
+
+/// An iterator over the pretokens of a borrowed
+/// [Pretokenizer](struct.Pretokenizer.html) that start before a byte
+/// offset, created by
+/// [Pretokenizer::take_to()](struct.Pretokenizer.html#method.take_to) and
+/// [Pretokenizer::take_line()](struct.Pretokenizer.html#method.take_line).
+/// The first pretoken past the bound is left for the pretokenizer's
+/// `next()`.
+/// ```
+/// use pretok::Pretokenizer;
+/// let mut pt = Pretokenizer::new("mov r0, r1\nret");
+/// let op = pt.next().unwrap();
+/// let args: Vec<_> = pt.take_line().map(|t| t.s).collect();
+/// assert_eq!((op.s, args), ("mov", vec!["r0,", "r1"]));
+/// assert_eq!(pt.next().unwrap().s, "ret");
+/// ```
+#[derive(Debug)]
+pub struct Bounded<'p, 'a> {
+    inner: &'p mut Pretokenizer<'a>,
+    /// The byte offset the pretokens start before
+    end: usize,
+}
+
+impl<'p, 'a> Bounded<'p, 'a> {
+    pub(crate) fn new(inner: &'p mut Pretokenizer<'a>, end: usize) -> Bounded<'p, 'a> {
+        Bounded{ inner, end }
+    }
+}
+
+impl<'p, 'a> Iterator for Bounded<'p, 'a> {
+    type Item = Pretoken<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.inner.peek()?.offset < self.end {
+            true => self.inner.next(),
+            false => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounded_test_1() {
+        let src = "a \"b\nc\" d\ne f";
+        let mut pt = Pretokenizer::new(src);
+        let v: Vec<_> = pt.take_line().map(|t| t.s).collect();
+        assert_eq!(v, vec!["a", "\"b\nc\""]);
+        assert_eq!(pt.take_line().map(|t| t.s).collect::<Vec<_>>(), vec!["d"]);
+        assert_eq!(pt.take_to(12).map(|t| t.s).collect::<Vec<_>>(), vec!["e"]);
+        assert_eq!(pt.take_to(12).count(), 0);
+        assert_eq!(pt.take_to(0).count(), 0);
+        assert_eq!(pt.take_line().map(|t| t.s).collect::<Vec<_>>(), vec!["f"]);
+        assert_eq!(pt.take_line().count(), 0);
+        assert!(pt.next().is_none());
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Matching brackets outside strings and comments.
+
+use crate::{Config, Kind, Pretokenizer};
+
+const OPEN: [char; 3] = ['(', '[', '{'];
+const CLOSE: [char; 3] = [')', ']', '}'];
+
+/// The byte offset of the bracket matching the one at byte `offset` of `s`,
+/// for `(`, `[` and `{` and their closing brackets.  Brackets in strings and
+/// comments are skipped, so a `)` in `"a)"` matches nothing and can't close
+/// a `(` outside the string.  Only brackets of the same kind are counted, so
+/// `(` matches the first `)` that closes as many `(` as it passed.  None if
+/// `offset` isn't a bracket outside strings and comments, or it has no
+/// match.
+/// ```
+/// use pretok::match_bracket;
+/// let src = "f(a, \")\", /* ( */ g(b))";
+/// assert_eq!(match_bracket(src, 1), Some(22));
+/// assert_eq!(match_bracket(src, 22), Some(1));
+/// assert_eq!(match_bracket(src, 19), Some(21));
+/// assert_eq!(match_bracket(src, 6), None);
+/// assert_eq!(match_bracket(src, 0), None);
+/// ```
+pub fn match_bracket(s: &str, offset: usize) -> Option<usize> {
+    match_bracket_with(s, offset, Config::default())
+}
+
+/// Like [match_bracket()](fn.match_bracket.html), with `cfg`.
+pub fn match_bracket_with(s: &str, offset: usize, cfg: Config) -> Option<usize> {
+    let c = s.get(offset..)?.chars().next()?;
+    let (open, close, forward) = if let Some(i) = OPEN.iter().position(|&o| o == c) {
+        (c, CLOSE[i], true)
+    } else {
+        let i = CLOSE.iter().position(|&x| x == c)?;
+        (OPEN[i], c, false)
+    };
+
+    let mut brackets = Vec::new();
+    let mut found = false;
+    for tok in Pretokenizer::with_config(s, cfg) {
+        if tok.kind != Kind::Word {
+            continue;
+        }
+        for (i, b) in tok.s.char_indices().filter(|&(_, b)| b == open || b == close) {
+            let at = tok.offset + i;
+            found |= at == offset;
+            // From the bracket on when going forward, or up to it going back.
+            if (forward && found) || (!forward && at <= offset) {
+                brackets.push((at, b));
+            }
+        }
+        if !forward && tok.offset + tok.s.len() > offset {
+            break;
+        }
+    }
+    if !found {
+        return None;
+    }
+    if !forward {
+        brackets.reverse();
+    }
+    let (first, last) = if forward { (open, close) } else { (close, open) };
+    let mut depth = 0;
+    for (at, b) in brackets {
+        if b == first {
+            depth += 1;
+        } else if b == last {
+            depth -= 1;
+            if depth == 0 {
+                return Some(at);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn brackets_test_1() {
+        let src = "{ [x[1]] // ]\n  '(' \"}\" ( ] } ";
+        assert_eq!(match_bracket(src, 0), Some(28));
+        assert_eq!(match_bracket(src, 28), Some(0));
+        assert_eq!(match_bracket(src, 2), Some(7));
+        assert_eq!(match_bracket(src, 4), Some(6));
+        assert_eq!(match_bracket(src, 7), Some(2));
+        // In a comment, and in a string.
+        assert_eq!(match_bracket(src, 12), None);
+        assert_eq!(match_bracket(src, 21), None);
+        // Unmatched, and an ordinary character.
+        assert_eq!(match_bracket(src, 17), None);
+        assert_eq!(match_bracket(src, 24), None);
+        assert_eq!(match_bracket(src, 26), None);
+        assert_eq!(match_bracket(src, 1), None);
+        assert_eq!(match_bracket(src, 100), None);
+        assert_eq!(match_bracket("\u{e9}", 1), None);
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Byte input that may not be UTF-8.
+
+use crate::{Config, InvalidUtf8, Pretokenizer};
+use std::borrow::Cow;
+use std::str;
+
+/// Check that `bytes` are UTF-8, returning them as a `str`, or the position
+/// of the first invalid byte.
+/// ```
+/// assert_eq!(pretok::validate_utf8(b"a b").unwrap(), "a b");
+/// assert_eq!(pretok::validate_utf8(b"a\nb\xc3").unwrap_err().line, 2);
+/// ```
+pub fn validate_utf8(bytes: &[u8]) -> Result<&str, InvalidUtf8> {
+    str::from_utf8(bytes).map_err(|e| InvalidUtf8{
+        offset: e.valid_up_to(),
+        line: line_at(bytes, e.valid_up_to()),
+        len: e.error_len(),
+    })
+}
+
+/// Convert `bytes` to UTF-8, replacing each invalid sequence with U+FFFD
+/// REPLACEMENT CHARACTER, as `String::from_utf8_lossy()` does.  Returns the
+/// text with the position of every replacement in `bytes`.  The input is
+/// borrowed if it is all valid.
+///
+/// A replacement is three bytes long, so pretoken offsets after one don't
+/// match offsets in `bytes`.  Line numbers are unchanged.
+/// ```
+/// let (text, bad) = pretok::utf8_lossy(b"caf\xe9 ok");
+/// assert_eq!(text, "caf\u{FFFD} ok");
+/// assert_eq!(bad[0].offset, 3);
+/// assert_eq!(pretok::tokenize(&text)[1].s, "ok");
+/// ```
+pub fn utf8_lossy(bytes: &[u8]) -> (Cow<'_, str>, Vec<InvalidUtf8>) {
+    let mut first = match validate_utf8(bytes) {
+        Ok(s) => return (Cow::Borrowed(s), Vec::new()),
+        Err(e) => e,
+    };
+    let mut text = String::with_capacity(bytes.len() + 2);
+    let mut invalid = Vec::new();
+    let mut at = 0;
+    loop {
+        let valid = &bytes[at..first.offset];
+        // SAFETY: Everything before the first error is UTF-8.
+        text.push_str(unsafe { str::from_utf8_unchecked(valid) });
+        text.push(char::REPLACEMENT_CHARACTER);
+        at = first.len.map_or(bytes.len(), |len| first.offset + len);
+        let line = first.line;
+        invalid.push(first);
+        first = match str::from_utf8(&bytes[at..]) {
+            Ok(rest) => {
+                text.push_str(rest);
+                return (Cow::Owned(text), invalid);
+            }
+            Err(e) => InvalidUtf8{
+                offset: at + e.valid_up_to(),
+                line: line + memchr::memchr_iter(b'\n', &bytes[at..at + e.valid_up_to()]).count(),
+                len: e.error_len(),
+            },
+        };
+    }
+}
+
+/// The line holding byte `offset` of `bytes`.
+fn line_at(bytes: &[u8], offset: usize) -> usize {
+    1 + memchr::memchr_iter(b'\n', &bytes[..offset]).count()
+}
+
+impl<'a> Pretokenizer<'a> {
+    /// Create a pretokenizer over `bytes`, or fail if they aren't UTF-8.
+    /// Use [utf8_lossy()](fn.utf8_lossy.html) to pretokenize input with
+    /// stray invalid bytes instead.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// assert_eq!(Pretokenizer::from_bytes(b"a b")?.count(), 2);
+    /// assert_eq!(Pretokenizer::from_bytes(b"a\n\xff").unwrap_err().offset, 2);
+    /// # Ok::<(), pretok::InvalidUtf8>(())
+    /// ```
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Pretokenizer<'a>, InvalidUtf8> {
+        Ok(Pretokenizer::new(validate_utf8(bytes)?))
+    }
+
+    /// Like [Pretokenizer::from_bytes()](struct.Pretokenizer.html#method.from_bytes),
+    /// with `cfg`.
+    pub fn from_bytes_with_config(bytes: &'a [u8], cfg: Config)
+        -> Result<Pretokenizer<'a>, InvalidUtf8> {
+        Ok(Pretokenizer::with_config(validate_utf8(bytes)?, cfg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_test_0() {
+        let (text, bad) = utf8_lossy(b"a\xff\xfe\n\"\xe2\x82\" \xf0\x9f\x98\x80\n\xe2");
+        assert_eq!(text, "a\u{FFFD}\u{FFFD}\n\"\u{FFFD}\" \u{1F600}\n\u{FFFD}");
+        assert_eq!(bad, vec![InvalidUtf8{ offset: 1, line: 1, len: Some(1) },
+                             InvalidUtf8{ offset: 2, line: 1, len: Some(1) },
+                             InvalidUtf8{ offset: 5, line: 2, len: Some(2) },
+                             InvalidUtf8{ offset: 14, line: 3, len: None }]);
+        let toks: Vec<_> = crate::tokenize(&text).iter().map(|t| (t.s, t.line)).collect();
+        assert_eq!(toks, vec![("a\u{FFFD}\u{FFFD}", 1), ("\"\u{FFFD}\"", 2), ("\u{1F600}", 2),
+                              ("\u{FFFD}", 3)]);
+        assert!(matches!(utf8_lossy(b"fine"), (Cow::Borrowed("fine"), ref v) if v.is_empty()));
+
+        let e = validate_utf8(b"\n\n\xe2\x82").unwrap_err();
+        assert_eq!(e, InvalidUtf8{ offset: 2, line: 3, len: None });
+        assert_eq!(e.to_string(), "incomplete UTF-8 at the end of line 3, byte offset 2");
+        let cfg = Config::new().lossless(true);
+        assert_eq!(Pretokenizer::from_bytes_with_config(b"x y", cfg).unwrap().count(), 3);
+    }
+}
@@ -0,0 +1,177 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! An on-disk cache of pretokens keyed by the input's content.
+
+use crate::fingerprint::Fnv;
+use crate::{Config, Kind, Pretoken};
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Identifies cache files, followed by the format version.
+const MAGIC: &[u8; 4] = b"PTKC";
+const VERSION: u32 = 1;
+
+/// Bytes per cached pretoken: offset, length and line as `u64`, then kind.
+const ENTRY: usize = 25;
+
+/// A directory of pretokenized inputs, each in a file named for a hash of
+/// the input and the configuration.  Unchanged inputs are loaded instead of
+/// pretokenized again, even across runs.
+///
+/// Only pretokens are cached, not diagnostics or pragmas.  Entries are never
+/// removed; delete the directory to clear the cache.  An unreadable or
+/// damaged entry is treated as missing and replaced.
+/// ```
+/// use pretok::TokenCache;
+/// let cache = TokenCache::new(std::env::temp_dir().join("pretok-doc-cache"));
+/// let src = "int x = 1;";
+/// let toks = cache.tokenize(src)?;
+/// assert_eq!(toks, pretok::tokenize(src));
+/// assert_eq!(cache.load(src), Some(toks));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct TokenCache {
+    dir: PathBuf,
+    cfg: Config,
+    /// Hash of `cfg`, part of every file name
+    cfg_hash: u64,
+}
+
+impl TokenCache {
+    /// Create a cache in `dir` for the default configuration.  The
+    /// directory is created when the first entry is stored.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> TokenCache {
+        TokenCache::with_config(dir, Config::default())
+    }
+
+    /// Create a cache in `dir` for pretokenizing with `cfg`.  Caches for
+    /// different configurations may share a directory.
+    pub fn with_config<P: Into<PathBuf>>(dir: P, cfg: Config) -> TokenCache {
+        let mut hash = Fnv::new();
+        hash.write(format!("{:?}", cfg).as_bytes());
+        TokenCache{ dir: dir.into(), cfg, cfg_hash: hash.0 }
+    }
+
+    /// The pretokens of `s`, loaded from the cache if present, otherwise
+    /// pretokenized and stored.  Fails only if the entry can't be stored.
+    pub fn tokenize<'a>(&self, s: &'a str) -> io::Result<Vec<Pretoken<'a>>> {
+        if let Some(toks) = self.load(s) {
+            return Ok(toks);
+        }
+        let toks = crate::tokenize_with(s, self.cfg.clone());
+        self.store(s, &toks)?;
+        Ok(toks)
+    }
+
+    /// The cached pretokens of `s`, if present.
+    pub fn load<'a>(&self, s: &'a str) -> Option<Vec<Pretoken<'a>>> {
+        let bytes = fs::read(self.path(s)).ok()?;
+        decode(s, &bytes)
+    }
+
+    /// Store `toks` as the pretokens of `s`.
+    fn store(&self, s: &str, toks: &[Pretoken]) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        // Write a temporary file and rename it, so a reader never sees a
+        // partial entry.
+        let path = self.path(s);
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&tmp, encode(s, toks))?;
+        fs::rename(&tmp, &path)
+    }
+
+    fn path(&self, s: &str) -> PathBuf {
+        let mut hash = Fnv::new();
+        hash.write(s.as_bytes());
+        self.dir.join(format!("{:016x}-{:016x}.ptk", hash.0, self.cfg_hash))
+    }
+}
+
+fn encode(s: &str, toks: &[Pretoken]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(24 + toks.len() * ENTRY);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    out.extend_from_slice(&(s.len() as u64).to_le_bytes());
+    out.extend_from_slice(&(toks.len() as u64).to_le_bytes());
+    for tok in toks {
+        out.extend_from_slice(&(tok.offset as u64).to_le_bytes());
+        out.extend_from_slice(&(tok.s.len() as u64).to_le_bytes());
+        out.extend_from_slice(&(tok.line as u64).to_le_bytes());
+        out.push(tok.kind.code());
+    }
+    out
+}
+
+/// Rebuild the pretokens of `s` from `bytes`, or None if `bytes` isn't a
+/// valid entry for `s`.
+fn decode<'a>(s: &'a str, bytes: &[u8]) -> Option<Vec<Pretoken<'a>>> {
+    let u64_at = |i: usize| -> Option<usize> {
+        let b = bytes.get(i..i + 8)?;
+        usize::try_from(u64::from_le_bytes(<[u8; 8]>::try_from(b).ok()?)).ok()
+    };
+    if bytes.get(..4)? != MAGIC || bytes.get(4..8)? != VERSION.to_le_bytes() {
+        return None;
+    }
+    if u64_at(8)? != s.len() {
+        return None;
+    }
+    let count = u64_at(16)?;
+    let body = &bytes[24..];
+    if Some(body.len()) != count.checked_mul(ENTRY) {
+        return None;
+    }
+    let mut toks = Vec::with_capacity(count);
+    for i in 0..count {
+        let at = 24 + i * ENTRY;
+        let (offset, len, line) = (u64_at(at)?, u64_at(at + 8)?, u64_at(at + 16)?);
+        let kind = Kind::from_code(bytes[at + 24])?;
+        let text = s.get(offset..offset.checked_add(len)?)?;
+        toks.push(Pretoken{ s: text, line, offset, kind });
+    }
+    Some(toks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_test_0() {
+        let dir = std::env::temp_dir().join(format!("pretok_cache_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let src = "f(\"a\\\"b\",\n  c); // d";
+        let cache = TokenCache::new(&dir);
+        assert_eq!(cache.load(src), None);
+        let toks = cache.tokenize(src).unwrap();
+        assert_eq!(toks, crate::tokenize(src));
+        assert_eq!(cache.load(src), Some(toks.clone()));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        // Another configuration gets its own entry.
+        let lossless = TokenCache::with_config(&dir, Config::new().lossless(true));
+        assert_eq!(lossless.load(src), None);
+        let all = lossless.tokenize(src).unwrap();
+        assert_eq!(all.len(), 8);
+        assert_eq!(lossless.load(src).unwrap(), all);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        // A damaged entry is replaced.
+        let path = cache.path(src);
+        let mut bytes = fs::read(&path).unwrap();
+        bytes.truncate(bytes.len() - 1);
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(cache.load(src), None);
+        assert_eq!(cache.tokenize(src).unwrap(), toks);
+        assert_eq!(cache.load(src), Some(toks));
+
+        // Every kind survives the trip.
+        for &kind in Kind::BY_CODE.iter() {
+            assert_eq!(Kind::from_code(kind.code()), Some(kind));
+        }
+        assert_eq!(Kind::from_code(13), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
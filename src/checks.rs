@@ -0,0 +1,271 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Character checks applied to all consumed input.
+
+use crate::{Config, Kind, Nul, PretokError, Pretoken, ZeroWidth};
+
+/// True for the Unicode bidirectional embedding, override and isolate
+/// controls used in "Trojan Source" attacks (CVE-2021-42574).
+pub(crate) fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+/// Return the ASCII character that `c` is commonly mistaken for, if any.
+/// This covers the Cyrillic and Greek letters most often used as homoglyphs,
+/// fullwidth ASCII, and a few lookalike punctuation marks.
+pub(crate) fn confusable(c: char) -> Option<char> {
+    let ascii = match c {
+        // Cyrillic
+        'а' => 'a', 'в' => 'B', 'е' => 'e', 'к' => 'k', 'м' => 'M', 'н' => 'H',
+        'о' => 'o', 'р' => 'p', 'с' => 'c', 'т' => 'T', 'у' => 'y', 'х' => 'x',
+        'ѕ' => 's', 'і' => 'i', 'ј' => 'j', 'һ' => 'h', 'ԁ' => 'd', 'ԛ' => 'q',
+        'ԝ' => 'w', 'А' => 'A', 'В' => 'B', 'Е' => 'E', 'К' => 'K', 'М' => 'M',
+        'Н' => 'H', 'О' => 'O', 'Р' => 'P', 'С' => 'C', 'Т' => 'T', 'Х' => 'X',
+        'Ѕ' => 'S', 'І' => 'I', 'Ј' => 'J',
+        // Greek
+        'α' => 'a', 'ο' => 'o', 'ν' => 'v', 'ρ' => 'p', 'ι' => 'i', 'Α' => 'A',
+        'Β' => 'B', 'Ε' => 'E', 'Ζ' => 'Z', 'Η' => 'H', 'Ι' => 'I', 'Κ' => 'K',
+        'Μ' => 'M', 'Ν' => 'N', 'Ο' => 'O', 'Ρ' => 'P', 'Τ' => 'T', 'Υ' => 'Y',
+        'Χ' => 'X',
+        // Punctuation
+        '\u{037E}' => ';', '\u{2010}' | '\u{2011}' | '\u{2012}' | '\u{2013}'
+            | '\u{2212}' => '-',
+        '\u{2018}' | '\u{2019}' => '\'', '\u{201C}' | '\u{201D}' => '"',
+        '\u{2044}' | '\u{2215}' => '/', '\u{02D0}' | '\u{FF1A}' => ':',
+        // Fullwidth ASCII
+        '\u{FF01}'..='\u{FF5E}' => {
+            return std::char::from_u32(c as u32 - 0xFF01 + 0x21);
+        }
+        _ => return None,
+    };
+    Some(ascii)
+}
+
+/// True for the zero-width space, non-joiner and joiner, and U+FEFF, which
+/// is a zero-width no-break space away from the start of the input.
+pub(crate) fn is_zero_width(c: char) -> bool {
+    matches!(c, '\u{200B}'..='\u{200D}' | '\u{FEFF}')
+}
+
+/// True for C0 control characters other than tab, line feed and carriage
+/// return, and for DEL.
+pub(crate) fn is_control(c: char) -> bool {
+    c.is_ascii_control() && !matches!(c, '\t' | '\n' | '\r')
+}
+
+/// True for the characters `Config::invalid_tokens()` sets apart: those of
+/// `is_control()`, and U+FFFD.
+pub(crate) fn is_invalid(c: char) -> bool {
+    is_control(c) || c == '\u{FFFD}'
+}
+
+/// True if any check done by `check_token()` is enabled in `cfg`.
+pub(crate) fn token_checks_enabled(cfg: &Config) -> bool {
+    cfg.detect_non_ascii || cfg.detect_control_chars || cfg.zero_width == ZeroWidth::Diagnose
+}
+
+/// Report unexpected characters in a pretoken.
+pub(crate) fn check_token(tok: &Pretoken, cfg: &Config,
+                          diagnostics: &mut Vec<PretokError>) {
+    let non_ascii = cfg.detect_non_ascii && tok.kind == Kind::Word
+        && !tok.s.is_ascii();
+    let controls = cfg.detect_control_chars;
+    let zero_width = cfg.zero_width == ZeroWidth::Diagnose && tok.kind == Kind::Word
+        && !tok.s.is_ascii();
+    if !non_ascii && !controls && !zero_width {
+        return;
+    }
+    for (i, c) in tok.s.char_indices() {
+        let at = tok.offset + i;
+        let span = at..at + c.len_utf8();
+        let line = || tok.line.saturating_sub(tok.s[i..].matches('\n').count());
+        if controls && is_control(c) {
+            diagnostics.push(PretokError::ControlChar{ch: c, line: line(), span});
+        } else if zero_width && is_zero_width(c) && at > 0 {
+            diagnostics.push(PretokError::ZeroWidth{ch: c, line: line(), span});
+        } else if non_ascii && !c.is_ascii() && !cfg.allow_non_ascii.contains(c) {
+            diagnostics.push(match confusable(c) {
+                Some(looks_like) => PretokError::Confusable{
+                    ch: c, looks_like, line: line(), span},
+                None => PretokError::NonAscii{ch: c, line: line(), span},
+            });
+        }
+    }
+}
+
+/// Follows the pieces scanned, both pretokens and the whitespace and
+/// comments between them, for `Config::detect_whitespace()` and
+/// `Config::detect_mixed_indent()`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct WhitespaceChecks {
+    /// True for `Config::detect_whitespace()`
+    whitespace: bool,
+    /// True for `Config::detect_mixed_indent()`
+    mixed_indent: bool,
+    /// True if nothing but whitespace came since the last line break
+    bol: bool,
+    /// Spaces and tabs that end the line if a line break follows: their
+    /// span, line, and whether they are all the line holds
+    pending: Option<(usize, usize, usize, bool)>,
+    /// Indentation of tabs and spaces reported if something besides a line
+    /// break follows: its span and line
+    indent: Option<(usize, usize, usize)>,
+}
+
+impl WhitespaceChecks {
+    pub(crate) fn new(cfg: &Config) -> WhitespaceChecks {
+        WhitespaceChecks::starting_at(true, cfg)
+    }
+
+    /// Check from a position at the start of a line if `bol`.
+    pub(crate) fn starting_at(bol: bool, cfg: &Config) -> WhitespaceChecks {
+        WhitespaceChecks{ whitespace: cfg.detect_whitespace,
+                          mixed_indent: cfg.detect_mixed_indent,
+                          bol, pending: None, indent: None }
+    }
+
+    /// True if any check is enabled.
+    pub(crate) fn enabled(&self) -> bool {
+        self.whitespace || self.mixed_indent
+    }
+
+    /// Check piece `tok`.
+    pub(crate) fn record(&mut self, tok: &Pretoken, diagnostics: &mut Vec<PretokError>) {
+        match tok.kind {
+            Kind::Whitespace => {
+                let span = tok.offset..tok.offset + tok.s.len();
+                if self.whitespace && self.bol && tok.s.contains(" \t") {
+                    diagnostics.push(PretokError::TabAfterSpace{
+                        line: tok.line, span: span.clone() });
+                }
+                if self.mixed_indent && self.bol && tok.s.contains(' ') && tok.s.contains('\t') {
+                    self.indent = Some((span.start, span.end, tok.line));
+                }
+                self.pending = Some((span.start, span.end, tok.line, self.bol));
+            }
+            Kind::Newline | Kind::Terminator => {
+                self.end_line(diagnostics);
+                self.indent = None;
+                self.bol = true;
+            }
+            // The carriage return of a CRLF line break after whitespace.
+            Kind::Word if tok.s == "\r" => {}
+            Kind::Comment => {
+                self.end_indent(diagnostics);
+                let first = tok.line - tok.s.matches('\n').count();
+                let mut at = tok.offset;
+                for (i, line) in tok.s.split('\n').enumerate() {
+                    let text = line.strip_suffix('\r').unwrap_or(line);
+                    let body = text.trim_end_matches([' ', '\t']).len();
+                    self.pending = Some((at + body, at + text.len(), first + i, false));
+                    if at + line.len() < tok.offset + tok.s.len() {
+                        self.end_line(diagnostics);
+                    }
+                    at += line.len() + 1;
+                }
+                self.bol = false;
+            }
+            _ => {
+                self.end_indent(diagnostics);
+                self.pending = None;
+                self.bol = false;
+            }
+        }
+    }
+
+    /// Check the end of input.
+    pub(crate) fn finish(&mut self, diagnostics: &mut Vec<PretokError>) {
+        self.end_line(diagnostics);
+    }
+
+    /// Report the indentation pending before something on its line.
+    fn end_indent(&mut self, diagnostics: &mut Vec<PretokError>) {
+        if let Some((start, end, line)) = self.indent.take() {
+            diagnostics.push(PretokError::MixedIndentation{ line, span: start..end });
+        }
+    }
+
+    /// Report the whitespace pending at the end of a line.
+    fn end_line(&mut self, diagnostics: &mut Vec<PretokError>) {
+        match self.pending.take().filter(|_| self.whitespace) {
+            Some((start, end, line, true)) => {
+                diagnostics.push(PretokError::WhitespaceOnlyLine{ line, span: start..end });
+            }
+            Some((start, end, line, false)) if start < end => {
+                diagnostics.push(PretokError::TrailingWhitespace{ line, span: start..end });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Walks the input consumed between calls to `next()` so that comments,
+/// strings and tokens are all checked exactly once.
+#[derive(Clone, Debug)]
+pub(crate) struct CharChecks {
+    /// Byte offset up to which input has been checked
+    checked: usize,
+    /// Line number at `checked`
+    line: usize,
+    /// The first line ending seen, or `MIXED` once mixed endings are found
+    ending: Option<&'static str>,
+}
+
+/// The line ending kind recorded once mixed endings have been reported.
+const MIXED: &str = "";
+
+impl CharChecks {
+    pub(crate) fn new(cfg: &Config) -> CharChecks {
+        CharChecks::starting_at(0, cfg.first_line())
+    }
+
+    /// Check only the input from byte offset `start`, on line `line`.
+    pub(crate) fn starting_at(start: usize, line: usize) -> CharChecks {
+        CharChecks{ checked: start, line, ending: None }
+    }
+
+    /// True if any check is enabled in `cfg`.
+    pub(crate) fn enabled(cfg: &Config) -> bool {
+        cfg.detect_bidi || cfg.nul == Nul::Reject || cfg.detect_line_endings
+    }
+
+    /// Check `s` up to byte offset `upto`.
+    pub(crate) fn check(&mut self, s: &str, upto: usize, cfg: &Config,
+                        diagnostics: &mut Vec<PretokError>) {
+        if upto <= self.checked {
+            return;
+        }
+        let nul = cfg.nul == Nul::Reject;
+        let bytes = s.as_bytes();
+        for (i, c) in s[self.checked..upto].char_indices() {
+            let at = self.checked + i;
+            if cfg.detect_line_endings && self.ending != Some(MIXED) {
+                let ending = match c {
+                    '\r' if bytes.get(at + 1) == Some(&b'\n') => Some("\r\n"),
+                    '\r' => Some("\r"),
+                    '\n' if at > 0 && bytes[at - 1] == b'\r' => None,
+                    '\n' => Some("\n"),
+                    _ => None,
+                };
+                match (ending, self.ending) {
+                    (Some(ending), None) => self.ending = Some(ending),
+                    (Some(ending), Some(first)) if ending != first => {
+                        diagnostics.push(PretokError::MixedLineEndings{
+                            line: self.line, span: at..at + ending.len()});
+                        self.ending = Some(MIXED);
+                    }
+                    _ => {}
+                }
+            }
+            if c == '\n' {
+                self.line += 1;
+            } else if cfg.detect_bidi && is_bidi_control(c) {
+                diagnostics.push(PretokError::BidiControl{
+                    ch: c, line: self.line, span: at..at + c.len_utf8()});
+            } else if nul && c == '\0' {
+                diagnostics.push(PretokError::Nul{ line: self.line, span: at..at + 1 });
+            }
+        }
+        self.checked = upto;
+    }
+}
@@ -0,0 +1,85 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Parsing pretokens with chumsky.
+
+use crate::{Kind, Pretoken};
+use chumsky::primitive::filter;
+use chumsky::{Error, Parser, Stream};
+use std::ops::Range;
+
+/// The pretokens `toks` of a source of `len` bytes as a
+/// [chumsky](https://docs.rs/chumsky) stream whose spans are the byte spans
+/// of the pretokens in the source, rather than their indexes as chumsky's
+/// own conversion from a slice gives.  The end of input is spanned
+/// `len..len`, so errors there point past the last pretoken.
+/// ```
+/// use chumsky::prelude::*;
+/// use pretok::{token_kind, token_stream, token_text, Kind, Pretokenizer};
+///
+/// let arg = token_kind::<Simple<_>>(Kind::Word).or(token_kind(Kind::Quoted))
+///     .map_with_span(|t, span| (t.s, span));
+/// let call = token_text("call")
+///     .ignore_then(arg.separated_by(token_text(",")).delimited_by(token_text("("),
+///                                                                  token_text(")")));
+/// let src = "call ( a , \"b\" )";
+/// let args = call.parse(token_stream(Pretokenizer::new(src), src.len())).unwrap();
+/// assert_eq!(args, vec![("a", 7..8), ("\"b\"", 11..14)]);
+///
+/// let src = "call ( a ,";
+/// let errors = call.parse(token_stream(Pretokenizer::new(src), src.len())).unwrap_err();
+/// assert_eq!(errors[0].span(), 10..10);
+/// ```
+pub fn token_stream<'a, I>(toks: I, len: usize)
+    -> Stream<'a, Pretoken<'a>, Range<usize>, impl Iterator<Item = (Pretoken<'a>, Range<usize>)>>
+where
+    I: IntoIterator<Item = Pretoken<'a>>,
+{
+    let spanned = toks.into_iter().map(|t| {
+        let span = t.offset..t.offset + t.s.len();
+        (t, span)
+    });
+    Stream::from_iter(len..len, spanned)
+}
+
+/// A chumsky parser taking one pretoken whose text is `s`.  See
+/// [token_stream()](fn.token_stream.html).
+pub fn token_text<'a, E>(s: &'a str) -> impl Parser<Pretoken<'a>, Pretoken<'a>, Error = E> + Clone
+where
+    E: Error<Pretoken<'a>>,
+{
+    filter(move |t: &Pretoken| t.s == s)
+}
+
+/// A chumsky parser taking one pretoken of kind `kind`.  See
+/// [token_stream()](fn.token_stream.html).
+pub fn token_kind<'a, E>(kind: Kind) -> impl Parser<Pretoken<'a>, Pretoken<'a>, Error = E> + Clone
+where
+    E: Error<Pretoken<'a>>,
+{
+    filter(move |t: &Pretoken| t.kind == kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Pretokenizer;
+    use chumsky::error::Simple;
+
+    #[test]
+    fn chumsky_input_test_1() {
+        let src = "let x =\n  \"y\" ;";
+        let stmt = token_text::<Simple<_>>("let")
+            .ignore_then(token_kind(Kind::Word))
+            .then_ignore(token_text("="))
+            .then(token_kind(Kind::Quoted).map_with_span(|t, span| (t.s, span)))
+            .then_ignore(token_text(";"));
+        let (name, value) = stmt.parse(token_stream(Pretokenizer::new(src), src.len())).unwrap();
+        assert_eq!(name.s, "x");
+        assert_eq!(value, ("\"y\"", 10..13));
+
+        let errors = stmt.parse(token_stream(Pretokenizer::new("let x = y"), 9)).unwrap_err();
+        assert_eq!(errors[0].span(), 8..9);
+        let errors = stmt.parse(token_stream(Pretokenizer::new("let x ="), 7)).unwrap_err();
+        assert_eq!(errors[0].span(), 7..7);
+    }
+}
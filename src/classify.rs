@@ -0,0 +1,88 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Character classification for languages the builder options don't fit.
+
+/// Tells the scanner what role each character plays, for
+/// [Pretokenizer::classifier()](struct.Pretokenizer.html#method.classifier).
+/// Each method's default gives the usual behavior, so an implementation
+/// overrides only what its language changes.  Line feeds always end lines,
+/// and `*` and `\` keep their roles in comments and strings.
+pub trait CharClassifier {
+    /// True if `c` separates pretokens and is skipped, like a space or tab.
+    fn is_whitespace(&self, c: char) -> bool {
+        c == ' ' || c == '\t'
+    }
+
+    /// True if `c` opens a quoted string, like `"`.  A string ends at the
+    /// next quote character, whichever opened it.
+    fn is_quote(&self, c: char) -> bool {
+        c == '"'
+    }
+
+    /// True if `c` plays the role of `/` in comments: twice it starts a line
+    /// comment, and before `*` a block comment, which `*` and `c` end.
+    fn is_comment_start(&self, c: char) -> bool {
+        c == '/'
+    }
+
+    /// True if `c` may be part of a word.  A word pretoken ends where a run
+    /// of word characters meets a run of others, as with
+    /// [Pretokenizer::word_chars()](struct.Pretokenizer.html#method.word_chars).
+    fn is_word_char(&self, _c: char) -> bool {
+        true
+    }
+}
+
+/// The classifier giving the usual behavior.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DefaultClassifier;
+
+impl CharClassifier for DefaultClassifier {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, Kind, Pretokenizer};
+
+    /// Lisp-like syntax: commas are blanks, `;` starts comments, `'` is a
+    /// quote as well as `"`, and brackets are not word characters.
+    struct Lisp;
+
+    impl CharClassifier for Lisp {
+        fn is_whitespace(&self, c: char) -> bool {
+            c == ' ' || c == ',' || c == '\u{3000}'
+        }
+
+        fn is_quote(&self, c: char) -> bool {
+            c == '"' || c == '\''
+        }
+
+        fn is_comment_start(&self, c: char) -> bool {
+            c == ';'
+        }
+
+        fn is_word_char(&self, c: char) -> bool {
+            !"()[]".contains(c)
+        }
+    }
+
+    #[test]
+    fn classify_test_1() {
+        let src = "(a,b\u{3000}'x y' [c/d];; note\n e;*1\n2*;f\t\"g\")";
+        let v: Vec<_> = Pretokenizer::new(src).classifier(Lisp).map(|t| (t.kind, t.s)).collect();
+        use Kind::*;
+        assert_eq!(v, vec![(Word, "("), (Word, "a"), (Word, "b"), (Quoted, "'x y'"),
+                           (Word, "["), (Word, "c/d"), (Word, "]"), (Word, "e"),
+                           (Word, "f\t"), (Quoted, "\"g\""), (Word, ")")]);
+        let cfg = Config::new().lossless(true);
+        let pieces: String = Pretokenizer::with_config(src, cfg.clone()).classifier(Lisp)
+            .map(|t| t.s).collect();
+        assert_eq!(pieces, src);
+
+        let src = "a+b /*c*/ \"d\" // e\n";
+        let default: Vec<_> = Pretokenizer::with_config(src, cfg.clone()).collect();
+        let classified: Vec<_> = Pretokenizer::with_config(src, cfg)
+            .classifier(DefaultClassifier).collect();
+        assert_eq!(default, classified);
+    }
+}
@@ -0,0 +1,114 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Deciding whether input is complete or needs another line.
+
+use crate::{balance, Config, PretokError};
+
+/// Whether input ends inside a construct, as returned by
+/// [is_complete()](fn.is_complete.html).  Each incomplete case gives the
+/// line and byte offset where the innermost open construct opened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Completeness {
+    /// Nothing is left open.  The input may still have errors, such as an
+    /// unmatched closing bracket, that more input wouldn't fix.
+    Complete,
+    /// Inside a quoted string.
+    InString {
+        /// The line of the opening quote.
+        line: usize,
+        /// The byte offset of the opening quote.
+        offset: usize,
+    },
+    /// Inside a block comment.
+    InComment {
+        /// The line of the opening `/*`.
+        line: usize,
+        /// The byte offset of the opening `/*`.
+        offset: usize,
+    },
+    /// Inside a region skipped by
+    /// [Config::skip_region()](struct.Config.html#method.skip_region).
+    InRegion {
+        /// The line of the opening marker.
+        line: usize,
+        /// The byte offset of the opening marker.
+        offset: usize,
+    },
+    /// Inside a bracket group.
+    InBracket {
+        /// The opening bracket: `(`, `[` or `{`.
+        ch: char,
+        /// The line of the opening bracket.
+        line: usize,
+        /// The byte offset of the opening bracket.
+        offset: usize,
+    },
+}
+
+impl Completeness {
+    /// True if nothing is left open.
+    pub fn is_complete(self) -> bool {
+        self == Completeness::Complete
+    }
+}
+
+/// Report whether `s` ends inside a string, block comment or bracket group,
+/// for a REPL deciding whether to prompt for a continuation line.  Brackets
+/// are as for [check_balance()](fn.check_balance.html): one left open when a
+/// later bracket closes an outer one doesn't count.
+/// ```
+/// use pretok::{is_complete, Completeness};
+/// assert_eq!(is_complete("f(a, [1, 2]);"), Completeness::Complete);
+/// assert_eq!(is_complete("f(a,\n  [1,"), Completeness::InBracket{ ch: '[', line: 2, offset: 7 });
+/// assert_eq!(is_complete("f(\"a)"), Completeness::InString{ line: 1, offset: 2 });
+/// assert!(is_complete("x = 1 } /* ok */").is_complete());
+/// ```
+pub fn is_complete(s: &str) -> Completeness {
+    is_complete_with(s, Config::default())
+}
+
+/// Like [is_complete()](fn.is_complete.html), with `cfg`.
+pub fn is_complete_with(s: &str, cfg: Config) -> Completeness {
+    let (open, _, pt) = balance::brackets(s, cfg);
+    for e in pt.diagnostics() {
+        let (line, offset) = (e.line(), e.span().start);
+        match e {
+            PretokError::UnterminatedString{ .. } => {
+                return Completeness::InString{ line, offset };
+            }
+            PretokError::UnterminatedBlockComment{ .. } => {
+                return Completeness::InComment{ line, offset };
+            }
+            PretokError::UnterminatedRegion{ .. } => {
+                return Completeness::InRegion{ line, offset };
+            }
+            _ => {}
+        }
+    }
+    match open.last() {
+        Some(&(ch, offset, line)) => Completeness::InBracket{ ch, line, offset },
+        None => Completeness::Complete,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complete_test_1() {
+        use Completeness::*;
+        assert_eq!(is_complete(""), Complete);
+        assert_eq!(is_complete("a) ]"), Complete);
+        assert_eq!(is_complete("{ ( [ ) x"), InBracket{ ch: '{', line: 1, offset: 0 });
+        assert_eq!(is_complete("{ (\n\"a\nb"), InString{ line: 2, offset: 4 });
+        assert_eq!(is_complete("( /* )\n"), InComment{ line: 1, offset: 2 });
+        assert_eq!(is_complete("( \"/*\" // (\n"), InBracket{ ch: '(', line: 1, offset: 0 });
+        let cfg = Config::new().skip_region("#if 0", "#endif");
+        assert_eq!(is_complete_with("x\n#if 0\n", cfg), InRegion{ line: 2, offset: 2 });
+        let cfg = Config::new().nested_comments(true);
+        assert_eq!(is_complete_with("/* /* */", cfg), InComment{ line: 1, offset: 0 });
+        assert!(!is_complete("(").is_complete());
+    }
+}
@@ -0,0 +1,1033 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Optional behaviors for the [Pretokenizer](struct.Pretokenizer.html).
+
+/// Configuration for a [Pretokenizer](struct.Pretokenizer.html).
+///
+/// The default configuration matches the behavior of
+/// [Pretokenizer::new()](struct.Pretokenizer.html#method.new).  Each option
+/// is enabled with a chained setter.
+/// ```
+/// use pretok::{Config, Pretokenizer};
+/// let cfg = Config::new().trigraphs(true);
+/// let mut pt = Pretokenizer::with_config("\"a??/\"b\"", cfg);
+/// assert_eq!(pt.next().unwrap().s, "\"a??/\"b\"");
+/// assert!(pt.next().is_none());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    pub(crate) trigraphs: bool,
+    pub(crate) directives: bool,
+    pub(crate) shebang: Shebang,
+    pub(crate) bom: Bom,
+    pub(crate) skip_regions: Vec<(String, String)>,
+    pub(crate) column_comments: Vec<(char, Option<usize>)>,
+    pub(crate) line_comments: Vec<String>,
+    pub(crate) keyword_comments: Vec<String>,
+    pub(crate) plain_slashes: bool,
+    pub(crate) literal_quotes: String,
+    pub(crate) triple_quotes: bool,
+    pub(crate) quotes: String,
+    pub(crate) block_comments_only: bool,
+    pub(crate) url_tokens: bool,
+    pub(crate) invalid_tokens: bool,
+    pub(crate) line_continuation: bool,
+    pub(crate) recipe_tabs: bool,
+    pub(crate) pragmas: Vec<String>,
+    pub(crate) tool_directives: Option<(String, String)>,
+    pub(crate) max_token_len: Option<usize>,
+    pub(crate) max_line_len: Option<usize>,
+    pub(crate) max_tokens: Option<usize>,
+    pub(crate) max_bytes: Option<usize>,
+    pub(crate) nested_comments: bool,
+    pub(crate) max_comment_depth: Option<usize>,
+    pub(crate) detect_bidi: bool,
+    pub(crate) detect_non_ascii: bool,
+    pub(crate) allow_non_ascii: String,
+    pub(crate) punctuation: String,
+    pub(crate) operators: Vec<String>,
+    pub(crate) split_numbers: bool,
+    pub(crate) separators: String,
+    pub(crate) escaped_spaces: bool,
+    pub(crate) detect_control_chars: bool,
+    pub(crate) detect_whitespace: bool,
+    pub(crate) detect_line_endings: bool,
+    pub(crate) detect_mixed_indent: bool,
+    pub(crate) zero_width: ZeroWidth,
+    pub(crate) nul: Nul,
+    #[cfg(feature = "graphemes")]
+    pub(crate) graphemes: bool,
+    #[cfg(feature = "xid")]
+    pub(crate) xid_words: bool,
+    pub(crate) lossless: bool,
+    pub(crate) lazy_lines: bool,
+    pub(crate) offsets_only: bool,
+    pub(crate) trace: bool,
+    pub(crate) base: Base,
+}
+
+/// Whether positions count from 0, as in the Language Server Protocol, or
+/// from 1, as in compiler messages.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Base {
+    /// The first line is line 0.
+    Zero,
+    /// The first line is line 1.  This is the default.
+    #[default]
+    One,
+}
+
+/// How a `#!` line at the very start of the input is handled.  A `#![` start
+/// is never a shebang, so Rust inner attributes are unaffected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Shebang {
+    /// The line is ordinary input.  This is the default.
+    #[default]
+    Text,
+    /// The line is skipped like a comment.
+    Skip,
+    /// The line is returned as a single pretoken of kind
+    /// [Kind::Shebang](enum.Kind.html#variant.Shebang).
+    Token,
+}
+
+/// How a U+FEFF byte order mark at the very start of the input is handled.
+/// Text exported by some editors starts with one, though UTF-8 has no byte
+/// order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Bom {
+    /// The mark is skipped like a comment.  This is the default.
+    #[default]
+    Skip,
+    /// The mark is ordinary input, so it starts the first pretoken.
+    Text,
+    /// The mark is returned as a pretoken of kind
+    /// [Kind::Bom](enum.Kind.html#variant.Bom).
+    Token,
+}
+
+/// How zero-width characters are handled: the zero-width space U+200B,
+/// non-joiner U+200C and joiner U+200D, and U+FEFF after the start of the
+/// input.  They are invisible in most editors, yet make otherwise equal
+/// words differ.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ZeroWidth {
+    /// The characters are ordinary input.  This is the default.
+    #[default]
+    Keep,
+    /// The characters are skipped like spaces, so they are never part of a
+    /// word.  One inside a word splits it.
+    Strip,
+    /// Each one in a word pretoken is reported as a
+    /// [PretokError::ZeroWidth](enum.PretokError.html#variant.ZeroWidth)
+    /// diagnostic.
+    Diagnose,
+}
+
+/// How U+0000 NUL characters are handled.  NULs in text are almost always
+/// a sign of binary or corrupted input.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Nul {
+    /// NULs are ordinary input.  This is the default.
+    #[default]
+    Keep,
+    /// NULs are skipped like spaces.
+    Whitespace,
+    /// Each NUL anywhere in the input is reported as a
+    /// [PretokError::Nul](enum.PretokError.html#variant.Nul) diagnostic,
+    /// which [Pretokenizer::strict()](struct.Pretokenizer.html#method.strict)
+    /// turns into an error.
+    Reject,
+}
+
+impl Config {
+    /// Create the default configuration.
+    pub fn new() -> Config {
+        Config::default()
+    }
+
+    /// A configuration for JSON with comments, as in `tsconfig.json` and
+    /// editor settings files: `//` and `/* */` comments, strings in double
+    /// quotes with backslash escapes, and `{`, `}`, `[`, `]`, `:` and `,`
+    /// as pretokens of their own.  Control characters, which JSON doesn't
+    /// allow unescaped, are reported as by
+    /// [detect_control_chars()](#method.detect_control_chars).  Further
+    /// options can be chained as usual.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let src = "{\"a\": [1, -2.5e3], // list\n \"b:c\":null}";
+    /// let v: Vec<_> = Pretokenizer::with_config(src, Config::jsonc()).map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["{", "\"a\"", ":", "[", "1", ",", "-2.5e3", "]", ",",
+    ///                    "\"b:c\"", ":", "null", "}"]);
+    /// ```
+    pub fn jsonc() -> Config {
+        Config::new().punctuation("{}[]:,").detect_control_chars(true)
+    }
+
+    /// A configuration for TOML and similar configuration files: `#`
+    /// comments, basic strings in double quotes with backslash escapes,
+    /// literal strings in single quotes without them, and both kinds in
+    /// triple quotes spanning lines.  Slashes are ordinary characters.  For
+    /// INI files, chain `.line_comment(";")` to add `;` comments.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let src = "[server] # main\nroot = '\\\\srv\\www' # unc\nmotd = '''\nIt's up.\n'''";
+    /// let v: Vec<_> = Pretokenizer::with_config(src, Config::toml()).map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["[server]", "root", "=", r"'\\srv\www'", "motd", "=",
+    ///                    "'''\nIt's up.\n'''"]);
+    /// ```
+    pub fn toml() -> Config {
+        Config::new().line_comment("#").plain_slashes(true).literal_quote('\'')
+            .triple_quotes(true)
+    }
+
+    /// A configuration for Makefiles: `#` comments, lines continued by a
+    /// backslash at their end, and a tab starting a line returned as a
+    /// pretoken of its own to mark a recipe line.  Slashes are ordinary
+    /// characters.  For Dockerfiles, where tabs are ordinary, chain
+    /// `.recipe_tabs(false)`.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let src = "# build\nall: main.o \\\n\tutil.o\n\tcc -o app $^ # link\n";
+    /// let v: Vec<_> = Pretokenizer::with_config(src, Config::makefile()).map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["all:", "main.o", "util.o", "\t", "cc", "-o", "app", "$^"]);
+    /// ```
+    pub fn makefile() -> Config {
+        Config::new().line_comment("#").plain_slashes(true).line_continuation(true)
+            .recipe_tabs(true)
+    }
+
+    /// A configuration for CSS: `/* */` comments only, strings in double or
+    /// single quotes with backslash escapes, an unquoted `url(...)` as one
+    /// pretoken, and `{`, `}`, `:`, `;` and `,` as pretokens of their own.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let src = "a:hover{background:url(//cdn.example/x.png);/* a, b */font:'A B',serif}";
+    /// let v: Vec<_> = Pretokenizer::with_config(src, Config::css()).map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["a", ":", "hover", "{", "background", ":",
+    ///                    "url(//cdn.example/x.png)", ";", "font", ":", "'A B'", ",", "serif",
+    ///                    "}"]);
+    /// ```
+    pub fn css() -> Config {
+        Config::new().block_comments_only(true).quote('\'').url_tokens(true)
+            .punctuation("{}:;,")
+    }
+
+    /// Recognize C trigraphs (`??=`, `??/`, `??'`, `??(`, `??)`, `??!`,
+    /// `??<`, `??>` and `??-`) while scanning.  Trigraphs are translated
+    /// inside the state machine only, so `??/` acts as a backslash in quoted
+    /// strings.  Returned pretokens are always slices of the original input.
+    pub fn trigraphs(mut self, enable: bool) -> Config {
+        self.trigraphs = enable;
+        self
+    }
+
+    /// Return a line whose first non-whitespace character is `#` as a single
+    /// pretoken, as C-family preprocessors treat directives.  The directive
+    /// continues across backslash-newline splices and ends before the
+    /// trailing whitespace, any line comment, or the end of the line.  The
+    /// `<...>` header name of an `#include` is kept whole, even if it holds
+    /// `//` or `/*`.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().directives(true);
+    /// let mut pt = Pretokenizer::with_config("#define X \\\n  1 // one\nX", cfg.clone());
+    /// assert_eq!(pt.next().unwrap().s, "#define X \\\n  1");
+    /// assert_eq!(pt.next().unwrap().s, "X");
+    /// let mut pt = Pretokenizer::with_config("#include <sys//types.h> // c", cfg);
+    /// assert_eq!(pt.next().unwrap().s, "#include <sys//types.h>");
+    /// ```
+    pub fn directives(mut self, enable: bool) -> Config {
+        self.directives = enable;
+        self
+    }
+
+    /// Select how a `#!` line at the start of the input is handled.
+    /// ```
+    /// use pretok::{Config, Kind, Pretokenizer, Shebang};
+    /// let cfg = Config::new().shebang(Shebang::Token);
+    /// let mut pt = Pretokenizer::with_config("#!/bin/sh -e\necho", cfg);
+    /// let tok = pt.next().unwrap();
+    /// assert_eq!((tok.s, tok.kind), ("#!/bin/sh -e", Kind::Shebang));
+    /// assert_eq!(pt.next().unwrap().s, "echo");
+    /// ```
+    pub fn shebang(mut self, policy: Shebang) -> Config {
+        self.shebang = policy;
+        self
+    }
+
+    /// Select how a byte order mark at the start of the input is handled.
+    /// A shebang may follow the mark.  Offsets are unaffected, so the first
+    /// pretoken after a skipped mark starts at byte 3.
+    /// ```
+    /// use pretok::{Bom, Config, Kind, Pretokenizer};
+    /// let tok = Pretokenizer::new("\u{FEFF}int x;").next().unwrap();
+    /// assert_eq!((tok.s, tok.offset), ("int", 3));
+    ///
+    /// let mut pt = Pretokenizer::with_config("\u{FEFF}int x;", Config::new().bom(Bom::Token));
+    /// assert_eq!(pt.next().unwrap().kind, Kind::Bom);
+    /// assert_eq!(pt.next().unwrap().s, "int");
+    /// ```
+    pub fn bom(mut self, policy: Bom) -> Config {
+        self.bom = policy;
+        self
+    }
+
+    /// Register a pair of line-anchored markers.  A line whose first
+    /// non-whitespace text starts with `start` begins a region that is
+    /// skipped like a comment through the end of the line starting with a
+    /// matching `end`.  Lines starting with `start` inside the region nest.
+    /// An unterminated region is skipped to the end of input.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().skip_region("#if 0", "#endif");
+    /// let src = "a\n#if 0\n don't \"care\n#endif\nb";
+    /// let mut pt = Pretokenizer::with_config(src, cfg);
+    /// assert_eq!(pt.next().unwrap().s, "a");
+    /// let b = pt.next().unwrap();
+    /// assert_eq!((b.s, b.line), ("b", 5));
+    /// ```
+    pub fn skip_region(mut self, start: &str, end: &str) -> Config {
+        self.skip_regions.push((start.to_string(), end.to_string()));
+        self
+    }
+
+    /// Skip a line like a comment from `marker` to its end, where `marker`
+    /// starts a pretoken in character column `column`, counted from 1, or
+    /// if `column` is None, where it is the first character on its line
+    /// besides spaces and tabs.  Elsewhere `marker` is ordinary input.  May
+    /// be called more than once to add markers.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().column_comment('*', Some(1)).column_comment(';', None);
+    /// let src = "* Add one\n  LDA X*2\n  ; Done\n  RTS ;1";
+    /// let v: Vec<_> = Pretokenizer::with_config(src, cfg).map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["LDA", "X*2", "RTS", ";1"]);
+    /// ```
+    pub fn column_comment(mut self, marker: char, column: Option<usize>) -> Config {
+        self.column_comments.push((marker, column));
+        self
+    }
+
+    /// Skip from `marker` to the end of its line like a comment, as with `%`
+    /// in LaTeX, Erlang and Prolog, alongside `//` and `/* */`.  The marker
+    /// also ends a word it appears in.  May be called more than once to add
+    /// markers.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().line_comment("%");
+    /// let src = "% Preamble\n\\usepackage{amsmath} % math\n\\begin{document}%\n";
+    /// let v: Vec<_> = Pretokenizer::with_config(src, cfg).map(|t| t.s).collect();
+    /// assert_eq!(v, vec![r"\usepackage{amsmath}", r"\begin{document}"]);
+    /// ```
+    pub fn line_comment(mut self, marker: &str) -> Config {
+        if !marker.is_empty() {
+            self.line_comments.push(marker.to_string());
+        }
+        self
+    }
+
+    /// Skip from `keyword` to the end of its line like a comment, as with
+    /// `REM` in BASIC, where `keyword` starts a pretoken that starts a
+    /// statement: one preceded on its line only by whitespace, a line
+    /// number, or text ending with `:`.
+    /// The keyword matches without regard to ASCII case and must not be
+    /// followed by a letter, digit or `_`.  Elsewhere it is an ordinary
+    /// word.  Combine with [line_comment()](#method.line_comment) for the
+    /// `'` comments of the same languages.  May be called more than once to
+    /// add keywords.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().keyword_comment("REM").line_comment("'");
+    /// let src = "10 rem Start\n20 X = 1: REM one\n30 PRINT REM ' remark\n40 REMARK";
+    /// let v: Vec<_> = Pretokenizer::with_config(src, cfg).map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["10", "20", "X", "=", "1", ":", "30", "PRINT", "REM", "40", "REMARK"]);
+    /// ```
+    pub fn keyword_comment(mut self, keyword: &str) -> Config {
+        if !keyword.is_empty() {
+            self.keyword_comments.push(keyword.to_string());
+        }
+        self
+    }
+
+    /// Treat `/` as an ordinary character, so that `//` and `/* */` don't
+    /// start comments, for formats with other comment markers.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().plain_slashes(true).line_comment("#");
+    /// let v: Vec<_> = Pretokenizer::with_config("/usr//lib/* # dirs", cfg).map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["/usr//lib/*"]);
+    /// ```
+    pub fn plain_slashes(mut self, enable: bool) -> Config {
+        self.plain_slashes = enable;
+        self
+    }
+
+    /// Start a string without escapes at `quote` where it starts a pretoken,
+    /// as `'` does in TOML and shells.  The string ends at the next `quote`,
+    /// and backslashes in it are ordinary.  Elsewhere `quote` is part of a
+    /// word.  May be called more than once to add quotes.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().literal_quote('\'');
+    /// let src = r#"path = 'C:\dir\' "it's" don't"#;
+    /// let v: Vec<_> = Pretokenizer::with_config(src, cfg).map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["path", "=", r"'C:\dir\'", "\"it's\"", "don't"]);
+    /// ```
+    pub fn literal_quote(mut self, quote: char) -> Config {
+        self.literal_quotes.push(quote);
+        self
+    }
+
+    /// Let three quotes in a row open a string that ends at the next three
+    /// of the same quote, as in TOML and Python, so that single quotes may
+    /// appear in it unescaped.  Up to two more quotes right before the
+    /// closing three belong to the string.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().triple_quotes(true);
+    /// let src = "doc = \"\"\"Say \"hi\".\n\"\"\"\"\n";
+    /// let v: Vec<_> = Pretokenizer::with_config(src, cfg).map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["doc", "=", "\"\"\"Say \"hi\".\n\"\"\"\""]);
+    /// ```
+    pub fn triple_quotes(mut self, enable: bool) -> Config {
+        self.triple_quotes = enable;
+        self
+    }
+
+    /// Start a string with backslash escapes at `quote` where it starts a
+    /// pretoken, as `"` does.  The string ends at the next unescaped
+    /// `quote`.  Elsewhere `quote` is part of a word.  May be called more
+    /// than once to add quotes.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().quote('\'');
+    /// let src = r#"'it\'s' "a'b" c'd"#;
+    /// let v: Vec<_> = Pretokenizer::with_config(src, cfg).map(|t| t.s).collect();
+    /// assert_eq!(v, vec![r"'it\'s'", "\"a'b\"", "c'd"]);
+    /// ```
+    pub fn quote(mut self, quote: char) -> Config {
+        self.quotes.push(quote);
+        self
+    }
+
+    /// Let `//` start no comment, keeping `/* */` comments, for formats
+    /// such as CSS where `//` appears in ordinary text.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().block_comments_only(true);
+    /// let v: Vec<_> = Pretokenizer::with_config("//x a//b /* c */", cfg).map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["//x", "a//b"]);
+    /// ```
+    pub fn block_comments_only(mut self, enable: bool) -> Config {
+        self.block_comments_only = enable;
+        self
+    }
+
+    /// Keep an unquoted CSS `url(...)` through its `)` as one pretoken,
+    /// however its contents would split otherwise, such as by
+    /// [punctuation()](#method.punctuation).  The `url(` is matched without
+    /// regard to case, and text before it in a word is a pretoken of its
+    /// own.  A URL can't hold unescaped blanks, so one with blanks isn't
+    /// kept whole.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().url_tokens(true).punctuation(":;,");
+    /// let src = "src:URL(data:font/woff;base64,AA==),url(\"b.woff\")";
+    /// let v: Vec<_> = Pretokenizer::with_config(src, cfg).map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["src", ":", "URL(data:font/woff;base64,AA==)", ",", "url(",
+    ///                    "\"b.woff\"", ")"]);
+    /// ```
+    pub fn url_tokens(mut self, enable: bool) -> Config {
+        self.url_tokens = enable;
+        self
+    }
+
+    /// Treat a backslash at the end of a line as joining the line to the
+    /// next, as in shells and build files.  The backslash and the newline
+    /// end any word before them and are skipped as whitespace, so no
+    /// newline is reported between the lines, though lines are still
+    /// counted.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().line_continuation(true);
+    /// let mut pt = Pretokenizer::with_config("RUN apt-get update \\\n  && make\\\nall", cfg);
+    /// let v: Vec<_> = pt.by_ref().map(|t| (t.s, t.line)).collect();
+    /// assert_eq!(v, vec![("RUN", 1), ("apt-get", 1), ("update", 1), ("&&", 2), ("make", 2),
+    ///                    ("all", 3)]);
+    /// ```
+    pub fn line_continuation(mut self, enable: bool) -> Config {
+        self.line_continuation = enable;
+        self
+    }
+
+    /// Return a tab at the start of a line as a pretoken of its own, of
+    /// kind [Kind::Word](enum.Kind.html#variant.Word), as Makefiles use it
+    /// to mark the lines of a recipe.  Only the first tab is returned, and
+    /// a tab after a continued line doesn't count.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().recipe_tabs(true);
+    /// let v: Vec<_> = Pretokenizer::with_config("a:\n\t\tb\n  \tc", cfg).map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["a:", "\t", "b", "c"]);
+    /// ```
+    pub fn recipe_tabs(mut self, enable: bool) -> Config {
+        self.recipe_tabs = enable;
+        self
+    }
+
+    /// The byte offset in word `s` after its first character where a
+    /// `line_comment()` marker starts, if any.
+    pub(crate) fn line_comment_at(&self, s: &str) -> Option<usize> {
+        s.char_indices().skip(1).map(|(i, _)| i)
+            .find(|&i| self.line_comments.iter().any(|m| s[i..].starts_with(m.as_str())))
+    }
+
+    /// Collect each line whose first non-whitespace text starts with
+    /// `prefix` into
+    /// [Pretokenizer::pragmas()](struct.Pretokenizer.html#method.pragmas).
+    /// Collected lines are still tokenized as usual.  May be called more than
+    /// once to register several prefixes.
+    pub fn pragma(mut self, prefix: &str) -> Config {
+        self.pragmas.push(prefix.to_string());
+        self
+    }
+
+    /// Disable tokenization between a line comment reading `off` and the
+    /// next line comment reading `on`, as in `// pretok:off`.  The comment
+    /// text is compared with surrounding whitespace trimmed.  The lines in
+    /// between are returned as a single pretoken of kind
+    /// [Kind::Opaque](enum.Kind.html#variant.Opaque) without the line break
+    /// before the on marker.  A region with no on marker runs to the end of
+    /// input, line breaks included, and is reported as a
+    /// [PretokError::UnterminatedRegion](enum.PretokError.html#variant.UnterminatedRegion).
+    /// ```
+    /// use pretok::{Config, Kind, Pretokenizer};
+    /// let cfg = Config::new().tool_directives("pretok:off", "pretok:on");
+    /// let src = "a\n// pretok:off\n SELECT \"x\n// pretok:on\nb";
+    /// let toks: Vec<_> = Pretokenizer::with_config(src, cfg)
+    ///     .map(|t| (t.s, t.kind)).collect();
+    /// assert_eq!(toks, vec![("a", Kind::Word),
+    ///                       (" SELECT \"x", Kind::Opaque),
+    ///                       ("b", Kind::Word)]);
+    /// ```
+    pub fn tool_directives(mut self, off: &str, on: &str) -> Config {
+        self.tool_directives = Some((off.to_string(), on.to_string()));
+        self
+    }
+
+    /// Report a [PretokError::TokenTooLong](enum.PretokError.html#variant.TokenTooLong)
+    /// diagnostic for each pretoken longer than `max` bytes.  The pretoken is
+    /// still returned.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().max_token_len(4);
+    /// let mut strict = Pretokenizer::with_config("tiny enormous", cfg).strict();
+    /// assert!(strict.next().unwrap().is_ok());
+    /// assert!(strict.next().unwrap().is_err());
+    /// ```
+    pub fn max_token_len(mut self, max: usize) -> Config {
+        self.max_token_len = Some(max);
+        self
+    }
+
+    /// Report a [PretokError::LineTooLong](enum.PretokError.html#variant.LineTooLong)
+    /// diagnostic for each physical line longer than `max` bytes, not
+    /// counting the line break.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().max_line_len(8);
+    /// let mut pt = Pretokenizer::with_config("short\n// but not this one", cfg);
+    /// pt.by_ref().count();
+    /// assert_eq!(pt.diagnostics()[0].line(), 2);
+    /// ```
+    pub fn max_line_len(mut self, max: usize) -> Config {
+        self.max_line_len = Some(max);
+        self
+    }
+
+    /// Stop after returning `max` pretokens.  If more input remains, a
+    /// [PretokError::BudgetExhausted](enum.PretokError.html#variant.BudgetExhausted)
+    /// diagnostic is reported and
+    /// [Pretokenizer::budget_exhausted()](struct.Pretokenizer.html#method.budget_exhausted)
+    /// returns true.  Use this as a hard stop when pretokenizing untrusted
+    /// input.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().max_tokens(1);
+    /// let v: Vec<_> = Pretokenizer::with_config("a b c", cfg).map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["a"]);
+    /// ```
+    pub fn max_tokens(mut self, max: usize) -> Config {
+        self.max_tokens = Some(max);
+        self
+    }
+
+    /// Stop before any pretoken or comment that extends past the first `max`
+    /// bytes of input, reporting the rest as with
+    /// [Config::max_tokens()](struct.Config.html#method.max_tokens).
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().max_bytes(4);
+    /// let mut strict = Pretokenizer::with_config("a bcdef", cfg).strict();
+    /// assert_eq!(strict.next().unwrap().unwrap().s, "a");
+    /// assert_eq!(strict.next().unwrap().unwrap_err().span(), 2..7);
+    /// assert!(strict.next().is_none());
+    /// ```
+    pub fn max_bytes(mut self, max: usize) -> Config {
+        self.max_bytes = Some(max);
+        self
+    }
+
+    /// Allow block comments to nest, as in Rust and Swift, so that
+    /// `/* a /* b */ c */` is a single comment.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().nested_comments(true);
+    /// let mut pt = Pretokenizer::with_config("/* a /* b */ c */ d", cfg);
+    /// assert_eq!(pt.next().unwrap().s, "d");
+    /// ```
+    pub fn nested_comments(mut self, enable: bool) -> Config {
+        self.nested_comments = enable;
+        self
+    }
+
+    /// Limit the nesting depth of nested block comments to `max`, where the
+    /// outermost comment has depth 1.  Openers beyond the limit don't nest,
+    /// and the first in each comment is reported as a
+    /// [PretokError::CommentTooDeep](enum.PretokError.html#variant.CommentTooDeep)
+    /// diagnostic.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().nested_comments(true).max_comment_depth(1);
+    /// let mut pt = Pretokenizer::with_config("/* /* */ x", cfg);
+    /// assert_eq!(pt.next().unwrap().s, "x");
+    /// assert_eq!(pt.diagnostics()[0].span(), 3..5);
+    /// ```
+    pub fn max_comment_depth(mut self, max: usize) -> Config {
+        self.max_comment_depth = Some(max);
+        self
+    }
+
+    /// Report each Unicode bidirectional control character (U+202A through
+    /// U+202E and U+2066 through U+2069) anywhere in the input as a
+    /// [PretokError::BidiControl](enum.PretokError.html#variant.BidiControl)
+    /// diagnostic.  These characters can make comments and strings display
+    /// differently than they compile, as in CVE-2021-42574 "Trojan Source".
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().detect_bidi(true);
+    /// let mut pt = Pretokenizer::with_config("x // \u{202E}", cfg);
+    /// pt.by_ref().count();
+    /// assert_eq!(pt.diagnostics()[0].span(), 5..8);
+    /// ```
+    pub fn detect_bidi(mut self, enable: bool) -> Config {
+        self.detect_bidi = enable;
+        self
+    }
+
+    /// Report each non-ASCII character in a word pretoken.  Characters
+    /// commonly mistaken for ASCII, such as Cyrillic `а` or fullwidth forms,
+    /// are reported as
+    /// [PretokError::Confusable](enum.PretokError.html#variant.Confusable)
+    /// and all others as
+    /// [PretokError::NonAscii](enum.PretokError.html#variant.NonAscii).
+    /// Quoted strings are not checked.
+    /// ```
+    /// use pretok::{Config, Pretokenizer, PretokError};
+    /// let cfg = Config::new().detect_non_ascii(true);
+    /// let mut pt = Pretokenizer::with_config("sсore", cfg); // Cyrillic с
+    /// pt.by_ref().count();
+    /// assert_eq!(pt.diagnostics(), &[PretokError::Confusable{
+    ///     ch: 'с', looks_like: 'c', line: 1, span: 1..3}]);
+    /// ```
+    pub fn detect_non_ascii(mut self, enable: bool) -> Config {
+        self.detect_non_ascii = enable;
+        self
+    }
+
+    /// Add the characters of `chars` to those allowed by
+    /// [detect_non_ascii()](#method.detect_non_ascii).
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().detect_non_ascii(true).allow_non_ascii("λμ");
+    /// let mut pt = Pretokenizer::with_config("λx.μ", cfg);
+    /// pt.by_ref().count();
+    /// assert!(pt.diagnostics().is_empty());
+    /// ```
+    pub fn allow_non_ascii(mut self, chars: &str) -> Config {
+        self.allow_non_ascii.push_str(chars);
+        self
+    }
+
+    /// Return each of the characters of `chars` that would be part of a word
+    /// as a pretoken of its own, so the pretokens are complete tokens for
+    /// languages whose operators are all one character.  May be called more
+    /// than once to add characters.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().punctuation("+;=");
+    /// let v: Vec<_> = Pretokenizer::with_config("x+y; z==\"+\"", cfg).map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["x", "+", "y", ";", "z", "=", "=", "\"+\""]);
+    /// ```
+    pub fn punctuation(mut self, chars: &str) -> Config {
+        self.punctuation.push_str(chars);
+        self
+    }
+
+    /// Return each of `ops` as one pretoken where
+    /// [punctuation()](#method.punctuation) would split it, taking the
+    /// longest operator that matches.  The characters of `ops` are added to
+    /// the punctuation.  May be called more than once to add operators.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().punctuation("();").operators(&["=", "==", "<<=", "<", "->"]);
+    /// let v: Vec<_> = Pretokenizer::with_config("p->x<<=(a==b);", cfg).map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["p", "->", "x", "<<=", "(", "a", "==", "b", ")", ";"]);
+    /// ```
+    pub fn operators(mut self, ops: &[&str]) -> Config {
+        for op in ops.iter().filter(|op| !op.is_empty()) {
+            self.punctuation.push_str(op);
+            self.operators.push(op.to_string());
+        }
+        self
+    }
+
+    /// Keep a space escaped with a backslash in a word, as a shell does,
+    /// instead of ending the word.  A backslash escaped with another
+    /// backslash escapes nothing.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().escaped_spaces(true);
+    /// let v: Vec<_> = Pretokenizer::with_config(r"cp My\ File.txt \\ \ x", cfg)
+    ///     .map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["cp", r"My\ File.txt", r"\\", r"\ x"]);
+    /// ```
+    pub fn escaped_spaces(mut self, enable: bool) -> Config {
+        self.escaped_spaces = enable;
+        self
+    }
+
+    /// Split numeric literals like other words when words are split by
+    /// [punctuation()](#method.punctuation),
+    /// [Pretokenizer::word_chars()](struct.Pretokenizer.html#method.word_chars)
+    /// or `xid_words()`.  By default a number at the start of a word, such
+    /// as `1.5e+10`, `0x1p-3` or `1_000`, is one pretoken.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().punctuation("+-.;");
+    /// let v: Vec<_> = Pretokenizer::with_config("x+1.5e-3;", cfg.clone()).map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["x", "+", "1.5e-3", ";"]);
+    /// let v: Vec<_> = Pretokenizer::with_config("x+1.5e-3;", cfg.split_numbers(true))
+    ///     .map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["x", "+", "1", ".", "5e", "-", "3", ";"]);
+    /// ```
+    pub fn split_numbers(mut self, enable: bool) -> Config {
+        self.split_numbers = enable;
+        self
+    }
+
+    /// Skip each of the characters of `chars` like a space, so that it ends a
+    /// word, as `,` does in some data formats.  Characters the scanner
+    /// gives a meaning, such as quotes, slashes and line breaks, are not
+    /// affected.  May be called more than once to add characters.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().separators(",;");
+    /// let v: Vec<_> = Pretokenizer::with_config("1,2;; \"a,b\",3", cfg).map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["1", "2", "\"a,b\"", "3"]);
+    /// ```
+    pub fn separators(mut self, chars: &str) -> Config {
+        self.separators.push_str(chars);
+        self
+    }
+
+    /// The byte offset in word `s` where a character of `punctuation()`,
+    /// or the longest of `operators()` there, starts or ends, if any.
+    pub(crate) fn punct_end(&self, s: &str) -> Option<usize> {
+        match s.char_indices().find(|&(_, c)| self.punctuation.contains(c))? {
+            (0, c) => {
+                let op = self.operators.iter().filter(|op| s.starts_with(op.as_str()))
+                    .map(|op| op.len()).max();
+                Some(op.unwrap_or(c.len_utf8())).filter(|&n| n < s.len())
+            }
+            (i, _) => Some(i),
+        }
+    }
+
+    /// Report each C0 control character other than tab, line feed and
+    /// carriage return, and each DEL, inside a pretoken as a
+    /// [PretokError::ControlChar](enum.PretokError.html#variant.ControlChar)
+    /// diagnostic.  Use
+    /// [Pretokenizer::strict()](struct.Pretokenizer.html#method.strict) to
+    /// reject such input.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().detect_control_chars(true);
+    /// let mut strict = Pretokenizer::with_config("\"nul\u{0}\"", cfg).strict();
+    /// assert_eq!(strict.next().unwrap().unwrap_err().span(), 4..5);
+    /// ```
+    pub fn detect_control_chars(mut self, enable: bool) -> Config {
+        self.detect_control_chars = enable;
+        self
+    }
+
+    /// Return each run of characters that belong in no pretoken, outside
+    /// strings and comments, as a pretoken of kind
+    /// [Kind::Invalid](enum.Kind.html#variant.Invalid) rather than as part
+    /// of the word around it, so a parser can report one error for it.
+    /// These are the control characters reported by
+    /// [detect_control_chars()](#method.detect_control_chars), and U+FFFD,
+    /// which stands for invalid UTF-8 in text decoded lossily.
+    /// ```
+    /// use pretok::{Config, Kind, Pretokenizer};
+    /// let cfg = Config::new().invalid_tokens(true);
+    /// let v: Vec<_> = Pretokenizer::with_config("ab\u{fffd}\u{1}c \"\u{1}\"", cfg)
+    ///     .map(|t| (t.kind, t.s)).collect();
+    /// assert_eq!(v, vec![(Kind::Word, "ab"), (Kind::Invalid, "\u{fffd}\u{1}"), (Kind::Word, "c"),
+    ///                    (Kind::Quoted, "\"\u{1}\"")]);
+    /// ```
+    pub fn invalid_tokens(mut self, enable: bool) -> Config {
+        self.invalid_tokens = enable;
+        self
+    }
+
+    /// Report whitespace that style checkers flag:
+    /// [PretokError::TrailingWhitespace](enum.PretokError.html#variant.TrailingWhitespace)
+    /// at the end of a line, including inside comments,
+    /// [PretokError::WhitespaceOnlyLine](enum.PretokError.html#variant.WhitespaceOnlyLine)
+    /// for a line holding nothing else, and
+    /// [PretokError::TabAfterSpace](enum.PretokError.html#variant.TabAfterSpace)
+    /// for indentation with a tab after a space.  Whitespace in strings is
+    /// not checked.
+    /// ```
+    /// use pretok::{Config, Pretokenizer, PretokError};
+    /// let cfg = Config::new().detect_whitespace(true);
+    /// let mut pt = Pretokenizer::with_config("a \n  \n \tb // c \n", cfg);
+    /// pt.by_ref().count();
+    /// assert_eq!(pt.diagnostics(), &[
+    ///     PretokError::TrailingWhitespace{line: 1, span: 1..2},
+    ///     PretokError::WhitespaceOnlyLine{line: 2, span: 3..5},
+    ///     PretokError::TabAfterSpace{line: 3, span: 6..8},
+    ///     PretokError::TrailingWhitespace{line: 3, span: 14..15}]);
+    /// ```
+    pub fn detect_whitespace(mut self, enable: bool) -> Config {
+        self.detect_whitespace = enable;
+        self
+    }
+
+    /// Report indentation holding both spaces and tabs, in either order, as
+    /// a [PretokError::MixedIndentation](enum.PretokError.html#variant.MixedIndentation)
+    /// diagnostic.  Lines holding only whitespace are not checked.
+    /// ```
+    /// use pretok::{Config, Pretokenizer, PretokError};
+    /// let cfg = Config::new().detect_mixed_indent(true);
+    /// let mut pt = Pretokenizer::with_config("if x:\n\t  y\n  \t\n\tz", cfg);
+    /// pt.by_ref().count();
+    /// assert_eq!(pt.diagnostics(), &[PretokError::MixedIndentation{line: 2, span: 6..9}]);
+    /// ```
+    pub fn detect_mixed_indent(mut self, enable: bool) -> Config {
+        self.detect_mixed_indent = enable;
+        self
+    }
+
+    /// Report the first line ending that differs from the first line ending
+    /// in the input, where `\n`, `\r\n` and a lone `\r` are the three
+    /// kinds, as a
+    /// [PretokError::MixedLineEndings](enum.PretokError.html#variant.MixedLineEndings)
+    /// diagnostic.
+    /// ```
+    /// use pretok::{Config, Pretokenizer, PretokError};
+    /// let cfg = Config::new().detect_line_endings(true);
+    /// let mut pt = Pretokenizer::with_config("a\r\nb\nc\n", cfg);
+    /// pt.by_ref().count();
+    /// assert_eq!(pt.diagnostics(), &[PretokError::MixedLineEndings{line: 2, span: 4..5}]);
+    /// ```
+    pub fn detect_line_endings(mut self, enable: bool) -> Config {
+        self.detect_line_endings = enable;
+        self
+    }
+
+    /// End pretokens, whitespace and comments only between grapheme
+    /// clusters, so that combining marks after a closing quote, a space or
+    /// the end of a comment stay with it instead of starting a word.
+    /// Scanning is still by code point, and only non-ASCII characters are
+    /// taken in.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().graphemes(true);
+    /// let v: Vec<_> = Pretokenizer::with_config("\"e\"\u{301} x", cfg).map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["\"e\"\u{301}", "x"]);
+    /// ```
+    #[cfg(feature = "graphemes")]
+    pub fn graphemes(mut self, enable: bool) -> Config {
+        self.graphemes = enable;
+        self
+    }
+
+    /// End a word pretoken where a run of Unicode identifier characters
+    /// (XID_Continue, such as letters, digits, `_` and combining marks)
+    /// meets a run of other characters, so operators and punctuation
+    /// become pretokens of their own.  Strings, comments and directives are
+    /// unaffected.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().xid_words(true);
+    /// let v: Vec<_> = Pretokenizer::with_config("f\u{e9}e+=bar(1);", cfg).map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["f\u{e9}e", "+=", "bar", "(", "1", ");"]);
+    /// ```
+    #[cfg(feature = "xid")]
+    pub fn xid_words(mut self, enable: bool) -> Config {
+        self.xid_words = enable;
+        self
+    }
+
+    /// Select how zero-width characters are handled.  Those in strings and
+    /// comments are always kept.
+    /// ```
+    /// use pretok::{Config, Pretokenizer, PretokError, ZeroWidth};
+    /// let src = "x\u{200B} = y\u{200D}z;";
+    /// let cfg = Config::new().zero_width(ZeroWidth::Strip);
+    /// let v: Vec<_> = Pretokenizer::with_config(src, cfg).map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["x", "=", "y", "z;"]);
+    ///
+    /// let cfg = Config::new().zero_width(ZeroWidth::Diagnose);
+    /// let mut pt = Pretokenizer::with_config(src, cfg);
+    /// pt.by_ref().count();
+    /// assert_eq!(pt.diagnostics()[1], PretokError::ZeroWidth{
+    ///     ch: '\u{200D}', line: 1, span: 8..11});
+    /// ```
+    pub fn zero_width(mut self, policy: ZeroWidth) -> Config {
+        self.zero_width = policy;
+        self
+    }
+
+    /// Select how NUL characters are handled.
+    /// ```
+    /// use pretok::{Config, Nul, Pretokenizer, PretokError};
+    /// let src = "a\0b // \0";
+    /// let v: Vec<_> = Pretokenizer::with_config(src, Config::new().nul(Nul::Whitespace))
+    ///     .map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["a", "b"]);
+    ///
+    /// let mut strict = Pretokenizer::with_config(src, Config::new().nul(Nul::Reject)).strict();
+    /// assert_eq!(strict.next().unwrap(), Err(PretokError::Nul{line: 1, span: 1..2}));
+    /// ```
+    pub fn nul(mut self, policy: Nul) -> Config {
+        self.nul = policy;
+        self
+    }
+
+    /// Return whitespace, newlines and comments as pretokens too, with
+    /// kinds for which [Kind::is_trivia()](enum.Kind.html#method.is_trivia)
+    /// is true, so that concatenating every pretoken reproduces the input
+    /// byte for byte.  Checks such as
+    /// [Config::max_token_len()](struct.Config.html#method.max_token_len)
+    /// skip these pretokens.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let src = "x = 1; // one\n";
+    /// let pt = Pretokenizer::with_config(src, Config::new().lossless(true));
+    /// assert_eq!(pt.map(|t| t.s).collect::<String>(), src);
+    /// ```
+    pub fn lossless(mut self, enable: bool) -> Config {
+        self.lossless = enable;
+        self
+    }
+
+    /// Don't count lines while scanning.  Pretokens are returned with a
+    /// `line` of 0, and
+    /// [Pretokenizer::line_at()](struct.Pretokenizer.html#method.line_at)
+    /// finds the line of an offset on demand.  Diagnostics,
+    /// [Pretokenizer::line()](struct.Pretokenizer.html#method.line) and
+    /// [Pretokenizer::stats()](struct.Pretokenizer.html#method.stats) still
+    /// report lines.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let mut pt = Pretokenizer::with_config("a\n\nb", Config::new().lazy_lines(true));
+    /// let b = pt.nth(1).unwrap();
+    /// assert_eq!(b.line, 0);
+    /// assert_eq!(pt.line_at(b.offset), 3);
+    /// ```
+    pub fn lazy_lines(mut self, enable: bool) -> Config {
+        self.lazy_lines = enable;
+        self
+    }
+
+    /// Don't track lines at all, for the most throughput when only byte
+    /// offsets matter.  As with
+    /// [Config::lazy_lines()](struct.Config.html#method.lazy_lines),
+    /// pretokens have a `line` of 0, but so do diagnostics and
+    /// [Pretokenizer::line()](struct.Pretokenizer.html#method.line).
+    /// [Pretokenizer::line_at()](struct.Pretokenizer.html#method.line_at)
+    /// still maps an offset to its line when asked.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let mut pt = Pretokenizer::with_config("a\n\"b", Config::new().offsets_only(true));
+    /// pt.by_ref().count();
+    /// assert_eq!((pt.line(), pt.diagnostics()[0].line()), (0, 0));
+    /// ```
+    pub fn offsets_only(mut self, enable: bool) -> Config {
+        self.offsets_only = enable;
+        self
+    }
+
+    /// Record each change of scanner state, for
+    /// [Pretokenizer::trace()](struct.Pretokenizer.html#method.trace).
+    /// When a corner case splits wrongly, the trace shows which character
+    /// sent the scanner where.  Recording slows scanning and keeps every
+    /// change, so leave it off otherwise.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let mut pt = Pretokenizer::with_config("x", Config::new().trace(true));
+    /// pt.next();
+    /// assert_eq!(pt.trace().len(), 2);
+    /// ```
+    pub fn trace(mut self, enable: bool) -> Config {
+        self.trace = enable;
+        self
+    }
+
+    /// Number lines from 0 or from 1, in pretokens, diagnostics and
+    /// [Pretokenizer::line()](struct.Pretokenizer.html#method.line).
+    /// Lines are numbered from 1 by default.
+    /// ```
+    /// use pretok::{Base, Config, Pretokenizer};
+    /// let v: Vec<_> = Pretokenizer::with_config("a\nb", Config::new().base(Base::Zero))
+    ///     .map(|t| (t.s, t.line)).collect();
+    /// assert_eq!(v, vec![("a", 0), ("b", 1)]);
+    /// ```
+    pub fn base(mut self, base: Base) -> Config {
+        self.base = base;
+        self
+    }
+
+    /// The number of the first line.
+    pub(crate) fn first_line(&self) -> usize {
+        match self.base {
+            Base::Zero => 0,
+            Base::One => 1,
+        }
+    }
+
+    /// True if lines are counted while scanning.
+    pub(crate) fn counts_lines(&self) -> bool {
+        !self.lazy_lines && !self.offsets_only
+    }
+}
+
+/// Return the character represented by the trigraph `??c`, if any.
+pub(crate) fn trigraph(c: char) -> Option<char> {
+    match c {
+        '=' => Some('#'),
+        '/' => Some('\\'),
+        '\'' => Some('^'),
+        '(' => Some('['),
+        ')' => Some(']'),
+        '!' => Some('|'),
+        '<' => Some('{'),
+        '>' => Some('}'),
+        '-' => Some('~'),
+        _ => None,
+    }
+}
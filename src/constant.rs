@@ -0,0 +1,184 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Pretokenizing string constants at compile time.
+
+use crate::{Kind, Pretoken};
+
+/// Pretokenize a string constant at compile time with the default
+/// configuration, giving an array of its pretokens, the same as
+/// [tokenize()](fn.tokenize.html) gives at run time.  For fixed snippets,
+/// such as those of an embedded DSL, nothing is left to do at run time.
+/// The argument may be any constant expression of type `&str`.
+/// ```
+/// use pretok::{pretok, Kind, Pretoken};
+/// const PROLOGUE: &[Pretoken] = &pretok!("push rbp /* save */\nmov rbp, rsp");
+/// assert_eq!(PROLOGUE.len(), 5);
+/// assert_eq!(PROLOGUE[2], Pretoken{ s: "mov", line: 2, offset: 20, kind: Kind::Word });
+/// assert_eq!(&pretok!(concat!("a ", "\"b c\""))[..], &pretok::tokenize("a \"b c\"")[..]);
+/// ```
+#[macro_export]
+macro_rules! pretok {
+    ($s:expr) => {{
+        const PRETOK_SRC: &str = $s;
+        const PRETOKENS: [$crate::Pretoken<'static>; $crate::const_count(PRETOK_SRC)] =
+            $crate::const_tokenize(PRETOK_SRC);
+        PRETOKENS
+    }};
+}
+
+/// The number of pretokens in `s` with the default configuration, as a
+/// `const fn` to size the array for
+/// [const_tokenize()](fn.const_tokenize.html).
+/// ```
+/// const N: usize = pretok::const_count("a /* b */ \"c d\"");
+/// assert_eq!(N, 2);
+/// ```
+pub const fn const_count(s: &str) -> usize {
+    let b = s.as_bytes();
+    let (mut n, mut pos, mut line) = (0, 0, 1);
+    while let Some((_, end, end_line, _)) = next_pretoken(b, pos, line) {
+        n += 1;
+        pos = end;
+        line = end_line;
+    }
+    n
+}
+
+/// The pretokens of `s` with the default configuration, as a `const fn`.
+/// `N` must be the number of pretokens, as given by
+/// [const_count()](fn.const_count.html), or evaluation fails.  The
+/// [pretok!](macro.pretok.html) macro works out `N` itself.
+/// ```
+/// use pretok::Pretoken;
+/// const SRC: &str = "x = 1; // one";
+/// const TOKS: [Pretoken; pretok::const_count(SRC)] = pretok::const_tokenize(SRC);
+/// assert_eq!(TOKS.iter().map(|t| t.s).collect::<Vec<_>>(), vec!["x", "=", "1;"]);
+/// ```
+pub const fn const_tokenize<const N: usize>(s: &str) -> [Pretoken<'_>; N] {
+    const EMPTY: Pretoken<'static> = Pretoken{ s: "", line: 0, offset: 0, kind: Kind::Word };
+    let b = s.as_bytes();
+    let mut toks = [EMPTY; N];
+    let (mut n, mut pos, mut line) = (0, 0, 1);
+    while let Some((start, end, end_line, kind)) = next_pretoken(b, pos, line) {
+        assert!(n < N, "more pretokens than N");
+        toks[n] = Pretoken{ s: slice(s, start, end), line: end_line, offset: start, kind };
+        n += 1;
+        pos = end;
+        line = end_line;
+    }
+    assert!(n == N, "fewer pretokens than N");
+    toks
+}
+
+/// The next pretoken in `b` at or after `pos`, on line `line`, as its
+/// start, end, last line and kind.
+const fn next_pretoken(b: &[u8], mut pos: usize, mut line: usize)
+    -> Option<(usize, usize, usize, Kind)> {
+    // A byte order mark is skipped.
+    if pos == 0 && b.len() >= 3 && b[0] == 0xEF && b[1] == 0xBB && b[2] == 0xBF {
+        pos = 3;
+    }
+    while pos < b.len() {
+        let (start, c) = (pos, b[pos]);
+        let comment = c == b'/' && pos + 1 < b.len() && (b[pos + 1] == b'/' || b[pos + 1] == b'*');
+        pos += 1;
+        if c == b' ' || c == b'\t' {
+            continue;
+        }
+        if c == b'\n' {
+            line += 1;
+            continue;
+        }
+        if comment && b[pos] == b'/' {
+            while pos < b.len() && b[pos] != b'\n' {
+                pos += 1;
+            }
+            continue;
+        }
+        if comment {
+            // The closing `*/` starts after the opening `/*`.
+            pos += 1;
+            while pos < b.len() && !(b[pos] == b'*' && pos + 1 < b.len() && b[pos + 1] == b'/') {
+                if b[pos] == b'\n' {
+                    line += 1;
+                }
+                pos += 1;
+            }
+            if pos < b.len() {
+                pos += 2;
+            }
+            continue;
+        }
+        if c == b'"' {
+            while pos < b.len() && b[pos] != b'"' {
+                if b[pos] == b'\\' && pos + 1 < b.len() {
+                    pos += 1;
+                }
+                if b[pos] == b'\n' {
+                    line += 1;
+                }
+                pos += 1;
+            }
+            if pos < b.len() {
+                pos += 1;
+            }
+            return Some((start, pos, line, Kind::Quoted));
+        }
+        while pos < b.len() {
+            let c = b[pos];
+            if c == b' ' || c == b'\t' || c == b'\n' || c == b'"' {
+                break;
+            }
+            if c == b'/' && pos + 1 < b.len() && (b[pos + 1] == b'/' || b[pos + 1] == b'*') {
+                break;
+            }
+            pos += 1;
+        }
+        return Some((start, pos, line, Kind::Word));
+    }
+    None
+}
+
+/// The text of `s` from byte `start` to byte `end`, which are character
+/// boundaries.
+const fn slice(s: &str, start: usize, end: usize) -> &str {
+    let (head, _) = s.as_bytes().split_at(end);
+    let (_, b) = head.split_at(start);
+    match std::str::from_utf8(b) {
+        Ok(s) => s,
+        Err(_) => panic!("not a character boundary"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testgen::Lcg;
+    use crate::tokenize;
+
+    #[test]
+    fn constant_test_1() {
+        let pieces = ["/", "*", "\"", "\\", "\n", " ", "\t", "a", "//", "/*", "*/",
+                      "\u{e9}", "\r", "\u{feff}"];
+        let mut rng = Lcg::new(11);
+        for _ in 0..3000 {
+            let src = rng.source(&pieces, 16);
+            let mut toks = Vec::new();
+            let (b, mut pos, mut line) = (src.as_bytes(), 0, 1);
+            while let Some((start, end, end_line, kind)) = next_pretoken(b, pos, line) {
+                toks.push(Pretoken{ s: slice(&src, start, end), line: end_line, offset: start,
+                                    kind });
+                pos = end;
+                line = end_line;
+            }
+            assert_eq!(toks, tokenize(&src), "{:?}", src);
+            assert_eq!(const_count(&src), toks.len());
+        }
+
+        const EMPTY: [Pretoken; 0] = pretok!("  // nothing\n");
+        assert!(EMPTY.is_empty());
+        const TOKS: [Pretoken; 2] = const_tokenize("\u{feff}\"a\\\"\n\" b/* c");
+        assert_eq!(TOKS.iter().map(|t| (t.s, t.line, t.offset)).collect::<Vec<_>>(),
+                   vec![("\"a\\\"\n\"", 2, 3), ("b", 2, 10)]);
+    }
+}
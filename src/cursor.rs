@@ -0,0 +1,176 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! A backtracking cursor over collected pretokens.
+
+use crate::Pretoken;
+use std::ops::Range;
+
+/// A cursor over a slice of collected pretokens, for parsers that prefer
+/// random access and backtracking to a streaming iterator.
+///
+/// `TokenCursor` is an iterator over references to the pretokens.  Save the
+/// position with `mark()` and return to it with `reset()`.
+/// ```
+/// use pretok::TokenCursor;
+/// let toks = pretok::tokenize("f ( x ) ;");
+/// let mut cur = TokenCursor::new(&toks);
+/// let m = cur.mark();
+/// assert_eq!(cur.next().unwrap().s, "f");
+/// assert_eq!(cur.next().unwrap().s, "(");
+/// cur.reset(m);
+/// assert_eq!(cur.peek().unwrap().s, "f");
+/// ```
+#[derive(Clone, Debug)]
+pub struct TokenCursor<'t, 'a> {
+    toks: &'t [Pretoken<'a>],
+    /// Index of the next pretoken
+    pos: usize,
+}
+
+/// A saved [TokenCursor](struct.TokenCursor.html) position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Mark(usize);
+
+impl<'t, 'a> TokenCursor<'t, 'a> {
+    /// Create a cursor at the first of `toks`.
+    pub fn new(toks: &'t [Pretoken<'a>]) -> TokenCursor<'t, 'a> {
+        TokenCursor{ toks, pos: 0 }
+    }
+
+    /// The next pretoken, without consuming it.
+    pub fn peek(&self) -> Option<&'t Pretoken<'a>> {
+        self.toks.get(self.pos)
+    }
+
+    /// The pretoken `i` places ahead, without consuming anything.
+    /// ```
+    /// use pretok::TokenCursor;
+    /// let toks = pretok::tokenize("a b c");
+    /// let cur = TokenCursor::new(&toks);
+    /// assert_eq!(cur.peek_n(2).unwrap().s, "c");
+    /// assert!(cur.peek_n(3).is_none());
+    /// ```
+    pub fn peek_n(&self, i: usize) -> Option<&'t Pretoken<'a>> {
+        self.toks.get(self.pos + i)
+    }
+
+    /// The index of the next pretoken.
+    pub fn index(&self) -> usize {
+        self.pos
+    }
+
+    /// True if every pretoken has been consumed.
+    pub fn is_at_end(&self) -> bool {
+        self.pos >= self.toks.len()
+    }
+
+    /// Save the current position.
+    pub fn mark(&self) -> Mark {
+        Mark(self.pos)
+    }
+
+    /// Return to a position saved by `mark()`.
+    pub fn reset(&mut self, mark: Mark) {
+        self.pos = mark.0.min(self.toks.len());
+    }
+
+    /// The pretokens consumed since `mark`.
+    /// ```
+    /// use pretok::TokenCursor;
+    /// let toks = pretok::tokenize("a b c");
+    /// let mut cur = TokenCursor::new(&toks);
+    /// cur.next();
+    /// let m = cur.mark();
+    /// cur.nth(1);
+    /// let v: Vec<_> = cur.since(m).iter().map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["b", "c"]);
+    /// ```
+    pub fn since(&self, mark: Mark) -> &'t [Pretoken<'a>] {
+        let start = mark.0.min(self.pos);
+        &self.toks[start..self.pos]
+    }
+
+    /// The pretokens not yet consumed.
+    pub fn rest(&self) -> &'t [Pretoken<'a>] {
+        &self.toks[self.pos.min(self.toks.len())..]
+    }
+
+    /// The byte span of the input covered by the pretokens consumed since
+    /// `mark`, from the start of the first to the end of the last.  If none
+    /// were consumed, the empty span at the next pretoken, or at the end of
+    /// the last one.
+    /// ```
+    /// use pretok::TokenCursor;
+    /// let src = "let x = 1 ;";
+    /// let toks = pretok::tokenize(src);
+    /// let mut cur = TokenCursor::new(&toks);
+    /// cur.next();
+    /// let m = cur.mark();
+    /// cur.nth(2);
+    /// assert_eq!(&src[cur.span_since(m)], "x = 1");
+    /// ```
+    pub fn span_since(&self, mark: Mark) -> Range<usize> {
+        match self.since(mark) {
+            [] => {
+                let at = match self.peek() {
+                    Some(tok) => tok.offset,
+                    None => self.toks.last().map_or(0, |tok| tok.offset + tok.s.len()),
+                };
+                at..at
+            }
+            [first, .., last] => first.offset..last.offset + last.s.len(),
+            [only] => only.offset..only.offset + only.s.len(),
+        }
+    }
+}
+
+impl<'t, 'a> Iterator for TokenCursor<'t, 'a> {
+    type Item = &'t Pretoken<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tok = self.toks.get(self.pos)?;
+        self.pos += 1;
+        Some(tok)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.rest().len();
+        (n, Some(n))
+    }
+}
+
+impl<'t, 'a> ExactSizeIterator for TokenCursor<'t, 'a> {}
+
+impl<'t, 'a> std::iter::FusedIterator for TokenCursor<'t, 'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_test_0() {
+        let src = "a \"b c\"\nd";
+        let toks = crate::tokenize(src);
+        let mut cur = TokenCursor::new(&toks);
+        let start = cur.mark();
+        assert_eq!(cur.span_since(start), 0..0);
+        assert_eq!(cur.len(), 3);
+        cur.next();
+        let m = cur.mark();
+        assert!(m > start);
+        assert_eq!(cur.next().unwrap().s, "\"b c\"");
+        assert_eq!(cur.span_since(m), 2..7);
+        assert_eq!(cur.index(), 2);
+        assert_eq!(cur.rest().len(), 1);
+        assert_eq!(cur.by_ref().count(), 1);
+        assert!(cur.is_at_end());
+        assert_eq!(cur.span_since(cur.mark()), 9..9);
+        assert_eq!(cur.span_since(start), 0..9);
+        cur.reset(m);
+        assert_eq!(cur.peek().unwrap().line, 1);
+        assert!(cur.since(cur.mark()).is_empty());
+
+        let empty = TokenCursor::new(&[]);
+        assert_eq!(empty.span_since(empty.mark()), 0..0);
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Pretokenizing input in legacy encodings.
+
+use crate::{Config, Pretokenizer};
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+
+/// A run of decoded text whose length differs from the bytes it came from.
+/// Text between steps maps byte for byte.
+#[derive(Clone, Copy, Debug)]
+struct Step {
+    text: usize,
+    text_end: usize,
+    src: usize,
+    src_end: usize,
+}
+
+/// Bytes in any encoding known to [encoding_rs](https://docs.rs/encoding_rs),
+/// decoded to UTF-8 for pretokenizing.  Pretoken offsets refer to the
+/// decoded text; [source_offset()](#method.source_offset) maps them back to
+/// the original bytes.  Malformed sequences become U+FFFD REPLACEMENT
+/// CHARACTER.
+/// ```
+/// use pretok::{Decoded, Pretokenizer};
+/// let bytes = b"caf\xe9 = \"cr\xe8me\";";
+/// let file = Decoded::new(bytes, "latin1").unwrap();
+/// assert_eq!(file.text(), "caf\u{e9} = \"cr\u{e8}me\";");
+/// let toks: Vec<_> = Pretokenizer::from_decoded(&file).collect();
+/// assert_eq!(toks[2].s, "\"cr\u{e8}me\"");
+/// assert_eq!(file.source_offset(toks[2].offset), 7);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Decoded {
+    text: String,
+    encoding: &'static Encoding,
+    had_errors: bool,
+    steps: Vec<Step>,
+}
+
+impl Decoded {
+    /// Decode `bytes` in the encoding named by `label`, such as `"latin1"`
+    /// or `"Shift_JIS"`, or return None if the label isn't known.  Labels
+    /// are those of the [WHATWG Encoding Standard](https://encoding.spec.whatwg.org/#names-and-labels).
+    /// A byte order mark overrides the label.
+    pub fn new(bytes: &[u8], label: &str) -> Option<Decoded> {
+        let encoding = Encoding::for_label(label.as_bytes())?;
+        Some(Decoded::decode(bytes, encoding))
+    }
+
+    /// Decode `bytes` in the encoding given by a byte order mark, or as
+    /// UTF-8 if they are valid UTF-8, or else as windows-1252, which accepts
+    /// any bytes.
+    /// ```
+    /// use pretok::Decoded;
+    /// assert_eq!(Decoded::detect(b"\xff\xfea\0").text(), "a");
+    /// assert_eq!(Decoded::detect("\u{e9}".as_bytes()).encoding().name(), "UTF-8");
+    /// assert_eq!(Decoded::detect(b"\xe9").encoding().name(), "windows-1252");
+    /// ```
+    pub fn detect(bytes: &[u8]) -> Decoded {
+        let encoding = match std::str::from_utf8(bytes) {
+            Ok(_) => UTF_8,
+            Err(_) => WINDOWS_1252,
+        };
+        Decoded::decode(bytes, encoding)
+    }
+
+    /// Decode `bytes` one byte at a time, so each character's source is
+    /// known.  ASCII in an ASCII-compatible encoding is copied directly.
+    fn decode(bytes: &[u8], encoding: &'static Encoding) -> Decoded {
+        let (encoding, bom) = Encoding::for_bom(bytes).unwrap_or((encoding, 0));
+        let mut decoder = encoding.new_decoder_without_bom_handling();
+        let ascii = encoding.is_ascii_compatible();
+        let mut text = String::with_capacity(bytes.len());
+        let mut steps = Vec::new();
+        if bom > 0 {
+            steps.push(Step{ text: 0, text_end: 0, src: 0, src_end: bom });
+        }
+        let mut had_errors = false;
+        // The first byte not yet decoded into a character
+        let mut start = bom;
+        for i in bom..=bytes.len() {
+            let input = bytes.get(i..i + 1).unwrap_or(&[]);
+            if ascii && start == i && input.first().is_some_and(u8::is_ascii) {
+                text.push(input[0] as char);
+                start += 1;
+                continue;
+            }
+            let before = text.len();
+            text.reserve(decoder.max_utf8_buffer_length(input.len()).unwrap_or(16));
+            let (_, _, errors) = decoder.decode_to_string(input, &mut text, i == bytes.len());
+            had_errors |= errors;
+            if text.len() > before {
+                let src_end = (i + 1).min(bytes.len());
+                if text.len() - before != src_end - start {
+                    steps.push(Step{ text: before, text_end: text.len(), src: start, src_end });
+                }
+                start = src_end;
+            }
+        }
+        Decoded{ text, encoding, had_errors, steps }
+    }
+
+    /// The decoded text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The encoding the bytes were decoded from.
+    pub fn encoding(&self) -> &'static Encoding {
+        self.encoding
+    }
+
+    /// True if any malformed sequence was replaced.
+    pub fn had_errors(&self) -> bool {
+        self.had_errors
+    }
+
+    /// The offset in the original bytes of the character at byte `offset`
+    /// of the decoded text.  An offset inside a character maps to the
+    /// start of its bytes, and the end of the text maps to the end of the
+    /// bytes.
+    pub fn source_offset(&self, offset: usize) -> usize {
+        let i = self.steps.partition_point(|st| st.text <= offset);
+        match i.checked_sub(1).map(|i| self.steps[i]) {
+            None => offset,
+            Some(st) if offset < st.text_end => st.src,
+            Some(st) => st.src_end + (offset - st.text_end),
+        }
+    }
+}
+
+impl<'a> Pretokenizer<'a> {
+    /// Create a pretokenizer over decoded text.  See
+    /// [Decoded](struct.Decoded.html).
+    pub fn from_decoded(file: &'a Decoded) -> Pretokenizer<'a> {
+        Pretokenizer::new(file.text())
+    }
+
+    /// Like [Pretokenizer::from_decoded()](struct.Pretokenizer.html#method.from_decoded),
+    /// with `cfg`.
+    pub fn from_decoded_with_config(file: &'a Decoded, cfg: Config) -> Pretokenizer<'a> {
+        Pretokenizer::with_config(file.text(), cfg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_test_0() {
+        // "x = あい; // \x82" in Shift_JIS, ending in a truncated sequence.
+        let bytes = b"x = \x82\xa0\x82\xa2;\n// \x82";
+        let file = Decoded::new(bytes, "shift_jis").unwrap();
+        assert_eq!(file.encoding().name(), "Shift_JIS");
+        assert_eq!(file.text(), "x = \u{3042}\u{3044};\n// \u{FFFD}");
+        assert!(file.had_errors());
+        let toks: Vec<_> = Pretokenizer::from_decoded(&file).collect();
+        assert_eq!(toks[2].s, "\u{3042}\u{3044};");
+        for (text, src) in [(0, 0), (4, 4), (7, 6), (10, 8), (11, 9), (15, 13), (18, 14)] {
+            assert_eq!(file.source_offset(text), src, "{}", text);
+        }
+        assert!(Decoded::new(bytes, "no such encoding").is_none());
+
+        // A byte order mark wins over the label and is dropped.
+        let utf16: Vec<u8> = b"\xfe\xff".iter().copied()
+            .chain("a \u{e9}".encode_utf16().flat_map(|u| u.to_be_bytes())).collect();
+        let file = Decoded::new(&utf16, "latin1").unwrap();
+        assert_eq!(file.encoding().name(), "UTF-16BE");
+        let toks: Vec<_> = Pretokenizer::from_decoded_with_config(&file, Config::new()).collect();
+        assert_eq!(toks.iter().map(|t| file.source_offset(t.offset)).collect::<Vec<_>>(), vec![2, 6]);
+        assert_eq!(file.source_offset(file.text().len()), utf16.len());
+
+        let file = Decoded::detect("plain \u{e9}".as_bytes());
+        assert!(!file.had_errors());
+        assert_eq!(file.source_offset(8), 8);
+    }
+}
@@ -0,0 +1,78 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! An iterator that annotates pretokens with their bracket depth.
+
+use crate::{Kind, Pretoken, Pretokenizer};
+
+/// An iterator over each pretoken with the number of brackets open where
+/// it starts, created by
+/// [Pretokenizer::depths()](struct.Pretokenizer.html#method.depths).
+///
+/// `(`, `[` and `{` open a bracket and `)`, `]` and `}` close one, whatever
+/// kind it is, but only in word pretokens, so brackets in strings and
+/// comments don't count.  A closing bracket with none open is ignored.
+/// ```
+/// use pretok::Pretokenizer;
+/// let src = "fn f() {\n  g(\"}\", [x]) // )\n}";
+/// let v: Vec<_> = Pretokenizer::new(src).depths().map(|(t, d)| (t.s, d)).collect();
+/// assert_eq!(v, vec![("fn", 0), ("f()", 0), ("{", 0), ("g(", 1), ("\"}\"", 2),
+///                    (",", 2), ("[x])", 2), ("}", 1)]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Depths<'a> {
+    inner: Pretokenizer<'a>,
+    depth: usize,
+}
+
+impl<'a> Depths<'a> {
+    pub(crate) fn new(inner: Pretokenizer<'a>) -> Depths<'a> {
+        Depths{ inner, depth: 0 }
+    }
+
+    /// The number of brackets open after the pretokens returned so far.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The underlying pretokenizer.
+    pub fn into_inner(self) -> Pretokenizer<'a> {
+        self.inner
+    }
+}
+
+impl<'a> Iterator for Depths<'a> {
+    type Item = (Pretoken<'a>, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tok = self.inner.next()?;
+        let depth = self.depth;
+        if tok.kind == Kind::Word {
+            for b in tok.s.bytes() {
+                match b {
+                    b'(' | b'[' | b'{' => self.depth += 1,
+                    b')' | b']' | b'}' => self.depth = self.depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+        }
+        Some((tok, depth))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    #[test]
+    fn depth_test_1() {
+        let src = ") a{ /* { */ b\n} } c( \"(\" {";
+        let mut depths = Pretokenizer::with_config(src, Config::new().lossless(true)).depths();
+        let v: Vec<_> = depths.by_ref().filter(|(t, _)| !t.kind.is_trivia())
+            .map(|(t, d)| (t.s, d)).collect();
+        assert_eq!(v, vec![(")", 0), ("a{", 0), ("b", 1), ("}", 1), ("}", 0), ("c(", 0),
+                           ("\"(\"", 1), ("{", 1)]);
+        assert_eq!(depths.depth(), 2);
+        assert!(depths.into_inner().next().is_none());
+    }
+}
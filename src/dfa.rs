@@ -0,0 +1,249 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! The transition table that drives the scanner.
+
+use crate::{CharClassifier, Config, Nul, ZeroWidth};
+
+/// The states of the scanner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum State {
+    WS,
+    MaybeComment,
+    LineComment,
+    BlockComment,
+    MaybeBlockCommentDone,
+    MaybeNestedComment,
+    StartTok,
+    NormalTok,
+    QuotedTok,
+    EscapeChar,
+}
+
+impl State {
+    /// The name of the state, for
+    /// [Config::trace()](../struct.Config.html#method.trace).
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            State::WS => "WS",
+            State::MaybeComment => "MaybeComment",
+            State::LineComment => "LineComment",
+            State::BlockComment => "BlockComment",
+            State::MaybeBlockCommentDone => "MaybeBlockCommentDone",
+            State::MaybeNestedComment => "MaybeNestedComment",
+            State::StartTok => "StartTok",
+            State::NormalTok => "NormalTok",
+            State::QuotedTok => "QuotedTok",
+            State::EscapeChar => "EscapeChar",
+        }
+    }
+}
+
+const STATES: usize = State::EscapeChar as usize + 1;
+
+/// The classes of characters the transitions distinguish.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Class {
+    Blank,
+    Newline,
+    Slash,
+    Star,
+    Quote,
+    Backslash,
+    Other,
+}
+
+const CLASSES: usize = Class::Other as usize + 1;
+
+/// The class of each ASCII character.
+const ASCII_CLASSES: [Class; 128] = {
+    let mut classes = [Class::Other; 128];
+    classes[b' ' as usize] = Class::Blank;
+    classes[b'\t' as usize] = Class::Blank;
+    classes[b'\n' as usize] = Class::Newline;
+    classes[b'/' as usize] = Class::Slash;
+    classes[b'*' as usize] = Class::Star;
+    classes[b'"' as usize] = Class::Quote;
+    classes[b'\\' as usize] = Class::Backslash;
+    classes
+};
+
+/// What the scanner does on a transition, besides changing state.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Act {
+    /// Consume the character.
+    Consume,
+    /// Consume a newline inside a comment or string.
+    ConsumeLine,
+    /// Handle the character in code specific to the state, such as ending
+    /// a pretoken.  The `to` state is unused.
+    Special,
+}
+
+/// A transition.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Step {
+    pub(crate) to: State,
+    pub(crate) act: Act,
+}
+
+/// The transitions from every state on every class of character, and the
+/// classes of characters, built once per configuration.
+#[derive(Clone, Debug)]
+pub(crate) struct Table {
+    steps: [[Step; CLASSES]; STATES],
+    /// The class of each ASCII character
+    ascii: [Class; 128],
+    /// True if zero-width characters are blanks
+    zero_width_blank: bool,
+    /// Other non-ASCII characters that are blanks
+    blanks: String,
+}
+
+impl Table {
+    pub(crate) fn new(cfg: &Config) -> Table {
+        use Act::*;
+        use State::*;
+
+        let special = Step{ to: WS, act: Special };
+        let mut ascii = ASCII_CLASSES;
+        if cfg.nul == Nul::Whitespace {
+            ascii[0] = Class::Blank;
+        }
+        if cfg.plain_slashes {
+            ascii[b'/' as usize] = Class::Other;
+        }
+        let mut blanks = String::new();
+        for c in cfg.separators.chars() {
+            match ascii.get_mut(c as usize) {
+                Some(class) if *class == Class::Other => *class = Class::Blank,
+                Some(_) => {}
+                None => blanks.push(c),
+            }
+        }
+        let mut table = Table{ steps: [[special; CLASSES]; STATES], ascii,
+                               zero_width_blank: cfg.zero_width == ZeroWidth::Strip, blanks };
+        let mut set = |from: State, classes: &[Class], to: State, act: Act| {
+            for &class in classes {
+                table.steps[from as usize][class as usize] = Step{ to, act };
+            }
+        };
+        // Without line counting, newlines in comments and strings are
+        // ordinary characters.
+        let newline = if cfg.counts_lines() { ConsumeLine } else { Consume };
+        let all = [Class::Blank, Class::Newline, Class::Slash, Class::Star, Class::Quote,
+                   Class::Backslash, Class::Other];
+
+        // Whitespace runs, and the bodies of comments, strings and words
+        set(WS, &[Class::Blank], WS, Consume);
+        set(LineComment, &all, LineComment, Consume);
+        set(LineComment, &[Class::Newline], LineComment, Special);
+        set(NormalTok, &[Class::Star, Class::Backslash, Class::Other], NormalTok, Consume);
+        if cfg.line_continuation {
+            set(NormalTok, &[Class::Backslash], NormalTok, Special);
+        }
+
+        set(BlockComment, &all, BlockComment, Consume);
+        set(BlockComment, &[Class::Newline], BlockComment, newline);
+        set(BlockComment, &[Class::Star], MaybeBlockCommentDone, Consume);
+        if cfg.nested_comments {
+            set(BlockComment, &[Class::Slash], MaybeNestedComment, Consume);
+        }
+        set(MaybeBlockCommentDone, &all, BlockComment, Consume);
+        set(MaybeBlockCommentDone, &[Class::Newline], BlockComment, newline);
+        set(MaybeBlockCommentDone, &[Class::Star], MaybeBlockCommentDone, Consume);
+        set(MaybeBlockCommentDone, &[Class::Slash], BlockComment, Special);
+        set(MaybeNestedComment, &all, BlockComment, Consume);
+        set(MaybeNestedComment, &[Class::Newline], BlockComment, newline);
+        set(MaybeNestedComment, &[Class::Slash], MaybeNestedComment, Consume);
+        set(MaybeNestedComment, &[Class::Star], BlockComment, Special);
+
+        set(QuotedTok, &all, QuotedTok, Consume);
+        set(QuotedTok, &[Class::Newline], QuotedTok, newline);
+        set(QuotedTok, &[Class::Backslash], EscapeChar, Consume);
+        set(QuotedTok, &[Class::Quote], QuotedTok, Special);
+        set(EscapeChar, &all, QuotedTok, Consume);
+        set(EscapeChar, &[Class::Newline], QuotedTok, newline);
+        table
+    }
+
+    /// Give the ASCII characters the classes `classifier` gives them.
+    pub(crate) fn classify_ascii(&mut self, classifier: &dyn CharClassifier) {
+        for (b, class) in self.ascii.iter_mut().enumerate().filter(|&(b, _)| b != b'\n' as usize) {
+            match classify(classifier, b as u8 as char) {
+                Some(by) => *class = by,
+                None if matches!(ASCII_CLASSES[b], Class::Blank | Class::Quote | Class::Slash) => {
+                    *class = Class::Other;
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// The class of `c`.
+    pub(crate) fn class(&self, c: char) -> Class {
+        match self.ascii.get(c as usize) {
+            Some(&class) => class,
+            None if self.zero_width_blank && crate::checks::is_zero_width(c) => Class::Blank,
+            None if self.blanks.contains(c) => Class::Blank,
+            None => Class::Other,
+        }
+    }
+
+    /// The transition from `state` on `c`.
+    #[cfg(test)]
+    fn step(&self, state: State, c: char) -> Step {
+        self.transition(state, self.class(c))
+    }
+
+    /// The transition from `state` on a character of class `class`.
+    pub(crate) fn transition(&self, state: State, class: Class) -> Step {
+        self.steps[state as usize][class as usize]
+    }
+}
+
+/// The class `classifier` gives `c`, if it gives one.
+pub(crate) fn classify(classifier: &dyn CharClassifier, c: char) -> Option<Class> {
+    if classifier.is_whitespace(c) {
+        Some(Class::Blank)
+    } else if classifier.is_quote(c) {
+        Some(Class::Quote)
+    } else if classifier.is_comment_start(c) {
+        Some(Class::Slash)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dfa_test_0() {
+        let table = Table::new(&Config::new());
+        let step = table.step(State::BlockComment, '/');
+        assert_eq!((step.to, step.act), (State::BlockComment, Act::Consume));
+        assert_eq!(table.step(State::WS, '\u{e9}').act, Act::Special);
+        assert_eq!(table.step(State::NormalTok, '\u{e9}').act, Act::Consume);
+        assert_eq!(table.step(State::EscapeChar, '\n').act, Act::ConsumeLine);
+
+        let table = Table::new(&Config::new().nested_comments(true).offsets_only(true));
+        assert_eq!(table.step(State::BlockComment, '/').to, State::MaybeNestedComment);
+        assert_eq!(table.step(State::EscapeChar, '\n').act, Act::Consume);
+        assert_eq!(table.step(State::WS, '\u{200B}').act, Act::Special);
+
+        let table = Table::new(&Config::new().zero_width(ZeroWidth::Strip));
+        assert_eq!(table.step(State::WS, '\u{200B}').act, Act::Consume);
+        assert_eq!(table.step(State::NormalTok, '\u{FEFF}').act, Act::Special);
+        assert_eq!(table.step(State::NormalTok, '\u{0}').act, Act::Consume);
+
+        let table = Table::new(&Config::new().nul(Nul::Whitespace));
+        assert_eq!(table.step(State::WS, '\u{0}').act, Act::Consume);
+
+        let table = Table::new(&Config::new().separators(",/\u{3001}"));
+        assert_eq!(table.step(State::WS, ',').act, Act::Consume);
+        assert_eq!(table.step(State::WS, '\u{3001}').act, Act::Consume);
+        assert_eq!(table.step(State::NormalTok, '\u{3001}').act, Act::Special);
+        assert_eq!(table.step(State::WS, '/').act, Act::Special);
+    }
+}
@@ -0,0 +1,88 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Finding what changed between two pretoken streams.
+
+use crate::{Config, Pretoken, Pretokenizer};
+use std::ops::Range;
+
+/// The pretokens that differ between two streams, as found by
+/// [diff_tokens()](fn.diff_tokens.html): pretokens `old` of the old stream
+/// were replaced by pretokens `new` of the new one, and those before and
+/// after are the same.  Either range may be empty, for a pure insertion or
+/// deletion.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TokenDiff {
+    /// The indices of the changed pretokens in the old stream.
+    pub old: Range<usize>,
+    /// The indices of the changed pretokens in the new stream.
+    pub new: Range<usize>,
+}
+
+/// The smallest range of pretokens that differs between `old` and `new`,
+/// or None if they are the same.  Pretokens are compared by text and kind,
+/// not position, so text that only moved is unchanged.
+/// ```
+/// let old = pretok::tokenize("a = f(x) + 1;");
+/// let new = pretok::tokenize("a  =  f(y) + 1;");
+/// let diff = pretok::diff_tokens(&old, &new).unwrap();
+/// assert_eq!((diff.old, diff.new), (2..3, 2..3));
+/// assert_eq!(new[2].s, "f(y)");
+/// assert_eq!(pretok::diff_tokens(&old, &old), None);
+/// ```
+pub fn diff_tokens(old: &[Pretoken], new: &[Pretoken]) -> Option<TokenDiff> {
+    let same = |a: &Pretoken, b: &Pretoken| a.s == b.s && a.kind == b.kind;
+    let prefix = old.iter().zip(new).take_while(|(a, b)| same(a, b)).count();
+    if prefix == old.len() && prefix == new.len() {
+        return None;
+    }
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| same(a, b))
+        .count();
+    Some(TokenDiff{ old: prefix..old.len() - suffix, new: prefix..new.len() - suffix })
+}
+
+/// The smallest range of pretokens that differs between two versions of an
+/// input, ignoring whitespace and comments.  See
+/// [diff_tokens()](fn.diff_tokens.html).
+/// ```
+/// let old = "int f() {\n  return 1; // one\n}\n";
+/// let new = "int f() {\n  /* two */ return 2;\n}\n";
+/// let diff = pretok::diff_sources(old, new).unwrap();
+/// assert_eq!((diff.old, diff.new), (4..5, 4..5));
+/// assert_eq!(pretok::diff_sources(old, "int  f() { return 1; }"), None);
+/// ```
+pub fn diff_sources(old: &str, new: &str) -> Option<TokenDiff> {
+    diff_sources_with(old, new, Config::default())
+}
+
+/// Like [diff_sources()](fn.diff_sources.html), with `cfg`.  Whitespace and
+/// comments are ignored even if `cfg` is lossless.
+pub fn diff_sources_with(old: &str, new: &str, cfg: Config) -> Option<TokenDiff> {
+    let cfg = cfg.lossless(false);
+    let old: Vec<_> = Pretokenizer::with_config(old, cfg.clone()).collect();
+    let new: Vec<_> = Pretokenizer::with_config(new, cfg).collect();
+    diff_tokens(&old, &new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_test_1() {
+        let diff = |a, b| diff_sources(a, b).map(|d| (d.old, d.new));
+        assert_eq!(diff("", ""), None);
+        assert_eq!(diff("a b", "a b c"), Some((2..2, 2..3)));
+        assert_eq!(diff("x a b", "a b"), Some((0..1, 0..0)));
+        // Repeats are matched at the start first.
+        assert_eq!(diff("a a", "a a a"), Some((2..2, 2..3)));
+        assert_eq!(diff("a \"b c\" d", "a \"b  c\" d"), Some((1..2, 1..2)));
+        let cfg = Config::new().lossless(true);
+        assert_eq!(diff_sources_with("a /* x */ b", "a b", cfg), None);
+        // The kind counts as well as the text.
+        let cfg = Config::new().directives(true);
+        assert_eq!(diff_sources_with("#x\n", "\n#x\n", cfg.clone()), None);
+        assert_eq!(diff_sources_with("#x\n", "; #x\n", cfg).map(|d| (d.old, d.new)),
+                   Some((0..1, 0..2)));
+    }
+}
@@ -0,0 +1,183 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Attaching doc comments to the pretokens they document.
+
+use crate::{Config, Kind, Pretoken, Pretokenizer};
+use std::ops::Range;
+
+/// A pretoken and the doc comments before it, found by
+/// [doc_comments()](fn.doc_comments.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct DocComments<'a> {
+    /// The first pretoken after the comments.
+    pub token: Pretoken<'a>,
+    /// The doc comments, in input order.
+    pub docs: Vec<Pretoken<'a>>,
+}
+
+impl<'a> DocComments<'a> {
+    /// The text of the doc comments without their decorations: the `///`,
+    /// `/**` and `*/`, and a `*` starting a line of a block comment, with
+    /// the space after each.  Blank first and last lines of block comments
+    /// are dropped, and lines are joined with `\n`.
+    /// ```
+    /// let src = "/// Sum\n/// of:\n/**\n * - `a`\n *   and `b`\n */\nfn sum";
+    /// let docs = pretok::doc_comments(src);
+    /// assert_eq!(docs[0].text(), "Sum\nof:\n- `a`\n  and `b`");
+    /// ```
+    pub fn text(&self) -> String {
+        let mut lines = Vec::new();
+        for doc in &self.docs {
+            if let Some(line) = doc.s.strip_prefix("///") {
+                lines.push(undecorate(line));
+                continue;
+            }
+            let body = &doc.s[3..doc.s.len() - 2];
+            let start = lines.len();
+            for (i, line) in body.lines().enumerate() {
+                let line = if i == 0 { line } else { line.trim_start() };
+                lines.push(undecorate(line.strip_prefix('*').unwrap_or(line)));
+            }
+            if lines.len() > start && lines[start].is_empty() {
+                lines.remove(start);
+            }
+            if lines.len() > start && lines.last().is_some_and(|l| l.is_empty()) {
+                lines.pop();
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// A line of a doc comment after its `///` or `*`, without the space after
+/// that or trailing whitespace.
+fn undecorate(line: &str) -> &str {
+    line.strip_prefix(' ').unwrap_or(line).trim_end()
+}
+
+/// The undecorated text of doc comments and where they are, found by
+/// [doc_blocks()](fn.doc_blocks.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct DocBlock<'a> {
+    /// The first pretoken after the comments.
+    pub token: Pretoken<'a>,
+    /// The text of the comments.  See
+    /// [DocComments::text()](struct.DocComments.html#method.text).
+    pub text: String,
+    /// The line the first comment starts on.
+    pub line: usize,
+    /// The byte range from the start of the first comment to the end of the
+    /// last.
+    pub span: Range<usize>,
+}
+
+/// The doc comments of `s` as text blocks, for generating documentation.
+/// See [doc_comments()](fn.doc_comments.html).
+/// ```
+/// let src = "x;\n\n/**\n * The count.\n */\nint count;\n";
+/// let blocks = pretok::doc_blocks(src);
+/// assert_eq!((blocks[0].token.s, blocks[0].line), ("int", 3));
+/// assert_eq!(blocks[0].text, "The count.");
+/// assert_eq!(&src[blocks[0].span.clone()], "/**\n * The count.\n */");
+/// ```
+pub fn doc_blocks(s: &str) -> Vec<DocBlock<'_>> {
+    doc_blocks_with(s, Config::default())
+}
+
+/// Like [doc_blocks()](fn.doc_blocks.html), with `cfg`.
+pub fn doc_blocks_with(s: &str, cfg: Config) -> Vec<DocBlock<'_>> {
+    doc_comments_with(s, cfg).into_iter().map(|d| {
+        let (first, last) = (&d.docs[0], &d.docs[d.docs.len() - 1]);
+        DocBlock{
+            text: d.text(),
+            line: first.line - first.s.matches('\n').count(),
+            span: first.offset..last.offset + last.s.len(),
+            token: d.token,
+        }
+    }).collect()
+}
+
+/// Each pretoken of `s` with doc comments before it, and those comments, in
+/// input order.  Doc comments are line comments starting `///` and block
+/// comments starting `/**`, but not `////`, `/***` or `/**/`.
+///
+/// The doc comments of a pretoken are those since the last pretoken or
+/// blank line before it, so a blank line breaks the comments above it from
+/// the pretoken.  Other comments in between are skipped.  A doc comment on
+/// the same line as an earlier pretoken, or with no pretoken after it,
+/// documents nothing.
+/// ```
+/// let src = "/// Lost.\n\n/// The count.\n// note\n/** Of things. */ count: u32, /// no\nx";
+/// let docs = pretok::doc_comments(src);
+/// assert_eq!(docs.len(), 1);
+/// assert_eq!(docs[0].token.s, "count:");
+/// let v: Vec<_> = docs[0].docs.iter().map(|t| t.s).collect();
+/// assert_eq!(v, vec!["/// The count.", "/** Of things. */"]);
+/// ```
+pub fn doc_comments(s: &str) -> Vec<DocComments<'_>> {
+    doc_comments_with(s, Config::default())
+}
+
+/// Like [doc_comments()](fn.doc_comments.html), with `cfg`.
+pub fn doc_comments_with(s: &str, cfg: Config) -> Vec<DocComments<'_>> {
+    let mut found = Vec::new();
+    let mut docs = Vec::new();
+    // Line breaks since the last pretoken or comment, and whether a
+    // pretoken came before on the current line.
+    let (mut newlines, mut code_on_line) = (0, false);
+    for tok in Pretokenizer::with_config(s, cfg.lossless(true)) {
+        match tok.kind {
+            Kind::Whitespace => {}
+            Kind::Newline => {
+                newlines += 1;
+                if newlines > 1 {
+                    docs.clear();
+                }
+                code_on_line = false;
+            }
+            Kind::Comment => {
+                if is_doc(tok.s) && !code_on_line {
+                    docs.push(tok);
+                }
+                newlines = 0;
+            }
+            _ => {
+                if !docs.is_empty() {
+                    found.push(DocComments{ token: tok.clone(), docs: std::mem::take(&mut docs) });
+                }
+                newlines = 0;
+                code_on_line = true;
+            }
+        }
+    }
+    found
+}
+
+/// True if comment `s` is a doc comment.
+fn is_doc(s: &str) -> bool {
+    (s.starts_with("///") && !s.starts_with("////"))
+        || (s.starts_with("/**") && !s.starts_with("/***") && s != "/**/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn docs_test_1() {
+        let src = "//// a\n/*** b */\n/**/ c /// d\n  /// e\n\t\n/// f\n  \
+                   /* g */ /** h\n */ i j\n/// k";
+        let v: Vec<_> = doc_comments(src).iter()
+            .map(|d| (d.token.s, d.docs.iter().map(|t| t.s).collect::<Vec<_>>()))
+            .collect();
+        assert_eq!(v, vec![("i", vec!["/// f", "/** h\n */"])]);
+        assert!(!is_doc("//! inner"));
+        assert!(is_doc("/** x */"));
+        assert!(doc_comments("").is_empty());
+        let src = "/// a\r\n///b  \r\n///\n/** c\r\n  *d\n   e */\n/***/ /** */\nf";
+        let blocks = doc_blocks(src);
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].text, "a\nb\n\nc\nd\ne");
+        assert_eq!((blocks[0].token.s, blocks[0].line, blocks[0].span.clone()), ("f", 1, 0..51));
+    }
+}
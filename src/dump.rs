@@ -0,0 +1,285 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! A compact binary encoding of pretoken streams.
+
+use crate::{Kind, Pretoken};
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+
+/// Identifies dumps, followed by the format version.
+const MAGIC: &[u8; 4] = b"PTKD";
+const VERSION: u8 = 1;
+
+/// Set in the flags byte when the dump holds the pretoken text.
+const HAS_TEXT: u8 = 1;
+
+/// A pretoken stored without a borrow of its input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Span {
+    offset: usize,
+    len: usize,
+    line: usize,
+    kind: Kind,
+}
+
+/// A pretoken stream in a form that can be saved and loaded quickly.  Each
+/// pretoken's offset, length, line and kind take a few bytes, as
+/// variable-length deltas from the pretoken before.  Optionally the text of
+/// the pretokens is kept too, so a dump can be used without its input.
+///
+/// The format starts with the bytes `PTKD` and a version number, so files
+/// from other versions are rejected rather than misread.
+/// ```
+/// use pretok::TokenDump;
+/// let src = "int x = 42;";
+/// let toks = pretok::tokenize(src);
+/// let mut file = Vec::new();
+/// TokenDump::new(&toks, true).save(&mut file)?;
+///
+/// let dump = TokenDump::load(&file[..])?;
+/// assert_eq!(dump.tokens_in(src).unwrap(), toks);
+/// assert_eq!(dump.tokens().unwrap().collect::<Vec<_>>(), toks);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TokenDump {
+    spans: Vec<Span>,
+    /// The text of every pretoken, concatenated
+    text: Option<String>,
+}
+
+impl TokenDump {
+    /// A dump of `toks`, with their text if `with_text` is true.
+    pub fn new(toks: &[Pretoken], with_text: bool) -> TokenDump {
+        let spans = toks.iter()
+            .map(|t| Span{ offset: t.offset, len: t.s.len(), line: t.line, kind: t.kind })
+            .collect();
+        let text = match with_text {
+            true => Some(toks.iter().map(|t| t.s).collect()),
+            false => None,
+        };
+        TokenDump{ spans, text }
+    }
+
+    /// The number of pretokens.
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    /// True if there are no pretokens.
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// True if the dump holds the pretoken text.
+    pub fn has_text(&self) -> bool {
+        self.text.is_some()
+    }
+
+    /// The pretokens, with text from the dump, or None if it has no text.
+    /// Offsets and lines still refer to the original input.
+    pub fn tokens(&self) -> Option<impl Iterator<Item = Pretoken<'_>> + '_> {
+        let text = self.text.as_deref()?;
+        let mut at = 0;
+        Some(self.spans.iter().map(move |sp| {
+            let s = &text[at..at + sp.len];
+            at += sp.len;
+            Pretoken{ s, line: sp.line, offset: sp.offset, kind: sp.kind }
+        }))
+    }
+
+    /// The pretokens with their text taken from `s`, the original input, or
+    /// None if they don't fit in `s`.
+    pub fn tokens_in<'a>(&self, s: &'a str) -> Option<Vec<Pretoken<'a>>> {
+        self.spans.iter()
+            .map(|sp| {
+                let text = s.get(sp.offset..sp.offset.checked_add(sp.len)?)?;
+                Some(Pretoken{ s: text, line: sp.line, offset: sp.offset, kind: sp.kind })
+            })
+            .collect()
+    }
+
+    /// Write the dump to `w`.  Wrap `w` in a `BufWriter` if it is a file.
+    pub fn save<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let mut out = Vec::with_capacity(16 + self.spans.len() * 5
+                                         + self.text.as_ref().map_or(0, String::len));
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.push(if self.has_text() { HAS_TEXT } else { 0 });
+        put_varint(&mut out, self.spans.len() as u64);
+        let (mut end, mut line) = (0, 0);
+        for sp in &self.spans {
+            put_varint(&mut out, zigzag(sp.offset as i64 - end as i64));
+            put_varint(&mut out, sp.len as u64);
+            put_varint(&mut out, zigzag(sp.line as i64 - line as i64));
+            out.push(sp.kind.code());
+            end = sp.offset + sp.len;
+            line = sp.line;
+        }
+        if let Some(ref text) = self.text {
+            out.extend_from_slice(text.as_bytes());
+        }
+        w.write_all(&out)
+    }
+
+    /// Read a dump written by `save()`.  Fails with an error of kind
+    /// [InvalidData](https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidData)
+    /// if the data isn't a dump of this version.
+    pub fn load<R: Read>(mut r: R) -> io::Result<TokenDump> {
+        let mut bytes = Vec::new();
+        r.read_to_end(&mut bytes)?;
+        let mut input = Input{ bytes: &bytes, pos: 0 };
+        if input.take(4)? != MAGIC {
+            return Err(invalid("not a pretok dump"));
+        }
+        if input.byte()? != VERSION {
+            return Err(invalid("unsupported pretok dump version"));
+        }
+        let flags = input.byte()?;
+        let count = input.usize()?;
+        // Each pretoken takes at least 4 bytes, so a huge count is an error
+        // rather than a huge allocation.
+        let mut spans = Vec::with_capacity(count.min(bytes.len() / 4));
+        let (mut end, mut line) = (0usize, 0usize);
+        let mut text_len = 0usize;
+        for _ in 0..count {
+            let offset = shift(end, input.varint()?)?;
+            let len = input.usize()?;
+            line = shift(line, input.varint()?)?;
+            let kind = Kind::from_code(input.byte()?).ok_or_else(|| invalid("unknown kind"))?;
+            end = offset.checked_add(len).ok_or_else(|| invalid("offset overflow"))?;
+            text_len = text_len.checked_add(len).ok_or_else(|| invalid("text overflow"))?;
+            spans.push(Span{ offset, len, line, kind });
+        }
+        let text = match flags {
+            0 => None,
+            HAS_TEXT => {
+                let text = std::str::from_utf8(input.take(text_len)?)
+                    .map_err(|_| invalid("text is not UTF-8"))?;
+                // Every pretoken must start on a character boundary.
+                let mut at = 0;
+                for sp in &spans {
+                    if !text.is_char_boundary(at) {
+                        return Err(invalid("text is not UTF-8"));
+                    }
+                    at += sp.len;
+                }
+                Some(text.to_string())
+            }
+            _ => return Err(invalid("unknown flags")),
+        };
+        if input.pos != bytes.len() {
+            return Err(invalid("trailing data"));
+        }
+        Ok(TokenDump{ spans, text })
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Map small negative numbers to small unsigned ones.
+fn zigzag(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+/// Apply the zigzag-encoded delta `delta` to `base`.
+fn shift(base: usize, delta: u64) -> io::Result<usize> {
+    let delta = (delta >> 1) as i64 ^ -((delta & 1) as i64);
+    i64::try_from(base).ok()
+        .and_then(|base| base.checked_add(delta))
+        .and_then(|n| usize::try_from(n).ok())
+        .ok_or_else(|| invalid("offset out of range"))
+}
+
+/// Append `n` in LEB128: 7 bits per byte, low bits first.
+fn put_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// The unread part of a dump.
+struct Input<'b> {
+    bytes: &'b [u8],
+    pos: usize,
+}
+
+impl<'b> Input<'b> {
+    fn take(&mut self, n: usize) -> io::Result<&'b [u8]> {
+        let end = self.pos.checked_add(n).filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| invalid("truncated pretok dump"))?;
+        let taken = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> io::Result<u64> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            n |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(invalid("varint too long"))
+    }
+
+    fn usize(&mut self) -> io::Result<usize> {
+        usize::try_from(self.varint()?).map_err(|_| invalid("number too large"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, Pretokenizer};
+
+    #[test]
+    fn dump_test_0() {
+        let src = "#!/bin/x\nf(\"\u{e9}\\\"\",\n  c); // d\n";
+        let cfg = Config::new().lossless(true).shebang(crate::Shebang::Token);
+        let toks: Vec<_> = Pretokenizer::with_config(src, cfg).collect();
+        for &with_text in &[false, true] {
+            let mut file = Vec::new();
+            TokenDump::new(&toks, with_text).save(&mut file).unwrap();
+            let dump = TokenDump::load(&file[..]).unwrap();
+            assert_eq!(dump, TokenDump::new(&toks, with_text));
+            assert_eq!(dump.len(), toks.len());
+            assert_eq!(dump.has_text(), with_text);
+            assert_eq!(dump.tokens_in(src).unwrap(), toks);
+            assert_eq!(dump.tokens().map(|t| t.collect::<Vec<_>>()),
+                       if with_text { Some(toks.clone()) } else { None });
+            assert_eq!(dump.tokens_in("short"), None);
+
+            // Every truncation is caught.
+            for n in 0..file.len() {
+                assert!(TokenDump::load(&file[..n]).is_err(), "{} bytes", n);
+            }
+        }
+
+        let mut file = Vec::new();
+        TokenDump::new(&toks, false).save(&mut file).unwrap();
+        assert!(file.len() <= 7 + toks.len() * 4);
+        file.push(0);
+        assert_eq!(TokenDump::load(&file[..]).unwrap_err().to_string(), "trailing data");
+        file[4] = VERSION + 1;
+        assert_eq!(TokenDump::load(&file[..]).unwrap_err().to_string(),
+                   "unsupported pretok dump version");
+        assert!(TokenDump::load(&b"JSON"[..]).is_err());
+
+        let empty = TokenDump::default();
+        let mut file = Vec::new();
+        empty.save(&mut file).unwrap();
+        assert_eq!(TokenDump::load(&file[..]).unwrap(), empty);
+        assert!(empty.is_empty());
+    }
+}
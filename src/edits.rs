@@ -0,0 +1,163 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Replacing pretokens to produce new source text.
+
+use crate::{EditError, Pretoken};
+use std::ops::Range;
+
+/// Replacements of pretokens or other spans of an input, collected and then
+/// applied together to produce new text.  Spans refer to the original
+/// input, so edits can be added in any order without adjusting offsets.
+/// Edits that overlap are rejected.  Insertions at the same offset are
+/// applied in the order added, before a replacement starting there.
+/// ```
+/// use pretok::{Pretokenizer, SourceEdits};
+/// let src = "let x = y + x;";
+/// let mut edits = SourceEdits::new();
+/// for tok in Pretokenizer::new(src).filter(|t| t.s.trim_end_matches(';') == "x") {
+///     edits.replace_span(tok.offset..tok.offset + 1, "total")?;
+/// }
+/// let first = Pretokenizer::new(src).next().unwrap();
+/// edits.insert_before(&first, "// renamed\n")?;
+/// assert_eq!(edits.apply(src)?, "// renamed\nlet total = y + total;");
+/// # Ok::<(), pretok::EditError>(())
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceEdits {
+    /// Spans and their replacements, sorted by span
+    edits: Vec<(Range<usize>, String)>,
+}
+
+impl SourceEdits {
+    /// Create an empty set of edits.
+    pub fn new() -> SourceEdits {
+        SourceEdits::default()
+    }
+
+    /// Replace pretoken `tok` with `text`.
+    pub fn replace(&mut self, tok: &Pretoken, text: &str) -> Result<(), EditError> {
+        self.replace_span(tok.offset..tok.offset + tok.s.len(), text)
+    }
+
+    /// Remove pretoken `tok`.  The whitespace around it is kept.
+    pub fn delete(&mut self, tok: &Pretoken) -> Result<(), EditError> {
+        self.replace(tok, "")
+    }
+
+    /// Insert `text` just before pretoken `tok`.
+    pub fn insert_before(&mut self, tok: &Pretoken, text: &str) -> Result<(), EditError> {
+        self.replace_span(tok.offset..tok.offset, text)
+    }
+
+    /// Insert `text` just after pretoken `tok`.
+    pub fn insert_after(&mut self, tok: &Pretoken, text: &str) -> Result<(), EditError> {
+        let end = tok.offset + tok.s.len();
+        self.replace_span(end..end, text)
+    }
+
+    /// Replace the bytes of the input in `span` with `text`.  An empty span
+    /// inserts.  Fails if the edit overlaps one already made: if both
+    /// replace some of the same bytes, or one inserts strictly inside the
+    /// other.
+    pub fn replace_span(&mut self, span: Range<usize>, text: &str) -> Result<(), EditError> {
+        if span.start > span.end {
+            return Err(EditError::OutOfRange{span});
+        }
+        // Edits end in order, since they don't overlap.
+        let first = self.edits.partition_point(|(e, _)| e.end < span.start);
+        for (existing, _) in self.edits[first..].iter().take_while(|(e, _)| e.start <= span.end) {
+            if overlap(existing, &span) {
+                return Err(EditError::Overlap{span, existing: existing.clone()});
+            }
+        }
+        let at = self.edits.partition_point(|(e, _)| (e.start, e.end) <= (span.start, span.end));
+        self.edits.insert(at, (span, text.to_string()));
+        Ok(())
+    }
+
+    /// Apply the edits to `s`, the input they refer to.  Fails if an edit
+    /// extends past the end of `s` or splits a character.
+    pub fn apply(&self, s: &str) -> Result<String, EditError> {
+        let added: usize = self.edits.iter().map(|(_, text)| text.len()).sum();
+        let mut out = String::with_capacity(s.len() + added);
+        let mut done = 0;
+        for (span, text) in &self.edits {
+            if span.end > s.len() {
+                return Err(EditError::OutOfRange{span: span.clone()});
+            }
+            for &offset in &[span.start, span.end] {
+                if !s.is_char_boundary(offset) {
+                    return Err(EditError::NotCharBoundary{offset});
+                }
+            }
+            out.push_str(&s[done..span.start]);
+            out.push_str(text);
+            done = span.end;
+        }
+        out.push_str(&s[done..]);
+        Ok(out)
+    }
+
+    /// The edits, sorted by span.
+    pub fn iter(&self) -> impl Iterator<Item = (Range<usize>, &str)> {
+        self.edits.iter().map(|(span, text)| (span.clone(), &text[..]))
+    }
+
+    /// The number of edits.
+    pub fn len(&self) -> usize {
+        self.edits.len()
+    }
+
+    /// True if there are no edits.
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+}
+
+/// True if edits at `a` and `b` conflict.
+fn overlap(a: &Range<usize>, b: &Range<usize>) -> bool {
+    match (a.is_empty(), b.is_empty()) {
+        (true, true) => false,
+        (true, false) => b.start < a.start && a.start < b.end,
+        (false, true) => a.start < b.start && b.start < a.end,
+        (false, false) => a.start < b.end && b.start < a.end,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Pretokenizer;
+
+    #[test]
+    fn edits_test_1() {
+        let src = "f(a, \"\u{e9}\") // c";
+        let toks: Vec<_> = Pretokenizer::new(src).collect();
+        let mut edits = SourceEdits::new();
+        assert!(edits.is_empty());
+        edits.replace(&toks[1], "'e'").unwrap();
+        edits.insert_after(&toks[0], " b,").unwrap();
+        edits.insert_after(&toks[0], " c,").unwrap();
+        edits.insert_before(&toks[2], " ").unwrap();
+        assert_eq!(edits.len(), 4);
+        assert_eq!(edits.apply(src).unwrap(), "f(a, b, c, 'e' ) // c");
+
+        let q = toks[1].offset..toks[1].offset + toks[1].s.len();
+        for bad in &[q.start..q.start + 1, q.start + 1..q.start + 1, q.start - 1..q.end + 1] {
+            assert_eq!(edits.replace_span(bad.clone(), "x"),
+                       Err(EditError::Overlap{span: bad.clone(), existing: q.clone()}));
+        }
+        edits.delete(&toks[2]).unwrap();
+        assert_eq!(edits.apply(src).unwrap(), "f(a, b, c, 'e'  // c");
+        assert_eq!(edits.iter().map(|(span, _)| span).collect::<Vec<_>>(),
+                   vec![4..4, 4..4, 5..9, 9..9, 9..10]);
+
+        let mut bad = SourceEdits::new();
+        bad.replace_span(7..8, "").unwrap();
+        assert_eq!(bad.apply(src), Err(EditError::NotCharBoundary{offset: 7}));
+        assert_eq!(bad.apply("short"), Err(EditError::OutOfRange{span: 7..8}));
+        let backward = Range{ start: 3, end: 2 };
+        assert_eq!(bad.replace_span(backward.clone(), ""),
+                   Err(EditError::OutOfRange{span: backward}));
+    }
+}
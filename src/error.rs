@@ -0,0 +1,581 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Problems found while pretokenizing.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// A recoverable problem found in the input.  The
+/// [Pretokenizer](struct.Pretokenizer.html) always continues past these, but
+/// [Pretokenizer::strict()](struct.Pretokenizer.html#method.strict) reports
+/// them as errors.
+///
+/// Each variant carries the line where the offending construct starts and
+/// its byte `span`.  `PretokError` implements `std::error::Error`, so it can
+/// be propagated with `?` into `Box<dyn Error>` and similar error stacks.
+/// ```
+/// use pretok::{Pretokenizer, PretokError};
+/// fn count(s: &str) -> Result<usize, Box<dyn std::error::Error>> {
+///     let mut n = 0;
+///     for tok in Pretokenizer::new(s).strict() {
+///         tok?;
+///         n += 1;
+///     }
+///     Ok(n)
+/// }
+/// assert_eq!(count("a b").unwrap(), 2);
+/// let e = count("a\n/* b").unwrap_err();
+/// assert_eq!(e.to_string(), "unterminated block comment starting at line 2");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PretokError {
+    /// A quoted string is still open at the end of input.
+    UnterminatedString {
+        /// The line of the opening quote.
+        line: usize,
+        /// From the opening quote to the end of input.
+        span: Range<usize>,
+    },
+    /// A block comment is still open at the end of input.
+    UnterminatedBlockComment {
+        /// The line of the opening `/*`.
+        line: usize,
+        /// From the opening `/*` to the end of input.
+        span: Range<usize>,
+    },
+    /// A skip region or tool directive region is still open at the end of
+    /// input.
+    UnterminatedRegion {
+        /// The line of the opening marker.
+        line: usize,
+        /// From the opening marker to the end of input.
+        span: Range<usize>,
+    },
+    /// A pretoken is longer than
+    /// [Config::max_token_len()](struct.Config.html#method.max_token_len).
+    TokenTooLong {
+        /// The last line of the pretoken.
+        line: usize,
+        /// The pretoken.
+        span: Range<usize>,
+    },
+    /// A physical line is longer than
+    /// [Config::max_line_len()](struct.Config.html#method.max_line_len).
+    LineTooLong {
+        /// The line.
+        line: usize,
+        /// The line without its line break.
+        span: Range<usize>,
+    },
+    /// Pretokenizing stopped early because the budget set by
+    /// [Config::max_tokens()](struct.Config.html#method.max_tokens) or
+    /// [Config::max_bytes()](struct.Config.html#method.max_bytes) ran out.
+    BudgetExhausted {
+        /// The line where pretokenizing stopped.
+        line: usize,
+        /// From where pretokenizing stopped to the end of input.
+        span: Range<usize>,
+    },
+    /// A nested block comment is deeper than
+    /// [Config::max_comment_depth()](struct.Config.html#method.max_comment_depth).
+    CommentTooDeep {
+        /// The line of the offending opener.
+        line: usize,
+        /// The offending `/*`.
+        span: Range<usize>,
+    },
+    /// A Unicode bidirectional control character found by
+    /// [Config::detect_bidi()](struct.Config.html#method.detect_bidi).
+    BidiControl {
+        /// The character.
+        ch: char,
+        /// The line of the character.
+        line: usize,
+        /// The character.
+        span: Range<usize>,
+    },
+    /// A character in a word pretoken that is commonly mistaken for an
+    /// ASCII character, found by
+    /// [Config::detect_non_ascii()](struct.Config.html#method.detect_non_ascii).
+    Confusable {
+        /// The character.
+        ch: char,
+        /// The ASCII character it resembles.
+        looks_like: char,
+        /// The line of the character.
+        line: usize,
+        /// The character.
+        span: Range<usize>,
+    },
+    /// A control character in a pretoken found by
+    /// [Config::detect_control_chars()](struct.Config.html#method.detect_control_chars).
+    ControlChar {
+        /// The character.
+        ch: char,
+        /// The line of the character.
+        line: usize,
+        /// The character.
+        span: Range<usize>,
+    },
+    /// A NUL character found with
+    /// [Nul::Reject](enum.Nul.html#variant.Reject).
+    Nul {
+        /// The line of the character.
+        line: usize,
+        /// The character.
+        span: Range<usize>,
+    },
+    /// A zero-width character in a word pretoken found with
+    /// [ZeroWidth::Diagnose](enum.ZeroWidth.html#variant.Diagnose).
+    ZeroWidth {
+        /// The character.
+        ch: char,
+        /// The line of the character.
+        line: usize,
+        /// The character.
+        span: Range<usize>,
+    },
+    /// Spaces or tabs at the end of a line found by
+    /// [Config::detect_whitespace()](struct.Config.html#method.detect_whitespace).
+    TrailingWhitespace {
+        /// The line.
+        line: usize,
+        /// The spaces and tabs.
+        span: Range<usize>,
+    },
+    /// A line holding only spaces and tabs found by
+    /// [Config::detect_whitespace()](struct.Config.html#method.detect_whitespace).
+    WhitespaceOnlyLine {
+        /// The line.
+        line: usize,
+        /// The spaces and tabs.
+        span: Range<usize>,
+    },
+    /// Indentation with a tab after a space found by
+    /// [Config::detect_whitespace()](struct.Config.html#method.detect_whitespace).
+    TabAfterSpace {
+        /// The line.
+        line: usize,
+        /// The indentation.
+        span: Range<usize>,
+    },
+    /// Indentation holding both spaces and tabs found by
+    /// [Config::detect_mixed_indent()](struct.Config.html#method.detect_mixed_indent).
+    MixedIndentation {
+        /// The line.
+        line: usize,
+        /// The indentation.
+        span: Range<usize>,
+    },
+    /// The first line ending unlike the first in the input, found by
+    /// [Config::detect_line_endings()](struct.Config.html#method.detect_line_endings).
+    MixedLineEndings {
+        /// The line the ending ends.
+        line: usize,
+        /// The line ending.
+        span: Range<usize>,
+    },
+    /// An opening bracket with no closing bracket, found by
+    /// [check_balance()](fn.check_balance.html).
+    UnclosedBracket {
+        /// The bracket.
+        ch: char,
+        /// The line of the bracket.
+        line: usize,
+        /// The bracket.
+        span: Range<usize>,
+    },
+    /// A closing bracket with no opening bracket, found by
+    /// [check_balance()](fn.check_balance.html).
+    UnmatchedBracket {
+        /// The bracket.
+        ch: char,
+        /// The line of the bracket.
+        line: usize,
+        /// The bracket.
+        span: Range<usize>,
+    },
+    /// A non-ASCII character in a word pretoken found by
+    /// [Config::detect_non_ascii()](struct.Config.html#method.detect_non_ascii).
+    NonAscii {
+        /// The character.
+        ch: char,
+        /// The line of the character.
+        line: usize,
+        /// The character.
+        span: Range<usize>,
+    },
+}
+
+impl PretokError {
+    /// The byte span of the offending construct.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut strict = Pretokenizer::new("x \"y").strict();
+    /// assert_eq!(strict.next().unwrap().unwrap().s, "x");
+    /// assert_eq!(strict.next().unwrap().unwrap_err().span(), 2..4);
+    /// ```
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            PretokError::UnterminatedString { span, .. }
+            | PretokError::UnterminatedBlockComment { span, .. }
+            | PretokError::UnterminatedRegion { span, .. }
+            | PretokError::TokenTooLong { span, .. }
+            | PretokError::LineTooLong { span, .. }
+            | PretokError::BudgetExhausted { span, .. }
+            | PretokError::CommentTooDeep { span, .. }
+            | PretokError::BidiControl { span, .. }
+            | PretokError::Confusable { span, .. }
+            | PretokError::NonAscii { span, .. }
+            | PretokError::ControlChar { span, .. }
+            | PretokError::Nul { span, .. }
+            | PretokError::ZeroWidth { span, .. }
+            | PretokError::TrailingWhitespace { span, .. }
+            | PretokError::WhitespaceOnlyLine { span, .. }
+            | PretokError::TabAfterSpace { span, .. }
+            | PretokError::MixedIndentation { span, .. }
+            | PretokError::MixedLineEndings { span, .. }
+            | PretokError::UnclosedBracket { span, .. }
+            | PretokError::UnmatchedBracket { span, .. } => span.clone(),
+        }
+    }
+
+    /// The line where the offending construct starts.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut strict = Pretokenizer::new("\n\n\"y").strict();
+    /// assert_eq!(strict.next().unwrap().unwrap_err().line(), 3);
+    /// ```
+    pub fn line(&self) -> usize {
+        match self {
+            PretokError::UnterminatedString { line, .. }
+            | PretokError::UnterminatedBlockComment { line, .. }
+            | PretokError::UnterminatedRegion { line, .. }
+            | PretokError::TokenTooLong { line, .. }
+            | PretokError::LineTooLong { line, .. }
+            | PretokError::BudgetExhausted { line, .. }
+            | PretokError::CommentTooDeep { line, .. }
+            | PretokError::BidiControl { line, .. }
+            | PretokError::Confusable { line, .. }
+            | PretokError::NonAscii { line, .. }
+            | PretokError::ControlChar { line, .. }
+            | PretokError::Nul { line, .. }
+            | PretokError::ZeroWidth { line, .. }
+            | PretokError::TrailingWhitespace { line, .. }
+            | PretokError::WhitespaceOnlyLine { line, .. }
+            | PretokError::TabAfterSpace { line, .. }
+            | PretokError::MixedIndentation { line, .. }
+            | PretokError::MixedLineEndings { line, .. }
+            | PretokError::UnclosedBracket { line, .. }
+            | PretokError::UnmatchedBracket { line, .. } => *line,
+        }
+    }
+
+    pub(crate) fn line_mut(&mut self) -> &mut usize {
+        match self {
+            PretokError::UnterminatedString { line, .. }
+            | PretokError::UnterminatedBlockComment { line, .. }
+            | PretokError::UnterminatedRegion { line, .. }
+            | PretokError::TokenTooLong { line, .. }
+            | PretokError::LineTooLong { line, .. }
+            | PretokError::BudgetExhausted { line, .. }
+            | PretokError::CommentTooDeep { line, .. }
+            | PretokError::BidiControl { line, .. }
+            | PretokError::Confusable { line, .. }
+            | PretokError::NonAscii { line, .. }
+            | PretokError::ControlChar { line, .. }
+            | PretokError::Nul { line, .. }
+            | PretokError::ZeroWidth { line, .. }
+            | PretokError::TrailingWhitespace { line, .. }
+            | PretokError::WhitespaceOnlyLine { line, .. }
+            | PretokError::TabAfterSpace { line, .. }
+            | PretokError::MixedIndentation { line, .. }
+            | PretokError::MixedLineEndings { line, .. }
+            | PretokError::UnclosedBracket { line, .. }
+            | PretokError::UnmatchedBracket { line, .. } => line,
+        }
+    }
+
+    pub(crate) fn span_mut(&mut self) -> &mut Range<usize> {
+        match self {
+            PretokError::UnterminatedString { span, .. }
+            | PretokError::UnterminatedBlockComment { span, .. }
+            | PretokError::UnterminatedRegion { span, .. }
+            | PretokError::TokenTooLong { span, .. }
+            | PretokError::LineTooLong { span, .. }
+            | PretokError::BudgetExhausted { span, .. }
+            | PretokError::CommentTooDeep { span, .. }
+            | PretokError::BidiControl { span, .. }
+            | PretokError::Confusable { span, .. }
+            | PretokError::NonAscii { span, .. }
+            | PretokError::ControlChar { span, .. }
+            | PretokError::Nul { span, .. }
+            | PretokError::ZeroWidth { span, .. }
+            | PretokError::TrailingWhitespace { span, .. }
+            | PretokError::WhitespaceOnlyLine { span, .. }
+            | PretokError::TabAfterSpace { span, .. }
+            | PretokError::MixedIndentation { span, .. }
+            | PretokError::MixedLineEndings { span, .. }
+            | PretokError::UnclosedBracket { span, .. }
+            | PretokError::UnmatchedBracket { span, .. } => span,
+        }
+    }
+}
+
+impl fmt::Display for PretokError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PretokError::UnterminatedString { line, .. } => {
+                write!(f, "unterminated string literal starting at line {}", line)
+            }
+            PretokError::UnterminatedBlockComment { line, .. } => {
+                write!(f, "unterminated block comment starting at line {}", line)
+            }
+            PretokError::UnterminatedRegion { line, .. } => {
+                write!(f, "unterminated region starting at line {}", line)
+            }
+            PretokError::TokenTooLong { line, span } => {
+                write!(f, "pretoken of {} bytes is too long at line {}",
+                       span.len(), line)
+            }
+            PretokError::LineTooLong { line, span } => {
+                write!(f, "line {} is too long at {} bytes", line, span.len())
+            }
+            PretokError::BudgetExhausted { line, .. } => {
+                write!(f, "budget exhausted at line {}", line)
+            }
+            PretokError::CommentTooDeep { line, .. } => {
+                write!(f, "block comments nested too deeply at line {}", line)
+            }
+            PretokError::BidiControl { ch, line, .. } => {
+                write!(f, "bidirectional control character U+{:04X} at line {}",
+                       *ch as u32, line)
+            }
+            PretokError::Confusable { ch, looks_like, line, .. } => {
+                write!(f, "'{}' (U+{:04X}) looks like '{}' at line {}",
+                       ch, *ch as u32, looks_like, line)
+            }
+            PretokError::ControlChar { ch, line, .. } => {
+                write!(f, "control character U+{:04X} at line {}", *ch as u32, line)
+            }
+            PretokError::Nul { line, .. } => {
+                write!(f, "NUL character at line {}", line)
+            }
+            PretokError::ZeroWidth { ch, line, .. } => {
+                write!(f, "zero-width character U+{:04X} at line {}", *ch as u32, line)
+            }
+            PretokError::TrailingWhitespace { line, .. } => {
+                write!(f, "trailing whitespace at line {}", line)
+            }
+            PretokError::WhitespaceOnlyLine { line, .. } => {
+                write!(f, "line {} holds only whitespace", line)
+            }
+            PretokError::TabAfterSpace { line, .. } => {
+                write!(f, "tab after space in indentation at line {}", line)
+            }
+            PretokError::MixedIndentation { line, .. } => {
+                write!(f, "tabs and spaces mixed in indentation at line {}", line)
+            }
+            PretokError::MixedLineEndings { line, .. } => {
+                write!(f, "mixed line endings at line {}", line)
+            }
+            PretokError::UnclosedBracket { ch, line, .. } => {
+                write!(f, "unclosed '{}' opened at line {}", ch, line)
+            }
+            PretokError::UnmatchedBracket { ch, line, .. } => {
+                write!(f, "unmatched '{}' at line {}", ch, line)
+            }
+            PretokError::NonAscii { ch, line, .. } => {
+                write!(f, "unexpected non-ASCII character '{}' (U+{:04X}) at line {}",
+                       ch, *ch as u32, line)
+            }
+        }
+    }
+}
+
+impl Error for PretokError {}
+
+/// Why [Pretokenizer::seek()](struct.Pretokenizer.html#method.seek) or
+/// [Pretokenizer::skip_to_line()](struct.Pretokenizer.html#method.skip_to_line)
+/// could not move to the requested position.
+/// ```
+/// use pretok::{Pretokenizer, SeekError};
+/// let e = Pretokenizer::new("/* a */").seek(3).unwrap_err();
+/// assert_eq!(e, SeekError::InsideComment{offset: 3});
+/// assert_eq!(e.to_string(), "byte offset 3 is inside a comment");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SeekError {
+    /// The offset or line is past the end of input, or for
+    /// [Pretokenizer::split_at()](struct.Pretokenizer.html#method.split_at),
+    /// behind the current position.
+    OutOfRange {
+        /// The requested offset.
+        offset: usize,
+    },
+    /// The offset is in the middle of a UTF-8 sequence.
+    NotCharBoundary {
+        /// The requested offset.
+        offset: usize,
+    },
+    /// The offset is inside a pretoken such as a quoted string.
+    InsidePretoken {
+        /// The pretoken.
+        span: Range<usize>,
+    },
+    /// The offset is inside a comment or other skipped region.
+    InsideComment {
+        /// The requested offset.
+        offset: usize,
+    },
+}
+
+impl fmt::Display for SeekError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SeekError::OutOfRange { offset } => {
+                write!(f, "byte offset {} is past the end of input", offset)
+            }
+            SeekError::NotCharBoundary { offset } => {
+                write!(f, "byte offset {} is not a character boundary", offset)
+            }
+            SeekError::InsidePretoken { span } => {
+                write!(f, "byte offset is inside the pretoken at {}..{}",
+                       span.start, span.end)
+            }
+            SeekError::InsideComment { offset } => {
+                write!(f, "byte offset {} is inside a comment", offset)
+            }
+        }
+    }
+}
+
+impl Error for SeekError {}
+
+/// Input bytes that aren't valid UTF-8, from
+/// [Pretokenizer::from_bytes()](struct.Pretokenizer.html#method.from_bytes)
+/// and related functions.
+/// ```
+/// let e = pretok::validate_utf8(b"ok\nnot \xff ok").unwrap_err();
+/// assert_eq!((e.offset, e.line, e.len), (7, 2, Some(1)));
+/// assert_eq!(e.to_string(), "invalid UTF-8 at line 2, byte offset 7");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidUtf8 {
+    /// The byte offset of the first invalid byte.
+    pub offset: usize,
+    /// The line of the first invalid byte.
+    pub line: usize,
+    /// The number of invalid bytes, or None if the input ends in the middle
+    /// of a UTF-8 sequence.
+    pub len: Option<usize>,
+}
+
+impl fmt::Display for InvalidUtf8 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.len {
+            Some(_) => write!(f, "invalid UTF-8 at line {}, byte offset {}",
+                              self.line, self.offset),
+            None => write!(f, "incomplete UTF-8 at the end of line {}, byte offset {}",
+                           self.line, self.offset),
+        }
+    }
+}
+
+impl Error for InvalidUtf8 {}
+
+/// A file that couldn't be read by
+/// [Pretokenizer::from_file()](struct.Pretokenizer.html#method.from_file).
+#[derive(Debug)]
+pub struct FileError {
+    /// The file.
+    pub path: PathBuf,
+    /// The cause.  A file that isn't UTF-8 gives an error of kind
+    /// [InvalidData](https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidData)
+    /// wrapping an [InvalidUtf8](struct.InvalidUtf8.html).
+    pub error: io::Error,
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.error)
+    }
+}
+
+impl Error for FileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// An edit rejected by [SourceEdits](struct.SourceEdits.html).
+/// ```
+/// use pretok::{EditError, SourceEdits};
+/// let mut edits = SourceEdits::new();
+/// edits.replace_span(2..5, "x").unwrap();
+/// let e = edits.replace_span(4..6, "y").unwrap_err();
+/// assert_eq!(e, EditError::Overlap{span: 4..6, existing: 2..5});
+/// assert_eq!(e.to_string(), "edit at 4..6 overlaps the edit at 2..5");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EditError {
+    /// The edit overlaps one already made.
+    Overlap {
+        /// The rejected edit.
+        span: Range<usize>,
+        /// The edit it overlaps.
+        existing: Range<usize>,
+    },
+    /// The edit extends past the end of the input or is out of order.
+    OutOfRange {
+        /// The edit.
+        span: Range<usize>,
+    },
+    /// The edit starts or ends in the middle of a UTF-8 sequence.
+    NotCharBoundary {
+        /// The offending offset.
+        offset: usize,
+    },
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditError::Overlap { span, existing } => {
+                write!(f, "edit at {}..{} overlaps the edit at {}..{}",
+                       span.start, span.end, existing.start, existing.end)
+            }
+            EditError::OutOfRange { span } => {
+                write!(f, "edit at {}..{} is out of range", span.start, span.end)
+            }
+            EditError::NotCharBoundary { offset } => {
+                write!(f, "byte offset {} is not a character boundary", offset)
+            }
+        }
+    }
+}
+
+impl Error for EditError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_test_0() {
+        let e = PretokError::UnterminatedString{ line: 3, span: 7..9 };
+        assert_eq!(e.to_string(), "unterminated string literal starting at line 3");
+        let e = PretokError::UnterminatedRegion{ line: 1, span: 0..9 };
+        assert_eq!(e.to_string(), "unterminated region starting at line 1");
+        let e = PretokError::BidiControl{ ch: '\u{202E}', line: 2, span: 4..7 };
+        assert_eq!(e.to_string(), "bidirectional control character U+202E at line 2");
+        let boxed: Box<dyn Error> = Box::new(e);
+        assert!(boxed.source().is_none());
+    }
+}
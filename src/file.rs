@@ -0,0 +1,74 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Pretokenizing files.
+
+use crate::{validate_utf8, Config, FileError, OwnedPretokenizer, Pretokenizer};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+impl Pretokenizer<'_> {
+    /// Read the file at `path` and return a pretokenizer that owns its
+    /// contents.  A leading byte order mark is skipped, as
+    /// [Config::bom()](struct.Config.html#method.bom) does by default.  A
+    /// file that isn't UTF-8 gives an error of kind
+    /// [InvalidData](https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidData)
+    /// whose source is an [InvalidUtf8](struct.InvalidUtf8.html) with the
+    /// position of the first bad byte.
+    /// ```no_run
+    /// use pretok::Pretokenizer;
+    /// for tok in Pretokenizer::from_file("main.c")? {
+    ///     println!("{}: {}", tok.line, tok.s);
+    /// }
+    /// # Ok::<(), pretok::FileError>(())
+    /// ```
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<OwnedPretokenizer, FileError> {
+        Pretokenizer::from_file_with_config(path, Config::default())
+    }
+
+    /// Like [Pretokenizer::from_file()](struct.Pretokenizer.html#method.from_file),
+    /// with `cfg`.
+    pub fn from_file_with_config<P: AsRef<Path>>(path: P, cfg: Config)
+        -> Result<OwnedPretokenizer, FileError> {
+        let path = path.as_ref();
+        let fail = |error| FileError{ path: path.to_path_buf(), error };
+        let bytes = fs::read(path).map_err(fail)?;
+        let text = String::from_utf8(bytes).map_err(|e| {
+            let bad = validate_utf8(e.as_bytes()).unwrap_err();
+            fail(io::Error::new(io::ErrorKind::InvalidData, bad))
+        })?;
+        Ok(OwnedPretokenizer::with_config(text, cfg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InvalidUtf8;
+    use std::error::Error;
+
+    #[test]
+    fn file_test_0() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("pretok_file_test_{}.c", std::process::id()));
+        fs::write(&path, "\u{FEFF}int x;\n").unwrap();
+        let toks: Vec<_> = Pretokenizer::from_file(&path).unwrap().map(|t| (t.s, t.offset)).collect();
+        assert_eq!(toks, vec![("int".to_string(), 3), ("x;".to_string(), 7)]);
+        let cfg = Config::new().lossless(true);
+        assert_eq!(Pretokenizer::from_file_with_config(&path, cfg).unwrap().count(), 5);
+
+        fs::write(&path, b"ok\nnot \xff ok").unwrap();
+        let e = Pretokenizer::from_file(&path).unwrap_err();
+        assert_eq!(e.path, path);
+        assert_eq!(e.error.kind(), io::ErrorKind::InvalidData);
+        assert!(e.to_string().ends_with("invalid UTF-8 at line 2, byte offset 7"));
+        let bad = e.source().unwrap().downcast_ref::<io::Error>().unwrap()
+            .get_ref().unwrap().downcast_ref::<InvalidUtf8>().unwrap();
+        assert_eq!(bad.offset, 7);
+        fs::remove_file(&path).unwrap();
+
+        let e = Pretokenizer::from_file(&path).unwrap_err();
+        assert_eq!(e.error.kind(), io::ErrorKind::NotFound);
+        assert!(e.to_string().contains("pretok_file_test_"));
+    }
+}
@@ -0,0 +1,67 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Finding occurrences of a pretoken.
+
+use crate::{Config, Kind, Pretoken, Pretokenizer};
+use std::ops::Range;
+
+/// The spans of the word pretokens of `s` equal to `needle`, in input
+/// order.  Text in strings and comments never matches, and neither does
+/// text that is only part of a pretoken, so use
+/// [Config::punctuation()](struct.Config.html#method.punctuation) to split
+/// identifiers from the punctuation around them.
+/// ```
+/// let src = "n = 1; // n\nprint(\"n\", n);";
+/// assert_eq!(pretok::find_all(src, "n"), vec![0..1]);
+/// let cfg = pretok::Config::new().punctuation("(),;");
+/// assert_eq!(pretok::find_all_with(src, "n", cfg), vec![0..1, 23..24]);
+/// ```
+pub fn find_all(s: &str, needle: &str) -> Vec<Range<usize>> {
+    find_all_with(s, needle, Config::default())
+}
+
+/// Like [find_all()](fn.find_all.html), with `cfg`.
+pub fn find_all_with(s: &str, needle: &str, cfg: Config) -> Vec<Range<usize>> {
+    find_matching_with(s, |t| t.s == needle, cfg)
+}
+
+/// The spans of the word pretokens of `s` for which `pred` is true, in
+/// input order.  Strings and comments are skipped as by
+/// [find_all()](fn.find_all.html).
+/// ```
+/// let src = "x1 = \"x2\" + x3 /* x4 */";
+/// let found = pretok::find_matching(src, |t| t.s.starts_with('x'));
+/// assert_eq!(found, vec![0..2, 12..14]);
+/// ```
+pub fn find_matching<F>(s: &str, pred: F) -> Vec<Range<usize>>
+where F: FnMut(&Pretoken<'_>) -> bool {
+    find_matching_with(s, pred, Config::default())
+}
+
+/// Like [find_matching()](fn.find_matching.html), with `cfg`.
+pub fn find_matching_with<F>(s: &str, mut pred: F, cfg: Config) -> Vec<Range<usize>>
+where F: FnMut(&Pretoken<'_>) -> bool {
+    Pretokenizer::with_config(s, cfg)
+        .filter(|t| t.kind == Kind::Word && pred(t))
+        .map(|t| t.span())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_test_1() {
+        assert_eq!(find_all("", "x"), vec![]);
+        assert_eq!(find_all("x", ""), vec![]);
+        let src = "#define x 1\nx x\"x\" `x` /*x*/ x// x\n";
+        assert_eq!(find_all(src, "x"), vec![8..9, 12..13, 14..15, 29..30]);
+        let cfg = Config::new().directives(true);
+        assert_eq!(find_all_with(src, "x", cfg), vec![12..13, 14..15, 29..30]);
+        assert_eq!(find_all(src, "`x`"), vec![19..22]);
+        let mut seen = 0;
+        assert_eq!(find_matching(src, |_| { seen += 1; false }), vec![]);
+        assert_eq!(seen, 7);
+    }
+}
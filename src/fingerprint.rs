@@ -0,0 +1,75 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! A stable hash of the pretokens of an input.
+
+use crate::{Config, Pretokenizer};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a, which unlike `std`'s hashers is specified and so gives
+/// the same result on every platform and Rust version.
+pub(crate) struct Fnv(pub(crate) u64);
+
+impl Fnv {
+    pub(crate) fn new() -> Fnv {
+        Fnv(FNV_OFFSET)
+    }
+
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ b as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+/// A 64-bit hash of the pretokens of `s`: their text and kind, but not
+/// their positions.  Inputs that differ only in comments and whitespace
+/// between pretokens have the same fingerprint, so a build system can skip
+/// work when only comments or formatting changed.  The hash is stable
+/// across runs and platforms.
+/// ```
+/// let a = pretok::fingerprint("int x = 1; // one");
+/// let b = pretok::fingerprint("int   x = 1;\n/* uno */");
+/// assert_eq!(a, b);
+/// assert_ne!(a, pretok::fingerprint("int x = 2;"));
+/// ```
+pub fn fingerprint(s: &str) -> u64 {
+    fingerprint_with(s, Config::default())
+}
+
+/// Same as [fingerprint()](fn.fingerprint.html), pretokenizing with `cfg`.
+/// With [Config::lossless()](struct.Config.html#method.lossless), comments
+/// and whitespace are hashed too.
+pub fn fingerprint_with(s: &str, cfg: Config) -> u64 {
+    let mut hash = Fnv::new();
+    for tok in Pretokenizer::with_config(s, cfg) {
+        // The length keeps "ab" distinct from "a" "b".
+        hash.write(&[tok.kind.code()]);
+        hash.write(&(tok.s.len() as u64).to_le_bytes());
+        hash.write(tok.s.as_bytes());
+    }
+    hash.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprint_test_0() {
+        let base = fingerprint("f(a, \"b c\");");
+        assert_eq!(base, fingerprint("  f(a,/* x */ \"b c\");\n\n// y"));
+        assert_eq!(base, fingerprint("f(a,\"b c\");"));
+        assert_ne!(base, fingerprint("f(a , \"b c\");"));
+        assert_ne!(base, fingerprint("f(a, \"b  c\");"));
+        assert_ne!(base, fingerprint("f(a, b c);"));
+        assert_ne!(fingerprint("ab"), fingerprint("a b"));
+        assert_eq!(fingerprint(""), FNV_OFFSET);
+        // Pinned, so an accidental change to the hash is noticed.
+        assert_eq!(fingerprint("a"), 0xd669_136e_8c5c_9e4d);
+
+        let cfg = Config::new().lossless(true);
+        assert_ne!(fingerprint_with("a b", cfg.clone()), fingerprint_with("a  b", cfg));
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! A pretokenizer whose grammar is fixed at compile time.
+
+use crate::{Kind, Pretoken};
+use memchr::{memchr, memchr2, memchr_iter, memmem};
+
+/// A pretokenizer for the default grammar, with features chosen by const
+/// generic parameters rather than a [Config](struct.Config.html).  Features
+/// turned off are compiled out entirely, leaving no runtime branches.
+///
+/// * `BLOCK_COMMENTS`: Skip `/* block comments */`.  Otherwise `/*` is
+///   ordinary text.
+/// * `ESCAPES`: A backslash in a quoted string escapes the next character.
+/// * `LINES`: Count lines.  Otherwise pretokens have a `line` of 0.
+///
+/// Every feature is on by default, giving the same pretokens as
+/// [Pretokenizer::new()](struct.Pretokenizer.html#method.new), although the
+/// defaults need a type annotation such as `<FixedPretokenizer>::new(s)`.
+/// A leading byte order mark is skipped, as there.  Nothing else is
+/// supported: there are no hooks, checks or diagnostics.
+/// ```
+/// use pretok::FixedPretokenizer;
+/// let v: Vec<_> = FixedPretokenizer::<false, true, false>::new("a /* b */ \"c\"")
+///     .map(|t| (t.s, t.line))
+///     .collect();
+/// assert_eq!(v, vec![("a", 0), ("/*", 0), ("b", 0), ("*/", 0), ("\"c\"", 0)]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct FixedPretokenizer<'a, const BLOCK_COMMENTS: bool = true,
+                             const ESCAPES: bool = true, const LINES: bool = true> {
+    s: &'a str,
+    /// Byte offset of the unconsumed input
+    pos: usize,
+    /// The current line, if counting lines
+    line: usize,
+}
+
+impl<'a, const BLOCK_COMMENTS: bool, const ESCAPES: bool, const LINES: bool>
+    FixedPretokenizer<'a, BLOCK_COMMENTS, ESCAPES, LINES> {
+    /// Create a pretokenizer over `s`, skipping a leading byte order mark.
+    pub fn new(s: &'a str) -> Self {
+        let pos = if s.starts_with('\u{feff}') { '\u{feff}'.len_utf8() } else { 0 };
+        FixedPretokenizer{ s, pos, line: 1 }
+    }
+
+    /// The byte offset of the unconsumed input.
+    pub fn byte_pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Consume the input up to `end`, counting its lines.
+    fn consume(&mut self, end: usize) {
+        if LINES {
+            self.line += memchr_iter(b'\n', &self.s.as_bytes()[self.pos..end]).count();
+        }
+        self.pos = end;
+    }
+
+    /// True if the input at `i` starts a comment.
+    fn is_comment(&self, i: usize) -> bool {
+        let b = self.s.as_bytes();
+        b[i] == b'/' && match b.get(i + 1) {
+            Some(b'/') => true,
+            Some(b'*') => BLOCK_COMMENTS,
+            _ => false,
+        }
+    }
+
+    /// The end of the quoted string starting at `start`.
+    fn quoted_end(&self, start: usize) -> usize {
+        let b = self.s.as_bytes();
+        let mut i = start + 1;
+        loop {
+            let rest = &b[i..];
+            let found = if ESCAPES { memchr2(b'"', b'\\', rest) } else { memchr(b'"', rest) };
+            match found {
+                None => return b.len(),
+                Some(n) if rest[n] == b'"' => return i + n + 1,
+                Some(n) => {
+                    // Skip the backslash and the character it escapes.
+                    i += n + 1;
+                    i += self.s[i..].chars().next().map_or(0, char::len_utf8);
+                }
+            }
+        }
+    }
+
+    /// The end of the word starting at `start`.
+    fn word_end(&self, start: usize) -> usize {
+        let b = self.s.as_bytes();
+        let mut i = start + 1;
+        while i < b.len() {
+            match b[i] {
+                b' ' | b'\t' | b'\n' | b'"' => break,
+                b'/' if self.is_comment(i) => break,
+                _ => i += 1,
+            }
+        }
+        i
+    }
+}
+
+impl<'a, const BLOCK_COMMENTS: bool, const ESCAPES: bool, const LINES: bool> Iterator
+    for FixedPretokenizer<'a, BLOCK_COMMENTS, ESCAPES, LINES> {
+    type Item = Pretoken<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let b = self.s.as_bytes();
+        loop {
+            let start = self.pos;
+            let (end, kind) = match *b.get(start)? {
+                b' ' | b'\t' | b'\n' => {
+                    self.consume(start + 1);
+                    continue;
+                }
+                b'/' if b.get(start + 1) == Some(&b'/') => {
+                    let end = memchr(b'\n', &b[start..]).map_or(b.len(), |n| start + n);
+                    self.consume(end);
+                    continue;
+                }
+                b'/' if BLOCK_COMMENTS && b.get(start + 1) == Some(&b'*') => {
+                    let body = start + 2;
+                    let end = memmem::find(&b[body..], b"*/").map_or(b.len(), |n| body + n + 2);
+                    self.consume(end);
+                    continue;
+                }
+                b'"' => (self.quoted_end(start), Kind::Quoted),
+                _ => (self.word_end(start), Kind::Word),
+            };
+            self.consume(end);
+            let line = if LINES { self.line } else { 0 };
+            return Some(Pretoken{ s: &self.s[start..end], line, offset: start, kind });
+        }
+    }
+}
+
+impl<'a, const BLOCK_COMMENTS: bool, const ESCAPES: bool, const LINES: bool>
+    std::iter::FusedIterator for FixedPretokenizer<'a, BLOCK_COMMENTS, ESCAPES, LINES> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testgen::Lcg;
+    use crate::Pretokenizer;
+
+    #[test]
+    fn fixed_test_0() {
+        // The same pretokens as the configurable pretokenizer
+        let pieces = ["/", "*", "\"", "\\", "\n", " ", "\t", "a", "//", "/*", "*/",
+                      "\u{e9}", "\r", "\u{feff}"];
+        let mut rng = Lcg::new(3);
+        for _ in 0..5000 {
+            let mut src = rng.source(&pieces, 24);
+            if rng.below(4) == 0 {
+                src.insert(0, '\u{feff}');
+            }
+            let want: Vec<_> = Pretokenizer::new(&src).collect();
+            let got: Vec<_> = <FixedPretokenizer>::new(&src).collect();
+            assert_eq!(got, want, "{:?}", src);
+        }
+
+        let v: Vec<_> = FixedPretokenizer::<true, false, true>::new("\"a\\\"b\" c\n/**/d")
+            .map(|t| (t.s, t.line))
+            .collect();
+        assert_eq!(v, vec![("\"a\\\"", 1), ("b", 1), ("\" c\n/**/d", 2)]);
+        let v: Vec<_> = <FixedPretokenizer>::new("\u{feff}a").map(|t| (t.s, t.offset)).collect();
+        assert_eq!(v, vec![("a", 3)]);
+    }
+}
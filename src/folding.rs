@@ -0,0 +1,103 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Finding the regions of an input an editor can fold.
+
+use crate::{Config, Kind, Pretokenizer};
+
+/// What a [FoldingRange](struct.FoldingRange.html) folds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FoldKind {
+    /// A block comment.
+    Comment,
+    /// A group in braces.
+    Braces,
+}
+
+/// A region of lines an editor can fold, found by
+/// [folding_ranges()](fn.folding_ranges.html).  Lines count from 1, or
+/// as set by [Config::base()](struct.Config.html#method.base).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FoldingRange {
+    /// The line where the region starts.
+    pub start_line: usize,
+    /// The line where the region ends, after `start_line`.
+    pub end_line: usize,
+    /// What the region holds.
+    pub kind: FoldKind,
+}
+
+/// The foldable regions of `s`: block comments and groups in braces that
+/// span more than one line, in order of where they start.  Braces in
+/// strings and comments are ignored, as are braces that aren't matched.
+/// ```
+/// use pretok::{folding_ranges, FoldKind, FoldingRange};
+/// let src = "/*\n * Main.\n */\nint main() {\n  puts(\"}\");\n}\n";
+/// assert_eq!(folding_ranges(src), vec![
+///     FoldingRange{ start_line: 1, end_line: 3, kind: FoldKind::Comment },
+///     FoldingRange{ start_line: 4, end_line: 6, kind: FoldKind::Braces },
+/// ]);
+/// ```
+pub fn folding_ranges(s: &str) -> Vec<FoldingRange> {
+    folding_ranges_with(s, Config::default())
+}
+
+/// Like [folding_ranges()](fn.folding_ranges.html), with `cfg`.
+pub fn folding_ranges_with(s: &str, cfg: Config) -> Vec<FoldingRange> {
+    let mut ranges = Vec::new();
+    // The start offset and line of each open brace.
+    let mut open = Vec::new();
+    let (mut pos, mut line) = (0, cfg.first_line());
+    let mut line_at = |offset: usize| {
+        line += memchr::memchr_iter(b'\n', &s.as_bytes()[pos..offset]).count();
+        pos = offset;
+        line
+    };
+    for tok in Pretokenizer::with_config(s, cfg.lossless(true)) {
+        match tok.kind {
+            Kind::Comment if tok.s.starts_with("/*") => {
+                let start_line = line_at(tok.offset);
+                let end_line = line_at(tok.offset + tok.s.len());
+                if end_line > start_line {
+                    ranges.push((tok.offset, FoldingRange{ start_line, end_line,
+                                                           kind: FoldKind::Comment }));
+                }
+            }
+            Kind::Word => {
+                for (i, c) in tok.s.char_indices().filter(|&(_, c)| c == '{' || c == '}') {
+                    let offset = tok.offset + i;
+                    let at = line_at(offset);
+                    if c == '{' {
+                        open.push((offset, at));
+                    } else if let Some((start, start_line)) = open.pop() {
+                        if at > start_line {
+                            ranges.push((start, FoldingRange{ start_line, end_line: at,
+                                                              kind: FoldKind::Braces }));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    ranges.sort_by_key(|&(start, _)| start);
+    ranges.into_iter().map(|(_, range)| range).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folding_test_1() {
+        let src = "} a {\n  /* one */ {x}\n  b{/*\n*/\n\n} \"{\" // {\n}{ \n}}";
+        let v: Vec<_> = folding_ranges(src).iter()
+            .map(|r| (r.start_line, r.end_line, r.kind))
+            .collect();
+        assert_eq!(v, vec![(1, 7, FoldKind::Braces),
+                           (3, 6, FoldKind::Braces),
+                           (3, 4, FoldKind::Comment),
+                           (7, 8, FoldKind::Braces)]);
+        assert!(folding_ranges("{\n").is_empty());
+        assert!(folding_ranges("// {\n}").is_empty());
+    }
+}
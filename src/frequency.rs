@@ -0,0 +1,126 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Counting how often each word occurs in a corpus.
+
+use crate::{Config, Interner, Kind, Pretokenizer, Symbol};
+
+/// How often each word pretoken occurs in the sources added, for
+/// vocabulary analysis.  Words are interned, so repeated words are stored
+/// once.
+/// ```
+/// use pretok::WordCounts;
+/// let mut counts = WordCounts::new();
+/// counts.add("int x = f(x); // x");
+/// counts.add_filtered("x = \"y\" + y;", |w| w != "=");
+/// assert_eq!(counts.count("x"), 2);
+/// assert_eq!(counts.count("\"y\""), 0);
+/// assert_eq!(counts.total(), 7);
+/// assert_eq!(counts.most_common(2), [("x", 2), ("+", 1)]);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct WordCounts {
+    cfg: Config,
+    interner: Interner,
+    /// Occurrences by symbol index
+    counts: Vec<usize>,
+}
+
+impl WordCounts {
+    /// Create empty counts for sources pretokenized with the default
+    /// configuration.
+    pub fn new() -> WordCounts {
+        WordCounts::default()
+    }
+
+    /// Create empty counts for sources pretokenized with `cfg`.
+    pub fn with_config(cfg: Config) -> WordCounts {
+        WordCounts{ cfg, ..WordCounts::default() }
+    }
+
+    /// Count the words of `s`.
+    pub fn add(&mut self, s: &str) {
+        self.add_filtered(s, |_| true);
+    }
+
+    /// Count the words of `s` for which `keep` is true.
+    pub fn add_filtered<F>(&mut self, s: &str, mut keep: F)
+        where F: FnMut(&str) -> bool
+    {
+        for tok in Pretokenizer::with_config(s, self.cfg.clone()) {
+            if tok.kind == Kind::Word && keep(tok.s) {
+                let sym = self.interner.intern(tok.s);
+                if sym.index() == self.counts.len() {
+                    self.counts.push(0);
+                }
+                self.counts[sym.index()] += 1;
+            }
+        }
+    }
+
+    /// The number of times `word` was counted.
+    pub fn count(&self, word: &str) -> usize {
+        self.interner.get(word).map_or(0, |sym| self.counts[sym.index()])
+    }
+
+    /// The number of words counted, including repeats.
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// The number of distinct words counted.
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// True if no words were counted.
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// The distinct words and their counts, in the order first seen.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, usize)> + '_ {
+        self.counts.iter().enumerate()
+            .map(move |(i, &n)| (self.interner.resolve_index(i), n))
+    }
+
+    /// The `n` most frequent words and their counts, most frequent first
+    /// and then by text.
+    pub fn most_common(&self, n: usize) -> Vec<(&str, usize)> {
+        let mut words: Vec<_> = self.iter().collect();
+        words.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        words.truncate(n);
+        words
+    }
+
+    /// The interner holding the words, whose symbols index the counts.
+    pub fn interner(&self) -> &Interner {
+        &self.interner
+    }
+
+    /// The number of times the word of `sym` was counted.
+    pub fn symbol_count(&self, sym: Symbol) -> usize {
+        self.counts.get(sym.index()).copied().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frequency_test_1() {
+        let mut counts = WordCounts::with_config(Config::new().directives(true));
+        assert!(counts.is_empty());
+        counts.add("#define N 1\nN N /* N */ \"N\" M");
+        counts.add_filtered("N a b a", |w| w.len() == 1);
+        assert_eq!(counts.count("N"), 3);
+        assert_eq!(counts.count("#define N 1"), 0);
+        assert_eq!(counts.len(), 4);
+        assert_eq!(counts.total(), 7);
+        let words: Vec<_> = counts.iter().collect();
+        assert_eq!(words, [("N", 3), ("M", 1), ("a", 2), ("b", 1)]);
+        assert_eq!(counts.most_common(9), [("N", 3), ("a", 2), ("M", 1), ("b", 1)]);
+        let sym = counts.interner().get("a").unwrap();
+        assert_eq!(counts.symbol_count(sym), 2);
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Pretokens with the trivia attached to them.
+
+use crate::{Config, Kind, Pretoken, RawPretokenizer};
+use std::collections::VecDeque;
+use std::ops::Range;
+
+/// A pretoken with its full extent: the whitespace, newlines and comments
+/// before it since the previous full extent, the pretoken, and its
+/// trailing trivia.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FullToken<'a> {
+    /// The pretoken.
+    pub token: Pretoken<'a>,
+    /// The text of the full extent.
+    pub s: &'a str,
+    /// The byte offset of the full extent.
+    pub offset: usize,
+}
+
+impl<'a> FullToken<'a> {
+    /// The byte range of the full extent in the input.
+    pub fn span(&self) -> Range<usize> {
+        self.offset..self.offset + self.s.len()
+    }
+
+    /// The trivia before the pretoken.
+    pub fn leading(&self) -> &'a str {
+        &self.s[..self.token.offset - self.offset]
+    }
+
+    /// The trivia after the pretoken.
+    pub fn trailing(&self) -> &'a str {
+        &self.s[self.token.offset - self.offset + self.token.s.len()..]
+    }
+}
+
+/// An iterator over the pretokens of the input, each with its
+/// [full extent](struct.FullToken.html), for formatters and refactorings
+/// that move code along with its comments.
+///
+/// As in Roslyn, a pretoken's trailing trivia runs through the first
+/// newline after it, or up to the next pretoken on the same line, and the
+/// rest of the trivia before a pretoken leads it.  Trivia after the last
+/// pretoken trails it, so the full extents cover the whole input, except
+/// for input without any pretokens.
+/// ```
+/// use pretok::FullTokens;
+/// let src = "// add\nx += 1; // inc\n\n  /* twice */ y";
+/// let v: Vec<_> = FullTokens::new(src).map(|t| (t.leading(), t.token.s, t.trailing()))
+///     .collect();
+/// assert_eq!(v, vec![("// add\n", "x", " "), ("", "+=", " "), ("", "1;", " // inc\n"),
+///                    ("\n  /* twice */ ", "y", "")]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct FullTokens<'a> {
+    s: &'a str,
+    raw: RawPretokenizer<'a>,
+    /// Pieces read ahead
+    queue: VecDeque<Pretoken<'a>>,
+    /// Offset of the next full extent
+    offset: usize,
+}
+
+impl<'a> FullTokens<'a> {
+    /// Create a full-extent iterator with the default configuration.
+    pub fn new(s: &'a str) -> FullTokens<'a> {
+        FullTokens::with_config(s, Config::default())
+    }
+
+    /// Create a full-extent iterator with `cfg`.
+    pub fn with_config(s: &'a str, cfg: Config) -> FullTokens<'a> {
+        FullTokens{ s, raw: RawPretokenizer::with_config(s, cfg), queue: VecDeque::new(),
+                    offset: 0 }
+    }
+
+    fn read(&mut self) -> Option<Pretoken<'a>> {
+        self.queue.pop_front().or_else(|| self.raw.next())
+    }
+}
+
+impl<'a> Iterator for FullTokens<'a> {
+    type Item = FullToken<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = loop {
+            let piece = self.read()?;
+            if !piece.kind.is_trivia() {
+                break piece;
+            }
+        };
+        let mut end = token.offset + token.s.len();
+        let mut at_eol = true;
+        while let Some(piece) = self.read() {
+            if !piece.kind.is_trivia() {
+                self.queue.push_back(piece);
+                at_eol = false;
+                break;
+            }
+            end = piece.offset + piece.s.len();
+            if piece.kind == Kind::Newline {
+                break;
+            }
+        }
+        if at_eol {
+            // Take the trivia up to the end of input if no pretoken follows.
+            for piece in self.raw.by_ref() {
+                let trivia = piece.kind.is_trivia();
+                self.queue.push_back(piece);
+                if !trivia {
+                    break;
+                }
+            }
+            if self.queue.back().is_none_or(|p| p.kind.is_trivia()) {
+                self.queue.clear();
+                end = self.s.len();
+            }
+        }
+        let offset = self.offset;
+        self.offset = end;
+        Some(FullToken{ token, s: &self.s[offset..end], offset })
+    }
+}
+
+impl<'a> std::iter::FusedIterator for FullTokens<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_test_1() {
+        let parts = |src| -> Vec<_> {
+            FullTokens::new(src).map(|t| (t.leading(), t.token.s, t.trailing())).collect()
+        };
+        assert_eq!(parts(""), vec![]);
+        assert_eq!(parts(" // only\n"), vec![]);
+        assert_eq!(parts(" a \n\n b /* c\n */ d\n// e\n\n"),
+                   vec![(" ", "a", " \n"), ("\n ", "b", " /* c\n */ "),
+                        ("", "d", "\n// e\n\n")]);
+        assert_eq!(parts("\"a\nb\"\t\nc"), vec![("", "\"a\nb\"", "\t\n"), ("", "c", "")]);
+        let src = "x /* 1 */\n\t/* 2 */ y\n";
+        let full: Vec<_> = FullTokens::new(src).collect();
+        assert_eq!(full.iter().map(|t| t.s).collect::<String>(), src);
+        assert_eq!(full.iter().map(|t| t.span()).collect::<Vec<_>>(), vec![0..10, 10..21]);
+        assert_eq!((full[1].token.offset, full[1].token.line), (19, 2));
+    }
+}
@@ -0,0 +1,114 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! The whitespace and comments between pretokens.
+
+use crate::{Config, Kind, Pretoken, RawPretokenizer};
+
+/// The input between two pretokens: whitespace, newlines and comments.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gap<'a> {
+    /// The text of the gap, possibly empty.
+    pub s: &'a str,
+    /// The byte offset of the gap.
+    pub offset: usize,
+    /// The pieces of the gap in order, each of a
+    /// [trivia](enum.Kind.html#method.is_trivia) kind.
+    pub pieces: Vec<Pretoken<'a>>,
+}
+
+impl<'a> Gap<'a> {
+    /// True if the gap holds a line break.
+    pub fn has_newline(&self) -> bool {
+        self.pieces.iter().any(|p| p.kind == Kind::Newline)
+    }
+
+    /// True if the gap holds a comment.
+    pub fn has_comment(&self) -> bool {
+        self.pieces.iter().any(|p| p.kind == Kind::Comment)
+    }
+}
+
+/// An iterator over the [Gap](struct.Gap.html)s of the input.  The first
+/// gap precedes the first pretoken, each following gap comes after the
+/// pretoken before it, and the last gap runs to the end of input, so there
+/// is always one more gap than pretokens.  Gaps between adjacent pretokens
+/// are empty.
+/// ```
+/// use pretok::Gaps;
+/// let gaps: Vec<_> = Gaps::new("a /* b */\n\"c\"d").map(|g| g.s).collect();
+/// assert_eq!(gaps, vec!["", " /* b */\n", "", ""]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Gaps<'a> {
+    s: &'a str,
+    raw: RawPretokenizer<'a>,
+    /// Offset of the next gap
+    offset: usize,
+    done: bool,
+}
+
+impl<'a> Gaps<'a> {
+    /// Create a gap iterator with the default configuration.
+    pub fn new(s: &'a str) -> Gaps<'a> {
+        Gaps::with_config(s, Config::default())
+    }
+
+    /// Create a gap iterator with `cfg`.
+    /// ```
+    /// use pretok::{Config, Gaps};
+    /// let cfg = Config::new().skip_region("#if 0", "#endif");
+    /// let mut gaps = Gaps::with_config("#if 0\nx\n#endif\ny", cfg);
+    /// assert!(gaps.next().unwrap().has_comment());
+    /// ```
+    pub fn with_config(s: &'a str, cfg: Config) -> Gaps<'a> {
+        Gaps{ s, raw: RawPretokenizer::with_config(s, cfg), offset: 0, done: false }
+    }
+}
+
+impl<'a> Iterator for Gaps<'a> {
+    type Item = Gap<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let start = self.offset;
+        let mut end = start;
+        let mut pieces = Vec::new();
+        self.done = true;
+        for tok in self.raw.by_ref() {
+            if !tok.kind.is_trivia() {
+                self.offset = tok.offset + tok.s.len();
+                self.done = false;
+                break;
+            }
+            end = tok.offset + tok.s.len();
+            pieces.push(tok);
+        }
+        Some(Gap{ s: &self.s[start..end], offset: start, pieces })
+    }
+}
+
+impl<'a> std::iter::FusedIterator for Gaps<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Pretokenizer;
+
+    #[test]
+    fn gaps_test_0() {
+        let gaps: Vec<_> = Gaps::new("").collect();
+        assert_eq!(gaps, vec![Gap{s: "", offset: 0, pieces: vec![]}]);
+
+        let src = " a\t// b\n  c \"d\" ";
+        let gaps: Vec<_> = Gaps::new(src).collect();
+        assert_eq!(gaps.len(), Pretokenizer::new(src).count() + 1);
+        let v: Vec<_> = gaps.iter().map(|g| (g.s, g.offset)).collect();
+        assert_eq!(v, vec![(" ", 0), ("\t// b\n  ", 2), (" ", 11), (" ", 15)]);
+        assert!(gaps[1].has_newline() && gaps[1].has_comment());
+        assert!(!gaps[2].has_newline() && !gaps[2].has_comment());
+        let kinds: Vec<_> = gaps[1].pieces.iter().map(|p| p.kind).collect();
+        assert_eq!(kinds, vec![Kind::Whitespace, Kind::Comment, Kind::Newline, Kind::Whitespace]);
+    }
+}
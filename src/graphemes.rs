@@ -0,0 +1,72 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Grapheme cluster columns and boundaries.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The line and the column in grapheme clusters, both from 1, of byte
+/// `offset` of `s`, as a user sees it, so that a letter followed by
+/// combining marks is one column.  An offset inside a cluster is taken as
+/// the start of the cluster, and an offset past the end as the end.  See
+/// also [LineIndex](struct.LineIndex.html), which counts characters.
+/// ```
+/// let s = "x;\ne\u{301}t\u{e9} = 1;";
+/// assert_eq!(pretok::grapheme_line_col(s, 6), (2, 2));
+/// assert_eq!(pretok::grapheme_line_col(s, 10), (2, 5));
+/// assert_eq!(pretok::LineIndex::new(s).line_col(10), (2, 6));
+/// ```
+pub fn grapheme_line_col(s: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(s.len());
+    let start = s.as_bytes()[..offset].iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+    let line = 1 + memchr::memchr_iter(b'\n', &s.as_bytes()[..start]).count();
+    let col = s[start..].grapheme_indices(true)
+        .take_while(|(i, g)| start + i + g.len() <= offset)
+        .count();
+    (line, col + 1)
+}
+
+/// The end of the grapheme cluster of `s` that contains the character
+/// before byte `end`, or `end` if that is a boundary.  Only non-ASCII
+/// characters are taken in, so line breaks and the characters the scanner
+/// looks for always stay outside.
+pub(crate) fn cluster_end(s: &str, end: usize) -> usize {
+    if end == 0 || s[end..].starts_with(|c: char| c.is_ascii()) {
+        return end;
+    }
+    let mut cursor = unicode_segmentation::GraphemeCursor::new(end, s.len(), true);
+    if cursor.is_boundary(s, 0) != Ok(false) {
+        return end;
+    }
+    let next = match cursor.next_boundary(s, 0) {
+        Ok(Some(next)) => next,
+        _ => s.len(),
+    };
+    s[end..next].find(|c: char| c.is_ascii()).map_or(next, |i| end + i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, Kind, Pretokenizer};
+
+    #[test]
+    fn graphemes_test_1() {
+        let s = "\"e\"\u{301} x \u{301}y/**/\u{20DD}\u{301}\n";
+        let pieces = |cfg: Config| -> Vec<_> {
+            Pretokenizer::with_config(s, cfg.lossless(true)).map(|t| (t.kind, t.s)).collect()
+        };
+        use Kind::*;
+        assert_eq!(pieces(Config::new().graphemes(true)),
+                   vec![(Quoted, "\"e\"\u{301}"), (Whitespace, " "), (Word, "x"),
+                        (Whitespace, " \u{301}"), (Word, "y"),
+                        (Comment, "/**/\u{20DD}\u{301}"), (Newline, "\n")]);
+        assert_eq!(pieces(Config::new())[1], (Word, "\u{301}"));
+        assert_eq!(cluster_end("a\r\n", 2), 2);
+        assert_eq!(cluster_end("\u{600}\"", 2), 2);
+        assert_eq!(grapheme_line_col(s, 2), (1, 3));
+        assert_eq!(grapheme_line_col(s, 4), (1, 3));
+        assert_eq!(grapheme_line_col(s, 5), (1, 4));
+        assert_eq!(grapheme_line_col(s, 100), (2, 1));
+        assert_eq!(grapheme_line_col("", 0), (1, 1));
+    }
+}
@@ -0,0 +1,180 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Building rowan green trees from lossless pretokens.
+
+use crate::{Config, Pretoken, Pretokenizer};
+use rowan::{Checkpoint, GreenNode, GreenNodeBuilder, SyntaxKind};
+
+/// Drives a [rowan](https://docs.rs/rowan) `GreenNodeBuilder` from the
+/// pretokens of an input, whitespace and comments included, so a parser
+/// deals only in the other pretokens and nodes while the tree still holds
+/// every byte of the input.  `kind_of` gives the syntax kind of each
+/// pretoken, trivia included.
+///
+/// Trivia is added just before the next pretoken or node, whichever comes
+/// first, so it belongs to the enclosing node: nodes start and end with a
+/// pretoken that isn't trivia unless they're empty.
+/// ```
+/// use pretok::{Config, GreenBuilder, Kind};
+/// use rowan::SyntaxKind;
+/// const ROOT: SyntaxKind = SyntaxKind(0);
+/// const CALL: SyntaxKind = SyntaxKind(1);
+/// let kind_of = |t: &pretok::Pretoken| SyntaxKind(t.kind as u16 + 10);
+///
+/// let mut b = GreenBuilder::new("f ( x ) // done", Config::new(), ROOT, kind_of);
+/// b.start_node(CALL);
+/// while b.peek().is_some() {
+///     b.bump();
+/// }
+/// b.finish_node();
+/// let tree = b.finish();
+/// assert_eq!(tree.to_string(), "f ( x ) // done");
+/// let call = tree.children().next().unwrap().into_node().unwrap();
+/// assert_eq!(call.to_string(), "f ( x )");
+/// ```
+pub struct GreenBuilder<'a, F> {
+    pt: Pretokenizer<'a>,
+    kind_of: F,
+    builder: GreenNodeBuilder<'static>,
+    /// Trivia not yet added, before `next`
+    trivia: Vec<Pretoken<'a>>,
+    /// The next pretoken that isn't trivia, once found
+    next: Option<Pretoken<'a>>,
+}
+
+impl<'a, F> GreenBuilder<'a, F>
+where
+    F: FnMut(&Pretoken<'a>) -> SyntaxKind,
+{
+    /// Build a tree for `s`, pretokenized with `cfg` made lossless, under a
+    /// root node of kind `root`.
+    pub fn new(s: &'a str, cfg: Config, root: SyntaxKind, kind_of: F) -> GreenBuilder<'a, F> {
+        let mut builder = GreenNodeBuilder::new();
+        builder.start_node(root);
+        GreenBuilder{
+            pt: Pretokenizer::with_config(s, cfg.lossless(true)),
+            kind_of,
+            builder,
+            trivia: Vec::new(),
+            next: None,
+        }
+    }
+
+    /// The next pretoken that isn't trivia, without adding it.
+    pub fn peek(&mut self) -> Option<&Pretoken<'a>> {
+        while self.next.is_none() {
+            let tok = self.pt.next()?;
+            if tok.kind.is_trivia() {
+                self.trivia.push(tok);
+            } else {
+                self.next = Some(tok);
+            }
+        }
+        self.next.as_ref()
+    }
+
+    /// Add the trivia before the next pretoken, then the pretoken, returning
+    /// it, or None at the end of input.
+    pub fn bump(&mut self) -> Option<Pretoken<'a>> {
+        self.peek();
+        self.add_trivia();
+        let tok = self.next.take()?;
+        let kind = (self.kind_of)(&tok);
+        self.builder.token(kind, tok.s);
+        Some(tok)
+    }
+
+    /// Start a node of kind `kind` after the trivia before the next pretoken.
+    pub fn start_node(&mut self, kind: SyntaxKind) {
+        self.peek();
+        self.add_trivia();
+        self.builder.start_node(kind);
+    }
+
+    /// Finish the innermost node.  Trivia after its last pretoken is left
+    /// for what follows.
+    pub fn finish_node(&mut self) {
+        self.builder.finish_node();
+    }
+
+    /// A point before the next pretoken, after its trivia, at which a node
+    /// may be started later with [start_node_at()](#method.start_node_at).
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        self.peek();
+        self.add_trivia();
+        self.builder.checkpoint()
+    }
+
+    /// Start a node of kind `kind` at `checkpoint`, wrapping everything added
+    /// since.
+    pub fn start_node_at(&mut self, checkpoint: Checkpoint, kind: SyntaxKind) {
+        self.builder.start_node_at(checkpoint, kind);
+    }
+
+    /// Add every pretoken left to the root node and return the tree.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a node other than the root is still open.
+    pub fn finish(mut self) -> GreenNode {
+        while self.bump().is_some() {}
+        self.add_trivia();
+        self.builder.finish_node();
+        self.builder.finish()
+    }
+
+    fn add_trivia(&mut self) {
+        for tok in self.trivia.drain(..) {
+            let kind = (self.kind_of)(&tok);
+            self.builder.token(kind, tok.s);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Kind;
+    use rowan::NodeOrToken;
+
+    #[test]
+    fn green_test_1() {
+        const ROOT: SyntaxKind = SyntaxKind(100);
+        const LIST: SyntaxKind = SyntaxKind(101);
+        const ITEM: SyntaxKind = SyntaxKind(102);
+        let src = "/* head */ ( a\n  b /* c */ ) tail \"s\"\n";
+        let mut b = GreenBuilder::new(src, Config::new(), ROOT, |t| SyntaxKind(t.kind as u16));
+        let list = b.checkpoint();
+        b.bump();
+        while b.peek().is_some_and(|t| t.s != ")") {
+            b.start_node(ITEM);
+            b.bump();
+            b.finish_node();
+        }
+        b.bump();
+        b.start_node_at(list, LIST);
+        b.finish_node();
+        let tree = b.finish();
+        assert_eq!(tree.to_string(), src);
+
+        let shape = |node: &rowan::GreenNodeData| -> Vec<String> {
+            node.children().map(|c| match c {
+                NodeOrToken::Node(n) => format!("{:?}:{}", n.kind(), n),
+                NodeOrToken::Token(t) => format!("{:?}", t.kind()),
+            }).collect()
+        };
+        let (ws, comment, word) = (Kind::Whitespace as u16, Kind::Comment as u16,
+                                   Kind::Word as u16);
+        assert_eq!(shape(&tree), vec![
+            format!("SyntaxKind({})", comment), format!("SyntaxKind({})", ws),
+            "SyntaxKind(101):( a\n  b /* c */ )".to_string(),
+            format!("SyntaxKind({})", ws), format!("SyntaxKind({})", word),
+            format!("SyntaxKind({})", ws), format!("SyntaxKind({})", Kind::Quoted as u16),
+            format!("SyntaxKind({})", Kind::Newline as u16)]);
+        let list = tree.children().nth(2).unwrap().into_node().unwrap();
+        assert_eq!(shape(list)[1..4], [format!("SyntaxKind({})", ws),
+                                       "SyntaxKind(102):a".to_string(),
+                                       format!("SyntaxKind({})", Kind::Newline as u16)]);
+        assert_eq!(GreenBuilder::new("", Config::new(), ROOT, |_| ROOT).finish().to_string(), "");
+    }
+}
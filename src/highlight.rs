@@ -0,0 +1,87 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Classifying every byte of an input for syntax highlighting.
+
+use crate::{Config, Pretoken, Pretokenizer};
+use std::ops::Range;
+
+/// A span of the input and the class [highlight()](fn.highlight.html) gave it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Highlight<C> {
+    /// The bytes of the input.
+    pub span: Range<usize>,
+    /// The caller's class for them, such as a style or a CSS class name.
+    pub class: C,
+}
+
+/// Split all of `s` into spans classed by `classify`, for syntax
+/// highlighting.  `classify` is called with each pretoken of `s`, including
+/// whitespace, newlines and comments, and returns its class.  Neighbouring
+/// pretokens of the same class share a span, so the spans are in order,
+/// don't touch one of the same class, and cover `s` with no gaps.
+/// ```
+/// use pretok::{highlight, Highlight, Kind};
+/// let src = "x = \"a\"; // b\n";
+/// let spans = highlight(src, |t| match t.kind {
+///     Kind::Quoted => "string",
+///     Kind::Comment => "comment",
+///     _ if t.s.chars().all(char::is_whitespace) => "plain",
+///     _ => if t.s.starts_with(char::is_alphabetic) { "name" } else { "plain" },
+/// });
+/// let v: Vec<_> = spans.iter().map(|h| (&src[h.span.clone()], h.class)).collect();
+/// assert_eq!(v, vec![("x", "name"), (" = ", "plain"), ("\"a\"", "string"),
+///                    ("; ", "plain"), ("// b", "comment"), ("\n", "plain")]);
+/// ```
+pub fn highlight<C, F>(s: &str, classify: F) -> Vec<Highlight<C>>
+where
+    C: PartialEq,
+    F: FnMut(&Pretoken<'_>) -> C,
+{
+    highlight_with(s, Config::default(), classify)
+}
+
+/// Like [highlight()](fn.highlight.html), with `cfg`.  The limits set by
+/// [Config::max_tokens()](struct.Config.html#method.max_tokens) and
+/// [Config::max_bytes()](struct.Config.html#method.max_bytes) are ignored,
+/// so that the spans cover all of `s`.
+pub fn highlight_with<C, F>(s: &str, mut cfg: Config, mut classify: F) -> Vec<Highlight<C>>
+where
+    C: PartialEq,
+    F: FnMut(&Pretoken<'_>) -> C,
+{
+    cfg.max_tokens = None;
+    cfg.max_bytes = None;
+    let mut spans: Vec<Highlight<C>> = Vec::new();
+    for tok in Pretokenizer::with_config(s, cfg.lossless(true)) {
+        let class = classify(&tok);
+        let end = tok.offset + tok.s.len();
+        match spans.last_mut() {
+            Some(last) if last.class == class => last.span.end = end,
+            _ => spans.push(Highlight{ span: tok.offset..end, class }),
+        }
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Kind;
+
+    #[test]
+    fn highlight_test_1() {
+        let src = "\u{feff}#if 0\nskip\n#endif\n  \"s\" x;\n";
+        let cfg = Config::new().skip_region("#if 0", "#endif").max_tokens(1);
+        let spans = highlight_with(src, cfg, |t| t.kind);
+        let v: Vec<_> = spans.iter().map(|h| (&src[h.span.clone()], h.class)).collect();
+        assert_eq!(v, vec![("\u{feff}#if 0\nskip\n#endif", Kind::Comment),
+                           ("\n", Kind::Newline),
+                           ("  ", Kind::Whitespace),
+                           ("\"s\"", Kind::Quoted),
+                           (" ", Kind::Whitespace),
+                           ("x;", Kind::Word),
+                           ("\n", Kind::Newline)]);
+        assert!(highlight("", |_| ()).is_empty());
+        assert_eq!(highlight("a /* b */\nc", |_| ()), vec![Highlight{ span: 0..11, class: () }]);
+    }
+}
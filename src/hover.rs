@@ -0,0 +1,139 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Finding the pretoken at an offset, with the comments around it.
+
+use crate::{Config, Kind, Pretoken, Pretokenizer};
+
+/// A pretoken found by [token_at()](fn.token_at.html), with its comments.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenAt<'a> {
+    /// The pretoken containing the offset.
+    pub token: Pretoken<'a>,
+    /// The comments just before the pretoken, in input order: those
+    /// separated from it only by whitespace, with no blank line, and not
+    /// trailing an earlier pretoken on the same line.
+    pub leading: Vec<Pretoken<'a>>,
+    /// The comments after the pretoken on the same line, in input order.
+    pub trailing: Vec<Pretoken<'a>>,
+}
+
+/// The pretoken of `s` containing byte `offset`, with its leading and
+/// trailing comments, or None if `offset` falls in whitespace or a comment.
+/// The input before the last pretoken ahead of `offset` is only scanned for
+/// pretoken boundaries, as with
+/// [Pretokenizer::tokens_in_range()](struct.Pretokenizer.html#method.tokens_in_range),
+/// so this suits hovering in a large file.
+/// ```
+/// let src = "x; // not this\n// The count.\ncount; // of things\n";
+/// let at = pretok::token_at(src, 31).unwrap();
+/// assert_eq!(at.token.s, "count;");
+/// assert_eq!(at.leading[0].s, "// The count.");
+/// assert_eq!(at.trailing[0].s, "// of things");
+/// assert!(pretok::token_at(src, 1).unwrap().leading.is_empty());
+/// assert!(pretok::token_at(src, 6).is_none());
+/// ```
+pub fn token_at(s: &str, offset: usize) -> Option<TokenAt<'_>> {
+    token_at_with(s, offset, Config::default())
+}
+
+/// Like [token_at()](fn.token_at.html), with `cfg`.
+pub fn token_at_with(s: &str, offset: usize, cfg: Config) -> Option<TokenAt<'_>> {
+    let offset = crate::floor_char_boundary(s, offset);
+    let point = crate::range::point_before(s, &cfg, offset);
+    let mut pt = Pretokenizer::resume(s, cfg.lossless(true), point);
+
+    // The whitespace and comments since the last pretoken, if any.
+    let mut before = Vec::new();
+    let token = loop {
+        let tok = pt.next()?;
+        if offset < tok.offset + tok.s.len() {
+            break tok;
+        }
+        before.push(tok);
+    };
+    if token.kind.is_trivia() || offset < token.offset {
+        return None;
+    }
+
+    // Nearest first, with the number found before the last line break.
+    let mut leading = Vec::new();
+    let (mut newlines, mut kept) = (0, 0);
+    let mut after_token = point.offset > 0;
+    for tok in before.into_iter().rev() {
+        match tok.kind {
+            Kind::Whitespace => {}
+            Kind::Newline if newlines == 0 => {
+                newlines += 1;
+                kept = leading.len();
+            }
+            Kind::Comment => {
+                leading.push(tok);
+                newlines = 0;
+            }
+            Kind::Newline => {
+                after_token = false;
+                break;
+            }
+            _ => {
+                after_token = true;
+                break;
+            }
+        }
+    }
+    if after_token {
+        // Comments on the same line as an earlier pretoken belong to it.
+        leading.truncate(kept);
+    }
+    leading.reverse();
+
+    let trailing = pt.take_while(|t| matches!(t.kind, Kind::Whitespace | Kind::Comment))
+        .filter(|t| t.kind == Kind::Comment)
+        .collect();
+    Some(TokenAt{ token, leading, trailing })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testgen::Lcg;
+
+    #[test]
+    fn hover_test_1() {
+        let src = "a /* x */ /* y */\n/* z */\n\n// one\n/* two */ b /* three */ c // four\nd";
+        let at = |offset| token_at(src, offset).map(|at| {
+            let text = |v: Vec<Pretoken<'static>>| v.iter().map(|t| t.s).collect::<Vec<_>>();
+            (at.token.s, text(at.leading), text(at.trailing))
+        });
+        assert_eq!(at(0), Some(("a", vec![], vec!["/* x */", "/* y */"])));
+        assert_eq!(at(44), Some(("b", vec!["// one", "/* two */"], vec!["/* three */"])));
+        assert_eq!(at(58), Some(("c", vec![], vec!["// four"])));
+        assert_eq!(at(src.len() - 1), Some(("d", vec![], vec![])));
+        assert_eq!(at(4), None);
+        assert_eq!(at(src.len()), None);
+    }
+
+    #[test]
+    fn hover_test_2() {
+        // The pretoken found is the one a full scan finds, with comments
+        // from that scan.
+        let at = token_at("\"\r*\r\n;\"\"*", 7).unwrap();
+        assert_eq!((at.token.s, at.token.offset), ("\"*", 7));
+
+        let pieces = ["/", "*", "\"", "\n", "\r", " ", "a", "// c", "/* d */", "\\", ";"];
+        let mut rng = Lcg::new(23);
+        for _ in 0..300 {
+            let src = rng.source(&pieces, 16);
+            let all = crate::tokenize_with(&src, Config::new().lossless(true));
+            for offset in (0..=src.len()).filter(|&i| src.is_char_boundary(i)) {
+                let want = all.iter()
+                    .find(|t| t.offset <= offset && offset < t.offset + t.s.len())
+                    .filter(|t| !t.kind.is_trivia());
+                let got = token_at(&src, offset);
+                assert_eq!(got.as_ref().map(|at| &at.token), want, "{:?} {}", src, offset);
+                for c in got.iter().flat_map(|at| at.leading.iter().chain(&at.trailing)) {
+                    assert!(all.contains(c), "{:?} {}", src, offset);
+                }
+            }
+        }
+    }
+}
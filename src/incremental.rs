@@ -0,0 +1,212 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Keeping the pretokens of a text up to date as it is edited.
+
+use crate::{Config, Kind, OwnedPretoken, Pretokenizer};
+use std::ops::Range;
+
+/// A text and its pretokens, kept up to date through edits by
+/// pretokenizing again only around each edit.  Scanning restarts at the
+/// first pretoken of the line holding the edit and stops at the first
+/// pretoken after it that matches an old one, so an edit costs about the size of the
+/// pretokens it touches rather than the size of the text.  The pretokens
+/// after that are reused with their offsets and lines moved.
+///
+/// The pretokens always equal those of pretokenizing the whole text with
+/// the same configuration, except that
+/// [Config::max_tokens()](struct.Config.html#method.max_tokens) and
+/// [Config::max_bytes()](struct.Config.html#method.max_bytes) are ignored.
+/// Diagnostics are not collected.
+/// ```
+/// use pretok::{Config, IncrementalTokens};
+/// let mut doc = IncrementalTokens::new("let x = 1;\nlet y = 2;".to_string(), Config::new());
+/// let splice = doc.edit(15..16, "long");
+/// assert_eq!(doc.text(), "let x = 1;\nlet long = 2;");
+/// // Scanning restarts at the start of the line.
+/// assert_eq!((splice.start, splice.removed, splice.inserted), (4, 2, 2));
+/// let v: Vec<_> = doc.tokens().iter().map(|t| (&t.s[..], t.offset)).collect();
+/// assert_eq!(&v[4..], &[("let", 11), ("long", 15), ("=", 20), ("2;", 22)]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct IncrementalTokens {
+    text: String,
+    cfg: Config,
+    toks: Vec<OwnedPretoken>,
+}
+
+/// How an edit changed the pretokens of an
+/// [IncrementalTokens](struct.IncrementalTokens.html): `removed` pretokens
+/// from index `start` were replaced by `inserted` new ones.  Pretokens
+/// after those are the old ones, moved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TokenSplice {
+    /// The index of the first pretoken replaced.
+    pub start: usize,
+    /// The number of old pretokens replaced.
+    pub removed: usize,
+    /// The number of new pretokens in their place.
+    pub inserted: usize,
+}
+
+impl IncrementalTokens {
+    /// Pretokenize `text` with `cfg`.
+    pub fn new(text: String, mut cfg: Config) -> IncrementalTokens {
+        cfg.max_tokens = None;
+        cfg.max_bytes = None;
+        let toks = Pretokenizer::with_config(&text, cfg.clone())
+            .map(OwnedPretoken::from)
+            .collect();
+        IncrementalTokens{ text, cfg, toks }
+    }
+
+    /// The current text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The pretokens of the current text.
+    pub fn tokens(&self) -> &[OwnedPretoken] {
+        &self.toks
+    }
+
+    /// Replace the bytes of the text in `range` with `replacement` and
+    /// update the pretokens to match, returning which changed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of order, extends past the text or doesn't
+    /// fall on character boundaries.
+    pub fn edit(&mut self, range: Range<usize>, replacement: &str) -> TokenSplice {
+        let lines_before = self.text[range.clone()].matches('\n').count() as isize;
+        self.text.replace_range(range.clone(), replacement);
+        let delta = replacement.len() as isize - (range.end - range.start) as isize;
+        let line_delta = replacement.matches('\n').count() as isize - lines_before;
+        let edit_end = range.start + replacement.len();
+
+        // Restart at the first pretoken of the last line starting before
+        // the edit.  The state there depends only on the text before it,
+        // unless a spliced line break joins it to the line before, or it's
+        // an opaque region, which depends on the marker comment before it.
+        // The end of a pretoken can depend on the text just after it, so one
+        // on the same line as the edit is scanned again.
+        let mut start = self.toks.partition_point(|t| t.offset < range.start).saturating_sub(1);
+        while start > 0 {
+            let tok = &self.toks[start];
+            if !tok.kind.is_trivia() && tok.kind != Kind::Opaque {
+                let prev_end = self.toks[..start].iter().rev().find(|t| !t.kind.is_trivia())
+                    .map_or(0, |t| t.offset + t.s.len());
+                if self.line_break_between(prev_end, tok.offset) {
+                    break;
+                }
+            }
+            start -= 1;
+        }
+        let mut pt = Pretokenizer::with_config(&self.text, self.cfg.clone());
+        if start > 0 {
+            let from = self.toks[start].offset;
+            pt.pos = crate::grapheme_cursor_at(&self.text, from);
+            pt.line = memchr::memchr_iter(b'\n', &self.text.as_bytes()[..from]).count()
+                + self.cfg.first_line();
+        }
+
+        // Scan until a pretoken after the edit matches an old one.  Whitespace
+        // and comments can't be matched, since a comment can change the
+        // state after it.
+        let mut old = self.toks.partition_point(|t| t.offset < range.end);
+        let mut new = Vec::new();
+        let mut end = self.toks.len();
+        for tok in pt {
+            if tok.offset >= edit_end && !tok.kind.is_trivia() {
+                let was = (tok.offset as isize - delta) as usize;
+                while old < self.toks.len() && self.toks[old].offset < was {
+                    old += 1;
+                }
+                if let Some(prev) = self.toks.get(old) {
+                    if prev.offset == was && prev.s == tok.s && prev.kind == tok.kind {
+                        end = old;
+                        break;
+                    }
+                }
+            }
+            new.push(OwnedPretoken::from(tok));
+        }
+
+        for tok in &mut self.toks[end..] {
+            tok.offset = (tok.offset as isize + delta) as usize;
+            if self.cfg.counts_lines() {
+                tok.line = (tok.line as isize + line_delta) as usize;
+            }
+        }
+        let inserted = new.len();
+        self.toks.splice(start..end, new);
+        TokenSplice{ start, removed: end - start, inserted }
+    }
+
+    /// True if a line break outside comments that isn't spliced falls in
+    /// the whitespace and comments between bytes `from` and `to` of the
+    /// text.
+    fn line_break_between(&self, from: usize, to: usize) -> bool {
+        let s = &self.text[..to];
+        let mut pt = Pretokenizer::with_config(s, self.cfg.clone().lossless(true));
+        if from > 0 {
+            pt.pos = crate::grapheme_cursor_at(s, from);
+            pt.bol = false;
+        }
+        pt.any(|t| {
+            let before = &s[..t.offset];
+            let before = before.strip_suffix('\r').unwrap_or(before);
+            t.kind == Kind::Newline && !before.ends_with('\\')
+                && !(self.cfg.trigraphs && before.ends_with("??/"))
+        })
+    }
+
+    /// Take back the text and pretokens.
+    pub fn into_parts(self) -> (String, Vec<OwnedPretoken>) {
+        (self.text, self.toks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testgen::Lcg;
+    use crate::Base;
+
+    #[test]
+    fn incremental_test_1() {
+        // Random edits give the same pretokens as starting over.
+        let pieces = ["/", "*", "\"", "\\", "\n", " ", "a", "b c", "#", "??/", "#if 0",
+                      "#endif", "// off", "// on", "\u{e9}", "/*x*/", ""];
+        let configs = [
+            Config::new(),
+            Config::new().trigraphs(true).directives(true),
+            Config::new().nested_comments(true).skip_region("#if 0", "#endif")
+                .tool_directives("off", "on").lossless(true),
+            Config::new().base(Base::Zero),
+        ];
+        let mut rng = Lcg::new(3);
+        for cfg in configs.iter() {
+            for _ in 0..200 {
+                let text = rng.source(&pieces, 20);
+                let mut doc = IncrementalTokens::new(text, cfg.clone());
+                for _ in 0..10 {
+                    let bounds: Vec<_> = (0..=doc.text().len())
+                        .filter(|&i| doc.text().is_char_boundary(i)).collect();
+                    let a = bounds[rng.below(bounds.len())];
+                    let b = bounds[rng.below(bounds.len())];
+                    let before = doc.tokens().len();
+                    let splice = doc.edit(a.min(b)..a.max(b), pieces[rng.below(pieces.len())]);
+                    let want: Vec<_> = Pretokenizer::with_config(doc.text(), cfg.clone())
+                        .map(OwnedPretoken::from).collect();
+                    assert_eq!(doc.tokens(), &want[..], "{:?} {:?}", cfg, doc.text());
+                    assert_eq!(before - splice.removed + splice.inserted, want.len());
+                }
+            }
+        }
+
+        // Pretokens on line 0 move down too.
+        let mut doc = IncrementalTokens::new("a b c".to_string(), Config::new().base(Base::Zero));
+        doc.edit(1..2, "\n");
+        assert_eq!(doc.tokens().iter().map(|t| t.line).collect::<Vec<_>>(), vec![0, 1, 1]);
+    }
+}
@@ -0,0 +1,150 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! The spans of every pretoken, for answering positional queries.
+
+use crate::{Config, Kind, Pretoken, Pretokenizer, TokenId};
+use std::iter::FromIterator;
+use std::ops::Range;
+
+/// The span and kind of every pretoken of an input, sorted by offset, for
+/// finding pretokens by position in O(log n) without pretokenizing again.
+/// The index holds no text, so it can outlive the input.  Pretokens are
+/// numbered with [TokenId](struct.TokenId.html)s in input order, matching
+/// a [TokenList](struct.TokenList.html) of the same pretokens.
+/// ```
+/// use pretok::TokenIndex;
+/// let src = "let x = \"a b\";";
+/// let index = TokenIndex::new(src);
+/// let id = index.containing(10).unwrap();
+/// assert_eq!(&src[index.span(id).unwrap()], "\"a b\"");
+/// assert_eq!(index.before(8).map(|id| index.span(id).unwrap()), Some(6..7));
+/// assert_eq!(index.after(13).map(|id| index.span(id).unwrap()), Some(13..14));
+/// assert_eq!(index.containing(3), None);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TokenIndex {
+    spans: Vec<Range<usize>>,
+    kinds: Vec<Kind>,
+}
+
+impl TokenIndex {
+    /// Index the pretokens of `s`.
+    pub fn new(s: &str) -> TokenIndex {
+        Pretokenizer::new(s).collect()
+    }
+
+    /// Index the pretokens of `s` found with `cfg`.
+    pub fn with_config(s: &str, cfg: Config) -> TokenIndex {
+        Pretokenizer::with_config(s, cfg).collect()
+    }
+
+    /// The byte span of pretoken `id`, if it is in this index.
+    pub fn span(&self, id: TokenId) -> Option<Range<usize>> {
+        self.spans.get(id.index()).cloned()
+    }
+
+    /// The kind of pretoken `id`, if it is in this index.
+    pub fn kind(&self, id: TokenId) -> Option<Kind> {
+        self.kinds.get(id.index()).copied()
+    }
+
+    /// The pretoken containing byte `offset`, if any.
+    pub fn containing(&self, offset: usize) -> Option<TokenId> {
+        let i = self.spans.partition_point(|span| span.end <= offset);
+        match self.spans.get(i) {
+            Some(span) if span.start <= offset => Some(TokenId::from_index(i)),
+            _ => None,
+        }
+    }
+
+    /// The last pretoken ending at or before byte `offset`, if any.
+    pub fn before(&self, offset: usize) -> Option<TokenId> {
+        match self.spans.partition_point(|span| span.end <= offset) {
+            0 => None,
+            i => Some(TokenId::from_index(i - 1)),
+        }
+    }
+
+    /// The first pretoken starting at or after byte `offset`, if any.
+    pub fn after(&self, offset: usize) -> Option<TokenId> {
+        let i = self.spans.partition_point(|span| span.start < offset);
+        match i < self.spans.len() {
+            true => Some(TokenId::from_index(i)),
+            false => None,
+        }
+    }
+
+    /// The ids of the pretokens overlapping the bytes in `range`, or for an
+    /// empty range, of the pretoken containing its start.
+    pub fn overlapping(&self, range: Range<usize>) -> impl Iterator<Item = TokenId> {
+        let last = range.end.max(range.start + 1);
+        let first = self.spans.partition_point(|span| span.end <= range.start);
+        let end = self.spans.partition_point(|span| span.start < last);
+        (first..end.max(first)).map(TokenId::from_index)
+    }
+
+    /// Every pretoken's id, span and kind, in input order.
+    pub fn iter(&self) -> impl Iterator<Item = (TokenId, Range<usize>, Kind)> + '_ {
+        self.spans.iter().zip(self.kinds.iter()).enumerate()
+            .map(|(i, (span, &kind))| (TokenId::from_index(i), span.clone(), kind))
+    }
+
+    /// The number of pretokens.
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    /// True if there are no pretokens.
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+}
+
+impl<'a> FromIterator<Pretoken<'a>> for TokenIndex {
+    /// Index pretokens given in input order.
+    fn from_iter<I: IntoIterator<Item = Pretoken<'a>>>(iter: I) -> TokenIndex {
+        let mut index = TokenIndex::default();
+        for tok in iter {
+            index.spans.push(tok.offset..tok.offset + tok.s.len());
+            index.kinds.push(tok.kind);
+        }
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TokenList;
+
+    #[test]
+    fn index_test_1() {
+        let src = "a  bc\n/* x */ \"d\ne\" f";
+        let index = TokenIndex::new(src);
+        let list: TokenList = Pretokenizer::new(src).collect();
+        assert_eq!(index.len(), list.len());
+        for (id, span, kind) in index.iter() {
+            assert_eq!(Some(span), list.span(id));
+            assert_eq!(kind, list[id].kind);
+        }
+        let spans = |ids: Vec<Option<TokenId>>| -> Vec<_> {
+            ids.into_iter().map(|id| id.and_then(|id| index.span(id))).collect()
+        };
+        let offsets = 0..=src.len() + 1;
+        let all: Vec<_> = offsets.clone().map(|i| index.containing(i)).collect();
+        for (i, span) in spans(all).into_iter().enumerate() {
+            let want = list.as_slice().iter().find(|t| t.offset <= i && i < t.offset + t.s.len())
+                .map(|t| t.offset..t.offset + t.s.len());
+            assert_eq!(span, want, "{}", i);
+        }
+        assert_eq!(spans(vec![index.before(0), index.before(2), index.before(5)]),
+                   vec![None, Some(0..1), Some(3..5)]);
+        assert_eq!(spans(vec![index.after(0), index.after(6), index.after(23)]),
+                   vec![Some(0..1), Some(14..19), None]);
+        let v: Vec<_> = index.overlapping(4..15).map(|id| id.index()).collect();
+        assert_eq!(v, vec![1, 2]);
+        assert_eq!(index.overlapping(2..2).count(), 0);
+        assert_eq!(index.kind(index.after(6).unwrap()), Some(Kind::Quoted));
+        assert!(TokenIndex::default().is_empty());
+    }
+}
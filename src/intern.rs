@@ -0,0 +1,120 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Interning pretoken text as small symbol ids.
+
+use crate::Pretoken;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+/// A small id for text interned by an [Interner](struct.Interner.html).
+/// Symbols from the same interner are equal exactly when their texts are.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// The position of the symbol in interning order, from 0.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A table of distinct pretoken texts, each stored once and identified by a
+/// [Symbol](struct.Symbol.html).  Use one interner for a whole corpus to
+/// share the storage of repeated identifiers and keywords.
+/// ```
+/// use pretok::{Interner, Pretokenizer};
+/// let mut interner = Interner::new();
+/// let syms: Vec<_> = Pretokenizer::new("x = x + y")
+///     .map(|t| t.symbol(&mut interner))
+///     .collect();
+/// assert_eq!(syms[0], syms[2]);
+/// assert_eq!(interner.len(), 4);
+/// assert_eq!(interner.resolve(syms[4]), "y");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Interner {
+    map: HashMap<Rc<str>, Symbol>,
+    strings: Vec<Rc<str>>,
+}
+
+impl Interner {
+    /// Create an empty interner.
+    pub fn new() -> Interner {
+        Interner::default()
+    }
+
+    /// The symbol for `s`, adding `s` if it is new.
+    ///
+    /// # Panics
+    ///
+    /// Panics if more than `u32::MAX` distinct texts are interned.
+    pub fn intern(&mut self, s: &str) -> Symbol {
+        if let Some(&sym) = self.map.get(s) {
+            return sym;
+        }
+        let sym = Symbol(u32::try_from(self.strings.len()).expect("too many symbols"));
+        let s: Rc<str> = Rc::from(s);
+        self.strings.push(s.clone());
+        self.map.insert(s, sym);
+        sym
+    }
+
+    /// The symbol for `s`, if it was interned.
+    pub fn get(&self, s: &str) -> Option<Symbol> {
+        self.map.get(s).copied()
+    }
+
+    /// The text of `sym`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sym` came from a different interner with more symbols.
+    pub fn resolve(&self, sym: Symbol) -> &str {
+        self.resolve_index(sym.index())
+    }
+
+    /// The text of the symbol with index `i`.
+    pub(crate) fn resolve_index(&self, i: usize) -> &str {
+        &self.strings[i]
+    }
+
+    /// The number of distinct texts interned.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// True if nothing has been interned.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+impl<'a> Pretoken<'a> {
+    /// The symbol for this pretoken's text in `interner`, interning it if
+    /// it is new.  See [Interner](struct.Interner.html).
+    pub fn symbol(&self, interner: &mut Interner) -> Symbol {
+        interner.intern(self.s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intern_test_0() {
+        let mut interner = Interner::new();
+        assert!(interner.is_empty());
+        let toks = crate::tokenize("if (a) a = \"a\"; else b = a;");
+        let syms: Vec<_> = toks.iter().map(|t| t.symbol(&mut interner)).collect();
+        for (i, t) in toks.iter().enumerate() {
+            assert_eq!(interner.resolve(syms[i]), t.s);
+            assert_eq!(interner.get(t.s), Some(syms[i]));
+        }
+        assert_eq!(interner.len(), 9);
+        assert_eq!(syms[1].index(), 1);
+        assert_eq!(interner.get("c"), None);
+        assert_ne!(interner.intern("\"a\""), interner.intern("a"));
+    }
+}
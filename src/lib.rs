@@ -16,6 +16,10 @@
 //! * Skips whitespace characters.
 //! * After above filters, returns ``Pretokens`` usually delineated by whitespace.
 //! * Returns the line number and byte offset of each pretoken
+//! * Records diagnostics for unterminated strings and comments.
+//! * Optionally returns whitespace and comments too, through the
+//!   [RawPretokenizer](struct.RawPretokenizer.html) or in
+//!   [lossless](struct.Config.html#method.lossless) mode.
 //!
 //! ## Motivation
 //! Common computer language features such comments, line number tracking,
@@ -37,44 +41,102 @@
 //! Whitespace typically separates [Pretoken](struct.Pretoken.html)s and is
 //! stripped outside of quoted strings.
 //! ```
-//!     use pretok::{Pretokenizer, Pretoken};
+//!     use pretok::{Kind, Pretokenizer, Pretoken};
 //!     let mut pt = Pretokenizer::new("Hello World!");
-//!     assert!(pt.next() == Some(Pretoken{s:"Hello", line:1, offset:0}));
-//!     assert!(pt.next() == Some(Pretoken{s:"World!", line:1, offset:6}));
+//!     assert!(pt.next() == Some(Pretoken{s:"Hello", line:1, offset:0, kind:Kind::Word}));
+//!     assert!(pt.next() == Some(Pretoken{s:"World!", line:1, offset:6, kind:Kind::Word}));
 //!     assert!(pt.next() == None);
 //! ```
 //! Comments are stripped and may also delineate [Pretoken](struct.Pretoken.html)s.
 //! ```
-//!     use pretok::{Pretokenizer, Pretoken};
+//!     use pretok::{Kind, Pretokenizer, Pretoken};
 //!     let mut pt = Pretokenizer::new("x/*y*/z");
-//!     assert!(pt.next() == Some(Pretoken{s:"x", line:1, offset:0}));
-//!     assert!(pt.next() == Some(Pretoken{s:"z", line:1, offset:6}));
+//!     assert!(pt.next() == Some(Pretoken{s:"x", line:1, offset:0, kind:Kind::Word}));
+//!     assert!(pt.next() == Some(Pretoken{s:"z", line:1, offset:6, kind:Kind::Word}));
 //!     assert!(pt.next() == None);
 //!
 //!     let mut pt = Pretokenizer::new("x\ny//z");
-//!     assert!(pt.next() == Some(Pretoken{s:"x", line:1, offset:0}));
-//!     assert!(pt.next() == Some(Pretoken{s:"y", line:2, offset:2}));
+//!     assert!(pt.next() == Some(Pretoken{s:"x", line:1, offset:0, kind:Kind::Word}));
+//!     assert!(pt.next() == Some(Pretoken{s:"y", line:2, offset:2, kind:Kind::Word}));
 //!     assert!(pt.next() == None);
 //! ```
 //! Quoted strings are a single [Pretoken](struct.Pretoken.html).
 //! ```
-//!     use pretok::{Pretokenizer, Pretoken};
+//!     use pretok::{Kind, Pretokenizer, Pretoken};
 //!     let mut pt = Pretokenizer::new("Hello \"W o r l d!\"");
-//!     assert!(pt.next() == Some(Pretoken{s:"Hello", line:1, offset:0}));
-//!     assert!(pt.next() == Some(Pretoken{s:"\"W o r l d!\"", line:1, offset:6}));
+//!     assert!(pt.next() == Some(Pretoken{s:"Hello", line:1, offset:0, kind:Kind::Word}));
+//!     assert!(pt.next() == Some(Pretoken{s:"\"W o r l d!\"", line:1, offset:6, kind:Kind::Quoted}));
 //!     assert!(pt.next() == None);
 //! ```
 //! Quoted strings create a single [Pretoken](struct.Pretoken.html) separate
 //! from the surrounding pretoken(s).
 //! ```
-//!     use pretok::{Pretokenizer, Pretoken};
+//!     use pretok::{Kind, Pretokenizer, Pretoken};
 //!     let mut pt = Pretokenizer::new("x+\"h e l l o\"+z");
-//!     assert!(pt.next() == Some(Pretoken{s:"x+", line:1, offset:0}));
-//!     assert!(pt.next() == Some(Pretoken{s:"\"h e l l o\"", line:1, offset:2}));
-//!     assert!(pt.next() == Some(Pretoken{s:"+z", line:1, offset:13}));
+//!     assert!(pt.next() == Some(Pretoken{s:"x+", line:1, offset:0, kind:Kind::Word}));
+//!     assert!(pt.next() == Some(Pretoken{s:"\"h e l l o\"", line:1, offset:2, kind:Kind::Quoted}));
+//!     assert!(pt.next() == Some(Pretoken{s:"+z", line:1, offset:13, kind:Kind::Word}));
 //!     assert!(pt.next() == None);
 //! ```
 //!
+//! ## Cargo Features
+//! * `tracing`: Emit [tracing](https://docs.rs/tracing) spans and events for
+//!   state machine transitions, pretokens and diagnostics.
+//! * `intern`: Intern pretoken text as small symbol ids with
+//!   [Interner](struct.Interner.html) and `Pretoken::symbol()`, and count
+//!   words with [WordCounts](struct.WordCounts.html).
+//! * `mmap`: Pretokenize memory-mapped files without copying them, with
+//!   `MappedFile` and `Pretokenizer::from_mmap()`.
+//! * `cache`: Keep pretokens on disk between runs with
+//!   [TokenCache](struct.TokenCache.html), keyed by a hash of the input.
+//! * `simd`: Scan long runs of whitespace and block comment bodies 16 bytes
+//!   at a time with SSE2 on x86_64.
+//! * `walk`: Pretokenize every file in a directory tree on several threads
+//!   with [TreeWalk](struct.TreeWalk.html).
+//! * `async`: Pretokenize input from a `futures::io::AsyncRead` as a
+//!   `Stream` with `AsyncPretokenizer`.
+//! * `encoding`: Pretokenize input in legacy encodings such as Latin-1 or
+//!   Shift_JIS, decoded with [encoding_rs](https://docs.rs/encoding_rs), with
+//!   `Decoded` and `Pretokenizer::from_decoded()`.
+//! * `cli`: Build the `pretok` command, which prints the pretokens of files
+//!   with their positions and kinds.  Enables `walk`.
+//! * `wasm`: Export `tokenize()` to JavaScript through
+//!   [wasm-bindgen](https://docs.rs/wasm-bindgen), returning an array of
+//!   `{text, line, offset, kind}` objects, with `tokenize_js()`.
+//! * `python`: Export a `pretok` Python module through
+//!   [pyo3](https://docs.rs/pyo3) whose `Pretokenizer` yields
+//!   `(text, line, offset, kind)` tuples, with
+//!   [PyPretokenizer](struct.PyPretokenizer.html).
+//! * `logos`: Run a [logos](https://docs.rs/logos) lexer over each pretoken
+//!   with spans in the whole input, with `SubLexer` and
+//!   `Pretokenizer::logos()`.
+//! * `nom`: Parse pretokens with [nom](https://docs.rs/nom) combinators,
+//!   with `TokenInput`.
+//! * `chumsky`: Parse pretokens with [chumsky](https://docs.rs/chumsky),
+//!   with spans in the source, with `token_stream()`.
+//! * `rowan`: Build a lossless [rowan](https://docs.rs/rowan) syntax tree,
+//!   with whitespace and comments added for the parser, with
+//!   `GreenBuilder`.
+//! * `arbitrary`: Generate structured inputs for fuzzing with
+//!   [arbitrary](https://docs.rs/arbitrary) as a `FuzzSource`, which renders
+//!   to source text along with the pretokens it should give.
+//! * `annotate-snippets`: Render diagnostics in the style of rustc with
+//!   [annotate-snippets](https://docs.rs/annotate-snippets), with
+//!   `render_snippets()`, `PretokError::to_group()` and
+//!   `Pretoken::annotation()`.
+//! * `graphemes`: Count columns in grapheme clusters with
+//!   `grapheme_line_col()`, and end pretokens only between clusters with
+//!   `Config::graphemes()`, using
+//!   [unicode-segmentation](https://docs.rs/unicode-segmentation).
+//! * `xid`: End words between identifier characters and others with
+//!   `Config::xid_words()`, using [unicode-ident](https://docs.rs/unicode-ident).
+//! * `width`: Count display columns, with wide CJK characters as two, with
+//!   `display_line_col()` and `display_width()`, using
+//!   [unicode-width](https://docs.rs/unicode-width).
+//! * `serde`: Serialize and deserialize `ScanState` and `ResumePoint` with
+//!   [serde](https://docs.rs/serde), to keep where pretokenizing got to
+//!   between runs.
+//!
 //! ## Unit Testing
 //! Pretok supports unit tests.
 //! <pre>
@@ -90,6 +152,12 @@
 //! <pre>
 //! cargo +nightly fuzz run fuzz_target_1
 //! </pre>
+//! The `no_panic` target also varies the configuration and mixes in calls
+//! such as `seek()` and `peek()`.  Pretok should never panic, whatever the
+//! input, so any crash it finds is a bug:
+//! <pre>
+//! cargo +nightly fuzz run no_panic
+//! </pre>
 //! Fuzz tests run until stopped with Ctrl-C.  In my experience, fuzz tests will
 //! catch a problem almost immediately or not at all.
 //!
@@ -107,18 +175,329 @@
 #![warn(clippy::all)]
 #![warn(missing_docs)]
 #![warn(missing_doc_code_examples)]
+
+/// Emit a `tracing` event when the `tracing` feature is enabled.
+macro_rules! trace {
+    ($level:ident, $($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)*);
+    };
+}
+
+use std::cell::{OnceCell, RefCell};
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
+use dfa::{Act, State};
+use stats::CommentStyle;
 use strcursor::StrCursor;
 
+#[cfg(feature = "async")]
+mod async_stream;
+mod balance;
+mod blank;
+mod bounded;
+mod brackets;
+mod bytes;
+#[cfg(feature = "cache")]
+mod cache;
+mod checks;
+#[cfg(feature = "chumsky")]
+mod chumsky_input;
+mod classify;
+mod complete;
+mod config;
+mod constant;
+mod cursor;
+#[cfg(feature = "encoding")]
+mod decode;
+mod depth;
+mod dfa;
+mod diff;
+mod docs;
+mod dump;
+mod edits;
+mod error;
+mod file;
+mod find;
+mod fingerprint;
+mod fixed;
+#[cfg(feature = "intern")]
+mod intern;
+mod folding;
+#[cfg(feature = "intern")]
+mod frequency;
+mod full;
+mod gaps;
+#[cfg(feature = "graphemes")]
+mod graphemes;
+#[cfg(feature = "rowan")]
+mod green;
+mod highlight;
+mod hover;
+mod incremental;
+mod index;
+mod limits;
+mod lines;
+mod list;
+mod logical;
+mod long_lines;
+mod lookahead;
+mod markers;
+mod metrics;
+mod minify;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod nested;
+#[cfg(feature = "nom")]
+mod nom_input;
+mod normalize;
+mod oracle;
+mod owned;
+mod owning;
+mod physical;
+mod prelex;
+mod push;
+#[cfg(feature = "python")]
+mod python;
+mod range;
+mod raw;
+mod rebuild;
+mod regions;
+mod reverse;
+mod scanner;
+mod selection;
+mod semantic;
+#[cfg(feature = "simd")]
+mod simd;
+mod sink;
+#[cfg(feature = "annotate-snippets")]
+mod snippets;
+mod source_map;
+mod state;
+mod stats;
+#[cfg(test)]
+mod testgen;
+mod stream;
+mod strict;
+mod strip;
+#[cfg(feature = "arbitrary")]
+mod structured;
+#[cfg(feature = "logos")]
+mod sublex;
+mod trace;
+#[cfg(feature = "walk")]
+mod walk;
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "width")]
+mod width;
+#[cfg(feature = "xid")]
+mod xid;
+#[cfg(feature = "async")]
+pub use async_stream::AsyncPretokenizer;
+#[cfg(feature = "cache")]
+pub use cache::TokenCache;
+#[cfg(feature = "chumsky")]
+pub use chumsky_input::{token_kind, token_stream, token_text};
+pub use balance::{check_balance, check_balance_with};
+pub use blank::BlankLines;
+pub use bounded::Bounded;
+pub use brackets::{match_bracket, match_bracket_with};
+pub use bytes::{utf8_lossy, validate_utf8};
+pub use classify::{CharClassifier, DefaultClassifier};
+pub use complete::{is_complete, is_complete_with, Completeness};
+pub use config::{Base, Bom, Config, Nul, Shebang, ZeroWidth};
+pub use constant::{const_count, const_tokenize};
+pub use cursor::{Mark, TokenCursor};
+#[cfg(feature = "encoding")]
+pub use decode::Decoded;
+pub use depth::Depths;
+pub use diff::{diff_sources, diff_sources_with, diff_tokens, TokenDiff};
+pub use docs::{doc_blocks, doc_blocks_with, doc_comments, doc_comments_with, DocBlock, DocComments};
+pub use dump::TokenDump;
+pub use edits::SourceEdits;
+pub use error::{EditError, FileError, InvalidUtf8, PretokError, SeekError};
+pub use find::{find_all, find_all_with, find_matching, find_matching_with};
+pub use fingerprint::{fingerprint, fingerprint_with};
+pub use fixed::FixedPretokenizer;
+#[cfg(feature = "intern")]
+pub use intern::{Interner, Symbol};
+pub use folding::{folding_ranges, folding_ranges_with, FoldKind, FoldingRange};
+#[cfg(feature = "intern")]
+pub use frequency::WordCounts;
+pub use full::{FullToken, FullTokens};
+pub use gaps::{Gap, Gaps};
+#[cfg(feature = "graphemes")]
+pub use graphemes::grapheme_line_col;
+#[cfg(feature = "rowan")]
+pub use green::GreenBuilder;
+pub use highlight::{highlight, highlight_with, Highlight};
+pub use hover::{token_at, token_at_with, TokenAt};
+pub use incremental::{IncrementalTokens, TokenSplice};
+pub use index::TokenIndex;
+pub use lines::LineIndex;
+pub use list::{TokenId, TokenList};
+pub use logical::{LineTokenizer, LogicalLine};
+pub use long_lines::{long_lines, long_lines_with, LongLine};
+pub use lookahead::Lookahead;
+pub use markers::{find_markers, find_markers_with, CommentMarker};
+pub use metrics::{metrics, metrics_with, Metrics};
+pub use minify::{minify, minify_with};
+#[cfg(feature = "mmap")]
+pub use mmap::MappedFile;
+pub use nested::Nested;
+#[cfg(feature = "nom")]
+pub use nom_input::TokenInput;
+pub use normalize::{normalize, normalize_with, Normalized};
+pub use oracle::{reference_tokenize, verify, verify_with};
+pub use owned::OwnedPretoken;
+pub use owning::OwnedPretokenizer;
+pub use physical::PhysicalLines;
+pub use prelex::PreLex;
+pub use push::PushPretokenizer;
+#[cfg(feature = "python")]
+pub use python::PyPretokenizer;
+pub use raw::RawPretokenizer;
+pub use rebuild::{rebuild, rebuild_with};
+pub use regions::{find_regions, find_regions_with, Region};
+pub use reverse::Before;
+pub use scanner::Scanner;
+pub use selection::{expand_selection, expand_selection_with};
+pub use semantic::{
+    semantic_tokens, semantic_tokens_from_spans, semantic_tokens_with, SemanticToken,
+    SEMANTIC_COMMENT, SEMANTIC_KEYWORD, SEMANTIC_STRING, SEMANTIC_TOKEN_TYPES,
+};
+pub use sink::PretokSink;
+#[cfg(feature = "annotate-snippets")]
+pub use snippets::render_snippets;
+pub use source_map::{FileId, FileSpan, Location, Provenance, SourceFile, SourceMap};
+pub use state::{tokenize_line, tokenize_line_with, ResumePoint, ScanState};
+pub use stats::Stats;
+pub use stream::StreamingPretokenizer;
+pub use strict::Strict;
+pub use strip::{strip_comments, strip_comments_with};
+#[cfg(feature = "arbitrary")]
+pub use structured::{FuzzPiece, FuzzSource};
+#[cfg(feature = "logos")]
+pub use sublex::SubLexer;
+pub use trace::Transition;
+#[cfg(feature = "walk")]
+pub use walk::{ErrorPolicy, FileTokens, TreeFiles, TreeWalk, WalkError};
+#[cfg(feature = "wasm")]
+pub use wasm::tokenize_js;
+#[cfg(feature = "width")]
+pub use width::{display_line_col, display_width};
+
+/// The classification of a [Pretoken](struct.Pretoken.html).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Kind {
+    /// Ordinary text delineated by whitespace, comments or quotes.
+    #[default]
+    Word,
+    /// A quoted string including its quotes.
+    Quoted,
+    /// A preprocessor directive line.  See
+    /// [Config::directives()](struct.Config.html#method.directives).
+    Directive,
+    /// A `#!` line at the start of the input.  See
+    /// [Config::shebang()](struct.Config.html#method.shebang).
+    Shebang,
+    /// A byte order mark at the start of the input.  See
+    /// [Config::bom()](struct.Config.html#method.bom).
+    Bom,
+    /// A line collected by [Config::pragma()](struct.Config.html#method.pragma).
+    /// These are only found in
+    /// [Pretokenizer::pragmas()](struct.Pretokenizer.html#method.pragmas).
+    Pragma,
+    /// Raw text excluded from tokenization by
+    /// [Config::tool_directives()](struct.Config.html#method.tool_directives).
+    Opaque,
+    /// A newline returned as a statement terminator by the hook given to
+    /// [Pretokenizer::on_newline()](struct.Pretokenizer.html#method.on_newline).
+    Terminator,
+    /// A run of spaces and tabs.  Only returned by
+    /// [RawPretokenizer](struct.RawPretokenizer.html) and in
+    /// [lossless](struct.Config.html#method.lossless) mode.
+    Whitespace,
+    /// A line break.  Only returned by
+    /// [RawPretokenizer](struct.RawPretokenizer.html) and in
+    /// [lossless](struct.Config.html#method.lossless) mode.
+    Newline,
+    /// A line or block comment, or other input skipped like one, such as a
+    /// region given to [Config::skip_region()](struct.Config.html#method.skip_region).
+    /// Only returned by [RawPretokenizer](struct.RawPretokenizer.html) and in
+    /// [lossless](struct.Config.html#method.lossless) mode.
+    Comment,
+    /// A run of lines holding only spaces and tabs.  Only returned by
+    /// [Pretokenizer::blank_lines()](struct.Pretokenizer.html#method.blank_lines).
+    BlankLines,
+    /// A run of characters that belong in no pretoken, such as control
+    /// characters.  Only returned with
+    /// [Config::invalid_tokens()](struct.Config.html#method.invalid_tokens).
+    Invalid,
+}
+
+impl Kind {
+    /// Every kind, indexed by `code()`.
+    const BY_CODE: [Kind; 13] = [Kind::Word, Kind::Quoted, Kind::Directive, Kind::Shebang,
+                                 Kind::Pragma, Kind::Opaque, Kind::Terminator,
+                                 Kind::Whitespace, Kind::Newline, Kind::Comment, Kind::Bom,
+                                 Kind::BlankLines, Kind::Invalid];
+
+    /// A fixed code for the kind, independent of the declaration order, for
+    /// hashes and files that must stay stable.
+    pub(crate) fn code(self) -> u8 {
+        match self {
+            Kind::Word => 0,
+            Kind::Quoted => 1,
+            Kind::Directive => 2,
+            Kind::Shebang => 3,
+            Kind::Pragma => 4,
+            Kind::Opaque => 5,
+            Kind::Terminator => 6,
+            Kind::Whitespace => 7,
+            Kind::Newline => 8,
+            Kind::Comment => 9,
+            Kind::Bom => 10,
+            Kind::BlankLines => 11,
+            Kind::Invalid => 12,
+        }
+    }
+
+    /// The kind with code `code`, if any.
+    pub(crate) fn from_code(code: u8) -> Option<Kind> {
+        Kind::BY_CODE.get(code as usize).copied()
+    }
+
+    /// True for the kinds that
+    /// [Pretokenizer](struct.Pretokenizer.html) skips: whitespace, newlines,
+    /// comments and blank lines.
+    /// ```
+    /// use pretok::Kind;
+    /// assert!(Kind::Comment.is_trivia());
+    /// assert!(!Kind::Terminator.is_trivia());
+    /// ```
+    pub fn is_trivia(self) -> bool {
+        matches!(self, Kind::Whitespace | Kind::Newline | Kind::Comment | Kind::BlankLines)
+    }
+}
+
 /// A pretoken object contains a slice of the `Pretokenizer` input string
 /// with lifetime a.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Pretoken<'a> {
     /// The UTF-8 string slice.
     pub s: &'a str,
-    /// Number > 0 of the _last_ line in this pretoken.
+    /// Number of the _last_ line in this pretoken, from 1 unless set by
+    /// [Config::base()](struct.Config.html#method.base), or 0 with
+    /// [Config::lazy_lines()](struct.Config.html#method.lazy_lines) or
+    /// [Config::offsets_only()](struct.Config.html#method.offsets_only).
     pub line: usize,
     /// The byte offset of the first character in the pretoken.
     pub offset: usize,
+    /// The classification of the pretoken.
+    pub kind: Kind,
 }
 
 impl<'a> Pretoken<'a> {
@@ -126,20 +505,86 @@ impl<'a> Pretoken<'a> {
     /// * `start`: The starting code point (inclusive).
     /// * `end`: The end code point (exclusive).
     /// * `offset`: The byte offset of `start` from the front
-    ///             of the string used to initialize the Pretokenizer.
+    ///   of the string used to initialize the Pretokenizer.
+    ///
+    /// If the cursors are over different strings, the pretoken is empty.
     pub fn new(
         start: StrCursor<'a>,
         end: StrCursor<'a>, line: usize,
         offset: usize) -> Pretoken<'a> {
-        Pretoken{ s:start.slice_between(end).unwrap(), line, offset}
+        Pretoken{ s:start.slice_between(end).unwrap_or_default(), line, offset, kind:Kind::Word}
+    }
+
+    /// The byte range of this pretoken in the input.
+    pub fn span(&self) -> Range<usize> {
+        self.offset..self.offset + self.s.len()
+    }
+
+    /// The byte range within `s` of `span`, a range in the input, if
+    /// `span` lies inside this pretoken.  For example, the span of a
+    /// pretoken found by scanning inside this one.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let src = "x = \"a b\";";
+    /// let tok = Pretokenizer::new(src).nth(2).unwrap();
+    /// assert_eq!(tok.to_relative(5..6), Some(1..2));
+    /// assert_eq!(&tok.s[1..2], &src[5..6]);
+    /// assert_eq!(tok.to_relative(5..10), None);
+    /// ```
+    pub fn to_relative(&self, span: Range<usize>) -> Option<Range<usize>> {
+        match self.offset <= span.start && span.start <= span.end
+            && span.end <= self.offset + self.s.len() {
+            true => Some(span.start - self.offset..span.end - self.offset),
+            false => None,
+        }
+    }
+
+    /// The byte range in the input of `range`, a range within `s`.  The
+    /// inverse of `to_relative()`.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let src = "x = \"a b\";";
+    /// let tok = Pretokenizer::new(src).nth(2).unwrap();
+    /// let b = tok.s.find('b').unwrap();
+    /// assert_eq!(tok.to_absolute(b..b + 1), 7..8);
+    /// ```
+    pub fn to_absolute(&self, range: Range<usize>) -> Range<usize> {
+        range.start + self.offset..range.end + self.offset
+    }
+}
+
+
+/// A shared callback.  The wrapper lets the
+/// [Pretokenizer](struct.Pretokenizer.html) keep deriving `Clone` and `Debug`.
+struct Hook<F: ?Sized>(Rc<RefCell<F>>);
+
+impl<F: ?Sized> Clone for Hook<F> {
+    fn clone(&self) -> Self {
+        Hook(self.0.clone())
+    }
+}
+
+impl<F: ?Sized> fmt::Debug for Hook<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Hook")
     }
 }
 
+type NewlineHook<'a> = Hook<dyn FnMut(&Pretoken<'a>) -> bool + 'a>;
+type CommentHook<'a> = Hook<dyn FnMut(&Pretoken<'a>) + 'a>;
+type TokenFilter<'a> = Hook<dyn FnMut(Pretoken<'a>) -> Option<Pretoken<'a>> + 'a>;
+type WordChars<'a> = Hook<dyn FnMut(char) -> bool + 'a>;
+type Classifier<'a> = Hook<dyn CharClassifier + 'a>;
+type ProgressHook<'a> = Hook<dyn FnMut(usize, usize) -> bool + 'a>;
 
 /// The Pretokenizer is an iterator that produces Option<[Pretoken]> objects over
 /// an input string.
 ///
-/// The Pretokenizer has a simple interface with only new() and next() functions.
+/// Create one with `new()`, or with `with_config()` to enable optional
+/// behaviors, and read pretokens with `next()`.  Hooks and filters such as
+/// `on_comment()` and `filter_tokens()` customize the scan.  Other methods
+/// peek ahead, seek or skip through the input, and report diagnostics and
+/// statistics.
 /// ```
 /// use pretok::{Pretokenizer, Pretoken};
 /// let pt = Pretokenizer::new("a+b c// stuff\nd");
@@ -162,637 +607,4261 @@ pub struct Pretokenizer<'a> {
 
     /// The current number of newlines encountered
     line: usize,
+
+    /// True until a token starts on the current line
+    bol: bool,
+
+    /// Optional behaviors
+    cfg: Config,
+
+    /// The scanner's transitions for `cfg`
+    dfa: dfa::Table,
+
+    /// Lines collected by `Config::pragma()`
+    pragmas: Vec<Pretoken<'a>>,
+
+    /// Called at newlines to decide whether to emit a terminator
+    newline_hook: Option<NewlineHook<'a>>,
+
+    /// Called with each comment skipped
+    comment_hook: Option<CommentHook<'a>>,
+
+    /// Drops or rewrites pretokens before they are returned
+    token_filter: Option<TokenFilter<'a>>,
+
+    /// Tells the characters of words from others
+    word_chars: Option<WordChars<'a>>,
+
+    /// Gives characters their roles in place of the defaults
+    classifier: Option<Classifier<'a>>,
+
+    /// Called every so many pretokens with the bytes consumed
+    progress_hook: Option<(usize, ProgressHook<'a>)>,
+
+    /// True if the progress hook stopped iteration
+    cancelled: bool,
+
+    /// Counters for `stats()`
+    stats: stats::StatsTracker,
+
+    /// How the scanner recognized the last comment
+    comment_style: CommentStyle,
+
+    /// The last pretoken returned, tracked only for the newline hook
+    last: Option<Pretoken<'a>>,
+
+    /// True if a pretoken was returned since the last newline
+    terminable: bool,
+
+    /// Recoverable problems found so far
+    diagnostics: Vec<PretokError>,
+
+    /// Progress of `Config::max_line_len()` checking
+    line_lengths: limits::LineLengths,
+
+    /// Progress of character checks such as `Config::detect_bidi()`
+    char_checks: checks::CharChecks,
+
+    /// Progress of `Config::detect_whitespace()`
+    ws_checks: checks::WhitespaceChecks,
+
+    /// Consumption against `Config::max_tokens()` and `Config::max_bytes()`
+    budget: limits::Budget,
+
+    /// The result of the next call to `next()`, if already scanned by `peek()`
+    peeked: Option<Option<Pretoken<'a>>>,
+
+    /// Offset and line of a `Config::tool_directives()` off marker whose
+    /// region starts on the next line
+    off_marker: Option<(usize, usize)>,
+
+    /// Line start offsets, built on the first call to `line_at()`
+    line_starts: OnceCell<lines::LineIndex>,
+
+    /// Number of diagnostics whose lines are final, when lines aren't counted
+    lines_fixed: usize,
+
+    /// The state to start the next scan in, when the last one already saw
+    /// the character at `pos`
+    resume: State,
+
+    /// The construct the input starts inside, until the first scan
+    start: ScanState,
+
+    /// The construct left open at the end of input
+    open: ScanState,
+
+    /// True if runs of whitespace and newlines are passed over rather than
+    /// returned, for callers that only want pretokens
+    skip_blanks: bool,
+
+    /// State changes recorded for `Config::trace()`
+    transitions: Vec<trace::Transition>,
+}
+
+/// The scanner states whose uninteresting characters are skipped in bulk.
+#[derive(Clone, Copy)]
+enum Skip {
+    #[cfg(feature = "simd")]
+    Blanks,
+    LineComment,
+    BlockComment,
+    Quoted,
+    Word,
 }
 
 impl<'a> Pretokenizer<'a> {
     /// Create a new tokenizer
-    pub fn new(s: &'a str) -> Pretokenizer {
+    pub fn new(s: &'a str) -> Pretokenizer<'a> {
+        Pretokenizer::with_config(s, Config::default())
+    }
+
+    /// Create a new tokenizer with optional behaviors enabled by `cfg`.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let mut pt = Pretokenizer::with_config("x", Config::new());
+    /// assert_eq!(pt.next().unwrap().s, "x");
+    /// ```
+    pub fn with_config(s: &'a str, cfg: Config) -> Pretokenizer<'a> {
         Pretokenizer{
             pos: StrCursor::new_at_start(s),
-            line: 1,  // Line number are not zero-based
+            line: cfg.first_line(),
+            bol: true,
+            dfa: dfa::Table::new(&cfg),
+            line_lengths: limits::LineLengths::new(&cfg),
+            char_checks: checks::CharChecks::new(&cfg),
+            ws_checks: checks::WhitespaceChecks::new(&cfg),
+            cfg,
+            pragmas: Vec::new(),
+            newline_hook: None,
+            comment_hook: None,
+            token_filter: None,
+            word_chars: None,
+            classifier: None,
+            progress_hook: None,
+            cancelled: false,
+            stats: stats::StatsTracker::default(),
+            comment_style: CommentStyle::default(),
+            last: None,
+            terminable: false,
+            diagnostics: Vec::new(),
+            budget: limits::Budget::new(),
+            peeked: None,
+            off_marker: None,
+            line_starts: OnceCell::new(),
+            lines_fixed: 0,
+            resume: State::WS,
+            start: ScanState::Normal,
+            open: ScanState::Normal,
+            skip_blanks: false,
+            transitions: Vec::new(),
         }
     }
 
-    fn make_pretok(&mut self, end: StrCursor<'a>) -> Option<Pretoken<'a>> {
-        // If the current position hasn't moved, then return None.
-        // This check simplifies corner cases like end-of-input.
-        if end == self.pos {
-            return None;
+    /// Iterate over the pretokens of `s` before byte `offset`, nearest
+    /// first.  See [Before](struct.Before.html).
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut before = Pretokenizer::before("fn main() {\n    foo.", 20);
+    /// assert_eq!(before.next().unwrap().s, "foo.");
+    /// ```
+    pub fn before(s: &'a str, offset: usize) -> Before<'a> {
+        Before::new(s, offset, Config::default())
+    }
+
+    /// Like [Pretokenizer::before()](struct.Pretokenizer.html#method.before),
+    /// with `cfg`.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().trigraphs(true);
+    /// let mut before = Pretokenizer::before_with_config("a ??( b", 5, cfg);
+    /// assert_eq!(before.next().unwrap().s, "??(");
+    /// ```
+    pub fn before_with_config(s: &'a str, offset: usize, cfg: Config) -> Before<'a> {
+        Before::new(s, offset, cfg)
+    }
+
+    /// Install a hook consulted at each newline that follows a pretoken on
+    /// the same line.  The hook receives the last pretoken returned.  If the
+    /// hook returns true, the newline itself is returned as a synthetic
+    /// pretoken of kind [Kind::Terminator](enum.Kind.html#variant.Terminator).
+    /// This is enough to implement Go or JavaScript style automatic
+    /// semicolon insertion.
+    ///
+    /// Newlines inside comments and quoted strings are not consulted.
+    /// ```
+    /// use pretok::{Kind, Pretokenizer};
+    /// let pt = Pretokenizer::new("x = y +\n  z // sum\nf(x)\n")
+    ///     .on_newline(|last| !last.s.ends_with('+'));
+    /// let toks: Vec<_> = pt.map(|t| if t.kind == Kind::Terminator { ";" } else { t.s })
+    ///     .collect();
+    /// assert_eq!(toks, vec!["x", "=", "y", "+", "z", ";", "f(x)", ";"]);
+    /// ```
+    pub fn on_newline<F>(mut self, hook: F) -> Pretokenizer<'a>
+    where F: FnMut(&Pretoken<'a>) -> bool + 'a {
+        self.newline_hook = Some(Hook(Rc::new(RefCell::new(hook))));
+        self
+    }
+
+    /// Install a hook called with each comment skipped, as a pretoken of
+    /// kind [Kind::Comment](enum.Kind.html#variant.Comment) whose `offset`
+    /// and `s.len()` give its span.  The pretokens returned are unchanged.
+    /// Input skipped like a comment, such as a
+    /// [Config::skip_region()](struct.Config.html#method.skip_region), is
+    /// included.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut todos = Vec::new();
+    /// let toks: Vec<_> = Pretokenizer::new("a // TODO: b\n/* c */ d")
+    ///     .on_comment(|c| if c.s.contains("TODO") { todos.push(c.line) })
+    ///     .map(|t| t.s)
+    ///     .collect();
+    /// assert_eq!(toks, vec!["a", "d"]);
+    /// assert_eq!(todos, vec![1]);
+    /// ```
+    pub fn on_comment<F>(mut self, hook: F) -> Pretokenizer<'a>
+    where F: FnMut(&Pretoken<'a>) + 'a {
+        self.comment_hook = Some(Hook(Rc::new(RefCell::new(hook))));
+        self
+    }
+
+    /// Install a filter applied to each pretoken before it is returned.  The
+    /// filter may return the pretoken unchanged, rewrite it, or return
+    /// `None` to drop it.  Checks such as
+    /// [Config::max_token_len()](struct.Config.html#method.max_token_len)
+    /// see the pretoken before the filter does.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let keywords = ["if", "else"];
+    /// let toks: Vec<_> = Pretokenizer::new("IF x \\\n ELSE y")
+    ///     .filter_tokens(|mut t| {
+    ///         if t.s == "\\" {
+    ///             return None;
+    ///         }
+    ///         if let Some(k) = keywords.iter().find(|k| k.eq_ignore_ascii_case(t.s)) {
+    ///             t.s = k;
+    ///         }
+    ///         Some(t)
+    ///     })
+    ///     .map(|t| t.s)
+    ///     .collect();
+    /// assert_eq!(toks, vec!["if", "x", "else", "y"]);
+    /// ```
+    pub fn filter_tokens<F>(mut self, filter: F) -> Pretokenizer<'a>
+    where F: FnMut(Pretoken<'a>) -> Option<Pretoken<'a>> + 'a {
+        self.token_filter = Some(Hook(Rc::new(RefCell::new(filter))));
+        self
+    }
+
+    /// Install a predicate telling which characters make up words.  A word
+    /// pretoken ends where a run of characters for which `pred` is true
+    /// meets a run of others, so each run is a pretoken of its own.  Quoted
+    /// strings, comments and whitespace are scanned as usual.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let toks: Vec<_> = Pretokenizer::new("(set! list-len (+ 1 n))")
+    ///     .word_chars(|c| c.is_alphanumeric() || "-!?".contains(c))
+    ///     .map(|t| t.s)
+    ///     .collect();
+    /// assert_eq!(toks, vec!["(", "set!", "list-len", "(+", "1", "n", "))"]);
+    /// ```
+    pub fn word_chars<F>(mut self, pred: F) -> Pretokenizer<'a>
+    where F: FnMut(char) -> bool + 'a {
+        self.word_chars = Some(Hook(Rc::new(RefCell::new(pred))));
+        self
+    }
+
+    /// Use `classifier` to tell which characters are whitespace, quotes,
+    /// comment starts and word characters, for languages that
+    /// [Config](struct.Config.html) options don't fit.  See
+    /// [CharClassifier](trait.CharClassifier.html).
+    /// ```
+    /// use pretok::{CharClassifier, Pretokenizer};
+    /// struct Shell;
+    /// impl CharClassifier for Shell {
+    ///     fn is_quote(&self, c: char) -> bool {
+    ///         c == '"' || c == '\''
+    ///     }
+    /// }
+    /// let toks: Vec<_> = Pretokenizer::new("echo 'a b' \"c\"").classifier(Shell)
+    ///     .map(|t| t.s).collect();
+    /// assert_eq!(toks, vec!["echo", "'a b'", "\"c\""]);
+    /// ```
+    pub fn classifier<C>(mut self, classifier: C) -> Pretokenizer<'a>
+    where C: CharClassifier + 'a {
+        self.dfa.classify_ascii(&classifier);
+        self.classifier = Some(Hook(Rc::new(RefCell::new(classifier))));
+        self
+    }
+
+    /// Install a hook called after every `every` pretokens returned, with
+    /// the number of bytes of input consumed and the total, to drive a
+    /// progress bar over a large input.  If the hook returns false,
+    /// iteration stops, as if at the end of input, and `cancelled()` is
+    /// true.  An `every` of 0 is taken as 1.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut seen = Vec::new();
+    /// let mut pt = Pretokenizer::new("a bb ccc dddd")
+    ///     .on_progress(1, |done, total| { seen.push((done, total)); done < 6 });
+    /// assert_eq!(pt.by_ref().map(|t| t.s).collect::<Vec<_>>(), vec!["a", "bb", "ccc"]);
+    /// assert!(pt.cancelled());
+    /// drop(pt);
+    /// assert_eq!(seen, vec![(1, 13), (4, 13), (8, 13)]);
+    /// ```
+    pub fn on_progress<F>(mut self, every: usize, hook: F) -> Pretokenizer<'a>
+    where F: FnMut(usize, usize) -> bool + 'a {
+        self.progress_hook = Some((every.max(1), Hook(Rc::new(RefCell::new(hook)))));
+        self
+    }
+
+    /// The fraction of the input consumed so far, from 0 to 1, for
+    /// progress bars.  Input skipped after the last pretoken returned, such
+    /// as a comment, counts as consumed.  Empty input is all consumed.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut pt = Pretokenizer::new("ab cd // x");
+    /// assert_eq!(pt.progress(), 0.0);
+    /// pt.next();
+    /// assert_eq!(pt.progress(), 0.2);
+    /// pt.by_ref().count();
+    /// assert_eq!(pt.progress(), 1.0);
+    /// ```
+    pub fn progress(&self) -> f64 {
+        match self.pos.slice_all().len() {
+            0 => 1.0,
+            len => self.byte_pos() as f64 / len as f64,
         }
+    }
 
-        // Update the state of the Pretokenizer to the end of this pretoken.
-        let start = self.pos;
-        self.pos = end;
-        Some(Pretoken::new(start, end, self.line, start.byte_pos()))
+    /// True if iteration stopped because the hook given to
+    /// [on_progress()](#method.on_progress) returned false.
+    pub fn cancelled(&self) -> bool {
+        self.cancelled
     }
-}
 
-/// Advances the internal iterator to the next pretoken. Skips whitespace
-/// and comments. If the result is OK(None), then we successfully reached
-/// end of the input string.
-impl <'a> std::iter::Iterator for Pretokenizer<'a> {
-    type Item = Pretoken<'a>;
-    fn next(&mut self) -> Option<Self::Item> {
+    /// The class of `c`.
+    fn class(&self, c: char) -> dfa::Class {
+        match &self.classifier {
+            Some(classifier) if !c.is_ascii() => dfa::classify(&*classifier.0.borrow(), c)
+                .unwrap_or_else(|| self.dfa.class(c)),
+            _ => self.dfa.class(c),
+        }
+    }
 
-        #[derive(Debug)]
-        enum STATE {
-            WS,
-            MaybeComment,
-            LineComment,
-            BlockComment,
-            MaybeBlockCommentDone,
-            StartTok,
-            NormalTok,
-            QuotedTok,
-            EscapeChar,
+    /// Consult the newline hook for the newline between `curs` and `next`.
+    fn terminate(&mut self, curs: StrCursor<'a>, next: StrCursor<'a>)
+        -> Option<Pretoken<'a>> {
+        let hook = self.newline_hook.clone()?;
+        let insert = match self.last {
+            Some(ref last) => (hook.0.borrow_mut())(last),
+            None => false,
         };
+        if !insert {
+            return None;
+        }
+        self.pos = curs;
+        let tok = self.make_pretok(next, Kind::Terminator);
+        self.line += 1;
+        self.bol = true;
+        tok
+    }
 
-        // Start by skipping any whitespace
-        let mut state = STATE::WS;
+    /// The recoverable problems found so far, in input order.  Inspect these
+    /// after draining the iterator to warn about input that pretokenized
+    /// without error but probably isn't what the author intended.
+    /// ```
+    /// use pretok::{Pretokenizer, PretokError};
+    /// let mut pt = Pretokenizer::new("x /* y");
+    /// let toks: Vec<_> = pt.by_ref().collect();
+    /// assert_eq!(toks.len(), 1);
+    /// assert_eq!(pt.diagnostics(),
+    ///            &[PretokError::UnterminatedBlockComment{line: 1, span: 2..6}]);
+    /// ```
+    pub fn diagnostics(&self) -> &[PretokError] {
+        &self.diagnostics
+    }
 
-        // Get a local cursor starting at our current position.
-        let mut curs = self.pos;
+    /// True if iteration stopped early because the budget set by
+    /// [Config::max_tokens()](struct.Config.html#method.max_tokens) or
+    /// [Config::max_bytes()](struct.Config.html#method.max_bytes) ran out.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let mut pt = Pretokenizer::with_config("a b c", Config::new().max_tokens(2));
+    /// assert_eq!(pt.by_ref().count(), 2);
+    /// assert!(pt.budget_exhausted());
+    /// ```
+    pub fn budget_exhausted(&self) -> bool {
+        self.budget.exhausted
+    }
 
-        loop {
+    /// Counts of the pretokens, comments, lines and whitespace seen so far.
+    /// See [Stats](struct.Stats.html).
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut pt = Pretokenizer::new("a\n  \nb");
+    /// pt.next();
+    /// assert_eq!(pt.stats().lines, 1);
+    /// pt.by_ref().count();
+    /// assert_eq!(pt.stats().lines, 3);
+    /// assert_eq!(pt.stats().whitespace_bytes, 4);
+    /// ```
+    pub fn stats(&self) -> Stats {
+        let consumed = &self.pos.slice_all()[..self.pos.byte_pos()];
+        let line = match self.cfg.counts_lines() {
+            true => self.line,
+            false => self.line_at(consumed.len()),
+        };
+        self.stats.stats(consumed, line + 1 - self.cfg.first_line())
+    }
 
-            // Note that we're dealing with unicode code points rather
-            // than grapheme clusters
-            let copt = curs.cp_after();
+    /// The next pretoken, without consuming it.  The pretoken is scanned
+    /// once and cached, so the following call to `next()` is free.  Any
+    /// diagnostics for the pretoken are recorded by `peek()`.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut pt = Pretokenizer::new("a b");
+    /// assert_eq!(pt.peek().unwrap().s, "a");
+    /// assert_eq!(pt.next().unwrap().s, "a");
+    /// assert_eq!(pt.next().unwrap().s, "b");
+    /// assert!(pt.peek().is_none());
+    /// ```
+    pub fn peek(&mut self) -> Option<&Pretoken<'a>> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.advance());
+        }
+        self.peeked.as_ref().and_then(|tok| tok.as_ref())
+    }
 
-            if copt.is_none() {
-                // End of input!
-                match state {
-                    STATE::NormalTok => {
-                        return self.make_pretok(curs);
-                    }
-                    STATE::BlockComment => {
-                        // Unterminated block comment at end of input
-                        // Caller may want to detect this and warn.
-                    }
-                    STATE::QuotedTok | STATE::EscapeChar => {
-                        // Unterminated quoted string at end of input
-                        // Caller may want to detect this and warn.
-                        return self.make_pretok(curs);
-                    }
+    /// The input not yet consumed, starting just after the last pretoken
+    /// returned by `next()`.  A pretoken cached by `peek()` is not consumed.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut pt = Pretokenizer::new("x = <<EOF\nraw text\nEOF");
+    /// pt.by_ref().take(3).count();
+    /// assert_eq!(pt.remainder(), "\nraw text\nEOF");
+    /// assert_eq!(pt.peek().unwrap().s, "raw");
+    /// assert_eq!(pt.remainder(), "raw text\nEOF");
+    /// ```
+    pub fn remainder(&self) -> &'a str {
+        match &self.peeked {
+            Some(Some(tok)) => &self.pos.slice_all()[tok.offset..],
+            _ => self.pos.slice_after(),
+        }
+    }
 
-                    _ => {}
-                }
+    /// The whole input, from which pretoken offsets are counted.  The head
+    /// from `split_at()` has the input up to the split.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut pt = Pretokenizer::new("a (b c)");
+    /// let tok = pt.nth(1).unwrap();
+    /// assert_eq!(pt.source(), "a (b c)");
+    /// assert_eq!(&pt.source()[tok.span()], "(b");
+    /// ```
+    pub fn source(&self) -> &'a str {
+        self.pos.slice_all()
+    }
 
-                self.pos = curs; // sync cursor position
-                return None;
-            }
+    /// The rest of the physical line at `byte_pos()`, without its line
+    /// break, for handlers that look at a whole line before deciding to
+    /// pretokenize it or skip it with `skip_line()`.  Nothing is consumed.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut pt = Pretokenizer::new("a\n.skip /* this\r\nb");
+    /// pt.next();
+    /// assert_eq!(pt.peek_line(), "");
+    /// pt.peek();
+    /// assert_eq!(pt.peek_line(), ".skip /* this");
+    /// if pt.peek_line().starts_with(".skip") {
+    ///     pt.skip_line();
+    /// }
+    /// assert_eq!(pt.next().unwrap().s, "b");
+    /// ```
+    pub fn peek_line(&self) -> &'a str {
+        let rest = self.remainder();
+        let line = rest.find('\n').map_or(rest, |i| &rest[..i]);
+        line.strip_suffix('\r').unwrap_or(line)
+    }
 
-            // Get the byte offset and character respectively
-            let c = copt.unwrap();
+    /// Skip the rest of the physical line at `byte_pos()`, whatever it
+    /// holds, so the next pretoken is on a later line.  Unlike `seek()`,
+    /// this may stop inside what would have been a string or comment.  The
+    /// skipped text is not checked.
+    pub fn skip_line(&mut self) {
+        let rest = self.remainder();
+        let start = self.byte_pos();
+        let end = start + rest.find('\n').unwrap_or(rest.len());
+        if self.cfg.counts_lines() {
+            self.line = self.line();
+        }
+        if end > start {
+            self.bol = false;
+        }
+        self.pos = cursor_at(self.pos.slice_all(), end);
+        self.resume = State::WS;
+        self.peeked = None;
+    }
 
-            match state {
-                STATE::WS => {
-                    match c {
-                        // need braces so each arm returns ()
-                        '\n' => {
-                            self.line += 1;
-                            curs.seek_next_cp();
-                        }
-                        ' ' | '\t' => {
-                            curs.seek_next_cp();
-                        }
-                        '/' => {
-                            state = STATE::MaybeComment;
-                            curs.seek_next_cp();
-                        }
-                        _ => state = STATE::StartTok,
-                    }
-                }
+    /// The byte offset where `remainder()` starts.  Use this with `line()`
+    /// to report problems such as an unexpected end of input.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut pt = Pretokenizer::new("a\n// end");
+    /// pt.by_ref().count();
+    /// assert_eq!(pt.byte_pos(), 8);
+    /// assert_eq!(pt.line(), 2);
+    /// ```
+    pub fn byte_pos(&self) -> usize {
+        match &self.peeked {
+            Some(Some(tok)) => tok.offset,
+            _ => self.pos.byte_pos(),
+        }
+    }
 
-                // We enter the this state after peeking a '/' character.
-                // We're looking for another '/' or '*'
-                STATE::MaybeComment => {
-                    match c {
-                        '/' => {
-                            // We're in a line comment.
-                            state = STATE::LineComment;
-                            curs.seek_next_cp();
-                        }
-                        '*' => {
-                            // We're in a block comment.
-                            state = STATE::BlockComment;
-                            curs.seek_next_cp();
-                        }
-                        _ => state = STATE::StartTok,
-                    }
-                }
+    /// The line number at `byte_pos()`.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut pt = Pretokenizer::new("a\nb");
+    /// assert_eq!(pt.line(), 1);
+    /// pt.next();
+    /// assert_eq!(pt.line(), 1);
+    /// pt.next();
+    /// assert_eq!(pt.line(), 2);
+    /// ```
+    pub fn line(&self) -> usize {
+        match &self.peeked {
+            _ if self.cfg.offsets_only => 0,
+            Some(Some(tok)) => self.start_line(tok),
+            _ if self.cfg.lazy_lines => self.line_at(self.pos.byte_pos()),
+            _ => self.line,
+        }
+    }
 
-                STATE::LineComment => {
-                    if c == '\n' {
-                        // handle the new line WS state.
-                        state = STATE::WS;
-                    } else {
-                        curs.seek_next_cp();
-                    }
-                }
+    /// The line number of the line containing byte `offset` of the input,
+    /// or of the last line if `offset` is past the end.  The first call
+    /// indexes the start of every line, so later calls are cheap.  Use this
+    /// to find the lines of pretokens returned with
+    /// [Config::lazy_lines()](struct.Config.html#method.lazy_lines).
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let mut pt = Pretokenizer::with_config("a\n/* b\n*/ c", Config::new().lazy_lines(true));
+    /// pt.next();
+    /// let c = pt.next().unwrap();
+    /// assert_eq!((c.s, c.line), ("c", 0));
+    /// assert_eq!(pt.line_at(c.offset), 3);
+    /// ```
+    pub fn line_at(&self, offset: usize) -> usize {
+        self.line_starts.get_or_init(|| lines::LineIndex::new(self.pos.slice_all()))
+            .line(offset) - 1 + self.cfg.first_line()
+    }
 
-                STATE::BlockComment => {
-                    match c {
-                        '*' => {
-                            state = STATE::MaybeBlockCommentDone;
-                        }
-                        '\n' => {
-                            self.line += 1;
-                        }
-                        _ => {}
-                    }
-                    curs.seek_next_cp();
-                }
+    /// The line on which `tok` starts.
+    pub(crate) fn start_line(&self, tok: &Pretoken<'a>) -> usize {
+        if self.cfg.offsets_only {
+            0
+        } else if self.cfg.lazy_lines {
+            self.line_at(tok.offset)
+        } else if tok.kind == Kind::Newline {
+            // A line break has the line it ends.
+            tok.line
+        } else {
+            tok.line - tok.s.matches('\n').count()
+        }
+    }
 
-                STATE::MaybeBlockCommentDone => {
-                    match c {
-                        '/' => {
-                            // Done with the block
-                            state = STATE::WS;
-                        }
-                        '\n' => {
-                            self.line += 1;
-                            // false alarm, not done with block
-                            state = STATE::BlockComment;
-                        }
-                        // False alarm, not done with the block
-                        _ => { state = STATE::BlockComment; }
-                    }
-                    curs.seek_next_cp();
-                }
+    /// Give the diagnostics recorded since the last call their lines, which
+    /// aren't counted while scanning with `Config::lazy_lines()`, or 0 with
+    /// `Config::offsets_only()`.
+    pub(crate) fn fix_lines(&mut self) {
+        for i in self.lines_fixed..self.diagnostics.len() {
+            if self.cfg.offsets_only {
+                *self.diagnostics[i].line_mut() = 0;
+                continue;
+            }
+            let at = match &self.diagnostics[i] {
+                // Reported at the pretoken's last line, like `Pretoken::line`.
+                PretokError::TokenTooLong{span, ..} => span.end - 1,
+                e => e.span().start,
+            };
+            let line = self.line_at(at);
+            *self.diagnostics[i].line_mut() = line;
+        }
+        self.lines_fixed = self.diagnostics.len();
+    }
 
-                STATE::StartTok => {
-                    // sync the real iterator with our temporary
-                    // If this is a quoted string, the returned token
-                    // will include the quote character.
-                    self.pos = curs;
+    /// Move to byte `offset` of the input, which must fall between pretokens.
+    /// Whitespace and comments between `offset` and the next pretoken are
+    /// skipped as usual.  The input is scanned from the current position, or
+    /// from the start if `offset` is behind it, but no pretokens are
+    /// returned or checked along the way.
+    ///
+    /// On error, the pretokenizer is left at the last pretoken boundary
+    /// before `offset`.
+    /// ```
+    /// use pretok::{Pretokenizer, SeekError};
+    /// let mut pt = Pretokenizer::new("a \"b c\" d");
+    /// assert_eq!(pt.seek(8), Ok(()));
+    /// assert_eq!(pt.next().unwrap().s, "d");
+    /// assert_eq!(pt.seek(4), Err(SeekError::InsidePretoken{span: 2..7}));
+    /// ```
+    pub fn seek(&mut self, offset: usize) -> Result<(), SeekError> {
+        let s = self.pos.slice_all();
+        if offset > s.len() {
+            return Err(SeekError::OutOfRange{offset});
+        }
+        if !s.is_char_boundary(offset) {
+            return Err(SeekError::NotCharBoundary{offset});
+        }
+        self.peeked = None;
+        self.last = None;
+        self.terminable = false;
+        self.off_marker = None;
+        if offset < self.pos.byte_pos() {
+            self.pos = StrCursor::new_at_start(s);
+            self.line = self.cfg.first_line();
+            self.bol = true;
+            self.resume = State::WS;
+        }
 
-                    if c == '"' {
-                        state = STATE::QuotedTok;
-                        curs.seek_next_cp();
-                    } else {
-                        state = STATE::NormalTok;
-                        curs.seek_next_cp();
-                    }
+        loop {
+            let (pos, line, bol, resume) = (self.pos, self.line, self.bol, self.resume);
+            let off_marker = self.off_marker;
+            if pos.byte_pos() == offset {
+                return Ok(());
+            }
+            let (diagnostics, pragmas) = (self.diagnostics.len(), self.pragmas.len());
+            let mut in_comment = false;
+            let tok = self.scan_with(|t, _| {
+                in_comment |= t.kind == Kind::Comment
+                    && t.offset < offset && offset < t.offset + t.s.len();
+            });
+            match tok {
+                Some(ref t) if t.offset + t.s.len() <= offset => continue,
+                Some(ref t) if t.offset < offset => {
+                    self.pos = pos;
+                    self.line = line;
+                    self.bol = bol;
+                    self.resume = resume;
+                    self.off_marker = off_marker;
+                    return Err(SeekError::InsidePretoken{span: t.offset..t.offset + t.s.len()});
                 }
-
-                STATE::NormalTok => {
-                    match c {
-                        ' ' | '\t' => {
-                            // we'll process this ws on the next next()
-                            return self.make_pretok(curs);
-                        }
-                        '\n' => {
-                            // we'll process this newline on the next next()
-                            return self.make_pretok(curs);
-                        }
-                        '"' => {
-                            // We found quote without whitespace separation.
-                            // Return whatever we've captured before the quote as the token.
-                            // We'll process the quote on the next next()
-                            return self.make_pretok(curs);
-                        }
-                        '/' => {
-                            // We maybe found a comment without whitespace separation.
-                            // Peek ahead one more character to know for sure.
-                            let mut temp = curs;
-                            temp.seek_next_cp();  // skip the / we're peeking at
-                            let temp_copt = temp.cp_after();
-                            if temp_copt.is_none() {
-                                // There's nothing past the /.  Return current token
-                                // including the / we're peeking at.
-                                return self.make_pretok(temp);
-                            } else {
-                                match temp_copt.unwrap() {
-                                    '/' | '*' => {
-                                        // Found a comment, so return the preceding token
-                                        return self.make_pretok(curs);
-                                    }
-                                    _ => {
-                                        // False alarm, It was just a lonely / so keep going.
-                                        curs.seek_next_cp();
-                                    }
-                                }
+                Some(ref t) if t.offset == offset => {
+                    // Scan what comes before `tok` again from the state it
+                    // started in, and stop where the scan of `tok` begins.
+                    self.pos = pos;
+                    self.line = line;
+                    self.bol = bol;
+                    self.resume = resume;
+                    self.off_marker = off_marker;
+                    self.diagnostics.truncate(diagnostics);
+                    self.lines_fixed = self.lines_fixed.min(diagnostics);
+                    self.pragmas.truncate(pragmas);
+                    loop {
+                        let (pos, line, bol, resume) =
+                            (self.pos, self.line, self.bol, self.resume);
+                        let (off_marker, open) = (self.off_marker, self.open);
+                        let (diagnostics, pragmas) =
+                            (self.diagnostics.len(), self.pragmas.len());
+                        match self.scan_event() {
+                            Some(t) if t.kind.is_trivia() && t.offset < offset => {}
+                            _ => {
+                                self.pos = pos;
+                                self.line = line;
+                                self.bol = bol;
+                                self.resume = resume;
+                                self.off_marker = off_marker;
+                                self.open = open;
+                                self.diagnostics.truncate(diagnostics);
+                                self.lines_fixed = self.lines_fixed.min(diagnostics);
+                                self.pragmas.truncate(pragmas);
+                                return Ok(());
                             }
                         }
-                        _ => { curs.seek_next_cp(); }
-                    }
-                }
-                STATE::QuotedTok => {
-                    match c {
-                        '\n' => {
-                            self.line +=1;
-                        }
-                        '"' => {
-                            // We found the closing quote.  Advance the cursor so the
-                            // closing quote is included in the returned token.
-                            curs.seek_next_cp();
-                            return self.make_pretok(curs);
-                        }
-                        '\\' => {
-                            // We found an escape sequence.  Next character is always inside the string,
-                            // if if it's another quote.
-                            state = STATE::EscapeChar;
-                        }
-                        _ => { }
                     }
-                    curs.seek_next_cp();
                 }
-                STATE::EscapeChar => {
-                    if c == '\n' {
-                        self.line +=1;
-                    }
-                    state = STATE::QuotedTok;
-                    curs.seek_next_cp();
+                _ => {}
+            }
+
+            // `offset` is in the whitespace and comments before `tok`.  It's
+            // a good place to stop only if it's outside the comments and
+            // scanning from there finds `tok` too.
+            if !in_comment {
+                let skipped = &s[pos.byte_pos()..offset];
+                let (diagnostics, pragmas) = (self.diagnostics.len(), self.pragmas.len());
+                self.pos = grapheme_cursor_at(s, offset);
+                self.line = line + skipped.matches('\n').count();
+                // Only whitespace may come between the start of a line and
+                // `offset`.
+                let before = s[..offset].trim_end_matches([' ', '\t']);
+                self.bol = before.len() <= self.bom_len() || before.ends_with('\n');
+                self.resume = State::WS;
+                let (line_at, bol_at) = (self.line, self.bol);
+                let again = self.scan();
+                self.diagnostics.truncate(diagnostics);
+                self.lines_fixed = self.lines_fixed.min(diagnostics);
+                self.pragmas.truncate(pragmas);
+                if again == tok {
+                    self.pos = grapheme_cursor_at(s, offset);
+                    self.line = line_at;
+                    self.bol = bol_at;
+                    self.resume = State::WS;
+                    self.off_marker = off_marker;
+                    return Ok(());
                 }
             }
+            self.pos = pos;
+            self.line = line;
+            self.bol = bol;
+            self.resume = resume;
+            self.off_marker = off_marker;
+            return Err(SeekError::InsideComment{offset});
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Move to the start of line `line`, as with `seek()`.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut pt = Pretokenizer::new("a\nb\nc");
+    /// pt.skip_to_line(3).unwrap();
+    /// assert_eq!(pt.next().unwrap().s, "c");
+    /// assert!(pt.skip_to_line(4).is_err());
+    /// ```
+    pub fn skip_to_line(&mut self, line: usize) -> Result<(), SeekError> {
+        let s = self.pos.slice_all();
+        let offset = match line {
+            0 => None,
+            1 => Some(0),
+            _ => s.match_indices('\n').nth(line - 2).map(|(i, _)| i + 1),
+        };
+        match offset {
+            Some(offset) => self.seek(offset),
+            None => Err(SeekError::OutOfRange{offset: s.len() + 1}),
+        }
+    }
 
-    #[test]
-    fn pretokenizer_test_0() {
-        let mut pt = Pretokenizer::new("");
-        assert!(pt.next().is_none());
+    /// Split into two independent pretokenizers: one for the input from the
+    /// current position up to `offset`, and one for the input from `offset`
+    /// on.  `offset` is verified as with `seek()`, so together the two
+    /// return exactly the pretokens this one would, with the same offsets
+    /// and lines.  The parts can be parsed separately and the results
+    /// joined.
+    ///
+    /// Each part has its own diagnostics, statistics and budget, and checks
+    /// such as [Config::max_line_len()](struct.Config.html#method.max_line_len)
+    /// see only its own input.  Hooks and the token filter are shared.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let src = "a \"b\nc\"\nd e";
+    /// let (head, tail) = Pretokenizer::new(src).split_at(8).unwrap();
+    /// let v: Vec<_> = head.map(|t| (t.s, t.line)).collect();
+    /// assert_eq!(v, vec![("a", 1), ("\"b\nc\"", 2)]);
+    /// let v: Vec<_> = tail.map(|t| (t.s, t.line)).collect();
+    /// assert_eq!(v, vec![("d", 3), ("e", 3)]);
+    /// assert!(Pretokenizer::new(src).split_at(4).is_err());
+    /// ```
+    pub fn split_at(mut self, offset: usize)
+        -> Result<(Pretokenizer<'a>, Pretokenizer<'a>), SeekError> {
+        if offset < self.byte_pos() {
+            return Err(SeekError::OutOfRange{offset});
+        }
+        let mut tail = self.clone();
+        tail.seek(offset)?;
+        tail.diagnostics.clear();
+        tail.lines_fixed = 0;
+        tail.pragmas.clear();
+        tail.stats = stats::StatsTracker::default();
+        tail.budget = limits::Budget::new();
+        tail.line_lengths = limits::LineLengths::starting_at(offset, tail.line);
+        tail.char_checks = checks::CharChecks::starting_at(offset, tail.line);
+        let bol = offset == 0 || self.pos.slice_all()[..offset].ends_with('\n');
+        tail.ws_checks = checks::WhitespaceChecks::starting_at(bol, &tail.cfg);
+
+        // The head's input ends at `offset`, which is at or after its
+        // position.
+        let s = &self.pos.slice_all()[..offset];
+        let pos = self.pos.byte_pos();
+        if pos == offset {
+            self.resume = State::WS;
+        }
+        self.pos = grapheme_cursor_at(s, pos);
+        self.line_starts = OnceCell::new();
+        Ok((self, tail))
     }
-    #[test]
-    fn pretokenizer_test_1() {
-        let mut pt = Pretokenizer::new("foo");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 0);
-        assert_eq!(t.line, 1);
-        assert_eq!(t.s, "foo");
+
+    /// Convert into an iterator whose items are
+    /// `Result<Pretoken, PretokError>`.  Recoverable problems such as an
+    /// unterminated string or block comment become errors with spans.  See
+    /// [Strict](struct.Strict.html).
+    /// ```
+    /// use pretok::{Pretokenizer, PretokError};
+    /// let results: Vec<_> = Pretokenizer::new("\"abc").strict().collect();
+    /// assert_eq!(results[0], Err(PretokError::UnterminatedString{line: 1, span: 0..4}));
+    /// assert_eq!(results[1].as_ref().unwrap().s, "\"abc");
+    /// ```
+    pub fn strict(self) -> Strict<'a> {
+        Strict::new(self)
     }
 
-    #[test]
-    fn pretokenizer_test_2() {
-        let mut pt = Pretokenizer::new("foo\n");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 0);
-        assert_eq!(t.line, 1);
-        assert_eq!(t.s, "foo");
+    /// Convert into an iterator over each pretoken with the number of
+    /// brackets open where it starts, for outlines and quick structural
+    /// queries.  See [Depths](struct.Depths.html).
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let v: Vec<_> = Pretokenizer::new("a { b ( c ) } d").depths().map(|(_, d)| d).collect();
+    /// assert_eq!(v, vec![0, 0, 1, 1, 2, 2, 1, 0]);
+    /// ```
+    pub fn depths(self) -> Depths<'a> {
+        Depths::new(self)
     }
 
-    #[test]
-    fn pretokenizer_test_3() {
-        let mut pt = Pretokenizer::new("\nfoo");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 1);
-        assert_eq!(t.line, 2);
-        assert_eq!(t.s, "foo");
+    /// Convert into an iterator over the pieces of a lossless pretokenizer
+    /// with each run of blank lines as one piece, for formatters that keep
+    /// the blank lines between items.  See [BlankLines](struct.BlankLines.html).
+    /// ```
+    /// use pretok::{Config, Kind, Pretokenizer};
+    /// let pt = Pretokenizer::with_config("a\n\n\nb", Config::new().lossless(true));
+    /// let blank: Vec<_> = pt.blank_lines().filter(|t| t.kind == Kind::BlankLines).collect();
+    /// assert_eq!((blank[0].s, blank[0].line), ("\n\n", 3));
+    /// ```
+    pub fn blank_lines(self) -> BlankLines<'a> {
+        let src = self.pos.slice_all();
+        BlankLines::new(src, self)
     }
 
-    #[test]
-    fn pretokenizer_test_4() {
-        let mut pt = Pretokenizer::new("\nfoo\n");
-        let t = pt.next();
-        assert!(t.is_some());
+    /// Convert into an iterator over the physical lines that have
+    /// pretokens, each with its line number and pretokens, for table-like
+    /// inputs and per-line checks.  See [PhysicalLines](struct.PhysicalLines.html).
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut lines = Pretokenizer::new("a b\n\nc").physical_lines();
+    /// assert_eq!(lines.next().unwrap().1.len(), 2);
+    /// assert_eq!(lines.next().unwrap().0, 3);
+    /// ```
+    pub fn physical_lines(self) -> PhysicalLines<'a> {
+        PhysicalLines::new(self)
+    }
+
+    /// Pretokenize the text inside `tok`, a pretoken of this input, with
+    /// `cfg`, for a language embedded in a string or comment.  The text
+    /// inside a quoted pretoken is between its quotes, or its triple
+    /// quotes, and inside other pretokens is all of their text.  The nested
+    /// pretokens and diagnostics have offsets and lines in this input.  See
+    /// [Nested](struct.Nested.html).
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let mut pt = Pretokenizer::new("printf(\n  \"%d {x}\\n\", n)");
+    /// let fmt = pt.nth(1).unwrap();
+    /// let v: Vec<_> = pt.nested(&fmt, Config::new().punctuation("{}")).map(|t| t.span())
+    ///     .collect();
+    /// assert_eq!(v, vec![11..13, 14..15, 15..16, 16..17, 17..19]);
+    /// ```
+    pub fn nested(&self, tok: &Pretoken<'a>, cfg: Config) -> Nested<'a> {
+        let (at, text) = nested::inside(tok);
+        Nested::new(Pretokenizer::with_config(text, cfg), tok.offset + at, self.start_line(tok))
+    }
+
+    /// Pretokenize the rest of the input, pushing each pretoken, comment,
+    /// newline and run of whitespace to `sink` in input order.  Pretokens
+    /// are checked as by `next()`.
+    /// ```
+    /// use pretok::{Pretoken, Pretokenizer, PretokSink};
+    /// #[derive(Default)]
+    /// struct Counts { tokens: usize, comments: usize, lines: usize }
+    /// impl<'a> PretokSink<'a> for Counts {
+    ///     fn on_token(&mut self, _: &Pretoken<'a>) { self.tokens += 1; }
+    ///     fn on_comment(&mut self, _: &Pretoken<'a>) { self.comments += 1; }
+    ///     fn on_newline(&mut self, _: &Pretoken<'a>) { self.lines += 1; }
+    /// }
+    /// let mut counts = Counts::default();
+    /// Pretokenizer::new("a b // c\n/* d */ e\n").drive(&mut counts);
+    /// assert_eq!((counts.tokens, counts.comments, counts.lines), (3, 2, 2));
+    /// ```
+    pub fn drive<S: PretokSink<'a>>(&mut self, sink: &mut S) {
+        fn dispatch<'a, S: PretokSink<'a>>(sink: &mut S, t: &Pretoken<'a>) {
+            match t.kind {
+                Kind::Comment => sink.on_comment(t),
+                Kind::Newline => sink.on_newline(t),
+                Kind::Whitespace => sink.on_whitespace(t),
+                _ => sink.on_token(t),
+            }
+        }
+
+        match self.peeked.take() {
+            Some(Some(tok)) => dispatch(sink, &tok),
+            Some(None) => return,
+            None => {}
+        }
+        loop {
+            // Trivia returned in lossless mode was already dispatched.
+            match self.advance_with(|t| dispatch(sink, t)) {
+                Some(tok) if tok.kind.is_trivia() => {}
+                Some(tok) => sink.on_token(&tok),
+                None => break,
+            }
+        }
+    }
+
+    /// Append up to `n` pretokens to `buf`, returning how many were
+    /// appended.  Fewer than `n` means the input is exhausted.  Reusing one
+    /// buffer for every chunk avoids the per-pretoken overhead of `next()`.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut pt = Pretokenizer::new("a b c d e");
+    /// let mut buf = Vec::new();
+    /// assert_eq!(pt.next_chunk(&mut buf, 3), 3);
+    /// buf.clear();
+    /// assert_eq!(pt.next_chunk(&mut buf, 3), 2);
+    /// assert_eq!(buf[1].s, "e");
+    /// ```
+    pub fn next_chunk(&mut self, buf: &mut Vec<Pretoken<'a>>, n: usize) -> usize {
+        let start = buf.len();
+        let mut want = n;
+        if want > 0 {
+            match self.peeked.take() {
+                Some(Some(tok)) => {
+                    buf.push(tok);
+                    want -= 1;
+                }
+                Some(None) => return 0,
+                None => {}
+            }
+        }
+        buf.reserve(want.min(self.pos.slice_after().len()));
+        for _ in 0..want {
+            match self.advance() {
+                Some(tok) => buf.push(tok),
+                None => break,
+            }
+        }
+        buf.len() - start
+    }
+
+    /// Skip the next `n` pretokens, returning how many were skipped.  Fewer
+    /// than `n` means the input is exhausted.  Unless hooks, filters, checks
+    /// or budgets need to see each pretoken, skipped pretokens are scanned
+    /// past without the rest of the work `next()` does.  `nth()` skips the
+    /// same way.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut pt = Pretokenizer::new("a b c d");
+    /// assert_eq!(pt.skip_tokens(2), 2);
+    /// assert_eq!(pt.next().unwrap().s, "c");
+    /// assert_eq!(pt.skip_tokens(2), 1);
+    /// ```
+    pub fn skip_tokens(&mut self, n: usize) -> usize {
+        let mut skipped = 0;
+        if n > 0 {
+            match self.peeked.take() {
+                Some(Some(_)) => skipped += 1,
+                Some(None) => return 0,
+                None => {}
+            }
+        }
+        if !self.needs_checks() {
+            let mut stats = self.stats;
+            while skipped < n {
+                match self.scan_with(|t, style| stats.record(t, style)) {
+                    Some(tok) => {
+                        if !tok.kind.is_trivia() {
+                            stats.record(&tok, CommentStyle::default());
+                        }
+                        stats.stats.tokens += 1;
+                        skipped += 1;
+                    }
+                    None => break,
+                }
+            }
+            self.stats = stats;
+            if !self.cfg.counts_lines() {
+                self.fix_lines();
+            }
+            return skipped;
+        }
+        while skipped < n && self.advance().is_some() {
+            skipped += 1;
+        }
+        skipped
+    }
+
+    /// Skip pretokens up to the next one for which `pred` is true, leaving
+    /// it to be returned by `next()`, and return how many were skipped.
+    /// Skipped pretokens are scanned past as by `skip_tokens()`, which
+    /// suits recovering from a parse error at a known synchronization
+    /// point.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut pt = Pretokenizer::new("x = ( ; y = 1 ;");
+    /// pt.by_ref().take(3).count();
+    /// assert_eq!(pt.skip_until(|t| t.s == ";"), 0);
+    /// pt.next();
+    /// assert_eq!(pt.skip_until(|t| t.s == ";"), 3);
+    /// assert_eq!(pt.next().unwrap().s, ";");
+    /// assert_eq!(pt.skip_until(|_| true), 0);
+    /// assert!(pt.next().is_none());
+    /// ```
+    pub fn skip_until<F>(&mut self, mut pred: F) -> usize
+    where F: FnMut(&Pretoken<'a>) -> bool {
+        let mut skipped = 0;
+        match self.peeked.take() {
+            Some(Some(tok)) if pred(&tok) => {
+                self.peeked = Some(Some(tok));
+                return 0;
+            }
+            Some(Some(_)) => skipped += 1,
+            Some(None) => {
+                self.peeked = Some(None);
+                return 0;
+            }
+            None => {}
+        }
+        if !self.needs_checks() {
+            let mut stats = self.stats;
+            while let Some(tok) = self.scan_with(|t, style| stats.record(t, style)) {
+                if !tok.kind.is_trivia() {
+                    stats.record(&tok, CommentStyle::default());
+                }
+                stats.stats.tokens += 1;
+                if pred(&tok) {
+                    self.peeked = Some(Some(tok));
+                    break;
+                }
+                skipped += 1;
+            }
+            self.stats = stats;
+            if !self.cfg.counts_lines() {
+                self.fix_lines();
+            }
+            return skipped;
+        }
+        while let Some(tok) = self.advance() {
+            if pred(&tok) {
+                self.peeked = Some(Some(tok));
+                break;
+            }
+            skipped += 1;
+        }
+        skipped
+    }
+
+    /// Skip the pretokens that start on the line at `byte_pos()`, as with
+    /// `skip_until()`, returning how many were skipped.  A skipped string
+    /// or other pretoken ending on a later line carries the skipping on to
+    /// the end of that line.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut pt = Pretokenizer::new("bad ( \"a\nb\" c\nnext line");
+    /// assert_eq!(pt.skip_past_line_end(), 4);
+    /// assert_eq!(pt.next().unwrap().s, "next");
+    /// ```
+    pub fn skip_past_line_end(&mut self) -> usize {
+        let s = self.source();
+        let line_end = |at: usize| s[at..].find('\n').map_or(s.len(), |i| at + i);
+        let mut eol = line_end(self.byte_pos());
+        self.skip_until(|t| {
+            let end = t.offset + t.s.len();
+            if t.offset <= eol && end > eol {
+                eol = line_end(end);
+            }
+            t.offset > eol
+        })
+    }
+
+    /// The next pretoken for which `pred` is true, skipping those before it
+    /// as with `skip_until()`.
+    /// ```
+    /// use pretok::{Kind, Pretokenizer};
+    /// let mut pt = Pretokenizer::new("print(\"a\", b, \"c\")");
+    /// assert_eq!(pt.next_matching(|t| t.kind == Kind::Quoted).unwrap().s, "\"a\"");
+    /// assert_eq!(pt.next_matching(|t| t.kind == Kind::Quoted).unwrap().s, "\"c\"");
+    /// assert!(pt.next_matching(|_| true).is_some());
+    /// assert!(pt.next_matching(|_| true).is_none());
+    /// ```
+    pub fn next_matching<F>(&mut self, pred: F) -> Option<Pretoken<'a>>
+    where F: FnMut(&Pretoken<'a>) -> bool {
+        self.skip_until(pred);
+        self.next()
+    }
+
+    /// Borrow as an iterator over the pretokens that start before byte
+    /// `offset`.  See [Bounded](struct.Bounded.html).
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut pt = Pretokenizer::new("a b c");
+    /// assert_eq!(pt.take_to(3).count(), 2);
+    /// assert_eq!(pt.next().unwrap().s, "c");
+    /// ```
+    pub fn take_to(&mut self, offset: usize) -> Bounded<'_, 'a> {
+        Bounded::new(self, offset)
+    }
+
+    /// Borrow as an iterator over the pretokens that start on the line at
+    /// `byte_pos()`.  See [Bounded](struct.Bounded.html).
+    pub fn take_line(&mut self) -> Bounded<'_, 'a> {
+        let rest = self.remainder();
+        let eol = self.byte_pos() + rest.find('\n').unwrap_or(rest.len());
+        Bounded::new(self, eol)
+    }
+
+    /// Convert into an iterator that can look up to `K` pretokens ahead.
+    /// See [Lookahead](struct.Lookahead.html).
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut la = Pretokenizer::new("a :: b").lookahead::<2>();
+    /// assert_eq!(la.peek_n(1).unwrap().s, "::");
+    /// ```
+    pub fn lookahead<const K: usize>(self) -> Lookahead<'a, K> {
+        Lookahead::new(self)
+    }
+
+    /// The lines collected so far by
+    /// [Config::pragma()](struct.Config.html#method.pragma), in input order.
+    /// Each line is a pretoken of kind [Kind::Pragma](enum.Kind.html#variant.Pragma)
+    /// that runs from the prefix to the end of the line, less trailing
+    /// whitespace.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new().pragma("#pragma").pragma("//!pretok");
+    /// let mut pt = Pretokenizer::with_config("#pragma once\n//!pretok x=1\ny", cfg);
+    /// let toks: Vec<_> = pt.by_ref().map(|t| t.s).collect();
+    /// assert_eq!(toks, vec!["#pragma", "once", "y"]);
+    /// let pragmas: Vec<_> = pt.pragmas().iter().map(|p| (p.s, p.line)).collect();
+    /// assert_eq!(pragmas, vec![("#pragma once", 1), ("//!pretok x=1", 2)]);
+    /// ```
+    pub fn pragmas(&self) -> &[Pretoken<'a>] {
+        &self.pragmas
+    }
+
+    /// Record the line at `curs` if it starts with a pragma prefix.
+    fn collect_pragma(&mut self, curs: StrCursor<'a>) {
+        let rest = curs.slice_after();
+        if self.cfg.pragmas.iter().any(|p| rest.starts_with(p.as_str())) {
+            let len = rest.find('\n').unwrap_or(rest.len());
+            self.pragmas.push(Pretoken{
+                s: rest[..len].trim_end(),
+                line: self.line,
+                offset: curs.byte_pos(),
+                kind: Kind::Pragma,
+            });
+        }
+    }
+
+    /// Read the logical character at `curs`, returning the character and the
+    /// cursor just past it.  With trigraphs enabled, a trigraph reads as the
+    /// single character it represents.
+    fn read(&self, curs: StrCursor<'a>) -> Option<(char, StrCursor<'a>)> {
+        let (c, next) = curs.next_cp()?;
+        if c == '?' && self.cfg.trigraphs {
+            let rest = next.slice_after().as_bytes();
+            if rest.len() >= 2 && rest[0] == b'?' {
+                if let Some(t) = config::trigraph(rest[1] as char) {
+                    let mut end = next;
+                    end.seek_next_cp();
+                    end.seek_next_cp();
+                    return Some((t, end));
+                }
+            }
+        }
+        Some((c, next))
+    }
+
+    /// Move `curs` past the bytes that can't end or change the `skip` state,
+    /// to the next byte that might, or to the end of input.  Only ASCII
+    /// bytes are searched for, so the result is always a character boundary.
+    fn skip_inert(&self, skip: Skip, curs: StrCursor<'a>) -> StrCursor<'a> {
+        // The characters looked for are those of the default classifier.
+        if self.classifier.is_some() {
+            return curs;
+        }
+        let rest = curs.slice_after().as_bytes();
+        let trigraphs = self.cfg.trigraphs;
+        let continuation = self.cfg.line_continuation;
+        let lazy = !self.cfg.counts_lines();
+        let zero_width = self.cfg.zero_width == ZeroWidth::Strip;
+        let nul = self.cfg.nul == Nul::Whitespace;
+        let seps = self.cfg.separators.as_bytes();
+        #[cfg(feature = "simd")]
+        let nested_slash = if self.cfg.nested_comments { b'/' } else { b'*' };
+        // Without line tracking, newlines only matter where they end something.
+        #[cfg(feature = "simd")]
+        let newline = if lazy { b'*' } else { b'\n' };
+        let found = match skip {
+            #[cfg(feature = "simd")]
+            Skip::Blanks => Some(simd::blank_run(rest)),
+            #[cfg(feature = "simd")]
+            Skip::BlockComment => simd::find3(b'*', nested_slash, newline, rest),
+            Skip::LineComment => memchr::memchr(b'\n', rest),
+            #[cfg(not(feature = "simd"))]
+            Skip::BlockComment => match (self.cfg.nested_comments, lazy) {
+                (true, false) => memchr::memchr3(b'*', b'/', b'\n', rest),
+                (true, true) => memchr::memchr2(b'*', b'/', rest),
+                (false, false) => memchr::memchr2(b'*', b'\n', rest),
+                (false, true) => memchr::memchr(b'*', rest),
+            },
+            // A trigraph may spell a backslash.
+            Skip::Quoted if trigraphs => rest.iter()
+                .position(|&b| matches!(b, b'"' | b'\\' | b'\n' | b'?')),
+            Skip::Quoted if lazy => memchr::memchr2(b'"', b'\\', rest),
+            Skip::Quoted => memchr::memchr3(b'"', b'\\', b'\n', rest),
+            // Zero-width characters start with 0xE2 or 0xEF.
+            Skip::Word => rest.iter().position(|&b| {
+                matches!(b, b' ' | b'\t' | b'\n' | b'"' | b'/') || (trigraphs && b == b'?')
+                    || (continuation && b == b'\\')
+                    || (zero_width && matches!(b, 0xE2 | 0xEF)) || (nul && b == 0)
+                    // The first byte of a separator, never a continuation byte.
+                    || (!(0x80..0xC0).contains(&b) && seps.contains(&b))
+            }),
+        };
+        match found {
+            Some(0) => curs,
+            Some(n) => cursor_at(curs.slice_all(), curs.byte_pos() + n),
+            None => StrCursor::new_at_end(curs.slice_all()),
+        }
+    }
+
+    /// Scan a directive line starting at `curs`, returning the end of the
+    /// directive.  `self.line` is left at the line of the returned end.
+    fn scan_directive(&mut self, mut curs: StrCursor<'a>) -> StrCursor<'a> {
+        // End of the last non-whitespace character and its line.
+        let mut end = curs;
+        let mut end_line = self.line;
+        // Whether the last character read was a splice, or a backslash that
+        // would be one if a line break followed.
+        let mut splice = false;
+        while let Some((c, next)) = self.read(curs) {
+            splice = false;
+            match c {
+                '\n' => break,
+                ' ' | '\t' => curs = next,
+                '\\' => {
+                    splice = true;
+                    if let Some(('\n', after)) = self.read(next) {
+                        // Spliced line.  The directive continues.
+                        self.line += 1;
+                        curs = after;
+                    } else {
+                        curs = next;
+                        end = curs;
+                        end_line = self.line;
+                    }
+                }
+                '"' | '\'' => {
+                    // Skip a quoted string or character literal so that
+                    // comment starts inside it are ignored.
+                    curs = next;
+                    while let Some((q, after)) = self.read(curs) {
+                        if q == '\n' {
+                            break;
+                        }
+                        curs = after;
+                        if q == c {
+                            break;
+                        }
+                        if q == '\\' {
+                            if let Some((e, after)) = self.read(curs) {
+                                if e == '\n' {
+                                    self.line += 1;
+                                }
+                                curs = after;
+                            }
+                        }
+                    }
+                    end = curs;
+                    end_line = self.line;
+                }
+                '<' if self.header_name_next(curs) => {
+                    // Keep a header name together, even if it holds what
+                    // would otherwise start a comment.
+                    curs = next;
+                    while let Some((h, after)) = self.read(curs) {
+                        if h == '\n' {
+                            break;
+                        }
+                        curs = after;
+                        if h == '>' {
+                            break;
+                        }
+                    }
+                    end = curs;
+                    end_line = self.line;
+                }
+                '/' => {
+                    match self.read(next) {
+                        Some(('/', _)) => break,
+                        Some(('*', after)) => {
+                            // Block comments may continue the directive
+                            // onto later lines.
+                            curs = after;
+                            let mut star = false;
+                            while let Some((b, after)) = self.read(curs) {
+                                curs = after;
+                                if b == '/' && star {
+                                    break;
+                                }
+                                if b == '\n' {
+                                    self.line += 1;
+                                }
+                                star = b == '*';
+                            }
+                        }
+                        _ => {
+                            curs = next;
+                            end = curs;
+                            end_line = self.line;
+                        }
+                    }
+                }
+                _ => {
+                    curs = next;
+                    end = curs;
+                    end_line = self.line;
+                }
+            }
+        }
+        if splice && self.read(curs).is_none() {
+            // A splice at the end of input continues the directive into
+            // whatever follows.  Keep it with the directive rather than
+            // leave it as a pretoken of its own.
+            let text = curs.slice_before();
+            let bare = text.strip_suffix('\n').unwrap_or(text);
+            self.open = ScanState::Directive;
+            end = cursor_at(curs.slice_all(), bare.len());
+            end_line = self.line - (text.len() - bare.len());
+        }
+        self.line = end_line;
+        end
+    }
+
+    /// True if `curs`, in a directive, is where the header name of an
+    /// `#include`, `#include_next` or `#import` directive starts.
+    fn header_name_next(&self, curs: StrCursor<'a>) -> bool {
+        let text = &curs.slice_all()[self.pos.byte_pos()..curs.byte_pos()];
+        let text = match text.strip_prefix('#') {
+            Some(text) => text.trim_start_matches([' ', '\t']),
+            None => return false,
+        };
+        ["include_next", "include", "import"].iter()
+            .find_map(|name| text.strip_prefix(name))
+            .is_some_and(|rest| rest.chars().all(|c| c == ' ' || c == '\t'))
+    }
+
+    /// True if an odd number of backslashes in the current pretoken come
+    /// right before `curs`.
+    fn escaped(&self, curs: StrCursor<'a>) -> bool {
+        let word = &curs.slice_all()[self.pos.byte_pos()..curs.byte_pos()];
+        let slashes = word.len() - word.trim_end_matches('\\').len();
+        slashes % 2 == 1
+    }
+
+    /// If `c` at `curs` is a `Config::column_comment()` marker in its column,
+    /// the end of its line.
+    fn column_comment(&self, curs: StrCursor<'a>, c: char) -> Option<StrCursor<'a>> {
+        let s = curs.slice_all();
+        let at = curs.byte_pos();
+        let col = || {
+            let start = s[..at].rfind('\n').map_or(0, |i| i + 1);
+            s[start..at].chars().count() + 1
+        };
+        self.cfg.column_comments.iter()
+            .find(|&&(marker, column)| marker == c && match column {
+                Some(column) => col() == column,
+                None => self.bol,
+            })?;
+        Some(line_end(s, at))
+    }
+
+    /// If a `Config::line_comment()` marker starts at `curs`, the end of its
+    /// line.
+    fn line_comment(&self, curs: StrCursor<'a>) -> Option<StrCursor<'a>> {
+        let rest = curs.slice_after();
+        self.cfg.line_comments.iter().find(|m| rest.starts_with(m.as_str()))?;
+        Some(line_end(curs.slice_all(), curs.byte_pos()))
+    }
+
+    /// If a `Config::keyword_comment()` keyword starts a statement at
+    /// `curs`, the end of its line.
+    fn keyword_comment(&self, curs: StrCursor<'a>) -> Option<StrCursor<'a>> {
+        let (s, at) = (curs.slice_all(), curs.byte_pos());
+        let rest = curs.slice_after();
+        self.cfg.keyword_comments.iter().find(|kw| {
+            let ends_word = |c: char| !c.is_alphanumeric() && c != '_';
+            rest.get(..kw.len()).is_some_and(|head| head.eq_ignore_ascii_case(kw))
+                && rest[kw.len()..].chars().next().is_none_or(ends_word)
+        })?;
+        let before = s[s[..at].rfind('\n').map_or(0, |i| i + 1)..at].trim_end();
+        match before.is_empty() || before.ends_with(':')
+            || before.bytes().all(|b| b.is_ascii_digit()) {
+            true => Some(line_end(s, at)),
+            false => None,
+        }
+    }
+
+    /// If `c` at `curs` opens a `Config::quote()` string, a
+    /// `Config::literal_quote()` string or a `Config::triple_quotes()`
+    /// string, the end of the string, counting lines on the way.
+    fn scan_string(&mut self, curs: StrCursor<'a>, c: char) -> Option<StrCursor<'a>> {
+        let literal = self.cfg.literal_quotes.contains(c);
+        let single = literal || self.cfg.quotes.contains(c);
+        if !single && self.class(c) != dfa::Class::Quote {
+            return None;
+        }
+        let (s, start) = (curs.slice_all(), curs.byte_pos());
+        let q = c.len_utf8();
+        let delim = [c; 3].iter().collect::<String>();
+        let triple = self.cfg.triple_quotes && s[start..].starts_with(delim.as_str());
+        if !single && !triple {
+            return None;
+        }
+        let mut at = start + if triple { delim.len() } else { q };
+        let end = loop {
+            let Some(i) = s[at..].find(|d| d == c || (d == '\\' && !literal)) else {
+                break None;
+            };
+            let here = at + i;
+            if s[here..].starts_with('\\') {
+                at = here + 1 + s[here + 1..].chars().next().map_or(0, char::len_utf8);
+            } else if !triple {
+                break Some(here + q);
+            } else if s[here..].starts_with(delim.as_str()) {
+                // Quotes just before the closing three are in the string.
+                let extra = s[here + delim.len()..].chars().take(2).take_while(|&d| d == c);
+                break Some(here + delim.len() + extra.count() * q);
+            } else {
+                at = here + q;
+            }
+        };
+        let end = end.unwrap_or_else(|| {
+            self.diagnostics.push(PretokError::UnterminatedString{
+                line: self.line, span: start..s.len()});
+            self.open = ScanState::Quoted{ escape: false };
+            s.len()
+        });
+        if self.cfg.counts_lines() {
+            self.line += memchr::memchr_iter(b'\n', &s.as_bytes()[start..end]).count();
+        }
+        Some(cursor_at(s, end))
+    }
+
+    /// If a registered skip region starts at `curs`, skip to the end of the
+    /// line holding its end marker, counting lines on the way.
+    fn skip_region(&mut self, curs: StrCursor<'a>) -> Option<StrCursor<'a>> {
+        let rest = curs.slice_after();
+        let region = self.cfg.skip_regions.iter()
+            .position(|(start, _)| rest.starts_with(start.as_str()))?;
+        self.skip_region_from(curs, region, 0, false)
+    }
+
+    /// Skip to the end of the line holding the end marker of skip region
+    /// `region`, from `curs` inside it at nesting `depth`.  The line at
+    /// `curs` is checked for markers only if `check_first` is true.
+    fn skip_region_from(&mut self, curs: StrCursor<'a>, region: usize, mut depth: usize,
+                        check_first: bool) -> Option<StrCursor<'a>> {
+        let rest = curs.slice_after();
+        let (start, end) = self.cfg.skip_regions.get(region)?;
+
+        let first_line = self.line;
+        let mut pos = 0;
+        let stop = loop {
+            let eol = rest[pos..].find('\n').map(|i| pos + i);
+            // Nothing after a final line break is a line.
+            if (pos > 0 || check_first) && pos < rest.len() {
+                let text = rest[pos..eol.unwrap_or(rest.len())]
+                    .trim_start_matches([' ', '\t']);
+                if text.starts_with(end.as_str()) {
+                    if depth == 0 {
+                        break eol.unwrap_or(rest.len());
+                    }
+                    depth -= 1;
+                } else if text.starts_with(start.as_str()) {
+                    depth += 1;
+                }
+            }
+            match eol {
+                Some(eol) => {
+                    self.line += 1;
+                    pos = eol + 1;
+                }
+                None => {
+                    // Unterminated region at end of input
+                    let start = curs.byte_pos();
+                    self.diagnostics.push(PretokError::UnterminatedRegion{
+                        line: first_line,
+                        span: start..start + rest.len()});
+                    self.open = ScanState::SkipRegion{ region, depth };
+                    break rest.len();
+                }
+            }
+        };
+        Some(cursor_at(curs.slice_all(), curs.byte_pos() + stop))
+    }
+
+    /// True if the line comment at `slash` is the off marker of
+    /// `Config::tool_directives()`.
+    fn is_off_marker(&self, slash: StrCursor<'a>) -> bool {
+        let off = match self.cfg.tool_directives {
+            Some((ref off, _)) => off,
+            None => return false,
+        };
+        let rest = slash.slice_after();
+        match rest.find('\n') {
+            Some(eol) => rest[2..eol].trim() == off,
+            None => false,
+        }
+    }
+
+    /// Return the text following the off marker at byte offset `marker` on
+    /// line `marker_line`, up to the line holding the on marker, as a single
+    /// opaque pretoken.
+    fn opaque_region(&mut self, marker: usize, marker_line: usize)
+        -> Option<Pretoken<'a>> {
+        let (_, on) = self.cfg.tool_directives.as_ref()?;
+        let rest = self.pos.slice_after();
+
+        // The region ends before the line break preceding the on marker.
+        let mut pos = 0;
+        let end = loop {
+            let line_end = rest[pos..].find('\n').map(|i| pos + i);
+            let text = rest[pos..line_end.unwrap_or(rest.len())].trim_start();
+            if text.starts_with("//") && text[2..].trim() == on {
+                break pos.saturating_sub(1);
+            }
+            match line_end {
+                Some(line_end) => pos = line_end + 1,
+                None => {
+                    // Unterminated region at end of input
+                    let all = self.pos.slice_all();
+                    self.diagnostics.push(PretokError::UnterminatedRegion{
+                        line: marker_line,
+                        span: marker..all.len()});
+                    self.open = ScanState::Opaque;
+                    break rest.len();
+                }
+            }
+        };
+        if end == 0 {
+            return None;
+        }
+
+        self.line += rest[..end].matches('\n').count();
+        self.bol = false;
+        let end = cursor_at(self.pos.slice_all(), self.pos.byte_pos() + end);
+        self.make_pretok(end, Kind::Opaque)
+    }
+
+    /// Handle a byte order mark at the very start of the input according
+    /// to the BOM policy.  Returns a pretoken only for `Bom::Token`.
+    fn bom(&mut self) -> Option<Pretoken<'a>> {
+        if self.cfg.bom == Bom::Text || !self.pos.slice_after().starts_with('\u{FEFF}') {
+            return None;
+        }
+        let end = cursor_at(self.pos.slice_all(), '\u{FEFF}'.len_utf8());
+        match self.cfg.bom {
+            Bom::Token => self.make_pretok(end, Kind::Bom),
+            _ => self.make_comment(end, CommentStyle::Region),
+        }
+    }
+
+    /// The length of a byte order mark skipped or returned at the start of
+    /// the input.
+    fn bom_len(&self) -> usize {
+        match self.cfg.bom != Bom::Text && self.pos.slice_all().starts_with('\u{FEFF}') {
+            true => '\u{FEFF}'.len_utf8(),
+            false => 0,
+        }
+    }
+
+    /// Handle a `#!` line at the very start of the input, after any byte
+    /// order mark, according to the shebang policy.  Returns a pretoken
+    /// only for `Shebang::Token`.
+    fn shebang(&mut self) -> Option<Pretoken<'a>> {
+        let rest = self.pos.slice_after();
+        if self.cfg.shebang == Shebang::Text
+            || !rest.starts_with("#!") || rest.starts_with("#![") {
+            return None;
+        }
+        let len = rest.find('\n').unwrap_or(rest.len());
+        let end = cursor_at(self.pos.slice_all(), self.pos.byte_pos() + len);
+        if self.cfg.shebang == Shebang::Skip {
+            return self.make_comment(end, CommentStyle::Region);
+        }
+        self.bol = false;
+        self.make_pretok(end, Kind::Shebang)
+    }
+
+    /// True if words may be split into several pretokens.
+    fn splits_words(&self) -> bool {
+        #[cfg(feature = "xid")]
+        if self.cfg.xid_words {
+            return true;
+        }
+        self.word_chars.is_some() || self.classifier.is_some() || !self.cfg.punctuation.is_empty()
+            || !self.cfg.line_comments.is_empty() || self.cfg.url_tokens
+            || self.cfg.invalid_tokens
+    }
+
+    /// The end of a line continued by the backslash `c` just before `next`,
+    /// if it is one.
+    fn continuation(&self, c: char, next: StrCursor<'a>) -> Option<StrCursor<'a>> {
+        if c != '\\' || !self.cfg.line_continuation {
+            return None;
+        }
+        let rest = next.slice_after();
+        let len = match rest.starts_with("\r\n") {
+            true => 2,
+            false => rest.starts_with('\n') as usize,
+        };
+        Some(cursor_at(next.slice_all(), next.byte_pos() + len)).filter(|_| len > 0)
+    }
+
+    /// The byte offset in `word` where its first pretoken ends, if before
+    /// its end.  A number at the start is kept whole unless
+    /// `Config::split_numbers()`.
+    fn word_cut(&self, mut word: &str) -> Option<usize> {
+        if self.cfg.invalid_tokens {
+            if let Some(at) = word.find(checks::is_invalid) {
+                // Invalid characters are pretokens of their own.
+                let end = match at {
+                    0 => run_end(word, checks::is_invalid).unwrap_or(word.len()),
+                    _ => self.word_cut(&word[..at]).unwrap_or(at),
+                };
+                return Some(end).filter(|&end| end < word.len());
+            }
+        }
+        if self.cfg.url_tokens {
+            if let Some((at, len)) = url_at(word) {
+                // The URL is a pretoken of its own, however the text before
+                // it splits.
+                return match at {
+                    0 => Some(len).filter(|&len| len < word.len()),
+                    _ => self.word_cut(&word[..at]).or(Some(at)),
+                };
+            }
+        }
+        if !self.cfg.split_numbers {
+            if let Some(len) = number_len(word) {
+                return Some(len).filter(|&len| len < word.len());
+            }
+        }
+        let len = word.len();
+        #[cfg(feature = "xid")]
+        if self.cfg.xid_words {
+            word = xid::word_end(word).map_or(word, |at| &word[..at]);
+        }
+        if let Some(pred) = &self.word_chars {
+            word = run_end(word, &mut *pred.0.borrow_mut()).map_or(word, |at| &word[..at]);
+        }
+        if let Some(classifier) = &self.classifier {
+            let classifier = classifier.0.borrow();
+            word = run_end(word, |c| classifier.is_word_char(c)).map_or(word, |at| &word[..at]);
+        }
+        if !self.cfg.line_comments.is_empty() {
+            word = self.cfg.line_comment_at(word).map_or(word, |at| &word[..at]);
+        }
+        if !self.cfg.punctuation.is_empty() {
+            word = self.cfg.punct_end(word).map_or(word, |at| &word[..at]);
+        }
+        Some(word.len()).filter(|&at| at < len)
+    }
+
+    fn make_pretok(&mut self, end: StrCursor<'a>, kind: Kind) -> Option<Pretoken<'a>> {
+        // If the current position hasn't moved, then return None.
+        // This check simplifies corner cases like end-of-input.
+        if end == self.pos {
+            return None;
+        }
+
+        let end = match kind == Kind::Word && self.splits_words() {
+            true => match self.word_cut(self.pos.slice_between(end).unwrap_or_default()) {
+                Some(cut) => {
+                    // Scan the rest of the word again as a new pretoken.
+                    self.resume = State::StartTok;
+                    cursor_at(end.slice_all(), self.pos.byte_pos() + cut)
+                }
+                None => end,
+            },
+            false => end,
+        };
+        #[cfg(feature = "graphemes")]
+        let end = match self.cfg.graphemes {
+            true => cursor_at(end.slice_all(), graphemes::cluster_end(end.slice_all(),
+                                                                     end.byte_pos())),
+            false => end,
+        };
+
+        // Update the state of the Pretokenizer to the end of this pretoken.
+        let start = self.pos;
+        self.pos = end;
+        let line = if self.cfg.counts_lines() { self.line } else { 0 };
+        let mut tok = Pretoken::new(start, end, line, start.byte_pos());
+        tok.kind = match kind {
+            Kind::Word if self.cfg.invalid_tokens && tok.s.starts_with(checks::is_invalid) => {
+                Kind::Invalid
+            }
+            _ => kind,
+        };
+        trace!(trace, kind = ?tok.kind, offset = tok.offset, line = tok.line, s = tok.s,
+               "region");
+        Some(tok)
+    }
+
+    /// Make a comment pretoken ending at `end`, recognized as `style`.
+    fn make_comment(&mut self, end: StrCursor<'a>, style: CommentStyle) -> Option<Pretoken<'a>> {
+        self.comment_style = style;
+        self.make_pretok(end, Kind::Comment)
+    }
+}
+
+impl<'a> Pretokenizer<'a> {
+    /// Run the state machine to the next pretoken, skipping whitespace and
+    /// comments.
+    fn scan(&mut self) -> Option<Pretoken<'a>> {
+        self.scan_with(|_, _| {})
+    }
+
+    /// Like `scan()`, passing the whitespace and comments skipped to `trivia`.
+    fn scan_with<F>(&mut self, mut trivia: F) -> Option<Pretoken<'a>>
+    where F: FnMut(&Pretoken<'a>, CommentStyle) {
+        loop {
+            let tok = self.scan_event()?;
+            if !tok.kind.is_trivia() {
+                return Some(tok);
+            }
+            trivia(&tok, self.comment_style);
+            if self.cfg.lossless {
+                return Some(tok);
+            }
+        }
+    }
+
+    /// Run the state machine to the next region of the input: a pretoken,
+    /// or a run of whitespace, a newline or a comment.
+    fn scan_event(&mut self) -> Option<Pretoken<'a>> {
+        if self.pos.byte_pos() == 0 {
+            if let Some(tok) = self.bom() {
+                return Some(tok);
+            }
+        }
+        if self.pos.byte_pos() == self.bom_len() {
+            if let Some(tok) = self.shebang() {
+                return Some(tok);
+            }
+        }
+
+        if let Some((marker, marker_line)) = self.off_marker {
+            if !self.pos.slice_after().starts_with('\n') {
+                self.off_marker = None;
+                if let Some(tok) = self.opaque_region(marker, marker_line) {
+                    return Some(tok);
+                }
+            }
+        }
+
+        // Start by skipping any whitespace, unless the last scan already
+        // knows what comes next.
+        let mut state = std::mem::replace(&mut self.resume, State::WS);
+
+        // Get a local cursor starting at our current position.
+        let mut curs = self.pos;
+
+        // Where the current '/' started while we decide if it is a comment.
+        let mut slash = curs;
+        let mut slash_line = self.line;
+        if state == State::MaybeComment {
+            curs.seek_next_cp();
+        }
+
+        // The line on which the current token started.
+        let mut start_line = self.line;
+
+        // Block comment nesting depth and whether it exceeded the limit.
+        let mut depth = 0;
+        let mut too_deep = false;
+
+        // Whether the current line comment is a tool directive off marker.
+        let mut off_marker = false;
+
+        // Pick up inside a construct left open by the input before this.
+        match std::mem::take(&mut self.start) {
+            ScanState::Normal | ScanState::Opaque => {}
+            ScanState::BlockComment{ depth: open } => {
+                state = State::BlockComment;
+                depth = open.max(1);
+                self.bol = false;
+            }
+            ScanState::Quoted{ escape } => {
+                state = if escape { State::EscapeChar } else { State::QuotedTok };
+                self.bol = false;
+            }
+            ScanState::Directive => {
+                let end = self.scan_directive(curs);
+                if end != curs {
+                    self.bol = false;
+                    return self.make_pretok(end, Kind::Directive);
+                }
+            }
+            ScanState::SkipRegion{ region, depth } => {
+                match self.skip_region_from(curs, region, depth, true) {
+                    Some(end) if end != curs => return self.make_comment(end, CommentStyle::Region),
+                    _ => {}
+                }
+            }
+        }
+
+        if self.cfg.recipe_tabs && state == State::WS && self.bol
+            && curs.slice_after().starts_with('\t')
+            && (curs.byte_pos() == self.bom_len() || curs.slice_before().ends_with('\n')) {
+            // A tab starting a recipe line.
+            self.bol = false;
+            return self.make_pretok(cursor_at(curs.slice_all(), curs.byte_pos() + 1), Kind::Word);
+        }
+
+        let mut traced = State::WS;
+
+        loop {
+            if state != traced {
+                #[cfg(feature = "tracing")]
+                tracing::trace!(from = ?traced, to = ?state, offset = curs.byte_pos(), "state");
+                if self.cfg.trace {
+                    self.transitions.push(trace::Transition{
+                        from: traced.name(), to: state.name(), offset: curs.byte_pos(),
+                        line: self.line});
+                }
+                traced = state;
+            }
+
+            // Skip ahead over runs of characters that don't matter here.
+            curs = match state {
+                #[cfg(feature = "simd")]
+                State::WS => self.skip_inert(Skip::Blanks, curs),
+                State::LineComment => self.skip_inert(Skip::LineComment, curs),
+                State::BlockComment => self.skip_inert(Skip::BlockComment, curs),
+                State::QuotedTok => self.skip_inert(Skip::Quoted, curs),
+                State::NormalTok => self.skip_inert(Skip::Word, curs),
+                _ => curs,
+            };
+
+            // Note that we're dealing with unicode code points rather
+            // than grapheme clusters
+            let (c, next) = match self.read(curs) {
+                Some(r) => r,
+                None => {
+                    // End of input!
+                    match state {
+                        State::NormalTok => {
+                            return self.make_pretok(curs, Kind::Word);
+                        }
+                        State::MaybeComment => {
+                            // A lonely '/' at the end of input.
+                            self.pos = slash;
+                            return self.make_pretok(curs, Kind::Word);
+                        }
+                        State::WS => {
+                            return self.make_pretok(curs, Kind::Whitespace);
+                        }
+                        State::LineComment => {
+                            // An off marker on the last line leaves the input
+                            // just before an opaque region.
+                            let text = &slash.slice_after()[2..];
+                            if self.cfg.tool_directives.as_ref()
+                                .is_some_and(|(off, _)| text.trim() == off) {
+                                self.open = ScanState::Opaque;
+                            }
+                            return self.make_comment(curs, CommentStyle::Line);
+                        }
+                        State::BlockComment | State::MaybeBlockCommentDone
+                            | State::MaybeNestedComment => {
+                            // Unterminated block comment at end of input
+                            self.diagnostics.push(PretokError::UnterminatedBlockComment{
+                                line: slash_line,
+                                span: slash.byte_pos()..curs.byte_pos()});
+                            self.open = ScanState::BlockComment{ depth };
+                            return self.make_comment(curs, CommentStyle::Block);
+                        }
+                        State::QuotedTok | State::EscapeChar => {
+                            // Unterminated quoted string at end of input
+                            self.diagnostics.push(PretokError::UnterminatedString{
+                                line: start_line,
+                                span: self.pos.byte_pos()..curs.byte_pos()});
+                            self.open = ScanState::Quoted{ escape: state == State::EscapeChar };
+                            return self.make_pretok(curs, Kind::Quoted);
+                        }
+
+                        _ => {}
+                    }
+
+                    self.pos = curs; // sync cursor position
+                    return None;
+                }
+            };
+
+            let class = self.class(c);
+            let step = self.dfa.transition(state, class);
+            match step.act {
+                Act::Consume => {
+                    state = step.to;
+                    curs = next;
+                    continue;
+                }
+                Act::ConsumeLine => {
+                    self.line += 1;
+                    state = step.to;
+                    curs = next;
+                    continue;
+                }
+                Act::Special => {}
+            }
+
+            match state {
+                // Any character but a blank
+                State::WS => {
+                    if let Some(end) = self.continuation(c, next) {
+                        // A continued line.  The whitespace goes on.
+                        self.line += 1;
+                        curs = end;
+                        continue;
+                    }
+                    if curs != self.pos && self.skip_blanks {
+                        self.pos = curs;
+                    }
+                    if curs != self.pos {
+                        // The end of a run of whitespace.  Pick up at `c`
+                        // next time, unless it needs the checks below.
+                        self.resume = match c {
+                            _ if class == dfa::Class::Slash => State::MaybeComment,
+                            '\n' => State::WS,
+                            _ if self.bol && !self.cfg.pragmas.is_empty() => State::WS,
+                            _ => State::StartTok,
+                        };
+                        return self.make_pretok(curs, Kind::Whitespace);
+                    }
+                    if self.bol && !self.cfg.pragmas.is_empty() && c != '\n' {
+                        self.collect_pragma(curs);
+                    }
+                    match c {
+                        '\n' => {
+                            if self.terminable {
+                                self.terminable = false;
+                                if let Some(tok) = self.terminate(curs, next) {
+                                    return Some(tok);
+                                }
+                            }
+                            // An off marker's region and a recipe tab start
+                            // after the line break, at the top of the next
+                            // scan.
+                            let recipe = self.cfg.recipe_tabs
+                                && next.slice_after().starts_with('\t');
+                            if self.skip_blanks && self.off_marker.is_none() && !recipe {
+                                self.pos = next;
+                                self.line += 1;
+                                self.bol = true;
+                                curs = next;
+                                continue;
+                            }
+                            let tok = self.make_pretok(next, Kind::Newline);
+                            self.line += 1;
+                            self.bol = true;
+                            return tok;
+                        }
+                        _ if class == dfa::Class::Slash => {
+                            state = State::MaybeComment;
+                            slash = curs;
+                            slash_line = self.line;
+                            curs = next;
+                        }
+                        _ => state = State::StartTok,
+                    }
+                }
+
+                // We enter the this state after peeking a '/' character.
+                // We're looking for another '/' or '*'
+                State::MaybeComment => {
+                    match c {
+                        _ if class == dfa::Class::Slash && !self.cfg.block_comments_only => {
+                            // We're in a line comment.
+                            off_marker = self.is_off_marker(slash);
+                            state = State::LineComment;
+                            curs = next;
+                        }
+                        '*' => {
+                            // We're in a block comment.
+                            state = State::BlockComment;
+                            depth = 1;
+                            too_deep = false;
+                            curs = next;
+                        }
+                        _ => {
+                            // Not a comment.  The token starts at the '/'.
+                            self.pos = slash;
+                            self.bol = false;
+                            state = State::NormalTok;
+                        }
+                    }
+                }
+
+                // A newline
+                State::LineComment => {
+                    // The newline is returned separately.
+                    if off_marker {
+                        self.off_marker = Some((slash.byte_pos(), slash_line));
+                    }
+                    return self.make_comment(curs, CommentStyle::Line);
+                }
+
+                // A '*' after a '/' inside a nestable block comment.
+                State::MaybeNestedComment => {
+                    match self.cfg.max_comment_depth {
+                        Some(max) if depth >= max => {
+                            // Don't nest any deeper.
+                            if !too_deep {
+                                too_deep = true;
+                                let at = curs.byte_pos() - 1;
+                                self.diagnostics.push(PretokError::CommentTooDeep{
+                                    line: self.line, span: at..at + 2});
+                            }
+                        }
+                        _ => depth += 1,
+                    }
+                    state = State::BlockComment;
+                    curs = next;
+                }
+
+                // A '/' after a '*'.  Done with the block, or with a nested
+                // block.
+                State::MaybeBlockCommentDone => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return self.make_comment(next, CommentStyle::Block);
+                    }
+                    state = State::BlockComment;
+                    curs = next;
+                }
+
+                State::StartTok => {
+                    // sync the real iterator with our temporary
+                    // If this is a quoted string, the returned token
+                    // will include the quote character.
+                    self.pos = curs;
+                    start_line = self.line;
+
+                    if self.bol && !self.cfg.skip_regions.is_empty() {
+                        if let Some(end) = self.skip_region(curs) {
+                            return self.make_comment(end, CommentStyle::Region);
+                        }
+                    }
+
+                    if !self.cfg.column_comments.is_empty() {
+                        if let Some(end) = self.column_comment(curs, c) {
+                            return self.make_comment(end, CommentStyle::Line);
+                        }
+                    }
+                    if !self.cfg.line_comments.is_empty() {
+                        if let Some(end) = self.line_comment(curs) {
+                            return self.make_comment(end, CommentStyle::Line);
+                        }
+                    }
+                    if !self.cfg.keyword_comments.is_empty() {
+                        if let Some(end) = self.keyword_comment(curs) {
+                            return self.make_comment(end, CommentStyle::Line);
+                        }
+                    }
+
+                    if c == '#' && self.bol && self.cfg.directives {
+                        self.bol = false;
+                        let end = self.scan_directive(curs);
+                        return self.make_pretok(end, Kind::Directive);
+                    }
+                    self.bol = false;
+
+                    if self.cfg.triple_quotes || !self.cfg.literal_quotes.is_empty()
+                        || !self.cfg.quotes.is_empty() {
+                        if let Some(end) = self.scan_string(curs, c) {
+                            return self.make_pretok(end, Kind::Quoted);
+                        }
+                    }
+                    if class == dfa::Class::Quote {
+                        state = State::QuotedTok;
+                    } else {
+                        state = State::NormalTok;
+                    }
+                    curs = next;
+                }
+
+                // A blank, newline, quote or '/'
+                State::NormalTok => {
+                    match class {
+                        dfa::Class::Quote => {
+                            // We found quote without whitespace separation.
+                            // Return whatever we've captured before the quote as the token.
+                            // We'll process the quote on the next next()
+                            self.resume = State::StartTok;
+                            return self.make_pretok(curs, Kind::Word);
+                        }
+                        dfa::Class::Slash => {
+                            // We maybe found a comment without whitespace separation.
+                            // Peek ahead one more character to know for sure.
+                            match self.read(next) {
+                                None => {
+                                    // There's nothing past the /.  Return current token
+                                    // including the / we're peeking at.
+                                    return self.make_pretok(next, Kind::Word);
+                                }
+                                Some((d, _)) if d == '*' || (self.class(d) == dfa::Class::Slash
+                                                             && !self.cfg.block_comments_only) => {
+                                    // Found a comment, so return the preceding token
+                                    self.resume = State::MaybeComment;
+                                    return self.make_pretok(curs, Kind::Word);
+                                }
+                                Some(_) => {
+                                    // False alarm, It was just a lonely / so keep going.
+                                    curs = next;
+                                }
+                            }
+                        }
+                        _ if c == ' ' && self.cfg.escaped_spaces && self.escaped(curs) => {
+                            curs = next;
+                        }
+                        dfa::Class::Backslash => match self.continuation(c, next) {
+                            Some(_) => {
+                                // Skip the continuation with the whitespace.
+                                self.resume = State::WS;
+                                return self.make_pretok(curs, Kind::Word);
+                            }
+                            None => curs = next,
+                        },
+                        _ => {
+                            // we'll process this whitespace or newline on the
+                            // next next()
+                            return self.make_pretok(curs, Kind::Word);
+                        }
+                    }
+                }
+
+                // The closing quote.  Advance the cursor so the closing quote
+                // is included in the returned token.
+                State::QuotedTok => {
+                    return self.make_pretok(next, Kind::Quoted);
+                }
+
+                // No special transitions
+                State::BlockComment | State::EscapeChar => {
+                    state = step.to;
+                    curs = next;
+                }
+            }
+        }
+    }
+
+    /// True if anything besides the scanner needs to see each pretoken.
+    fn needs_checks(&self) -> bool {
+        let cfg = &self.cfg;
+        self.newline_hook.is_some() || self.comment_hook.is_some()
+            || self.token_filter.is_some() || self.progress_hook.is_some()
+            || cfg.max_token_len.is_some()
+            || cfg.max_line_len.is_some() || cfg.max_tokens.is_some()
+            || cfg.max_bytes.is_some() || checks::CharChecks::enabled(cfg)
+            || checks::token_checks_enabled(cfg) || self.ws_checks.enabled()
+    }
+
+    /// Scan and check the next pretoken.
+    fn advance(&mut self) -> Option<Pretoken<'a>> {
+        self.advance_with(|_| {})
+    }
+
+    /// Like `advance()`, passing the whitespace and comments skipped to
+    /// `trivia`.
+    fn advance_with<F>(&mut self, mut trivia: F) -> Option<Pretoken<'a>>
+    where F: FnMut(&Pretoken<'a>) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("next", offset = self.pos.byte_pos()).entered();
+        #[cfg(feature = "tracing")]
+        let reported = self.diagnostics.len();
+        let tok = self.filter_next(&mut trivia);
+        if !self.cfg.counts_lines() {
+            self.fix_lines();
+        }
+        #[cfg(feature = "tracing")]
+        {
+            for e in &self.diagnostics[reported..] {
+                tracing::warn!(line = e.line(), span = ?e.span(), "{}", e);
+            }
+            match &tok {
+                Some(t) => tracing::debug!(kind = ?t.kind, offset = t.offset, line = t.line,
+                                           s = t.s, "pretoken"),
+                None => tracing::debug!("end of input"),
+            }
+        }
+        tok
+    }
+
+    /// Apply the token filter to checked pretokens.
+    fn filter_next<F>(&mut self, mut trivia: F) -> Option<Pretoken<'a>>
+    where F: FnMut(&Pretoken<'a>) {
+        loop {
+            let tok = self.check_next(&mut trivia)?;
+            let tok = match self.token_filter.clone() {
+                Some(filter) => (filter.0.borrow_mut())(tok),
+                None => Some(tok),
+            };
+            if let Some(tok) = tok {
+                self.stats.stats.tokens += 1;
+                if let Some((every, hook)) = &self.progress_hook {
+                    if self.stats.stats.tokens.is_multiple_of(*every) {
+                        let (done, total) = (self.pos.byte_pos(), self.pos.slice_all().len());
+                        self.cancelled = !(hook.0.borrow_mut())(done, total);
+                    }
+                }
+                return Some(tok);
+            }
+        }
+    }
+
+    /// Scan and check the next pretoken before the token filter.
+    fn check_next<F>(&mut self, trivia: &mut F) -> Option<Pretoken<'a>>
+    where F: FnMut(&Pretoken<'a>) {
+        if self.budget.exhausted || self.cancelled {
+            return None;
+        }
+        let hook = self.comment_hook.clone();
+        let mut stats = self.stats;
+        let check_whitespace = self.ws_checks.enabled();
+        let mut ws_checks = self.ws_checks;
+        let mut found = Vec::new();
+        let tok = self.scan_with(|t, style| {
+            stats.record(t, style);
+            if check_whitespace {
+                ws_checks.record(t, &mut found);
+            }
+            if let (Kind::Comment, Some(hook)) = (t.kind, &hook) {
+                (hook.0.borrow_mut())(t);
+            }
+            trivia(t);
+        });
+        match tok {
+            Some(ref tok) if !tok.kind.is_trivia() => stats.record(tok, CommentStyle::default()),
+            _ => {}
+        }
+        self.stats = stats;
+        if check_whitespace {
+            match tok {
+                Some(ref tok) if !tok.kind.is_trivia() => ws_checks.record(tok, &mut found),
+                Some(_) => {}
+                None => ws_checks.finish(&mut found),
+            }
+            self.ws_checks = ws_checks;
+            self.diagnostics.append(&mut found);
+        }
+        if let Some(max) = self.cfg.max_line_len {
+            let upto = self.pos.byte_pos();
+            self.line_lengths.check(self.pos.slice_all(), upto, tok.is_none(),
+                                    max, &mut self.diagnostics);
+        }
+        if checks::CharChecks::enabled(&self.cfg) {
+            let upto = self.pos.byte_pos();
+            self.char_checks.check(self.pos.slice_all(), upto, &self.cfg,
+                                   &mut self.diagnostics);
+        }
+        if self.cfg.max_tokens.is_some() || self.cfg.max_bytes.is_some() {
+            let upto = self.pos.byte_pos();
+            if !self.budget.spend(self.pos.slice_all(), tok.as_ref(), upto, &self.cfg,
+                                  &mut self.diagnostics) {
+                return None;
+            }
+        }
+        let tok = tok?;
+        if tok.kind.is_trivia() {
+            // Only returned in lossless mode, and not checked.
+            return Some(tok);
+        }
+        if checks::token_checks_enabled(&self.cfg) {
+            checks::check_token(&tok, &self.cfg, &mut self.diagnostics);
+        }
+        if let Some(max) = self.cfg.max_token_len {
+            if tok.s.len() > max {
+                self.diagnostics.push(PretokError::TokenTooLong{
+                    line: tok.line, span: tok.offset..tok.offset + tok.s.len()});
+            }
+        }
+        if self.newline_hook.is_some() && tok.kind != Kind::Terminator {
+            self.last = Some(tok.clone());
+            self.terminable = true;
+        }
+        Some(tok)
+    }
+}
+
+/// Advances the internal iterator to the next pretoken. Skips whitespace
+/// and comments. If the result is OK(None), then we successfully reached
+/// end of the input string.
+impl <'a> std::iter::Iterator for Pretokenizer<'a> {
+    type Item = Pretoken<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.peeked.take() {
+            Some(tok) => tok,
+            None => self.advance(),
+        }
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if self.skip_tokens(n) < n {
+            return None;
+        }
+        self.next()
+    }
+
+    /// Unless hooks, filters, checks or budgets need to see each pretoken,
+    /// this only scans for pretoken boundaries, without counting lines or
+    /// making regions for whitespace.
+    fn count(mut self) -> usize {
+        let mut n = match self.peeked.take() {
+            Some(Some(_)) => 1,
+            Some(None) => return 0,
+            None => 0,
+        };
+        if self.needs_checks() {
+            return n + self.skip_tokens(usize::MAX);
+        }
+        // Nothing sees a line number or a run of whitespace from here on.
+        self.cfg.offsets_only = true;
+        self.skip_blanks = !self.cfg.lossless;
+        while Pretokenizer::scan(&mut self).is_some() {
+            n += 1;
+        }
+        n
+    }
+
+    /// Every pretoken is at least one byte long, so the remaining input
+    /// bounds the number of pretokens left.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (peeked, rest) = match &self.peeked {
+            Some(Some(_)) => (1, self.pos.slice_after().len()),
+            Some(None) => return (0, Some(0)),
+            None => (0, self.pos.slice_after().len()),
+        };
+        let mut upper = peeked + rest;
+        if let Some(left) = self.budget.tokens_left(&self.cfg) {
+            upper = upper.min(peeked + left);
+        }
+        (peeked, Some(upper))
+    }
+}
+
+/// Once `next()` returns `None`, it always returns `None`.
+impl<'a> std::iter::FusedIterator for Pretokenizer<'a> {}
+
+/// Pretokenize all of `s` with the default configuration.
+/// ```
+/// let v: Vec<_> = pretok::tokenize("a \"b c\"").iter().map(|t| t.s).collect();
+/// assert_eq!(v, vec!["a", "\"b c\""]);
+/// ```
+pub fn tokenize(s: &str) -> Vec<Pretoken<'_>> {
+    tokenize_with(s, Config::default())
+}
+
+/// Pretokenize all of `s` with `cfg`.  Diagnostics are discarded; use a
+/// [Pretokenizer](struct.Pretokenizer.html) directly to inspect them.
+/// ```
+/// use pretok::Config;
+/// let toks = pretok::tokenize_with("a ??( b", Config::new().trigraphs(true));
+/// assert_eq!(toks[1].s, "??(");
+/// ```
+pub fn tokenize_with(s: &str, cfg: Config) -> Vec<Pretoken<'_>> {
+    // Source code averages a pretoken every several bytes.  Guessing low
+    // saves the first few reallocations without over-reserving for inputs
+    // full of comments or cut short by a budget.
+    let mut cap = s.len().min(cfg.max_bytes.unwrap_or(usize::MAX)) / 16;
+    if let Some(max) = cfg.max_tokens {
+        cap = cap.min(max);
+    }
+    let mut toks = Vec::with_capacity(cap);
+    toks.extend(Pretokenizer::with_config(s, cfg));
+    toks
+}
+
+/// Count the pretokens of `s` with the default configuration, scanning
+/// only for their boundaries.
+/// ```
+/// assert_eq!(pretok::count_tokens("a \"b c\" /* d */ e"), 3);
+/// ```
+pub fn count_tokens(s: &str) -> usize {
+    count_tokens_with(s, Config::default())
+}
+
+/// Count the pretokens of `s` with `cfg`, as `tokenize_with()` would return
+/// them.  Only boundaries are scanned for unless `cfg` enables checks or
+/// limits that need to see each pretoken.
+/// ```
+/// use pretok::Config;
+/// assert_eq!(pretok::count_tokens_with("a // b", Config::new().lossless(true)), 3);
+/// ```
+pub fn count_tokens_with(s: &str, cfg: Config) -> usize {
+    Pretokenizer::with_config(s, cfg).count()
+}
+
+/// The byte offset in word `s` where a run of characters for which `pred`
+/// is true meets a run of others, if any.
+pub(crate) fn run_end(s: &str, mut pred: impl FnMut(char) -> bool) -> Option<usize> {
+    let mut chars = s.char_indices();
+    let first = pred(chars.next()?.1);
+    chars.find(|&(_, c)| pred(c) != first).map(|(i, _)| i)
+}
+
+/// The offset and length of the first unquoted CSS `url(...)` in `s`, if
+/// any, through its `)` or to the end of `s`.
+fn url_at(s: &str) -> Option<(usize, usize)> {
+    let mut prev = ' ';
+    for (i, c) in s.char_indices() {
+        let word = prev.is_alphanumeric() || prev == '-' || prev == '_';
+        prev = c;
+        if word || !s[i..].get(..4).is_some_and(|p| p.eq_ignore_ascii_case("url(")) {
+            continue;
+        }
+        let rest = &s[i + 4..];
+        if rest.is_empty() || rest.starts_with(['"', '\'']) {
+            continue;
+        }
+        return Some((i, 4 + rest.find(')').map_or(rest.len(), |n| n + 1)));
+    }
+    None
+}
+
+/// The length of the numeric literal at the start of `s`, if any, such as
+/// `1_000`, `1.5e+10` or `0x1p-3`.  A sign is taken in only after an
+/// exponent marker, and a `.` only if another doesn't follow.
+fn number_len(s: &str) -> Option<usize> {
+    if !s.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let hex = s.starts_with("0x") || s.starts_with("0X");
+    let exponent = if hex { ['p', 'P'] } else { ['e', 'E'] };
+    let mut prev = '0';
+    for (i, c) in s.char_indices() {
+        let more = match c {
+            '+' | '-' => exponent.contains(&prev),
+            '.' => !s[i + 1..].starts_with('.'),
+            _ => c.is_alphanumeric() || c == '_',
+        };
+        if !more {
+            return Some(i);
+        }
+        prev = c;
+    }
+    Some(s.len())
+}
+
+/// The greatest character boundary of `s` at or before `offset`.
+pub(crate) fn floor_char_boundary(s: &str, offset: usize) -> usize {
+    let mut offset = offset.min(s.len());
+    while !s.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+/// A cursor at the code point of `s` at or before byte `offset`.
+/// `StrCursor` looks at the byte at the position it is given, even at the
+/// end of `s`, so the end is handled here.
+pub(crate) fn cursor_at(s: &str, offset: usize) -> StrCursor<'_> {
+    match offset >= s.len() {
+        true => StrCursor::new_at_end(s),
+        false => StrCursor::new_at_cp_left_of_byte_pos(s, offset),
+    }
+}
+
+/// A cursor at the line feed ending the line of `s` holding byte `offset`,
+/// or at the end of `s`.
+fn line_end(s: &str, offset: usize) -> StrCursor<'_> {
+    cursor_at(s, s[offset..].find('\n').map_or(s.len(), |i| offset + i))
+}
+
+/// Like `cursor_at()`, at the grapheme cluster at or before `offset`.
+pub(crate) fn grapheme_cursor_at(s: &str, offset: usize) -> StrCursor<'_> {
+    match offset >= s.len() {
+        true => StrCursor::new_at_end(s),
+        false => StrCursor::new_at_left_of_byte_pos(s, offset),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testgen::Lcg;
+
+    #[test]
+    fn pretokenizer_test_0() {
+        let mut pt = Pretokenizer::new("");
+        assert!(pt.next().is_none());
+    }
+    #[test]
+    fn pretokenizer_test_1() {
+        let mut pt = Pretokenizer::new("foo");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 0);
+        assert_eq!(t.line, 1);
+        assert_eq!(t.s, "foo");
+    }
+
+    #[test]
+    fn pretokenizer_test_2() {
+        let mut pt = Pretokenizer::new("foo\n");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 0);
+        assert_eq!(t.line, 1);
+        assert_eq!(t.s, "foo");
+    }
+
+    #[test]
+    fn pretokenizer_test_3() {
+        let mut pt = Pretokenizer::new("\nfoo");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 1);
+        assert_eq!(t.line, 2);
+        assert_eq!(t.s, "foo");
+    }
+
+    #[test]
+    fn pretokenizer_test_4() {
+        let mut pt = Pretokenizer::new("\nfoo\n");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 1);
+        assert_eq!(t.line, 2);
+        assert_eq!(t.s, "foo");
+    }
+
+    #[test]
+    fn pretokenizer_test_5() {
+        let mut pt = Pretokenizer::new("/* */foo");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 5);
+        assert_eq!(t.line, 1);
+        assert_eq!(t.s, "foo");
+    }
+
+    #[test]
+    fn pretokenizer_test_6() {
+        let mut pt = Pretokenizer::new("\n/* */foo");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 6);
+        assert_eq!(t.line, 2);
+        assert_eq!(t.s, "foo");
+    }
+
+    #[test]
+    fn pretokenizer_test_7() {
+        let mut pt = Pretokenizer::new("\n/* */\nfoo");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 7);
+        assert_eq!(t.line, 3);
+        assert_eq!(t.s, "foo");
+    }
+
+    #[test]
+    fn pretokenizer_test_8() {
+        let mut pt = Pretokenizer::new("// bar");
+        let t = pt.next();
+        assert!(t.is_none());
+    }
+
+    #[test]
+    fn pretokenizer_test_9() {
+        let mut pt = Pretokenizer::new("\n// bar");
+        let t = pt.next();
+        assert!(t.is_none());
+    }
+
+    #[test]
+    fn pretokenizer_test_10() {
+        let mut pt = Pretokenizer::new("// bar\n");
+        let t = pt.next();
+        assert!(t.is_none());
+    }
+
+    #[test]
+    fn pretokenizer_test_11() {
+        let mut pt = Pretokenizer::new("// bar\nfoo");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 7);
+        assert_eq!(t.line, 2);
+        assert_eq!(t.s, "foo");
+    }
+
+    #[test]
+    fn pretokenizer_test_12() {
+        let mut pt = Pretokenizer::new("// bar\n\nfoo");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 8);
+        assert_eq!(t.line, 3);
+        assert_eq!(t.s, "foo");
+    }
+
+    #[test]
+    fn pretokenizer_test_13() {
+        let mut pt = Pretokenizer::new("\"");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 0);
+        assert_eq!(t.line, 1);
+        assert_eq!(t.s, "\"");
+    }
+
+    #[test]
+    fn pretokenizer_test_14() {
+        let mut pt = Pretokenizer::new("\"\"");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 0);
+        assert_eq!(t.line, 1);
+        assert_eq!(t.s, "\"\"");
+    }
+
+    #[test]
+    fn pretokenizer_test_15() {
+        let mut pt = Pretokenizer::new("\"x\"");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 0);
+        assert_eq!(t.line, 1);
+        assert_eq!(t.s, "\"x\"");
+    }
+
+    #[test]
+    fn pretokenizer_test_16() {
+        let mut pt = Pretokenizer::new("\" x\"");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 0);
+        assert_eq!(t.line, 1);
+        assert_eq!(t.s, "\" x\"");
+    }
+
+    #[test]
+    fn pretokenizer_test_17() {
+        let mut pt = Pretokenizer::new("\" x x \"");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 0);
+        assert_eq!(t.line, 1);
+        assert_eq!(t.s, "\" x x \"");
+    }
+
+    #[test]
+    fn pretokenizer_test_18() {
+        let mut pt = Pretokenizer::new("//\" x x \"");
+        let t = pt.next();
+        assert!(t.is_none());
+    }
+
+    #[test]
+    fn pretokenizer_test_19() {
+        let mut pt = Pretokenizer::new("\"// x x \"");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 0);
+        assert_eq!(t.line, 1);
+        assert_eq!(t.s, "\"// x x \"");
+    }
+
+    #[test]
+    fn pretokenizer_test_20() {
+        let mut pt = Pretokenizer::new("\" /* x x */ \"");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 0);
+        assert_eq!(t.line, 1);
+        assert_eq!(t.s, "\" /* x x */ \"");
+    }
+
+    #[test]
+    fn pretokenizer_test_21() {
+        let mut pt = Pretokenizer::new("\" \\\" x \"");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 0);
+        assert_eq!(t.line, 1);
+        assert_eq!(t.s, "\" \\\" x \"");
+    }
+
+    #[test]
+    fn pretokenizer_test_22() {
+        let mut pt = Pretokenizer::new("\" \\");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 0);
+        assert_eq!(t.line, 1);
+        assert_eq!(t.s, "\" \\");
+    }
+
+    #[test]
+    fn pretokenizer_test_23() {
+        let mut pt = Pretokenizer::new("\" \\\"");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 0);
+        assert_eq!(t.line, 1);
+        assert_eq!(t.s, "\" \\\"");
+    }
+
+    #[test]
+    fn pretokenizer_test_24() {
+        // Found by fuzz testing
+        let mut pt = Pretokenizer::new("\" x\nx\"");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 0);
+        // tokens that span lines are reported on the
+        // last line of the token.
+        assert_eq!(t.line, 2);
+        assert_eq!(t.s, "\" x\nx\"");
+    }
+
+    #[test]
+    fn pretokenizer_test_25() {
+        let mut pt = Pretokenizer::new("x//x");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 0);
+        assert_eq!(t.line, 1);
+        assert_eq!(t.s, "x");
+    }
+
+    #[test]
+    fn pretokenizer_test_26() {
+        let mut pt = Pretokenizer::new("x/*x*/");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 0);
+        assert_eq!(t.line, 1);
+        assert_eq!(t.s, "x");
+    }
+
+    #[test]
+    fn pretokenizer_test_27() {
+        let mut pt = Pretokenizer::new("x/*y*/z");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 0);
+        assert_eq!(t.line, 1);
+        assert_eq!(t.s, "x");
+        // Now get the z.
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 6);
+        assert_eq!(t.line, 1);
+        assert_eq!(t.s, "z");
+    }
+
+    #[test]
+    fn pretokenizer_test_28() {
+        let mut pt = Pretokenizer::new("x y z");
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 0);
+        assert_eq!(t.line, 1);
+        assert_eq!(t.s, "x");
+        // Now get the 7.
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 2);
+        assert_eq!(t.line, 1);
+        assert_eq!(t.s, "y");
+        // Now get the z.
+        let t = pt.next();
+        assert!(t.is_some());
         let t = t.unwrap();
-        assert_eq!(t.offset, 1);
-        assert_eq!(t.line, 2);
-        assert_eq!(t.s, "foo");
+        assert_eq!(t.offset, 4);
+        assert_eq!(t.line, 1);
+        assert_eq!(t.s, "z");
     }
 
     #[test]
-    fn pretokenizer_test_5() {
-        let mut pt = Pretokenizer::new("/* */foo");
+    fn pretokenizer_test_29() {
+        let mut pt = Pretokenizer::new("  x\n y\n   z");
         let t = pt.next();
         assert!(t.is_some());
         let t = t.unwrap();
-        assert_eq!(t.offset, 5);
+        assert_eq!(t.offset, 2);
         assert_eq!(t.line, 1);
-        assert_eq!(t.s, "foo");
+        assert_eq!(t.s, "x");
+        // Now get the 7.
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 5);
+        assert_eq!(t.line, 2);
+        assert_eq!(t.s, "y");
+        // Now get the z.
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 10);
+        assert_eq!(t.line, 3);
+        assert_eq!(t.s, "z");
     }
 
     #[test]
-    fn pretokenizer_test_6() {
-        let mut pt = Pretokenizer::new("\n/* */foo");
+    fn pretokenizer_test_30() {
+        let mut pt = Pretokenizer::new("  x // foo\ny\n   z");
         let t = pt.next();
         assert!(t.is_some());
         let t = t.unwrap();
-        assert_eq!(t.offset, 6);
+        assert_eq!(t.offset, 2);
+        assert_eq!(t.line, 1);
+        assert_eq!(t.s, "x");
+        // Now get the 7.
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 11);
         assert_eq!(t.line, 2);
-        assert_eq!(t.s, "foo");
+        assert_eq!(t.s, "y");
+        // Now get the z.
+        let t = pt.next();
+        assert!(t.is_some());
+        let t = t.unwrap();
+        assert_eq!(t.offset, 16);
+        assert_eq!(t.line, 3);
+        assert_eq!(t.s, "z");
+    }
+
+    #[test]
+    fn pretokenizer_test_31() {
+        let pt = Pretokenizer::new("a+b c// stuff\nd");
+        for tok in pt {
+            println!("{} found on line {}, offset {}",
+                    tok.s, tok.line, tok.offset);
+        }
+    }
+
+    #[test]
+    fn pretokenizer_test_32() {
+        // A '/' that doesn't start a comment is part of a token.
+        let v: Vec<_> = Pretokenizer::new("a / b /x /").map(|t| t.s).collect();
+        assert_eq!(v, vec!["a", "/", "b", "/x", "/"]);
+    }
+
+    #[test]
+    fn pretokenizer_test_33() {
+        let v: Vec<_> = Pretokenizer::new("a/* **/b").map(|t| t.s).collect();
+        assert_eq!(v, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn pretokenizer_test_34() {
+        // Without trigraphs, ??/ is just text and the quote ends the string.
+        let v: Vec<_> = Pretokenizer::new("\"a??/\"b\"").map(|t| t.s).collect();
+        assert_eq!(v, vec!["\"a??/\"", "b", "\""]);
+
+        // With trigraphs, ??/ escapes the quote.
+        let cfg = Config::new().trigraphs(true);
+        let v: Vec<_> = Pretokenizer::with_config("\"a??/\"b\" c", cfg)
+            .map(|t| t.s).collect();
+        assert_eq!(v, vec!["\"a??/\"b\"", "c"]);
+    }
+
+    #[test]
+    fn pretokenizer_test_35() {
+        // Trigraphs are only three characters long and don't disturb offsets.
+        let cfg = Config::new().trigraphs(true);
+        let mut pt = Pretokenizer::with_config("??< ?? ??x ??>", cfg);
+        assert_eq!(pt.next(), Some(Pretoken{s:"??<", line:1, offset:0, kind:Kind::Word}));
+        assert_eq!(pt.next(), Some(Pretoken{s:"??", line:1, offset:4, kind:Kind::Word}));
+        assert_eq!(pt.next(), Some(Pretoken{s:"??x", line:1, offset:7, kind:Kind::Word}));
+        assert_eq!(pt.next(), Some(Pretoken{s:"??>", line:1, offset:11, kind:Kind::Word}));
+        assert_eq!(pt.next(), None);
+    }
+
+    #[test]
+    fn pretokenizer_test_36() {
+        // Without directives enabled, # is an ordinary character.
+        let v: Vec<_> = Pretokenizer::new("#include <x.h>").map(|t| t.s).collect();
+        assert_eq!(v, vec!["#include", "<x.h>"]);
+
+        let cfg = Config::new().directives(true);
+        let mut pt = Pretokenizer::with_config(
+            "  #include <x.h>  \n# if A \\\n && B /* c\n */\nx # y", cfg);
+        assert_eq!(pt.next(), Some(Pretoken{s:"#include <x.h>", line:1, offset:2, kind:Kind::Directive}));
+        assert_eq!(pt.next(), Some(Pretoken{s:"# if A \\\n && B", line:3, offset:19, kind:Kind::Directive}));
+        let t = pt.next().unwrap();
+        assert_eq!((t.s, t.line), ("x", 5));
+        // A # after a token on the same line is not a directive.
+        assert_eq!(pt.next().unwrap().s, "#");
+        assert_eq!(pt.next().unwrap().s, "y");
+        assert_eq!(pt.next(), None);
+    }
+
+    #[test]
+    fn pretokenizer_test_37() {
+        // Quotes protect comment starts and an unterminated quote stops at the
+        // end of the line.
+        let cfg = Config::new().directives(true);
+        let v: Vec<_> = Pretokenizer::with_config(
+            "#error \"//\" '/*' //x\n#x \"abc\ny", cfg).map(|t| t.s).collect();
+        assert_eq!(v, vec!["#error \"//\" '/*'", "#x \"abc", "y"]);
+
+        // Trigraph ??= introduces a directive.
+        let cfg = Config::new().directives(true).trigraphs(true);
+        let v: Vec<_> = Pretokenizer::with_config("??=define X\nX", cfg)
+            .map(|t| t.s).collect();
+        assert_eq!(v, vec!["??=define X", "X"]);
+    }
+
+    #[test]
+    fn pretokenizer_test_38() {
+        let v: Vec<_> = Pretokenizer::new("#!/bin/sh\nx").map(|t| t.s).collect();
+        assert_eq!(v, vec!["#!/bin/sh", "x"]);
+
+        let cfg = Config::new().shebang(Shebang::Skip);
+        let mut pt = Pretokenizer::with_config("#!/usr/bin/env x y\nz", cfg);
+        assert_eq!(pt.next(), Some(Pretoken{s:"z", line:2, offset:19, kind:Kind::Word}));
+        assert_eq!(pt.next(), None);
+
+        // Only at the very start of input, and not a Rust inner attribute.
+        let cfg = Config::new().shebang(Shebang::Skip);
+        let v: Vec<_> = Pretokenizer::with_config(" #!x", cfg.clone())
+            .map(|t| t.s).collect();
+        assert_eq!(v, vec!["#!x"]);
+        let v: Vec<_> = Pretokenizer::with_config("#![no_std]", cfg)
+            .map(|t| t.s).collect();
+        assert_eq!(v, vec!["#![no_std]"]);
+
+        // A shebang is not a directive.
+        let cfg = Config::new().shebang(Shebang::Token).directives(true);
+        let mut pt = Pretokenizer::with_config("#!a\n#b", cfg);
+        assert_eq!(pt.next().unwrap().kind, Kind::Shebang);
+        assert_eq!(pt.next().unwrap().kind, Kind::Directive);
+    }
+
+    #[test]
+    fn pretokenizer_test_39() {
+        let cfg = Config::new().skip_region("#if 0", "#endif").directives(true);
+        let src = "a\n  #if 0\n#if 0\n/*\n#endif\n \"\n  #endif // x\n#endif\nb";
+        let mut pt = Pretokenizer::with_config(src, cfg.clone());
+        assert_eq!(pt.next().unwrap().s, "a");
+        assert_eq!(pt.next(), Some(Pretoken{s:"#endif", line:8, offset:43, kind:Kind::Directive}));
+        assert_eq!(pt.next(), Some(Pretoken{s:"b", line:9, offset:50, kind:Kind::Word}));
+        assert_eq!(pt.next(), None);
+
+        // Markers only count at the start of a line.
+        let v: Vec<_> = Pretokenizer::with_config("x #if 0\ny", cfg.clone())
+            .map(|t| t.s).collect();
+        assert_eq!(v, vec!["x", "#if", "0", "y"]);
+
+        // Unterminated region
+        let mut pt = Pretokenizer::with_config("#if 0\nx\ny", cfg);
+        assert_eq!(pt.next(), None);
+    }
+
+    #[test]
+    fn pretokenizer_test_40() {
+        let cfg = Config::new().pragma("#pragma").directives(true);
+        let mut pt = Pretokenizer::with_config(
+            "x #pragma no\n/* c */ #pragma a  \n  #pragma b\n", cfg);
+        let v: Vec<_> = pt.by_ref().map(|t| t.s).collect();
+        assert_eq!(v, vec!["x", "#pragma", "no", "#pragma a", "#pragma b"]);
+        assert_eq!(pt.pragmas(), &[
+            Pretoken{s:"#pragma a", line:2, offset:21, kind:Kind::Pragma},
+            Pretoken{s:"#pragma b", line:3, offset:35, kind:Kind::Pragma},
+        ]);
+    }
+
+    #[test]
+    fn pretokenizer_test_41() {
+        let cfg = Config::new().tool_directives("pretok:off", "pretok:on");
+        let src = "a // pretok:off\n  x \"y\n/* z\n  //pretok:on  \nb";
+        let mut pt = Pretokenizer::with_config(src, cfg.clone());
+        assert_eq!(pt.next().unwrap().s, "a");
+        assert_eq!(pt.next(), Some(Pretoken{s:"  x \"y\n/* z", line:3, offset:16, kind:Kind::Opaque}));
+        assert_eq!(pt.next(), Some(Pretoken{s:"b", line:5, offset:44, kind:Kind::Word}));
+        assert_eq!(pt.next(), None);
+
+        // Empty and unterminated regions
+        let v: Vec<_> = Pretokenizer::with_config(
+            "//pretok:off\n//pretok:on\na\n//pretok:off\nb c\n", cfg)
+            .map(|t| (t.s, t.kind)).collect();
+        assert_eq!(v, vec![("a", Kind::Word), ("b c\n", Kind::Opaque)]);
+    }
+
+    #[test]
+    fn pretokenizer_test_42() {
+        let mut pt = Pretokenizer::new("a\n\n/*\n*/b // c\n \"\n\"\n")
+            .on_newline(|_| true);
+        assert_eq!(pt.next(), Some(Pretoken{s:"a", line:1, offset:0, kind:Kind::Word}));
+        assert_eq!(pt.next(), Some(Pretoken{s:"\n", line:1, offset:1, kind:Kind::Terminator}));
+        assert_eq!(pt.next(), Some(Pretoken{s:"b", line:4, offset:8, kind:Kind::Word}));
+        assert_eq!(pt.next(), Some(Pretoken{s:"\n", line:4, offset:14, kind:Kind::Terminator}));
+        assert_eq!(pt.next(), Some(Pretoken{s:"\"\n\"", line:6, offset:16, kind:Kind::Quoted}));
+        assert_eq!(pt.next(), Some(Pretoken{s:"\n", line:6, offset:19, kind:Kind::Terminator}));
+        assert_eq!(pt.next(), None);
+    }
+
+    #[test]
+    fn pretokenizer_test_43() {
+        let results: Vec<_> = Pretokenizer::new("a \"b\" c").strict().collect();
+        assert!(results.iter().all(|r| r.is_ok()));
+        assert_eq!(results.len(), 3);
+
+        let mut strict = Pretokenizer::new("a\n/* b\n").strict();
+        assert_eq!(strict.next().unwrap().unwrap().s, "a");
+        assert_eq!(strict.next().unwrap().unwrap_err().span(), 2..7);
+        assert_eq!(strict.next(), None);
+        assert_eq!(strict.next(), None);
+
+        let cfg = Config::new().skip_region("#if 0", "#endif")
+            .tool_directives("off", "on");
+        let mut strict = Pretokenizer::with_config("//off\nx\n#if 0\n", cfg.clone())
+            .strict();
+        assert_eq!(strict.next(), Some(Err(PretokError::UnterminatedRegion{line: 1, span: 0..14})));
+        assert_eq!(strict.next().unwrap().unwrap().kind, Kind::Opaque);
+        assert_eq!(strict.next(), None);
+        let mut strict = Pretokenizer::with_config("x\n#if 0\n", cfg).strict();
+        assert_eq!(strict.next().unwrap().unwrap().s, "x");
+        assert_eq!(strict.next(), Some(Err(PretokError::UnterminatedRegion{line: 2, span: 2..8})));
+        assert_eq!(strict.next(), None);
+    }
+
+    #[test]
+    fn pretokenizer_test_44() {
+        let mut pt = Pretokenizer::new("\"a\" b");
+        assert!(pt.by_ref().count() == 2);
+        assert!(pt.diagnostics().is_empty());
+
+        // Diagnostics accumulate and survive further calls to next().
+        let mut pt = Pretokenizer::new("\"a\\\"");
+        assert_eq!(pt.next().unwrap().s, "\"a\\\"");
+        assert_eq!(pt.next(), None);
+        assert_eq!(pt.next(), None);
+        assert_eq!(pt.diagnostics(), &[PretokError::UnterminatedString{line: 1, span: 0..4}]);
+    }
+
+    #[test]
+    fn pretokenizer_test_45() {
+        // The error reports the line of the opening quote even though the
+        // pretoken reports its last line.
+        let mut strict = Pretokenizer::new("x\n  \"a\nb\nc").strict();
+        assert_eq!(strict.next().unwrap().unwrap().s, "x");
+        match strict.next() {
+            Some(Err(PretokError::UnterminatedString{line, span})) => {
+                assert_eq!(line, 2);
+                assert_eq!(span, 4..10);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        let t = strict.next().unwrap().unwrap();
+        assert_eq!((t.line, t.offset), (4, 4));
+    }
+
+    #[test]
+    fn pretokenizer_test_46() {
+        let cfg = Config::new().max_token_len(3);
+        let mut pt = Pretokenizer::with_config("abc abcd \"ab\"", cfg);
+        assert_eq!(pt.by_ref().count(), 3);
+        assert_eq!(pt.diagnostics(), &[
+            PretokError::TokenTooLong{line: 1, span: 4..8},
+            PretokError::TokenTooLong{line: 1, span: 9..13},
+        ]);
+
+        // Lines are measured in bytes without the line break, including
+        // comments and whitespace.
+        let cfg = Config::new().max_line_len(4);
+        let mut pt = Pretokenizer::with_config(
+            "abcd\na /* */\n\"a\nbcdefg\"\n// long\n    \nx  y z", cfg);
+        assert_eq!(pt.by_ref().count(), 6);
+        assert_eq!(pt.diagnostics(), &[
+            PretokError::LineTooLong{line: 2, span: 5..12},
+            PretokError::LineTooLong{line: 4, span: 16..23},
+            PretokError::LineTooLong{line: 5, span: 24..31},
+            PretokError::LineTooLong{line: 7, span: 37..43},
+        ]);
+
+        // A long line is reported before the rest of it is tokenized.
+        let cfg = Config::new().max_line_len(4);
+        let mut strict = Pretokenizer::with_config("a b c d e f\ng", cfg).strict();
+        assert_eq!(strict.next().unwrap().unwrap().s, "a");
+        assert_eq!(strict.next().unwrap().unwrap().s, "b");
+        assert_eq!(strict.next(), Some(Err(PretokError::LineTooLong{line: 1, span: 0..11})));
+        assert_eq!(strict.next().unwrap().unwrap().s, "c");
+        assert_eq!(strict.filter(|r| r.is_err()).count(), 0);
+    }
+
+    #[test]
+    fn pretokenizer_test_47() {
+        // Block comments don't nest by default.
+        let v: Vec<_> = Pretokenizer::new("a /* /* */ b */").map(|t| t.s).collect();
+        assert_eq!(v, vec!["a", "b", "*/"]);
+
+        let cfg = Config::new().nested_comments(true);
+        let v: Vec<_> = Pretokenizer::with_config("a /* /* */ b */ c //*/* */ d", cfg.clone())
+            .map(|t| t.s).collect();
+        assert_eq!(v, vec!["a", "c"]);
+        let v: Vec<_> = Pretokenizer::with_config("a/*/**/*/b/*//*/ */*/c", cfg)
+            .map(|t| t.s).collect();
+        assert_eq!(v, vec!["a", "b", "c"]);
+
+        // Beyond the limit, openers don't nest and the first is reported.
+        let cfg = Config::new().nested_comments(true).max_comment_depth(2);
+        let mut pt = Pretokenizer::with_config("/*1/*2/*3/*4*/x*/y", cfg);
+        assert_eq!(pt.next().unwrap().s, "y");
+        assert_eq!(pt.next(), None);
+        assert_eq!(pt.diagnostics(), &[PretokError::CommentTooDeep{line: 1, span: 6..8}]);
+    }
+
+    #[test]
+    fn pretokenizer_test_48() {
+        // The classic Trojan Source example hides code in a comment.
+        let src = "x /* \u{202E} } \u{2066}if (admin)\u{2069} \u{2066} begin */\n\"\u{202B}\" a\u{202A}";
+        let mut pt = Pretokenizer::new(src);
+        assert_eq!(pt.by_ref().count(), 3);
+        assert!(pt.diagnostics().is_empty());
+
+        let mut pt = Pretokenizer::with_config(src, Config::new().detect_bidi(true));
+        assert_eq!(pt.by_ref().count(), 3);
+        let found: Vec<_> = pt.diagnostics().iter().map(|d| match d {
+            PretokError::BidiControl{ch, line, span} => (*ch, *line, span.start),
+            _ => panic!("unexpected {:?}", d),
+        }).collect();
+        assert_eq!(found, vec![('\u{202E}', 1, 5), ('\u{2066}', 1, 11),
+                               ('\u{2069}', 1, 24), ('\u{2066}', 1, 28),
+                               ('\u{202B}', 2, 42), ('\u{202A}', 2, 48)]);
+    }
+
+    #[test]
+    fn pretokenizer_test_49() {
+        let src = "pаypal = \"Grüße\" + café\u{FF08}x) ; naïve";
+        let cfg = Config::new().detect_non_ascii(true).allow_non_ascii("ï");
+        let mut pt = Pretokenizer::with_config(src, cfg);
+        assert_eq!(pt.by_ref().count(), 7);
+        assert_eq!(pt.diagnostics(), &[
+            PretokError::Confusable{ch: 'а', looks_like: 'a', line: 1, span: 1..3},
+            PretokError::NonAscii{ch: 'é', line: 1, span: 25..27},
+            PretokError::Confusable{ch: '\u{FF08}', looks_like: '(', line: 1, span: 27..30},
+        ]);
+    }
+
+    #[test]
+    fn pretokenizer_test_50() {
+        let src = "a\u{0}b \"x\u{8}\ny\"\t\r\n// \u{7}\n\u{7F}";
+        let mut pt = Pretokenizer::new(src);
+        assert_eq!(pt.by_ref().count(), 4);
+        assert!(pt.diagnostics().is_empty());
+
+        // Comments aren't checked.  Tab, line feed and carriage return are
+        // allowed.
+        let mut pt = Pretokenizer::with_config(src, Config::new().detect_control_chars(true));
+        assert_eq!(pt.by_ref().count(), 4);
+        assert_eq!(pt.diagnostics(), &[
+            PretokError::ControlChar{ch: '\u{0}', line: 1, span: 1..2},
+            PretokError::ControlChar{ch: '\u{8}', line: 1, span: 6..7},
+            PretokError::ControlChar{ch: '\u{7F}', line: 4, span: 18..19},
+        ]);
+    }
+
+    #[test]
+    fn pretokenizer_test_51() {
+        // Exactly enough budget isn't exhausted
+        let mut pt = Pretokenizer::with_config("a b", Config::new().max_tokens(2).max_bytes(3));
+        assert_eq!(pt.by_ref().count(), 2);
+        assert!(!pt.budget_exhausted());
+        assert!(pt.diagnostics().is_empty());
+
+        let mut pt = Pretokenizer::with_config("a\nbc d", Config::new().max_tokens(1));
+        let v: Vec<_> = pt.by_ref().map(|t| t.s).collect();
+        assert_eq!(v, vec!["a"]);
+        assert!(pt.budget_exhausted());
+        assert_eq!(pt.diagnostics(), &[PretokError::BudgetExhausted{line: 2, span: 2..6}]);
+        assert!(pt.next().is_none());
+
+        // A pretoken that runs past the byte budget isn't returned
+        let mut pt = Pretokenizer::with_config("a bcdef g", Config::new().max_bytes(4));
+        let v: Vec<_> = pt.by_ref().map(|t| t.s).collect();
+        assert_eq!(v, vec!["a"]);
+        assert_eq!(pt.diagnostics(), &[PretokError::BudgetExhausted{line: 1, span: 2..9}]);
+
+        // Neither is a comment
+        let mut pt = Pretokenizer::with_config("a /* long */", Config::new().max_bytes(4));
+        assert_eq!(pt.by_ref().count(), 1);
+        assert_eq!(pt.diagnostics(), &[PretokError::BudgetExhausted{line: 1, span: 4..12}]);
+    }
+
+    #[test]
+    fn pretokenizer_test_52() {
+        let mut pt = Pretokenizer::new("a \"b\" c");
+        assert_eq!(pt.peek(), Some(&Pretoken{s: "a", line: 1, offset: 0, kind: Kind::Word}));
+        assert_eq!(pt.peek().unwrap().s, "a");
+        assert_eq!(pt.next().unwrap().s, "a");
+        assert_eq!(pt.peek().unwrap().kind, Kind::Quoted);
+        let v: Vec<_> = pt.by_ref().map(|t| t.s).collect();
+        assert_eq!(v, vec!["\"b\"", "c"]);
+        assert_eq!(pt.peek(), None);
+        assert_eq!(pt.next(), None);
+    }
+
+    #[test]
+    fn pretokenizer_test_53() {
+        fn fused<I: std::iter::FusedIterator>(_: &I) {}
+
+        let mut pt = Pretokenizer::new("ab /* c */ d\n");
+        fused(&pt);
+        assert_eq!(pt.size_hint(), (0, Some(13)));
+        pt.peek();
+        assert_eq!(pt.size_hint(), (1, Some(12)));
+        pt.next();
+        assert_eq!(pt.size_hint(), (0, Some(11)));
+        assert_eq!(pt.by_ref().count(), 1);
+        assert_eq!(pt.size_hint(), (0, Some(0)));
+        assert_eq!(pt.next(), None);
+        assert_eq!(pt.next(), None);
+
+        let cfg = Config::new().max_tokens(2);
+        let mut pt = Pretokenizer::with_config("a b c d", cfg);
+        assert_eq!(pt.size_hint(), (0, Some(2)));
+        pt.next();
+        assert_eq!(pt.size_hint(), (0, Some(1)));
+        assert_eq!(pt.by_ref().count(), 1);
+        assert_eq!(pt.size_hint(), (0, Some(0)));
+        assert_eq!(pt.next(), None);
+    }
+
+    #[test]
+    fn pretokenizer_test_54() {
+        assert!(tokenize("").is_empty());
+        assert_eq!(tokenize("a // b\nc"), vec![
+            Pretoken{s: "a", line: 1, offset: 0, kind: Kind::Word},
+            Pretoken{s: "c", line: 2, offset: 7, kind: Kind::Word},
+        ]);
+        let v = tokenize_with("a b c", Config::new().max_tokens(2));
+        assert_eq!(v.len(), 2);
+    }
+
+    #[test]
+    fn pretokenizer_test_55() {
+        let mut pt = Pretokenizer::new("a /* b */ \"c\"");
+        assert_eq!(pt.remainder(), "a /* b */ \"c\"");
+        pt.next();
+        assert_eq!(pt.remainder(), " /* b */ \"c\"");
+        pt.peek();
+        assert_eq!(pt.remainder(), "\"c\"");
+        pt.next();
+        assert_eq!(pt.remainder(), "");
+        assert_eq!(pt.peek(), None);
+        assert_eq!(pt.remainder(), "");
+    }
+
+    #[test]
+    fn pretokenizer_test_56() {
+        let mut pt = Pretokenizer::new("a\n\"b\nc\" /* d\n");
+        assert_eq!((pt.byte_pos(), pt.line()), (0, 1));
+        pt.next();
+        assert_eq!((pt.byte_pos(), pt.line()), (1, 1));
+        pt.peek();
+        assert_eq!((pt.byte_pos(), pt.line()), (2, 2));
+        pt.next();
+        assert_eq!((pt.byte_pos(), pt.line()), (7, 3));
+        assert_eq!(pt.next(), None);
+        assert_eq!((pt.byte_pos(), pt.line()), (13, 4));
+    }
+
+    #[test]
+    fn pretokenizer_test_57() {
+        let src = "a /* b */ c\n\"d\ne\" // f\ng";
+        let mut pt = Pretokenizer::new(src);
+        assert_eq!(pt.seek(10), Ok(()));
+        assert_eq!(pt.next(), Some(Pretoken{s: "c", line: 1, offset: 10, kind: Kind::Word}));
+
+        // Backward, and between a comment and a pretoken
+        assert_eq!(pt.seek(9), Ok(()));
+        assert_eq!(pt.next().unwrap().s, "c");
+        assert_eq!(pt.seek(4), Err(SeekError::InsideComment{offset: 4}));
+        assert_eq!(pt.byte_pos(), 1);
+        assert_eq!(pt.next().unwrap().s, "c");
+
+        assert_eq!(pt.seek(14), Err(SeekError::InsidePretoken{span: 12..17}));
+        assert_eq!(pt.skip_to_line(3), Err(SeekError::InsidePretoken{span: 12..17}));
+        assert_eq!((pt.byte_pos(), pt.line()), (11, 1));
+        assert_eq!(pt.skip_to_line(4), Ok(()));
+        assert_eq!(pt.next(), Some(Pretoken{s: "g", line: 4, offset: 23, kind: Kind::Word}));
+        assert_eq!(pt.seek(src.len()), Ok(()));
+        assert_eq!(pt.next(), None);
+        assert_eq!(pt.seek(src.len() + 1), Err(SeekError::OutOfRange{offset: 25}));
+        assert_eq!(Pretokenizer::new("\u{e9}").seek(1),
+                   Err(SeekError::NotCharBoundary{offset: 1}));
+
+        // The start of every pretoken is a place to stop, whatever came before.
+        let cases = [("// off\n+", Config::new().tool_directives("off", "on")),
+                     ("*\t/*#pragma x\n*/#if 0\n", Config::new().directives(true)),
+                     ("a /*\n*/ #if\n", Config::new().directives(true))];
+        for (src, cfg) in cases.iter() {
+            let want: Vec<_> = Pretokenizer::with_config(src, cfg.clone()).collect();
+            for (i, tok) in want.iter().enumerate() {
+                let mut pt = Pretokenizer::with_config(src, cfg.clone());
+                assert_eq!(pt.seek(tok.offset), Ok(()), "{:?} {:?}", src, tok);
+                assert_eq!(pt.collect::<Vec<_>>(), want[i..]);
+            }
+        }
+        let mut pt = Pretokenizer::with_config("a /*\n*/ #if\n", Config::new().directives(true));
+        assert_eq!(pt.seek(7), Ok(()));
+        assert_eq!(pt.next().unwrap().kind, Kind::Word);
+        let pieces = ["/", "*", "\"", "\n", " ", "a", "#if", "// off", "// on", "\t", "??/"];
+        let configs = [Config::new().directives(true).pragma("#if"),
+                       Config::new().tool_directives("off", "on").trigraphs(true)];
+        let mut rng = Lcg::new(17);
+        for cfg in configs.iter() {
+            for _ in 0..300 {
+                let src = rng.source(&pieces, 16);
+                let want: Vec<_> = Pretokenizer::with_config(&src, cfg.clone()).collect();
+                for (i, tok) in want.iter().enumerate() {
+                    let mut pt = Pretokenizer::with_config(&src, cfg.clone());
+                    assert_eq!(pt.seek(tok.offset), Ok(()), "{:?} {:?}", src, tok);
+                    assert_eq!(pt.collect::<Vec<_>>(), want[i..], "{:?}", src);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pretokenizer_test_58() {
+        #[derive(Default)]
+        struct Log(Vec<(char, usize, usize)>);
+        impl<'a> PretokSink<'a> for Log {
+            fn on_token(&mut self, t: &Pretoken<'a>) { self.0.push(('t', t.offset, t.line)) }
+            fn on_comment(&mut self, t: &Pretoken<'a>) { self.0.push(('c', t.offset, t.line)) }
+            fn on_newline(&mut self, t: &Pretoken<'a>) { self.0.push(('n', t.offset, t.line)) }
+        }
+
+        let mut pt = Pretokenizer::with_config("a /*\n*/ b\n", Config::new().max_tokens(1));
+        let mut log = Log::default();
+        pt.peek();
+        pt.drive(&mut log);
+        assert_eq!(log.0, vec![('t', 0, 1), ('c', 2, 2)]);
+        assert!(pt.budget_exhausted());
+        pt.drive(&mut log);
+        assert_eq!(log.0.len(), 2);
+    }
+
+    #[test]
+    fn pretokenizer_test_59() {
+        let comments = Rc::new(RefCell::new(Vec::new()));
+        let log = comments.clone();
+        let cfg = Config::new().skip_region("#if 0", "#endif");
+        let src = "a /* b */ c // d\n#if 0\ne\n#endif\nf";
+        let mut pt = Pretokenizer::with_config(src, cfg)
+            .on_comment(move |c| log.borrow_mut().push((c.s, c.offset, c.line)));
+        assert_eq!(pt.next().unwrap().s, "a");
+        assert!(comments.borrow().is_empty());
+        let v: Vec<_> = pt.map(|t| t.s).collect();
+        assert_eq!(v, vec!["c", "f"]);
+        assert_eq!(*comments.borrow(), vec![
+            ("/* b */", 2, 1),
+            ("// d", 12, 1),
+            ("#if 0\ne\n#endif", 17, 4),
+        ]);
+    }
+
+    #[test]
+    fn pretokenizer_test_60() {
+        let mut pt = Pretokenizer::new("a bb c dd")
+            .filter_tokens(|t| if t.s.len() == 1 { None } else { Some(t) });
+        assert_eq!(pt.peek().unwrap().s, "bb");
+        let v: Vec<_> = pt.map(|t| (t.s, t.offset)).collect();
+        assert_eq!(v, vec![("bb", 2), ("dd", 7)]);
+
+        let mut pt = Pretokenizer::new("a b").filter_tokens(|_| None);
+        assert_eq!(pt.next(), None);
+        assert_eq!(pt.byte_pos(), 3);
+    }
+
+    #[test]
+    fn pretokenizer_test_61() {
+        let stats = |s, cfg| {
+            let mut pt = Pretokenizer::with_config(s, cfg);
+            pt.by_ref().count();
+            pt.stats()
+        };
+        assert_eq!(stats("", Config::new()), Stats::default());
+
+        let s = stats("#if 0\nx\n#endif\n \t\n\"a\nb\" /**/ //\n  ",
+                      Config::new().skip_region("#if 0", "#endif"));
+        assert_eq!(s, Stats{
+            tokens: 1, line_comments: 1, block_comments: 1, skipped_regions: 1,
+            lines: 7, blank_lines: 2, whitespace_bytes: 9,
+        });
+
+        // Terminators end lines too
+        let mut pt = Pretokenizer::new("a\n\nb\n").on_newline(|_| true);
+        assert_eq!(pt.by_ref().count(), 4);
+        let s = pt.stats();
+        assert_eq!((s.tokens, s.lines, s.blank_lines), (4, 3, 1));
+    }
+
+    #[test]
+    fn pretokenizer_test_62() {
+        // Concatenating everything returned in lossless mode reproduces the
+        // input, whatever the input and configuration.
+        let pieces = ["/", "*", "\"", "\\", "\n", " ", "\t", "a", "#", "??/", "??=",
+                      "#!", "//x", "/*", "*/", "#if 0", "#endif", "// off", "// on",
+                      "\u{e9}", "\r"];
+        let configs = [
+            Config::new(),
+            Config::new().trigraphs(true).directives(true).shebang(Shebang::Skip),
+            Config::new().nested_comments(true).max_comment_depth(2)
+                .skip_region("#if 0", "#endif").tool_directives("off", "on")
+                .shebang(Shebang::Token).pragma("#"),
+        ];
+        let mut rng = Lcg::new(1);
+        for _ in 0..2000 {
+            let src = rng.source(&pieces, 24);
+            for cfg in configs.iter() {
+                let pt = Pretokenizer::with_config(&src, cfg.clone().lossless(true));
+                let out: String = pt.map(|t| t.s).collect();
+                assert_eq!(out, src, "{:?}", cfg);
+                let raw: String = RawPretokenizer::with_config(&src, cfg.clone())
+                    .map(|t| t.s).collect();
+                assert_eq!(raw, src, "{:?}", cfg);
+            }
+        }
+
+        let mut pt = Pretokenizer::with_config("a /* b */\n", Config::new().lossless(true));
+        let v: Vec<_> = pt.by_ref().map(|t| t.kind).collect();
+        assert_eq!(v, vec![Kind::Word, Kind::Whitespace, Kind::Comment, Kind::Newline]);
+        assert_eq!(pt.stats().tokens, 4);
+        assert_eq!(pt.stats().block_comments, 1);
+    }
+
+    #[test]
+    fn pretokenizer_test_63() {
+        // Long runs skipped in bulk, with multibyte characters throughout
+        let long = "\u{e9}\u{2603}x".repeat(100);
+        let src = format!("{0}/{0} \"{0}\\\"{0}\n\" // {0}\n/* {0}\n*{0}/ */ {0}", long);
+        let v: Vec<_> = Pretokenizer::new(&src).collect();
+        assert_eq!(v.len(), 3);
+        assert_eq!(v[0].s, format!("{0}/{0}", long));
+        assert_eq!(v[1].s.len(), 2 * long.len() + 5);
+        assert_eq!((v[1].kind, v[1].line), (Kind::Quoted, 2));
+        assert_eq!((v[2].s, v[2].line), (long.as_str(), 4));
+
+        // Trigraph escapes inside strings, and nested comments
+        let cfg = Config::new().trigraphs(true);
+        let v: Vec<_> = Pretokenizer::with_config("\"a??/\"b\" c??/d", cfg)
+            .map(|t| t.s).collect();
+        assert_eq!(v, vec!["\"a??/\"b\"", "c??/d"]);
+        let cfg = Config::new().nested_comments(true);
+        let v: Vec<_> = Pretokenizer::with_config("/* a /* b */ c */ d /", cfg)
+            .map(|t| t.s).collect();
+        assert_eq!(v, vec!["d", "/"]);
+    }
+
+    #[test]
+    fn pretokenizer_test_64() {
+        // Lazy lines agree with eager ones
+        let src = "a\n/* b\n\n*/ \"c\nd\" e\n\n// f\n\"g\n\u{7}";
+        let cfg = Config::new().detect_control_chars(true).max_token_len(4);
+        let mut eager = Pretokenizer::with_config(src, cfg.clone());
+        let mut lazy = Pretokenizer::with_config(src, cfg.lazy_lines(true));
+        loop {
+            assert_eq!(lazy.line(), eager.line());
+            let (e, l) = (eager.next(), lazy.next());
+            assert_eq!(e.is_some(), l.is_some());
+            let (e, l) = match (e, l) {
+                (Some(e), Some(l)) => (e, l),
+                _ => break,
+            };
+            assert_eq!((l.s, l.offset, l.line), (e.s, e.offset, 0));
+            assert_eq!(lazy.line_at(l.offset + l.s.len() - 1), e.line);
+        }
+        let lines = |pt: &Pretokenizer| -> Vec<usize> {
+            pt.diagnostics().iter().map(|e| e.line()).collect()
+        };
+        assert_eq!(lines(&lazy), lines(&eager));
+        assert_eq!(lines(&lazy), vec![5, 8, 9]);
+        assert_eq!(lazy.stats(), eager.stats());
+        assert_eq!(lazy.line_at(src.len() + 1), 9);
+
+        let mut raw = RawPretokenizer::with_config("\n\n/* x", Config::new().lazy_lines(true));
+        raw.by_ref().count();
+        assert_eq!(raw.diagnostics()[0].line(), 3);
+    }
+
+    #[test]
+    fn pretokenizer_test_67() {
+        // No lines, but the same pretokens
+        let src = "a\n/* b\n\n*/ \"c\nd\" e\n// f\n\"g\n\u{7}";
+        let cfg = Config::new().detect_control_chars(true).max_line_len(2);
+        let want: Vec<_> = tokenize_with(src, cfg.clone()).iter()
+            .map(|t| (t.s, t.offset, 0)).collect();
+        let mut pt = Pretokenizer::with_config(src, cfg.offsets_only(true));
+        assert_eq!(pt.peek().unwrap().line, 0);
+        assert_eq!(pt.line(), 0);
+        let got: Vec<_> = pt.by_ref().map(|t| (t.s, t.offset, t.line)).collect();
+        assert_eq!(got, want);
+        assert_eq!(pt.diagnostics().len(), 6);
+        assert!(pt.diagnostics().iter().all(|e| e.line() == 0));
+        assert_eq!(pt.line_at(8), 4);
+        assert_eq!(pt.stats().lines, 8);
+    }
+
+    #[test]
+    fn pretokenizer_test_68() {
+        // Skipping ends up in the same place as stepping
+        let src = "#!x\na \"b\" /* c */ d\n// e\nf \"g";
+        let configs = [
+            Config::new(),
+            Config::new().shebang(Shebang::Skip).lossless(true),
+            Config::new().lazy_lines(true),
+            Config::new().max_token_len(1),
+        ];
+        for cfg in configs.iter() {
+            for n in 0..10 {
+                let mut want = Pretokenizer::with_config(src, cfg.clone());
+                let stepped = want.by_ref().take(n).count();
+                let mut got = Pretokenizer::with_config(src, cfg.clone());
+                assert_eq!(got.skip_tokens(n), stepped);
+                assert_eq!(got.stats(), want.stats());
+                assert_eq!(got.byte_pos(), want.byte_pos());
+                assert_eq!(got.next(), want.next());
+                assert_eq!(got.diagnostics(), want.diagnostics());
+                let mut by_next = Pretokenizer::with_config(src, cfg.clone());
+                by_next.by_ref().take(n).count();
+                assert_eq!(Pretokenizer::with_config(src, cfg.clone()).nth(n), by_next.next());
+            }
+        }
+        let mut pt = Pretokenizer::new("a b");
+        assert_eq!(pt.peek().unwrap().s, "a");
+        assert_eq!(pt.nth(1).unwrap().s, "b");
+        assert_eq!(pt.nth(1), None);
+    }
+
+    #[test]
+    fn pretokenizer_test_65() {
+        // Scans that pick up where the last left off
+        let v: Vec<_> = Pretokenizer::new("a\"b\"/*c*/d//e\n  /f \"g\" /*h*/")
+            .map(|t| t.s).collect();
+        assert_eq!(v, vec!["a", "\"b\"", "d", "/f", "\"g\""]);
+        let cfg = Config::new().pragma("#pragma").directives(true).skip_region("#if 0", "#endif");
+        let src = "x\n  #pragma once\n  #define Y\n  #if 0\nz\n#endif\n  w/**/";
+        let mut pt = Pretokenizer::with_config(src, cfg);
+        let v: Vec<_> = pt.by_ref().map(|t| (t.s, t.kind)).collect();
+        assert_eq!(v, vec![("x", Kind::Word), ("#pragma once", Kind::Directive),
+                           ("#define Y", Kind::Directive), ("w", Kind::Word)]);
+        assert_eq!(pt.pragmas()[0].s, "#pragma once");
+
+        let mut pt = Pretokenizer::new("a/*x*/b \"c\"");
+        assert_eq!(pt.next().unwrap().s, "a");
+        assert_eq!(pt.seek(1), Ok(()));
+        assert_eq!(pt.next().unwrap().s, "b");
+        assert_eq!(pt.seek(1), Ok(()));
+        assert_eq!(pt.next().unwrap().s, "b");
+    }
+
+    #[test]
+    fn pretokenizer_test_66() {
+        let src = "a b /* c */ d \"e\" f";
+        let mut pt = Pretokenizer::new(src);
+        let mut buf = vec![Pretokenizer::new("z").next().unwrap()];
+        assert_eq!(pt.next_chunk(&mut buf, 0), 0);
+        assert_eq!(pt.peek().unwrap().s, "a");
+        assert_eq!(pt.next_chunk(&mut buf, 2), 2);
+        assert_eq!(pt.next_chunk(&mut buf, 10), 3);
+        assert_eq!(pt.next_chunk(&mut buf, 10), 0);
+        let v: Vec<_> = buf.iter().map(|t| t.s).collect();
+        assert_eq!(v, vec!["z", "a", "b", "d", "\"e\"", "f"]);
+        assert_eq!(&buf[1..], &tokenize(src)[..]);
+
+        let mut pt = Pretokenizer::new("");
+        assert!(pt.peek().is_none());
+        assert_eq!(pt.next_chunk(&mut buf, 1), 0);
+    }
+
+    #[test]
+    fn pretokenizer_test_69() {
+        // Inputs and calls that once panicked
+        let cfg = Config::new().lossless(true).tool_directives("off", "on");
+        let mut pt = Pretokenizer::with_config("a // off\nx", cfg);
+        assert!(pt.seek(4).is_err());
+        let v: Vec<_> = pt.map(|t| (t.s, t.kind)).collect();
+        assert_eq!(v, vec![("// off", Kind::Comment), ("\n", Kind::Newline), ("x", Kind::Opaque)]);
+
+        let mut pt = Pretokenizer::with_config("a/*\u{e9}*/", Config::new().max_bytes(4));
+        assert_eq!(pt.next().unwrap().s, "a");
+        assert_eq!(pt.next(), None);
+        assert_eq!(pt.diagnostics(), &[PretokError::BudgetExhausted{line: 1, span: 3..7}]);
+
+        let mut pt = Pretokenizer::with_config("*", Config::new().max_line_len(0));
+        assert!(pt.next().is_some());
+        assert!(pt.next().is_none());
+        assert!(pt.next().is_none());
+        assert_eq!(pt.diagnostics().len(), 1);
+
+        assert_eq!(Pretokenizer::before("a \u{e9}", 99).next().unwrap().s, "\u{e9}");
+        assert_eq!(Pretokenizer::before("a \u{e9}", 3).next().unwrap().s, "a");
+    }
+
+    #[test]
+    fn pretokenizer_test_70() {
+        // Wherever a split succeeds, the parts rejoin to the whole.
+        let pieces = ["/", "*", "\"", "\\", "\n", " ", "a", "//", "/*", "*/", "\u{e9}"];
+        let configs = [Config::new(), Config::new().lossless(true),
+                       Config::new().lazy_lines(true).nested_comments(true)];
+        let mut rng = Lcg::new(7);
+        let mut splits = 0;
+        for _ in 0..2000 {
+            let src = rng.source(&pieces, 16);
+            let cfg = configs[rng.below(configs.len())].clone();
+            let want: Vec<_> = Pretokenizer::with_config(&src, cfg.clone()).collect();
+            for offset in 0..=src.len() + 1 {
+                let pt = Pretokenizer::with_config(&src, cfg.clone());
+                if let Ok((head, tail)) = pt.split_at(offset) {
+                    let got: Vec<_> = head.chain(tail).collect();
+                    assert_eq!(got, want, "{:?} at {}", src, offset);
+                    splits += 1;
+                }
+            }
+        }
+        assert!(splits > 5000);
+
+        // Splitting after the position, and not before it
+        let mut pt = Pretokenizer::new("a b c");
+        assert_eq!(pt.peek().unwrap().s, "a");
+        let (head, mut tail) = pt.split_at(2).unwrap();
+        assert_eq!(head.map(|t| t.s).collect::<Vec<_>>(), vec!["a"]);
+        assert_eq!(tail.next().unwrap().s, "b");
+        assert_eq!(tail.split_at(2).unwrap_err(), SeekError::OutOfRange{offset: 2});
+
+        // Diagnostics belong to the part that found them.
+        let (head, tail) = Pretokenizer::new("\"a\" \"b").split_at(4).unwrap();
+        let mut head = head.strict();
+        assert!(head.all(|r| r.is_ok()));
+        assert!(tail.strict().any(|r| r.is_err()));
+    }
+
+    #[test]
+    fn pretokenizer_test_71() {
+        let src = "\u{FEFF}#!/bin/x\n\u{FEFF}y";
+        let v: Vec<_> = Pretokenizer::new(src).map(|t| (t.s, t.offset)).collect();
+        assert_eq!(v, vec![("#!/bin/x", 3), ("\u{FEFF}y", 12)]);
+
+        // The shebang still follows a mark, and lossless input is covered.
+        let cfg = Config::new().shebang(Shebang::Token).lossless(true);
+        let v: Vec<_> = Pretokenizer::with_config(src, cfg.clone()).map(|t| t.kind).collect();
+        assert_eq!(v, vec![Kind::Comment, Kind::Shebang, Kind::Newline, Kind::Word]);
+        let v: Vec<_> = Pretokenizer::with_config(src, cfg.clone().bom(Bom::Token))
+            .map(|t| t.kind).collect();
+        assert_eq!(v, vec![Kind::Bom, Kind::Shebang, Kind::Newline, Kind::Word]);
+        let v: Vec<_> = Pretokenizer::with_config(src, cfg.bom(Bom::Text))
+            .map(|t| t.s).collect();
+        assert_eq!(v, vec!["\u{FEFF}#!/bin/x", "\n", "\u{FEFF}y"]);
+        assert!(Pretokenizer::new("\u{FEFF}").next().is_none());
+    }
+
+    #[test]
+    fn pretokenizer_test_72() {
+        // Lines inside block comments are checked, and those in strings
+        // aren't.
+        let src = "x /* a  \r\n b */\t\r\n\"s  \n\" \n \t\n  y  ";
+        let want = [
+            PretokError::TrailingWhitespace{line: 1, span: 6..8},
+            PretokError::TrailingWhitespace{line: 2, span: 15..16},
+            PretokError::TrailingWhitespace{line: 4, span: 24..25},
+            PretokError::TabAfterSpace{line: 5, span: 26..28},
+            PretokError::WhitespaceOnlyLine{line: 5, span: 26..28},
+            PretokError::TrailingWhitespace{line: 6, span: 32..34},
+        ];
+        for cfg in [Config::new(), Config::new().lossless(true)].iter() {
+            let mut pt = Pretokenizer::with_config(src, cfg.clone().detect_whitespace(true));
+            pt.by_ref().count();
+            assert_eq!(pt.diagnostics(), &want[..]);
+        }
+        let pt = Pretokenizer::with_config(src, Config::new().detect_whitespace(true));
+        let (_, mut tail) = pt.split_at(26).unwrap();
+        tail.by_ref().count();
+        assert_eq!(tail.diagnostics(), &want[3..]);
+        assert_eq!(want[4].to_string(), "line 5 holds only whitespace");
+    }
+
+    #[test]
+    fn pretokenizer_test_73() {
+        // Strings and comments keep zero-width characters.
+        let src = "\u{FEFF}a\u{FEFF}b \"\u{200C}\" /*\u{200B}*/\u{200B}\u{200B}\n\u{2000}";
+        let cfg = Config::new().zero_width(ZeroWidth::Strip);
+        let v: Vec<_> = Pretokenizer::with_config(src, cfg.clone()).map(|t| t.s).collect();
+        assert_eq!(v, vec!["a", "b", "\"\u{200C}\"", "\u{2000}"]);
+        let pieces: String = Pretokenizer::with_config(src, cfg.lossless(true))
+            .map(|t| t.s).collect();
+        assert_eq!(pieces, src);
+
+        // A mark at the start isn't reported.
+        let cfg = Config::new().zero_width(ZeroWidth::Diagnose).bom(Bom::Text);
+        let mut pt = Pretokenizer::with_config(src, cfg);
+        assert_eq!(pt.by_ref().count(), 4);
+        assert_eq!(pt.diagnostics(), &[
+            PretokError::ZeroWidth{ch: '\u{FEFF}', line: 1, span: 4..7},
+            PretokError::ZeroWidth{ch: '\u{200B}', line: 1, span: 22..25},
+            PretokError::ZeroWidth{ch: '\u{200B}', line: 1, span: 25..28},
+        ]);
+        assert_eq!(pt.diagnostics()[0].to_string(), "zero-width character U+FEFF at line 1");
+    }
+
+    #[test]
+    fn pretokenizer_test_74() {
+        let src = "a\0\0b\n\"\0\"\n\0";
+        let v: Vec<_> = Pretokenizer::new(src).map(|t| t.s).collect();
+        assert_eq!(v, vec!["a\0\0b", "\"\0\"", "\0"]);
+
+        let cfg = Config::new().nul(Nul::Whitespace);
+        let v: Vec<_> = Pretokenizer::with_config(src, cfg.clone())
+            .map(|t| (t.s, t.line)).collect();
+        assert_eq!(v, vec![("a", 1), ("b", 1), ("\"\0\"", 2)]);
+        let pieces: String = Pretokenizer::with_config(src, cfg.lossless(true))
+            .map(|t| t.s).collect();
+        assert_eq!(pieces, src);
+
+        let mut pt = Pretokenizer::with_config(src, Config::new().nul(Nul::Reject));
+        assert_eq!(pt.by_ref().count(), 3);
+        assert_eq!(pt.diagnostics(), &[
+            PretokError::Nul{line: 1, span: 1..2},
+            PretokError::Nul{line: 1, span: 2..3},
+            PretokError::Nul{line: 2, span: 6..7},
+            PretokError::Nul{line: 3, span: 9..10},
+        ]);
+        assert_eq!(pt.diagnostics()[0].to_string(), "NUL character at line 1");
     }
 
     #[test]
-    fn pretokenizer_test_7() {
-        let mut pt = Pretokenizer::new("\n/* */\nfoo");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 7);
-        assert_eq!(t.line, 3);
-        assert_eq!(t.s, "foo");
+    fn pretokenizer_test_75() {
+        let cfg = Config::new().detect_line_endings(true);
+        let cases: &[(&str, &[PretokError])] = &[
+            ("a\nb\nc", &[]),
+            ("a\r\nb\r\n", &[]),
+            ("a\rb\r", &[]),
+            ("a\nb /* \r\n */ c\rd\n", &[PretokError::MixedLineEndings{line: 2, span: 7..9}]),
+            ("a\r\n\"b\rc\"\r\n\n", &[PretokError::MixedLineEndings{line: 2, span: 5..6}]),
+            ("\r\n\r\r\n", &[PretokError::MixedLineEndings{line: 2, span: 2..3}]),
+        ];
+        for (src, want) in cases {
+            let mut pt = Pretokenizer::with_config(src, cfg.clone());
+            pt.by_ref().count();
+            assert_eq!(pt.diagnostics(), *want, "{:?}", src);
+            let mut pt = Pretokenizer::with_config(src, cfg.clone().lossless(true));
+            pt.by_ref().count();
+            assert_eq!(pt.diagnostics(), *want, "{:?}", src);
+        }
+        assert_eq!(cases[3].1[0].to_string(), "mixed line endings at line 2");
     }
 
     #[test]
-    fn pretokenizer_test_8() {
-        let mut pt = Pretokenizer::new("// bar");
-        let t = pt.next();
-        assert!(t.is_none());
+    fn pretokenizer_test_76() {
+        let src = " \ta\n\t b\n\t\t// c\n \t\n\t \n  d\t e \t\n\t \"x\n\t y\"";
+        let want = [
+            PretokError::MixedIndentation{line: 1, span: 0..2},
+            PretokError::MixedIndentation{line: 2, span: 4..6},
+            PretokError::MixedIndentation{line: 7, span: 30..32},
+        ];
+        for lossless in [false, true] {
+            let cfg = Config::new().detect_mixed_indent(true).lossless(lossless);
+            let mut pt = Pretokenizer::with_config(src, cfg);
+            pt.by_ref().count();
+            assert_eq!(pt.diagnostics(), &want);
+        }
+        assert_eq!(want[0].to_string(), "tabs and spaces mixed in indentation at line 1");
+
+        // Both checks report a tab after a space.
+        let cfg = Config::new().detect_mixed_indent(true).detect_whitespace(true);
+        let mut pt = Pretokenizer::with_config(" \ta", cfg);
+        pt.by_ref().count();
+        assert_eq!(pt.diagnostics(), &[
+            PretokError::TabAfterSpace{line: 1, span: 0..2},
+            PretokError::MixedIndentation{line: 1, span: 0..2},
+        ]);
     }
 
     #[test]
-    fn pretokenizer_test_9() {
-        let mut pt = Pretokenizer::new("\n// bar");
-        let t = pt.next();
-        assert!(t.is_none());
+    fn pretokenizer_test_77() {
+        let src = "a\n/* b\n*/ c \u{202E}\nlonger \"d";
+        let cfg = Config::new().base(Base::Zero).max_line_len(5).detect_bidi(true);
+        for lazy in [false, true] {
+            let mut pt = Pretokenizer::with_config(src, cfg.clone().lazy_lines(lazy));
+            assert_eq!(pt.line(), 0);
+            let toks: Vec<_> = pt.by_ref().collect();
+            assert_eq!(pt.line(), 3);
+            assert_eq!(pt.line_at(2), 1);
+            assert_eq!(pt.stats().lines, 4);
+            assert_eq!(pt.diagnostics(), &[
+                PretokError::LineTooLong{line: 2, span: 7..15},
+                PretokError::BidiControl{ch: '\u{202E}', line: 2, span: 12..15},
+                PretokError::LineTooLong{line: 3, span: 16..25},
+                PretokError::UnterminatedString{line: 3, span: 23..25},
+            ]);
+            let lines: Vec<_> = toks.iter().map(|t| pt.start_line(t)).collect();
+            assert_eq!(lines, vec![0, 2, 2, 3, 3]);
+            pt.seek(10).unwrap();
+            assert_eq!(pt.line(), 2);
+        }
+
+        let (_, mut tail) = Pretokenizer::with_config(src, cfg.clone()).split_at(10).unwrap();
+        assert_eq!(tail.next().map(|t| (t.s, t.line)), Some(("c", 2)));
+        assert_eq!(folding_ranges_with(src, cfg.clone()),
+                   vec![FoldingRange{ start_line: 1, end_line: 2, kind: FoldKind::Comment }]);
+        assert_eq!(check_balance_with("(\n\"", cfg.clone()), vec![
+            PretokError::UnclosedBracket{ch: '(', line: 0, span: 0..1},
+            PretokError::UnterminatedString{line: 1, span: 2..3},
+        ]);
+        assert_eq!(metrics_with(src, cfg.clone()), metrics(src));
+        assert_eq!(long_lines_with(src, 5, cfg.clone())[0].line, 2);
+        assert_eq!(Pretokenizer::before_with_config(src, 10, cfg).next().unwrap().line, 0);
     }
 
     #[test]
-    fn pretokenizer_test_10() {
-        let mut pt = Pretokenizer::new("// bar\n");
-        let t = pt.next();
-        assert!(t.is_none());
+    fn pretokenizer_test_78() {
+        let src = "f(a,b)+=-1;/ /*c;*/ \"d;\"\u{2192}\u{2192}x\n;";
+        let cfg = Config::new().punctuation("(),+=;").punctuation("\u{2192}/");
+        let v: Vec<_> = Pretokenizer::with_config(src, cfg.clone())
+            .map(|t| (t.s, t.line)).collect();
+        assert_eq!(v, vec![("f", 1), ("(", 1), ("a", 1), (",", 1), ("b", 1), (")", 1),
+                           ("+", 1), ("=", 1), ("-1", 1), (";", 1), ("/", 1), ("\"d;\"", 1),
+                           ("\u{2192}", 1), ("\u{2192}", 1), ("x", 1), (";", 2)]);
+        let pieces: String = Pretokenizer::with_config(src, cfg.lossless(true))
+            .map(|t| t.s).collect();
+        assert_eq!(pieces, src);
     }
 
     #[test]
-    fn pretokenizer_test_11() {
-        let mut pt = Pretokenizer::new("// bar\nfoo");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 7);
-        assert_eq!(t.line, 2);
-        assert_eq!(t.s, "foo");
+    fn pretokenizer_test_79() {
+        let src = "a,b\u{3001}\u{3002}c ,/*,*/\"d,\"\n,,e\u{3001}//,\n\u{e9}";
+        let cfg = Config::new().separators(",").separators("\u{3001}\n");
+        let v: Vec<_> = Pretokenizer::with_config(src, cfg.clone())
+            .map(|t| (t.s, t.line)).collect();
+        assert_eq!(v, vec![("a", 1), ("b", 1), ("\u{3002}c", 1), ("\"d,\"", 1), ("e", 2),
+                           ("\u{e9}", 3)]);
+        let v: Vec<_> = Pretokenizer::with_config(src, cfg.lossless(true))
+            .map(|t| (t.kind, t.s)).collect();
+        assert_eq!(v[..4], [(Kind::Word, "a"), (Kind::Whitespace, ","), (Kind::Word, "b"),
+                            (Kind::Whitespace, "\u{3001}")]);
+        assert_eq!(v.iter().map(|t| t.1).collect::<String>(), src);
     }
 
     #[test]
-    fn pretokenizer_test_12() {
-        let mut pt = Pretokenizer::new("// bar\n\nfoo");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 8);
-        assert_eq!(t.line, 3);
-        assert_eq!(t.s, "foo");
+    fn pretokenizer_test_80() {
+        let src = "a-b+c \"x-y\" /*p-q*/ \u{e9}-\u{e9}.\n-";
+        let pred = |c: char| c.is_alphabetic() || c == '-';
+        let v: Vec<_> = Pretokenizer::new(src).word_chars(pred).map(|t| (t.s, t.line)).collect();
+        assert_eq!(v, vec![("a-b", 1), ("+", 1), ("c", 1), ("\"x-y\"", 1),
+                           ("\u{e9}-\u{e9}", 1), (".", 1), ("-", 2)]);
+        let pieces: String = Pretokenizer::with_config(src, Config::new().lossless(true))
+            .word_chars(pred).map(|t| t.s).collect();
+        assert_eq!(pieces, src);
+
+        // The predicate sees only word characters.
+        let mut seen = String::new();
+        Pretokenizer::new("ab \"c\" d").word_chars(|c| { seen.push(c); true }).count();
+        assert_eq!(seen, "abd");
     }
 
     #[test]
-    fn pretokenizer_test_13() {
-        let mut pt = Pretokenizer::new("\"");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 0);
-        assert_eq!(t.line, 1);
-        assert_eq!(t.s, "\"");
+    fn pretokenizer_test_81() {
+        let src = "a=>=b<< c<<=\u{2192}=>d !== x/ =//=\n";
+        let cfg = Config::new().operators(&["=>", "<<=", "<<", "\u{2192}="])
+            .operators(&["!==", "!"]);
+        let v: Vec<_> = Pretokenizer::with_config(src, cfg.clone()).map(|t| t.s).collect();
+        assert_eq!(v, vec!["a", "=>", "=", "b", "<<", "c", "<<=", "\u{2192}=", ">", "d",
+                           "!==", "x/", "="]);
+        let pieces: String = Pretokenizer::with_config(src, cfg.lossless(true))
+            .map(|t| t.s).collect();
+        assert_eq!(pieces, src);
     }
 
     #[test]
-    fn pretokenizer_test_14() {
-        let mut pt = Pretokenizer::new("\"\"");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 0);
-        assert_eq!(t.line, 1);
-        assert_eq!(t.s, "\"\"");
+    fn pretokenizer_test_82() {
+        assert_eq!(number_len("1.5e+10+x"), Some(7));
+        assert_eq!(number_len("0x1p-3-1"), Some(6));
+        assert_eq!(number_len("0x1e+3"), Some(4));
+        assert_eq!(number_len("1_000u32)"), Some(8));
+        assert_eq!(number_len("0..10"), Some(1));
+        assert_eq!(number_len("x1"), None);
+
+        let src = "a[0..10]=1.5e+10*0x1p-3-1_000;x1+2";
+        fn split(pt: Pretokenizer<'_>) -> Vec<&str> {
+            pt.map(|t| t.s).collect()
+        }
+        let cfg = Config::new().punctuation("[]=*;+-").operators(&[".."]);
+        assert_eq!(split(Pretokenizer::with_config(src, cfg.clone())),
+                   vec!["a", "[", "0", "..", "10", "]", "=", "1.5e+10", "*", "0x1p-3", "-",
+                        "1_000", ";", "x1", "+", "2"]);
+        assert_eq!(split(Pretokenizer::with_config("1.5e+10", cfg.split_numbers(true))),
+                   vec!["1", ".", "5e", "+", "10"]);
+        assert_eq!(split(Pretokenizer::new(src).word_chars(char::is_alphanumeric)),
+                   vec!["a", "[", "0", "..", "10", "]=", "1.5e+10", "*", "0x1p-3", "-",
+                        "1_000", ";", "x1", "+", "2"]);
+        // Without a splitting mode, words are whole.
+        assert_eq!(split(Pretokenizer::new("1+2 3.x")), vec!["1+2", "3.x"]);
     }
 
     #[test]
-    fn pretokenizer_test_15() {
-        let mut pt = Pretokenizer::new("\"x\"");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 0);
-        assert_eq!(t.line, 1);
-        assert_eq!(t.s, "\"x\"");
+    fn pretokenizer_test_83() {
+        let src = "C a \"b\nx C\"\n\u{e9}\tC c\r\n  C\nab C d\n\t!e\nf !g\n!";
+        let cfg = Config::new().column_comment('C', Some(3)).column_comment('!', None)
+            .lossless(true);
+        let mut pt = Pretokenizer::with_config(src, cfg);
+        let v: Vec<_> = pt.by_ref().map(|t| (t.kind, t.s, t.line))
+            .filter(|t| t.0 != Kind::Whitespace && t.0 != Kind::Newline).collect();
+        use Kind::*;
+        assert_eq!(v, vec![(Word, "C", 1), (Word, "a", 1), (Quoted, "\"b\nx C\"", 2),
+                           (Word, "\u{e9}", 3), (Comment, "C c\r", 3), (Comment, "C", 4),
+                           (Word, "ab", 5), (Word, "C", 5), (Word, "d", 5), (Comment, "!e", 6),
+                           (Word, "f", 7), (Word, "!g", 7), (Comment, "!", 8)]);
+        assert_eq!((pt.stats().line_comments, pt.stats().skipped_regions), (4, 0));
     }
 
     #[test]
-    fn pretokenizer_test_16() {
-        let mut pt = Pretokenizer::new("\" x\"");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 0);
-        assert_eq!(t.line, 1);
-        assert_eq!(t.s, "\" x\"");
+    fn pretokenizer_test_84() {
+        let src = "a\\ b \\\\ c\\\\\\ d \"e\\ f\" g\\\tx\\\n";
+        let words = |cfg| -> Vec<_> { Pretokenizer::with_config(src, cfg).map(|t| t.s).collect() };
+        assert_eq!(words(Config::new().escaped_spaces(true)),
+                   vec!["a\\ b", "\\\\", "c\\\\\\ d", "\"e\\ f\"", "g\\", "x\\"]);
+        assert_eq!(words(Config::new()),
+                   vec!["a\\", "b", "\\\\", "c\\\\\\", "d", "\"e\\ f\"", "g\\", "x\\"]);
     }
 
     #[test]
-    fn pretokenizer_test_17() {
-        let mut pt = Pretokenizer::new("\" x x \"");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 0);
-        assert_eq!(t.line, 1);
-        assert_eq!(t.s, "\" x x \"");
+    fn pretokenizer_test_85() {
+        let src = "%a\n\"b%\"%c\r\nd-- e // f\n-g --\n--";
+        let cfg = Config::new().line_comment("%").line_comment("--").line_comment("")
+            .lossless(true);
+        let mut pt = Pretokenizer::with_config(src, cfg);
+        let v: Vec<_> = pt.by_ref().map(|t| (t.kind, t.s, t.line))
+            .filter(|t| t.0 != Kind::Whitespace && t.0 != Kind::Newline).collect();
+        use Kind::*;
+        assert_eq!(v, vec![(Comment, "%a", 1), (Quoted, "\"b%\"", 2), (Comment, "%c\r", 2),
+                           (Word, "d", 3), (Comment, "-- e // f", 3), (Word, "-g", 4),
+                           (Comment, "--", 4), (Comment, "--", 5)]);
+        assert_eq!((pt.stats().line_comments, pt.stats().skipped_regions), (5, 0));
     }
 
     #[test]
-    fn pretokenizer_test_18() {
-        let mut pt = Pretokenizer::new("//\" x x \"");
-        let t = pt.next();
-        assert!(t.is_none());
+    fn pretokenizer_test_86() {
+        let src = "Rem\n10 REM: a\n20 A$=\"REM\": rem \"b\n\t x rem y ' z\n30: Rem_1\n40 REMé";
+        let cfg = Config::new().keyword_comment("rem").line_comment("'").lossless(true);
+        let mut pt = Pretokenizer::with_config(src, cfg);
+        let v: Vec<_> = pt.by_ref().map(|t| (t.kind, t.s, t.line))
+            .filter(|t| t.0 != Kind::Whitespace && t.0 != Kind::Newline).collect();
+        use Kind::*;
+        assert_eq!(v, vec![(Comment, "Rem", 1), (Word, "10", 2), (Comment, "REM: a", 2),
+                           (Word, "20", 3), (Word, "A$=", 3), (Quoted, "\"REM\"", 3),
+                           (Word, ":", 3), (Comment, "rem \"b", 3), (Word, "x", 4),
+                           (Word, "rem", 4), (Word, "y", 4), (Comment, "' z", 4), (Word, "30", 5),
+                           (Word, ":", 5), (Word, "Rem_1", 5), (Word, "40", 6), (Word, "REMé", 6)]);
+        assert_eq!((pt.stats().line_comments, pt.stats().skipped_regions), (4, 0));
     }
 
     #[test]
-    fn pretokenizer_test_19() {
-        let mut pt = Pretokenizer::new("\"// x x \"");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 0);
-        assert_eq!(t.line, 1);
-        assert_eq!(t.s, "\"// x x \"");
+    fn pretokenizer_test_87() {
+        let src = "ab \"c d\"\ne";
+        let (head, tail) = Pretokenizer::new(src).split_at(9).unwrap();
+        assert_eq!((head.source(), tail.source()), ("ab \"c d\"\n", src));
+        let toks: Vec<_> = Pretokenizer::new(src).collect();
+        let q = &toks[1];
+        assert_eq!((q.span(), toks[2].span()), (3..8, 9..10));
+        assert_eq!(q.to_relative(q.span()), Some(0..5));
+        assert_eq!(q.to_relative(8..8), Some(5..5));
+        assert_eq!(q.to_relative(2..4), None);
+        assert_eq!(q.to_relative(7..9), None);
+        assert_eq!(q.to_relative(toks[0].span()), None);
+        assert_eq!(q.to_absolute(1..4), 4..7);
+        assert_eq!(&src[q.to_absolute(1..4)], &q.s[1..4]);
     }
 
     #[test]
-    fn pretokenizer_test_20() {
-        let mut pt = Pretokenizer::new("\" /* x x */ \"");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 0);
-        assert_eq!(t.line, 1);
-        assert_eq!(t.s, "\" /* x x */ \"");
+    fn pretokenizer_test_88() {
+        let src = "#x \"a\n  b \"c\nd\"\" e\n#y \"f\ng";
+        let mut pt = Pretokenizer::new(src);
+        assert_eq!(pt.peek_line(), "#x \"a");
+        pt.skip_line();
+        assert_eq!((pt.peek_line(), pt.line()), ("", 1));
+        assert_eq!(pt.next().unwrap().s, "b");
+        assert_eq!(pt.peek().unwrap().s, "\"c\nd\"");
+        assert_eq!((pt.peek_line(), pt.line()), ("\"c", 2));
+        pt.skip_line();
+        let d = pt.next().unwrap();
+        assert_eq!((d.s, d.line), ("d", 3));
+        assert_eq!(pt.next().unwrap().s, "\"\"");
+        assert_eq!(pt.next().unwrap().s, "e");
+        pt.skip_line();
+        assert_eq!(pt.next().unwrap().s, "#y");
+        assert_eq!(pt.peek_line(), " \"f");
+        pt.skip_line();
+        let g = pt.next().unwrap();
+        assert_eq!((g.s, g.line), ("g", 5));
+        pt.skip_line();
+        assert_eq!(pt.peek_line(), "");
+        assert!(pt.next().is_none());
+
+        let mut pt = Pretokenizer::with_config(src, Config::new().lazy_lines(true));
+        pt.skip_line();
+        pt.skip_line();
+        pt.next();
+        pt.skip_line();
+        assert_eq!((pt.next().unwrap().s, pt.line()), ("d", 3));
     }
 
     #[test]
-    fn pretokenizer_test_21() {
-        let mut pt = Pretokenizer::new("\" \\\" x \"");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 0);
-        assert_eq!(t.line, 1);
-        assert_eq!(t.s, "\" \\\" x \"");
+    fn pretokenizer_test_89() {
+        let src = "f(x; /* a\n */ y \"b\nc\" z\nw; v\n\nu";
+        for cfg in [Config::new(), Config::new().detect_control_chars(true),
+                    Config::new().lazy_lines(true)] {
+            let mut pt = Pretokenizer::with_config(src, cfg);
+            assert_eq!(pt.skip_until(|t| t.s.ends_with(';')), 0);
+            assert_eq!(pt.next().unwrap().s, "f(x;");
+            assert_eq!(pt.skip_past_line_end(), 0);
+            assert_eq!(pt.peek().unwrap().s, "y");
+            assert_eq!(pt.skip_past_line_end(), 3);
+            assert_eq!(pt.peek().unwrap().s, "w;");
+            assert_eq!(pt.skip_past_line_end(), 2);
+            let u = pt.next().unwrap();
+            assert_eq!((u.s, pt.line_at(u.offset)), ("u", 6));
+            assert_eq!(pt.skip_past_line_end(), 0);
+            assert_eq!(pt.stats().tokens, 7);
+            assert!(pt.next().is_none());
+        }
     }
 
     #[test]
-    fn pretokenizer_test_22() {
-        let mut pt = Pretokenizer::new("\" \\");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 0);
-        assert_eq!(t.line, 1);
-        assert_eq!(t.s, "\" \\");
+    fn pretokenizer_test_90() {
+        let src = "#!/bin/x\na /* b */ \"c\nd\" e // f\n\u{feff}g\th \"i";
+        let cfgs = [Config::new(), Config::new().lossless(true), Config::new().max_tokens(3),
+                    Config::new().shebang(Shebang::Skip).lazy_lines(true),
+                    Config::new().punctuation("/\"").detect_control_chars(true),
+                    Config::new().tool_directives("f", "on").directives(true)];
+        for cfg in cfgs {
+            let n = Pretokenizer::with_config(src, cfg.clone()).count();
+            assert_eq!(n, tokenize_with(src, cfg.clone()).len());
+            assert_eq!(n, count_tokens_with(src, cfg.clone()));
+            let mut pt = Pretokenizer::with_config(src, cfg);
+            pt.peek();
+            assert_eq!(pt.count(), n);
+        }
+        let mut pt = Pretokenizer::new(src).filter_tokens(|t| Some(t).filter(|t| t.s != "e"));
+        assert_eq!(pt.clone().count(), count_tokens(src) - 1);
+        pt.by_ref().count();
+        assert_eq!(pt.count(), 0);
+
+        // Every profile counts what it collects.
+        let pieces = ["\n", "\t", " ", "a", "#", "/*", "*/", "//", "\"", "'", "[b]", ":", "\\",
+                      "$(x)", ";", "\r\n"];
+        let mut rng = Lcg::new(13);
+        for cfg in [Config::new(), Config::jsonc(), Config::toml(), Config::makefile(),
+                    Config::css()] {
+            assert_eq!(count_tokens_with("\n\t", cfg.clone()),
+                       tokenize_with("\n\t", cfg.clone()).len());
+            for _ in 0..300 {
+                let src = rng.source(&pieces, 20);
+                let n = tokenize_with(&src, cfg.clone()).len();
+                assert_eq!(Pretokenizer::with_config(&src, cfg.clone()).count(), n, "{:?}", src);
+            }
+        }
     }
 
     #[test]
-    fn pretokenizer_test_23() {
-        let mut pt = Pretokenizer::new("\" \\\"");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 0);
-        assert_eq!(t.line, 1);
-        assert_eq!(t.s, "\" \\\"");
+    fn pretokenizer_test_91() {
+        let src = "/* cfg */{\n  \"k\\\"[\": [true,{}],\n  \"t\": \"\u{1}\", // x\n}";
+        let mut pt = Pretokenizer::with_config(src, Config::jsonc());
+        let v: Vec<_> = pt.by_ref().map(|t| (t.kind, t.s, t.line)).collect();
+        use Kind::*;
+        assert_eq!(v, vec![(Word, "{", 1), (Quoted, "\"k\\\"[\"", 2), (Word, ":", 2),
+                           (Word, "[", 2), (Word, "true", 2), (Word, ",", 2), (Word, "{", 2),
+                           (Word, "}", 2), (Word, "]", 2), (Word, ",", 2), (Quoted, "\"t\"", 3),
+                           (Word, ":", 3), (Quoted, "\"\u{1}\"", 3), (Word, ",", 3),
+                           (Word, "}", 4)]);
+        assert_eq!(pt.diagnostics(), &[PretokError::ControlChar{ ch: '\u{1}', line: 3,
+                                                                 span: 40..41 }]);
     }
 
     #[test]
-    fn pretokenizer_test_24() {
-        // Found by fuzz testing
-        let mut pt = Pretokenizer::new("\" x\nx\"");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 0);
-        // tokens that span lines are reported on the
-        // last line of the token.
-        assert_eq!(t.line, 2);
-        assert_eq!(t.s, "\" x\nx\"");
+    fn pretokenizer_test_92() {
+        let src = concat!("a = \"x\\\"#\" # c\n", "b = \"\"\"1\\\"\"\"\n2\"\"\"\"\"\n",
+                          "c = '''3'''''; d\n", "url = http://e/* ; f\n", "g = 'C:\\d\\'");
+        let cfg = Config::toml().line_comment(";").lossless(true);
+        let mut pt = Pretokenizer::with_config(src, cfg);
+        let v: Vec<_> = pt.by_ref().map(|t| (t.kind, t.s, t.line))
+            .filter(|t| t.0 != Kind::Whitespace && t.0 != Kind::Newline).collect();
+        use Kind::*;
+        assert_eq!(v, vec![(Word, "a", 1), (Word, "=", 1), (Quoted, "\"x\\\"#\"", 1),
+                           (Comment, "# c", 1), (Word, "b", 2), (Word, "=", 2),
+                           (Quoted, "\"\"\"1\\\"\"\"\n2\"\"\"\"\"", 3), (Word, "c", 4),
+                           (Word, "=", 4), (Quoted, "'''3'''''", 4), (Comment, "; d", 4),
+                           (Word, "url", 5), (Word, "=", 5), (Word, "http://e/*", 5),
+                           (Comment, "; f", 5), (Word, "g", 6), (Word, "=", 6),
+                           (Quoted, "'C:\\d\\'", 6)]);
+        assert!(pt.diagnostics().is_empty());
+        assert_eq!((pt.stats().line_comments, pt.stats().skipped_regions), (3, 0));
+
+        let mut pt = Pretokenizer::with_config("x\n'''a\n'' ", Config::toml());
+        assert_eq!(pt.by_ref().map(|t| t.line).collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(pt.diagnostics(), &[PretokError::UnterminatedString{ line: 2, span: 2..10 }]);
     }
 
     #[test]
-    fn pretokenizer_test_25() {
-        let mut pt = Pretokenizer::new("x//x");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 0);
-        assert_eq!(t.line, 1);
-        assert_eq!(t.s, "x");
+    fn pretokenizer_test_93() {
+        let src = "\tx a\\b \\\r\n\"c\\\nd\"\\\n\n\t\t\\\ny\\";
+        let cfg = Config::new().line_continuation(true).recipe_tabs(true).lossless(true);
+        let v: Vec<_> = Pretokenizer::with_config(src, cfg).map(|t| (t.kind, t.s, t.line))
+            .collect();
+        use Kind::*;
+        assert_eq!(v, vec![(Word, "\t", 1), (Word, "x", 1), (Whitespace, " ", 1),
+                           (Word, "a\\b", 1), (Whitespace, " \\\r\n", 2),
+                           (Quoted, "\"c\\\nd\"", 3), (Whitespace, "\\\n", 4), (Newline, "\n", 4),
+                           (Word, "\t", 5), (Whitespace, "\t\\\n", 6), (Word, "y\\", 6)]);
+        let v: Vec<_> = Pretokenizer::with_config(src, Config::makefile().lazy_lines(true))
+            .map(|t| t.s).collect();
+        assert_eq!(v, vec!["\t", "x", "a\\b", "\"c\\\nd\"", "\t", "y\\"]);
+        let mut pt = Pretokenizer::with_config("a # b\n# c", Config::makefile());
+        pt.by_ref().count();
+        assert_eq!((pt.stats().line_comments, pt.stats().skipped_regions), (2, 0));
+        let v: Vec<_> = Pretokenizer::with_config("\u{feff}\ta", Config::new().recipe_tabs(true))
+            .map(|t| t.s).collect();
+        assert_eq!(v, vec!["\t", "a"]);
     }
 
     #[test]
-    fn pretokenizer_test_26() {
-        let mut pt = Pretokenizer::new("x/*x*/");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 0);
-        assert_eq!(t.line, 1);
-        assert_eq!(t.s, "x");
+    fn pretokenizer_test_94() {
+        let src = "x{a:url(a;b)c;b:myurl(d;e);c:url(;d:url( f g) // h\n'i\\'j'/**/'k";
+        let mut pt = Pretokenizer::with_config(src, Config::css());
+        let v: Vec<_> = pt.by_ref().map(|t| (t.s, t.line)).collect();
+        assert_eq!(v, vec![("x", 1), ("{", 1), ("a", 1), (":", 1), ("url(a;b)", 1), ("c", 1),
+                           (";", 1), ("b", 1), (":", 1), ("myurl(d", 1), (";", 1), ("e)", 1),
+                           (";", 1), ("c", 1), (":", 1), ("url(;d:url(", 1), ("f", 1),
+                           ("g)", 1), ("//", 1), ("h", 1), ("'i\\'j'", 2), ("'k", 2)]);
+        assert_eq!(pt.diagnostics(), &[PretokError::UnterminatedString{ line: 2, span: 61..63 }]);
+        let cfg = Config::new().url_tokens(true);
+        let v: Vec<_> = Pretokenizer::with_config("-url(a)url(b)", cfg).map(|t| t.s).collect();
+        assert_eq!(v, vec!["-url(a)", "url(b)"]);
+        let cfg = Config::new().url_tokens(true);
+        let v: Vec<_> = Pretokenizer::with_config("(url(a))", cfg).map(|t| t.s).collect();
+        assert_eq!(v, vec!["(", "url(a)", ")"]);
     }
 
     #[test]
-    fn pretokenizer_test_27() {
-        let mut pt = Pretokenizer::new("x/*y*/z");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 0);
-        assert_eq!(t.line, 1);
-        assert_eq!(t.s, "x");
-        // Now get the z.
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 6);
-        assert_eq!(t.line, 1);
-        assert_eq!(t.s, "z");
+    fn pretokenizer_test_95() {
+        let src = "\u{7f}a\u{1}\u{2}b,\u{fffd} 12\u{1b}[0m /* \u{1} */ \"\u{fffd}\"\u{0}\n\u{1}";
+        let cfg = Config::new().invalid_tokens(true).punctuation(",");
+        let v: Vec<_> = Pretokenizer::with_config(src, cfg).map(|t| (t.kind, t.s, t.line))
+            .collect();
+        use Kind::*;
+        assert_eq!(v, vec![(Invalid, "\u{7f}", 1), (Word, "a", 1), (Invalid, "\u{1}\u{2}", 1),
+                           (Word, "b", 1), (Word, ",", 1), (Invalid, "\u{fffd}", 1),
+                           (Word, "12", 1), (Invalid, "\u{1b}", 1), (Word, "[0m", 1),
+                           (Quoted, "\"\u{fffd}\"", 1), (Invalid, "\u{0}", 1),
+                           (Invalid, "\u{1}", 2)]);
+        let cfg = Config::new().invalid_tokens(true).nul(Nul::Whitespace);
+        let v: Vec<_> = Pretokenizer::with_config("a\u{0}b\u{1}", cfg).map(|t| t.s).collect();
+        assert_eq!(v, vec!["a", "b", "\u{1}"]);
+        assert_eq!(Kind::from_code(Kind::Invalid.code()), Some(Kind::Invalid));
     }
 
     #[test]
-    fn pretokenizer_test_28() {
-        let mut pt = Pretokenizer::new("x y z");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 0);
-        assert_eq!(t.line, 1);
-        assert_eq!(t.s, "x");
-        // Now get the 7.
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 2);
-        assert_eq!(t.line, 1);
-        assert_eq!(t.s, "y");
-        // Now get the z.
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 4);
-        assert_eq!(t.line, 1);
-        assert_eq!(t.s, "z");
+    fn pretokenizer_test_96() {
+        let src = "a b c d e f g";
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let log = calls.clone();
+        let mut pt = Pretokenizer::new(src)
+            .on_progress(3, move |done, total| { log.borrow_mut().push((done, total)); true });
+        assert_eq!(pt.skip_tokens(5), 5);
+        assert_eq!(*calls.borrow(), vec![(5, 13)]);
+        assert_eq!(pt.clone().count(), 2);
+        assert_eq!(pt.by_ref().count(), 2);
+        assert_eq!(*calls.borrow(), vec![(5, 13), (11, 13), (11, 13)]);
+        assert!(!pt.cancelled());
+        assert_eq!(pt.progress(), 1.0);
+
+        let mut pt = Pretokenizer::new(src).on_progress(0, |done, _| done < 3);
+        assert_eq!(pt.peek().unwrap().s, "a");
+        assert_eq!(pt.progress(), 0.0);
+        assert_eq!(pt.by_ref().map(|t| t.s).collect::<Vec<_>>(), vec!["a", "b"]);
+        assert!(pt.cancelled());
+        assert!(pt.peek().is_none());
+        assert_eq!(Pretokenizer::new("").progress(), 1.0);
     }
 
     #[test]
-    fn pretokenizer_test_29() {
-        let mut pt = Pretokenizer::new("  x\n y\n   z");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 2);
-        assert_eq!(t.line, 1);
-        assert_eq!(t.s, "x");
-        // Now get the 7.
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 5);
-        assert_eq!(t.line, 2);
-        assert_eq!(t.s, "y");
-        // Now get the z.
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 10);
-        assert_eq!(t.line, 3);
-        assert_eq!(t.s, "z");
+    fn pretokenizer_test_97() {
+        // Header names stay whole in include directives only.
+        let src = "#include <a//b.h> // c\n#  include_next</*x*/y.h>\n#import <a b>\n\
+                   #define A <a//b>\n#include <a/* b\nc */ d\n#include \"x//y\" <z//w>";
+        let v: Vec<_> = Pretokenizer::with_config(src, Config::new().directives(true))
+            .map(|t| (t.s, t.line)).collect();
+        assert_eq!(v, vec![("#include <a//b.h>", 1), ("#  include_next</*x*/y.h>", 2),
+                           ("#import <a b>", 3), ("#define A <a", 4),
+                           ("#include <a/* b", 5), ("c", 6), ("*/", 6), ("d", 6),
+                           ("#include \"x//y\" <z", 7)]);
     }
 
     #[test]
-    fn pretokenizer_test_30() {
-        let mut pt = Pretokenizer::new("  x // foo\ny\n   z");
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 2);
-        assert_eq!(t.line, 1);
-        assert_eq!(t.s, "x");
-        // Now get the 7.
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 11);
-        assert_eq!(t.line, 2);
-        assert_eq!(t.s, "y");
-        // Now get the z.
-        let t = pt.next();
-        assert!(t.is_some());
-        let t = t.unwrap();
-        assert_eq!(t.offset, 16);
-        assert_eq!(t.line, 3);
-        assert_eq!(t.s, "z");
+    fn pretokenizer_test_98() {
+        // An unterminated opaque region keeps its trailing line breaks.
+        let cfg = Config::new().tool_directives("off", "on");
+        for (src, rest) in [("a // off\nb c\n", "b c\n"), ("// off\nb\n\n", "b\n\n"),
+                            ("// off\nb c", "b c")] {
+            let mut pt = Pretokenizer::with_config(src, cfg.clone());
+            let v: Vec<_> = pt.by_ref().filter(|t| t.kind == Kind::Opaque).map(|t| t.s)
+                .collect();
+            assert_eq!(v, vec![rest]);
+            let off = src.find("//").unwrap();
+            assert_eq!(pt.diagnostics(),
+                       &[PretokError::UnterminatedRegion{ line: 1, span: off..src.len() }]);
+            let all: String = Pretokenizer::with_config(src, cfg.clone().lossless(true))
+                .map(|t| t.s).collect();
+            assert_eq!(all, src);
+        }
     }
 
     #[test]
-    fn pretokenizer_test_31() {
-        let pt = Pretokenizer::new("a+b c// stuff\nd");
-        for tok in pt {
-            println!("{} found on line {}, offset {}",
-                    tok.s, tok.line, tok.offset);
+    fn pretokenizer_test_99() {
+        // Peeked line breaks report the line they end.
+        let src = "\na\r\n\n/* b\n*/\n";
+        for cfg in [Config::new().lossless(true), Config::new().lossless(true).base(Base::Zero)] {
+            let want: Vec<_> = Pretokenizer::with_config(src, cfg.clone()).collect();
+            let mut pt = Pretokenizer::with_config(src, cfg.clone());
+            for tok in want.iter() {
+                let first = cfg.first_line() + src[..tok.offset].matches('\n').count();
+                assert_eq!(pt.peek(), Some(tok));
+                assert_eq!(pt.line(), first, "{:?}", tok);
+                let point = pt.resume_point();
+                assert_eq!(point.line, first);
+                let rest: Vec<_> = Pretokenizer::resume(src, cfg.clone(), point).collect();
+                assert_eq!(rest, want[want.len() - rest.len()..]);
+                assert_eq!(rest[0], *tok);
+                pt.next();
+            }
         }
     }
 }
-
-
@@ -0,0 +1,114 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Incremental physical line length checking.
+
+use crate::{Config, PretokError, Pretoken};
+
+/// Tracks physical lines between calls to `next()` so that each byte of
+/// input is examined for line breaks only once.
+#[derive(Clone, Debug)]
+pub(crate) struct LineLengths {
+    /// Byte offset of the start of the current line
+    start: usize,
+    /// Byte offset up to which line breaks have been found
+    checked: usize,
+    /// Line number of the current line
+    line: usize,
+    /// True if the current line has already been reported
+    reported: bool,
+}
+
+impl LineLengths {
+    pub(crate) fn new(cfg: &Config) -> LineLengths {
+        LineLengths::starting_at(0, cfg.first_line())
+    }
+
+    /// Check only the input from byte offset `start`, on line `line`.
+    pub(crate) fn starting_at(start: usize, line: usize) -> LineLengths {
+        LineLengths{ start, checked: start, line, reported: false }
+    }
+
+    /// Check the lines of `s` up to byte offset `upto` against `max`.  At
+    /// the end of input, `eof` also finishes the last line.
+    pub(crate) fn check(&mut self, s: &str, upto: usize, eof: bool, max: usize,
+                        diagnostics: &mut Vec<PretokError>) {
+        if upto > self.checked {
+            for (i, _) in s[self.checked..upto].match_indices('\n') {
+                let nl = self.checked + i;
+                self.finish(nl, max, diagnostics);
+            }
+            self.checked = upto;
+        }
+
+        if eof {
+            self.finish(s.len(), max, diagnostics);
+        } else if !self.reported && upto.saturating_sub(self.start) > max {
+            // Report a long line as soon as we know about it.
+            let end = s[upto..].find('\n').map_or(s.len(), |i| upto + i);
+            self.report(end, diagnostics);
+        }
+    }
+
+    fn finish(&mut self, end: usize, max: usize, diagnostics: &mut Vec<PretokError>) {
+        if !self.reported && end.saturating_sub(self.start) > max {
+            self.report(end, diagnostics);
+        }
+        self.start = end + 1;
+        self.line += 1;
+        self.reported = false;
+    }
+
+    fn report(&mut self, end: usize, diagnostics: &mut Vec<PretokError>) {
+        diagnostics.push(PretokError::LineTooLong{
+            line: self.line, span: self.start..end});
+        self.reported = true;
+    }
+}
+
+/// Tracks the pretokens and bytes consumed against `Config::max_tokens()`
+/// and `Config::max_bytes()`.
+#[derive(Clone, Debug)]
+pub(crate) struct Budget {
+    /// Pretokens returned so far
+    returned: usize,
+    /// True once the budget has run out
+    pub(crate) exhausted: bool,
+}
+
+impl Budget {
+    pub(crate) fn new() -> Budget {
+        Budget{ returned: 0, exhausted: false }
+    }
+
+    /// The number of pretokens that may still be returned, if limited.
+    pub(crate) fn tokens_left(&self, cfg: &Config) -> Option<usize> {
+        if self.exhausted {
+            return Some(0);
+        }
+        cfg.max_tokens.map(|max| max.saturating_sub(self.returned))
+    }
+
+    /// Decide whether `tok`, just scanned with `s` consumed up to byte
+    /// offset `upto`, fits the budget.  If not, report the rest of the input
+    /// as exhausted and return false.
+    pub(crate) fn spend(&mut self, s: &str, tok: Option<&Pretoken>, upto: usize,
+                        cfg: &Config, diagnostics: &mut Vec<PretokError>) -> bool {
+        let over_tokens = tok.is_some()
+            && cfg.max_tokens.is_some_and(|max| self.returned >= max);
+        let over_bytes = cfg.max_bytes.is_some_and(|max| upto > max);
+        if !over_tokens && !over_bytes {
+            self.returned += tok.is_some() as usize;
+            return true;
+        }
+
+        let start = match (tok, cfg.max_bytes) {
+            (Some(tok), _) => tok.offset,
+            (None, Some(max)) => crate::floor_char_boundary(s, max),
+            (None, None) => upto,
+        };
+        let line = s[..start].matches('\n').count() + cfg.first_line();
+        diagnostics.push(PretokError::BudgetExhausted{ line, span: start..s.len() });
+        self.exhausted = true;
+        false
+    }
+}
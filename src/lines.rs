@@ -0,0 +1,139 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Line numbers and columns computed from byte offsets.
+
+/// An index of the lines of an input, for turning byte offsets into lines
+/// and columns and back, as error messages need.  Lines and columns count
+/// from 1, and columns count characters.  The index doesn't keep the input.
+/// ```
+/// use pretok::LineIndex;
+/// let index = LineIndex::new("int x;\n\u{e9}t\u{e9} = 1;");
+/// assert_eq!(index.line_count(), 2);
+/// assert_eq!(index.line_col(4), (1, 5));
+/// assert_eq!(index.line_col(12), (2, 4));
+/// assert_eq!(index.offset(2, 4), Some(12));
+/// assert_eq!(index.offset(3, 1), None);
+/// ```
+#[derive(Clone, Debug)]
+pub struct LineIndex {
+    /// The byte offset of the start of each line
+    starts: Vec<usize>,
+    /// The byte offset of each character longer than one byte
+    wide: Vec<usize>,
+    /// The extra bytes of the characters in `wide`, up to and including each
+    extra: Vec<usize>,
+    /// The length of the input
+    len: usize,
+}
+
+impl LineIndex {
+    /// Index the lines of `s`.
+    pub fn new(s: &str) -> LineIndex {
+        let mut starts = vec![0];
+        starts.extend(memchr::memchr_iter(b'\n', s.as_bytes()).map(|i| i + 1));
+        let (mut wide, mut extra) = (Vec::new(), Vec::new());
+        if !s.is_ascii() {
+            let mut total = 0;
+            for (i, c) in s.char_indices().filter(|(_, c)| !c.is_ascii()) {
+                total += c.len_utf8() - 1;
+                wide.push(i);
+                extra.push(total);
+            }
+        }
+        LineIndex{ starts, wide, extra, len: s.len() }
+    }
+
+    /// The number of lines.  An input ending in a line break has an empty
+    /// last line.
+    pub fn line_count(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// The line, from 1, containing byte `offset`, or the last line if
+    /// `offset` is past the end.
+    pub fn line(&self, offset: usize) -> usize {
+        self.starts.partition_point(|&start| start <= offset)
+    }
+
+    /// The line and the column in characters, both from 1, of byte
+    /// `offset`.  An offset inside a character is taken as the start of the
+    /// character, and an offset past the end as the end.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let mut offset = offset.min(self.len);
+        let i = self.wide.partition_point(|&at| at < offset);
+        if i > 0 && offset <= self.wide[i - 1] + self.extra_at(i) - self.extra_at(i - 1) {
+            offset = self.wide[i - 1];
+        }
+        let line = self.line(offset);
+        let start = self.starts[line - 1];
+        let extra = self.extra_before(offset) - self.extra_before(start);
+        (line, offset - start - extra + 1)
+    }
+
+    /// The byte offset of column `col` of `line`, both from 1, or None if
+    /// there is no such line or column.  The column just past the last
+    /// character of a line, where its line break is, is allowed.
+    pub fn offset(&self, line: usize, col: usize) -> Option<usize> {
+        if line == 0 || line > self.starts.len() || col == 0 {
+            return None;
+        }
+        let start = self.starts[line - 1];
+        let end = match self.starts.get(line) {
+            Some(next) => next - 1,
+            None => self.len,
+        };
+        let mut offset = start + (col - 1);
+        let mut i = self.wide.partition_point(|&at| at < start);
+        while i < self.wide.len() && self.wide[i] < offset.min(end) {
+            offset += self.extra_at(i + 1) - self.extra_at(i);
+            i += 1;
+        }
+        match offset <= end {
+            true => Some(offset),
+            false => None,
+        }
+    }
+
+    /// The extra bytes of the first `i` wide characters.
+    fn extra_at(&self, i: usize) -> usize {
+        match i {
+            0 => 0,
+            i => self.extra[i - 1],
+        }
+    }
+
+    /// The extra bytes of the wide characters before byte `offset`.
+    fn extra_before(&self, offset: usize) -> usize {
+        self.extra_at(self.wide.partition_point(|&at| at < offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_test_1() {
+        let s = "a\u{e9}b\n\n\u{1F600}\u{e9}x\r\nend\n";
+        let index = LineIndex::new(s);
+        assert_eq!(index.line_count(), 5);
+        for (offset, _) in s.char_indices().chain(Some((s.len(), ' '))) {
+            let (line, col) = index.line_col(offset);
+            assert_eq!(index.offset(line, col), Some(offset), "{}", offset);
+            let start = index.offset(line, 1).unwrap();
+            assert_eq!(col, s[start..offset].chars().count() + 1);
+        }
+        // Inside a character, and past the end
+        assert_eq!(index.line_col(2), (1, 2));
+        assert_eq!(index.line_col(7), (3, 1));
+        assert_eq!(index.line_col(100), (5, 1));
+        assert_eq!(index.offset(1, 4), Some(4));
+        assert_eq!(index.offset(1, 5), None);
+        assert_eq!(index.offset(3, 5), Some(14));
+        assert_eq!(index.offset(3, 6), None);
+        assert_eq!(index.offset(0, 1), None);
+        assert_eq!(index.offset(5, 1), Some(s.len()));
+        assert_eq!(index.offset(5, 2), None);
+        assert_eq!(LineIndex::new("").line_col(0), (1, 1));
+    }
+}
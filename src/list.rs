@@ -0,0 +1,211 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! A contiguous store of pretokens addressed by small ids.
+
+use crate::{Pretoken, TokenCursor};
+use std::convert::TryFrom;
+use std::iter::FromIterator;
+use std::ops::{Index, Range};
+
+/// The stable index of a pretoken in a [TokenList](struct.TokenList.html).
+/// Ids are small, `Copy` and free of lifetimes, so syntax tree nodes can
+/// hold them in place of pretokens.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TokenId(u32);
+
+impl TokenId {
+    /// The id at position `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` doesn't fit in a `u32`.
+    pub(crate) fn from_index(index: usize) -> TokenId {
+        TokenId(u32::try_from(index).expect("too many pretokens"))
+    }
+
+    /// The position of the pretoken in its list, from 0.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Every pretoken of an input, stored contiguously in input order.  Pretokens
+/// are only ever appended, so a [TokenId](struct.TokenId.html) stays valid
+/// for the life of the list.
+/// ```
+/// use pretok::{Pretokenizer, TokenList};
+/// let src = "let x = y + 1 ;";
+/// let list: TokenList = Pretokenizer::new(src).collect();
+/// let ids: Vec<_> = list.ids().collect();
+/// assert_eq!(list[ids[3]].s, "y");
+/// assert_eq!(&src[list.span_between(ids[3], ids[5])], "y + 1");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TokenList<'a> {
+    toks: Vec<Pretoken<'a>>,
+}
+
+impl<'a> TokenList<'a> {
+    /// Create an empty list.
+    pub fn new() -> TokenList<'a> {
+        TokenList::default()
+    }
+
+    /// Append `tok`, returning its id.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the list already holds `u32::MAX` pretokens.
+    pub fn push(&mut self, tok: Pretoken<'a>) -> TokenId {
+        let id = TokenId::from_index(self.toks.len());
+        self.toks.push(tok);
+        id
+    }
+
+    /// The pretoken with id `id`, if it is in this list.
+    pub fn get(&self, id: TokenId) -> Option<&Pretoken<'a>> {
+        self.toks.get(id.index())
+    }
+
+    /// The byte span of the input covered by pretoken `id`.
+    /// ```
+    /// use pretok::TokenList;
+    /// let list: TokenList = pretok::tokenize("a bc").into_iter().collect();
+    /// let id = list.ids().last().unwrap();
+    /// assert_eq!(list.span(id), Some(2..4));
+    /// ```
+    pub fn span(&self, id: TokenId) -> Option<Range<usize>> {
+        self.get(id).map(|tok| tok.offset..tok.offset + tok.s.len())
+    }
+
+    /// The byte span of the input from the start of pretoken `first` to the
+    /// end of pretoken `last`, including everything between them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either id is not in this list.
+    pub fn span_between(&self, first: TokenId, last: TokenId) -> Range<usize> {
+        let (first, last) = (&self[first], &self[last]);
+        first.offset..last.offset + last.s.len()
+    }
+
+    /// The pretokens with ids in `ids`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of order or extends past the list.
+    /// ```
+    /// use pretok::TokenList;
+    /// let list: TokenList = pretok::tokenize("a b c").into_iter().collect();
+    /// let ids: Vec<_> = list.ids().collect();
+    /// assert_eq!(list.slice(ids[1]..ids[2]).len(), 1);
+    /// ```
+    pub fn slice(&self, ids: Range<TokenId>) -> &[Pretoken<'a>] {
+        &self.toks[ids.start.index()..ids.end.index()]
+    }
+
+    /// The pretokens that overlap the bytes in `range`, or for an empty
+    /// range, the pretoken containing its start, found by binary search.
+    /// Pretokens pushed out of input order give unspecified results.  To
+    /// pretokenize just part of an input, see
+    /// [Pretokenizer::tokens_in_range()](struct.Pretokenizer.html#method.tokens_in_range).
+    /// ```
+    /// use pretok::TokenList;
+    /// let list: TokenList = pretok::tokenize("a bc d").into_iter().collect();
+    /// let v: Vec<_> = list.in_range(3..5).iter().map(|t| t.s).collect();
+    /// assert_eq!(v, vec!["bc"]);
+    /// assert_eq!(list.in_range(5..5)[0].s, "d");
+    /// assert!(list.in_range(1..1).is_empty());
+    /// ```
+    pub fn in_range(&self, range: Range<usize>) -> &[Pretoken<'a>] {
+        let last = range.end.max(range.start + 1);
+        let first = self.toks.partition_point(|t| t.offset + t.s.len() <= range.start);
+        let end = self.toks.partition_point(|t| t.offset < last);
+        &self.toks[first..end.max(first)]
+    }
+
+    /// Every pretoken, in input order.
+    pub fn as_slice(&self) -> &[Pretoken<'a>] {
+        &self.toks
+    }
+
+    /// The id of every pretoken, in input order.
+    pub fn ids(&self) -> impl Iterator<Item = TokenId> {
+        (0..self.toks.len() as u32).map(TokenId)
+    }
+
+    /// Every pretoken with its id, in input order.
+    pub fn iter(&self) -> impl Iterator<Item = (TokenId, &Pretoken<'a>)> {
+        self.ids().zip(self.toks.iter())
+    }
+
+    /// A backtracking cursor over the pretokens.  See
+    /// [TokenCursor](struct.TokenCursor.html).
+    pub fn cursor(&self) -> TokenCursor<'_, 'a> {
+        TokenCursor::new(&self.toks)
+    }
+
+    /// The number of pretokens.
+    pub fn len(&self) -> usize {
+        self.toks.len()
+    }
+
+    /// True if there are no pretokens.
+    pub fn is_empty(&self) -> bool {
+        self.toks.is_empty()
+    }
+}
+
+impl<'a> Index<TokenId> for TokenList<'a> {
+    type Output = Pretoken<'a>;
+
+    fn index(&self, id: TokenId) -> &Pretoken<'a> {
+        &self.toks[id.index()]
+    }
+}
+
+impl<'a> Extend<Pretoken<'a>> for TokenList<'a> {
+    fn extend<I: IntoIterator<Item = Pretoken<'a>>>(&mut self, iter: I) {
+        for tok in iter {
+            self.push(tok);
+        }
+    }
+}
+
+impl<'a> FromIterator<Pretoken<'a>> for TokenList<'a> {
+    fn from_iter<I: IntoIterator<Item = Pretoken<'a>>>(iter: I) -> TokenList<'a> {
+        let mut list = TokenList::new();
+        list.extend(iter);
+        list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Pretokenizer;
+
+    #[test]
+    fn list_test_0() {
+        let src = "f(\"a b\",\n  c)";
+        let mut list = TokenList::new();
+        assert!(list.is_empty());
+        let first = list.push(Pretokenizer::new(src).next().unwrap());
+        list.extend(Pretokenizer::new(src).skip(1));
+        assert_eq!(list.len(), 4);
+        let ids: Vec<_> = list.ids().collect();
+        assert_eq!(ids[0], first);
+        assert_eq!(ids[2].index(), 2);
+        assert_eq!(list.span(ids[1]), Some(2..7));
+        assert_eq!(&src[list.span_between(first, ids[3])], src);
+        assert!(list.slice(ids[1]..ids[1]).is_empty());
+        assert_eq!(list.slice(first..ids[2]), &list.as_slice()[..2]);
+        let v: Vec<_> = list.iter().map(|(id, t)| (id.index(), t.s)).collect();
+        assert_eq!(v, vec![(0, "f("), (1, "\"a b\""), (2, ","), (3, "c)")]);
+        assert_eq!(list.cursor().count(), 4);
+
+        let empty = TokenList::new();
+        assert_eq!(empty.get(first), None);
+        assert_eq!(empty.span(first), None);
+    }
+}
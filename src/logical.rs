@@ -0,0 +1,144 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Grouping pretokens by logical line.
+
+use crate::{Config, Kind, PretokError, Pretoken, Pretokenizer};
+use std::ops::Range;
+
+/// The pretokens of one logical line, returned by
+/// [LineTokenizer](struct.LineTokenizer.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogicalLine<'a> {
+    /// The physical line where the logical line starts, from 1.
+    pub line: usize,
+    /// The bytes of the physical lines of the logical line, without the
+    /// final line break.
+    pub span: Range<usize>,
+    /// The pretokens, without whitespace and comments.
+    pub tokens: Vec<Pretoken<'a>>,
+}
+
+/// An iterator over the logical lines of an input that have pretokens, for
+/// line-oriented formats such as assembly and configuration files.  Line
+/// breaks inside strings and block comments don't end a logical line.
+/// With [splice()](#method.splice), neither does one right after a
+/// backslash.
+/// ```
+/// use pretok::LineTokenizer;
+/// let src = "mov r0, 1 ; /* x\n y */ add r0 \\\n  r1\n\n// only\nret";
+/// let lines: Vec<_> = LineTokenizer::new(src).splice(true)
+///     .map(|l| (l.line, l.tokens.iter().map(|t| t.s).collect::<Vec<_>>()))
+///     .collect();
+/// assert_eq!(lines, vec![
+///     (1, vec!["mov", "r0,", "1", ";", "add", "r0", "r1"]),
+///     (6, vec!["ret"]),
+/// ]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct LineTokenizer<'a> {
+    s: &'a str,
+    pt: Pretokenizer<'a>,
+    splice: bool,
+    /// Where the next logical line starts, and its line.
+    start: usize,
+    line: usize,
+}
+
+impl<'a> LineTokenizer<'a> {
+    /// Create a line tokenizer with the default configuration.
+    pub fn new(s: &'a str) -> LineTokenizer<'a> {
+        LineTokenizer::with_config(s, Config::default())
+    }
+
+    /// Create a line tokenizer with `cfg`.
+    pub fn with_config(s: &'a str, cfg: Config) -> LineTokenizer<'a> {
+        LineTokenizer{ s, pt: Pretokenizer::with_config(s, cfg.lossless(true)), splice: false,
+                       start: 0, line: 1 }
+    }
+
+    /// Continue a logical line past a line break right after a backslash
+    /// that ends a word, dropping the backslash from the word, and the word
+    /// if nothing else is left.  The default is false.
+    pub fn splice(mut self, enable: bool) -> LineTokenizer<'a> {
+        self.splice = enable;
+        self
+    }
+
+    /// The recoverable problems found so far, in input order.  See
+    /// [Pretokenizer::diagnostics()](struct.Pretokenizer.html#method.diagnostics).
+    pub fn diagnostics(&self) -> &[PretokError] {
+        self.pt.diagnostics()
+    }
+}
+
+impl<'a> Iterator for LineTokenizer<'a> {
+    type Item = LogicalLine<'a>;
+
+    fn next(&mut self) -> Option<LogicalLine<'a>> {
+        while self.start < self.s.len() {
+            let start = self.start;
+            let mut tokens: Vec<Pretoken<'a>> = Vec::new();
+            let end = loop {
+                let tok = match self.pt.next() {
+                    Some(tok) => tok,
+                    None => {
+                        self.start = self.s.len();
+                        break self.s.len();
+                    }
+                };
+                match tok.kind {
+                    Kind::Newline => {
+                        let before = &self.s[..tok.offset];
+                        let before = before.strip_suffix('\r').unwrap_or(before);
+                        if self.splice && before.ends_with('\\') {
+                            if let Some(last) = tokens.last_mut() {
+                                let end = last.offset + last.s.len();
+                                if last.kind == Kind::Word && end == tok.offset {
+                                    last.s = &last.s[..before.len() - 1 - last.offset];
+                                    if last.s.is_empty() {
+                                        tokens.pop();
+                                    }
+                                    continue;
+                                }
+                            }
+                        }
+                        self.start = tok.offset + 1;
+                        break before.len();
+                    }
+                    Kind::Whitespace | Kind::Comment => {}
+                    _ => tokens.push(tok),
+                }
+            };
+            let line = self.line;
+            self.line += memchr::memchr_iter(b'\n', &self.s.as_bytes()[start..self.start]).count();
+            if !tokens.is_empty() {
+                return Some(LogicalLine{ line, span: start..end, tokens });
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logical_test_1() {
+        let src = "a \\\r\nb\\\n\"s\n\" c\\ \nd // e \\\nf\\";
+        let lines: Vec<_> = LineTokenizer::new(src).splice(true)
+            .map(|l| (l.line, &src[l.span], l.tokens.iter().map(|t| t.s).collect::<Vec<_>>()))
+            .collect();
+        assert_eq!(lines, vec![
+            (1, "a \\\r\nb\\\n\"s\n\" c\\ ", vec!["a", "b", "\"s\n\"", "c\\"]),
+            (5, "d // e \\", vec!["d"]),
+            (6, "f\\", vec!["f\\"]),
+        ]);
+        let lines: Vec<_> = LineTokenizer::new(src).map(|l| l.line).collect();
+        assert_eq!(lines, vec![1, 2, 3, 5, 6]);
+        assert_eq!(LineTokenizer::new("\n\n").next(), None);
+        let mut lt = LineTokenizer::new("x \"y");
+        assert_eq!(lt.next().unwrap().span, 0..4);
+        assert_eq!(lt.diagnostics().len(), 1);
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Finding long lines and where to break them.
+
+use crate::{Config, Kind, Pretokenizer};
+use std::ops::Range;
+
+/// A line longer than the limit given to
+/// [long_lines()](fn.long_lines.html).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LongLine {
+    /// The line.
+    pub line: usize,
+    /// The line without its line break.
+    pub span: Range<usize>,
+    /// The byte offset of the pretoken or comment start nearest the limit
+    /// on the line, as a hint for where to break it, or None if nothing
+    /// starts on the line after its first pretoken or comment.
+    pub break_at: Option<usize>,
+}
+
+/// Each line of `s` longer than `max` bytes, not counting the line break,
+/// with a place to break it.  The break is at the last pretoken or comment
+/// that starts within the limit, or if there is none, the first after it.
+/// Pretokens are never split, so strings and comments are kept whole.
+/// ```
+/// let src = "short\nlet total = first + \"second\"; // sum\n";
+/// let long = pretok::long_lines(src, 24);
+/// assert_eq!(long.len(), 1);
+/// assert_eq!((long[0].line, long[0].span.clone()), (2, 6..42));
+/// assert_eq!(&src[long[0].break_at.unwrap()..], "\"second\"; // sum\n");
+/// ```
+pub fn long_lines(s: &str, max: usize) -> Vec<LongLine> {
+    long_lines_with(s, max, Config::default())
+}
+
+/// Like [long_lines()](fn.long_lines.html), with `cfg`.
+pub fn long_lines_with(s: &str, max: usize, cfg: Config) -> Vec<LongLine> {
+    // Where pretokens and comments start, other than first on a line.
+    let first = cfg.first_line();
+    let mut breaks = Vec::new();
+    let mut bol = true;
+    for tok in Pretokenizer::with_config(s, cfg.lossless(true)) {
+        match tok.kind {
+            Kind::Whitespace => {}
+            Kind::Newline | Kind::Terminator => bol = true,
+            _ => {
+                if !bol {
+                    breaks.push(tok.offset);
+                }
+                bol = false;
+            }
+        }
+    }
+    let mut long = Vec::new();
+    let mut start = 0;
+    for (i, line) in s.split('\n').enumerate() {
+        let span = start..start + line.len();
+        start = span.end + 1;
+        if span.len() <= max {
+            continue;
+        }
+        let limit = span.start + max;
+        let within = breaks.partition_point(|&b| b <= span.start);
+        let before = breaks.partition_point(|&b| b <= limit);
+        let break_at = if before > within {
+            Some(breaks[before - 1])
+        } else {
+            breaks.get(before).copied().filter(|&b| b < span.end)
+        };
+        long.push(LongLine{ line: i + first, span, break_at });
+    }
+    long
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_lines_test_1() {
+        let src = "  aaaa bbbb\n\"cc\ncc\" dd/* e */\nfff\n  gggggggg  \nh";
+        let long: Vec<_> = long_lines(src, 3).iter()
+            .map(|l| (l.line, l.span.clone(), l.break_at.map(|b| &src[b..b + 1])))
+            .collect();
+        assert_eq!(long, vec![(1, 0..11, Some("b")), (3, 16..29, Some("d")),
+                              (5, 34..46, None)]);
+        let long = long_lines(src, 6);
+        assert_eq!(long[1].break_at.map(|b| &src[b..b + 1]), Some("/"));
+        assert!(long_lines(src, 13).is_empty());
+        assert!(long_lines("", 0).is_empty());
+    }
+}
@@ -0,0 +1,182 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! A fixed-size lookahead buffer for LL(k) parsers.
+
+use crate::{Pretoken, Pretokenizer};
+
+/// An iterator that keeps up to `K` upcoming pretokens available through
+/// [peek_n()](struct.Lookahead.html#method.peek_n), created by
+/// [Pretokenizer::lookahead()](struct.Pretokenizer.html#method.lookahead).
+///
+/// The pretokens are held in a ring buffer, so lookahead costs no
+/// allocation.  As with
+/// [Pretokenizer::peek()](struct.Pretokenizer.html#method.peek), position
+/// accessors report the start of a buffered pretoken rather than the end of
+/// the last one returned.
+/// ```
+/// use pretok::Pretokenizer;
+/// let mut la = Pretokenizer::new("x = y ;").lookahead::<2>();
+/// assert_eq!(la.peek_n(1).unwrap().s, "=");
+/// assert_eq!(la.next().unwrap().s, "x");
+/// assert_eq!(la.byte_pos(), 2);
+/// assert_eq!(la.peek_n(1).unwrap().s, "y");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Lookahead<'a, const K: usize> {
+    inner: Pretokenizer<'a>,
+    buf: [Option<Pretoken<'a>>; K],
+    /// Index of the next pretoken in `buf`
+    head: usize,
+    /// Number of pretokens in `buf`
+    len: usize,
+}
+
+impl<'a, const K: usize> Lookahead<'a, K> {
+    pub(crate) fn new(inner: Pretokenizer<'a>) -> Lookahead<'a, K> {
+        Lookahead{ inner, buf: std::array::from_fn(|_| None), head: 0, len: 0 }
+    }
+
+    /// The pretoken `i` places ahead, without consuming anything.
+    /// `peek_n(0)` is the pretoken the next call to `next()` returns.
+    /// Returns `None` if the input ends first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is not less than `K`.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut la = Pretokenizer::new("a b").lookahead::<3>();
+    /// assert_eq!(la.peek_n(0).unwrap().s, "a");
+    /// assert!(la.peek_n(2).is_none());
+    /// ```
+    pub fn peek_n(&mut self, i: usize) -> Option<&Pretoken<'a>> {
+        assert!(i < K, "lookahead of {} exceeds buffer size {}", i, K);
+        while self.len <= i {
+            let tok = self.inner.next()?;
+            self.buf[(self.head + self.len) % K] = Some(tok);
+            self.len += 1;
+        }
+        self.buf[(self.head + i) % K].as_ref()
+    }
+
+    /// Same as `peek_n(0)`.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut la = Pretokenizer::new("a").lookahead::<1>();
+    /// assert_eq!(la.peek().unwrap().s, "a");
+    /// ```
+    pub fn peek(&mut self) -> Option<&Pretoken<'a>> {
+        self.peek_n(0)
+    }
+
+    /// The byte offset of the next pretoken, or of the unconsumed input if
+    /// nothing is buffered.  See
+    /// [Pretokenizer::byte_pos()](struct.Pretokenizer.html#method.byte_pos).
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut la = Pretokenizer::new("a  b").lookahead::<2>();
+    /// la.next();
+    /// la.peek_n(1);
+    /// assert_eq!(la.byte_pos(), 3);
+    /// ```
+    pub fn byte_pos(&self) -> usize {
+        match self.front() {
+            Some(tok) => tok.offset,
+            None => self.inner.byte_pos(),
+        }
+    }
+
+    /// The line number at `byte_pos()`.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut la = Pretokenizer::new("a\nb").lookahead::<2>();
+    /// la.next();
+    /// la.peek_n(0);
+    /// assert_eq!(la.line(), 2);
+    /// ```
+    pub fn line(&self) -> usize {
+        match self.front() {
+            Some(tok) => self.inner.start_line(tok),
+            None => self.inner.line(),
+        }
+    }
+
+    /// The input from `byte_pos()` on.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let mut la = Pretokenizer::new("a b c").lookahead::<2>();
+    /// la.next();
+    /// la.peek_n(1);
+    /// assert_eq!(la.remainder(), "b c");
+    /// ```
+    pub fn remainder(&self) -> &'a str {
+        match self.front() {
+            Some(tok) => &self.inner.pos.slice_all()[tok.offset..],
+            None => self.inner.remainder(),
+        }
+    }
+
+    /// The underlying pretokenizer, for diagnostics and other accessors.
+    /// It is positioned after any buffered pretokens.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let la = Pretokenizer::new("x").lookahead::<2>();
+    /// assert!(la.inner().diagnostics().is_empty());
+    /// ```
+    pub fn inner(&self) -> &Pretokenizer<'a> {
+        &self.inner
+    }
+
+    fn front(&self) -> Option<&Pretoken<'a>> {
+        if self.len == 0 {
+            return None;
+        }
+        self.buf[self.head].as_ref()
+    }
+}
+
+impl<'a, const K: usize> Iterator for Lookahead<'a, K> {
+    type Item = Pretoken<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return self.inner.next();
+        }
+        let tok = self.buf[self.head].take();
+        self.head = (self.head + 1) % K;
+        self.len -= 1;
+        tok
+    }
+}
+
+impl<'a, const K: usize> std::iter::FusedIterator for Lookahead<'a, K> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookahead_test_0() {
+        let mut la = Pretokenizer::new("a b\nc d e").lookahead::<3>();
+        assert_eq!(la.peek_n(2).unwrap().s, "c");
+        assert_eq!(la.line(), 1);
+        let v: Vec<_> = la.by_ref().take(2).map(|t| t.s).collect();
+        assert_eq!(v, vec!["a", "b"]);
+        assert_eq!((la.byte_pos(), la.line()), (4, 2));
+        assert_eq!(la.peek_n(2).unwrap().s, "e");
+        assert_eq!(la.peek_n(0).unwrap().s, "c");
+        let v: Vec<_> = la.by_ref().map(|t| t.s).collect();
+        assert_eq!(v, vec!["c", "d", "e"]);
+        assert!(la.peek_n(2).is_none());
+        assert_eq!(la.byte_pos(), 9);
+
+        let mut la = Pretokenizer::new("a").lookahead::<0>();
+        assert_eq!(la.next().unwrap().s, "a");
+    }
+
+    #[test]
+    #[should_panic]
+    fn lookahead_test_1() {
+        Pretokenizer::new("a").lookahead::<2>().peek_n(2);
+    }
+}
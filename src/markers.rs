@@ -0,0 +1,115 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Finding markers such as TODO in comments.
+
+use crate::{Config, Kind, Pretokenizer};
+use std::ops::Range;
+
+/// A marker found in a comment by [find_markers()](fn.find_markers.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommentMarker<'a> {
+    /// The marker, such as `TODO`.
+    pub marker: &'a str,
+    /// The rest of the comment line after the marker, without a
+    /// parenthesized note such as an owner right after the marker, a colon,
+    /// surrounding whitespace or the `*/` closing a block comment.
+    pub text: &'a str,
+    /// The line of the marker.
+    pub line: usize,
+    /// From the start of the marker to the end of the text, or of the marker
+    /// if there is no text.
+    pub span: Range<usize>,
+}
+
+/// The markers in `markers`, such as `TODO`, `FIXME` and `HACK`, found in
+/// the comments of `s`, with the text after each, in input order.  A marker
+/// matches only as a whole word, and only in comments, never in strings or
+/// code.
+/// ```
+/// let src = "// TODO(sk): handle tabs\nlet s = \"TODO: no\";\n/* FIXME later */ x; // TODOS";
+/// let found = pretok::find_markers(src, &["TODO", "FIXME", "HACK"]);
+/// let v: Vec<_> = found.iter().map(|m| (m.marker, m.text, m.line)).collect();
+/// assert_eq!(v, vec![("TODO", "handle tabs", 1), ("FIXME", "later", 3)]);
+/// assert_eq!(&src[found[0].span.clone()], "TODO(sk): handle tabs");
+/// ```
+pub fn find_markers<'a>(s: &'a str, markers: &[&str]) -> Vec<CommentMarker<'a>> {
+    find_markers_with(s, markers, Config::default())
+}
+
+/// Like [find_markers()](fn.find_markers.html), with `cfg`.
+pub fn find_markers_with<'a>(s: &'a str, markers: &[&str], cfg: Config) -> Vec<CommentMarker<'a>> {
+    let mut found = Vec::new();
+    let (mut pos, mut line) = (0, cfg.first_line());
+    for tok in Pretokenizer::with_config(s, cfg.lossless(true)) {
+        if tok.kind != Kind::Comment {
+            continue;
+        }
+        let block = tok.s.starts_with("/*");
+        // The first marker on each line of the comment.
+        let mut base = tok.offset;
+        for piece in tok.s.split('\n') {
+            if let Some((at, marker)) = first_marker(piece, markers) {
+                let mut rest = &piece[at + marker.len()..];
+                if rest.starts_with('(') {
+                    rest = rest.find(')').map_or(rest, |close| &rest[close + 1..]);
+                }
+                let rest = rest.trim_start().trim_start_matches(':').trim_start();
+                let text = match block {
+                    true => rest.strip_suffix("*/").unwrap_or(rest).trim_end(),
+                    false => rest.trim_end(),
+                };
+                let text_start = base + (piece.len() - rest.len());
+                let end = if text.is_empty() { base + at + marker.len() }
+                          else { text_start + text.len() };
+                line += memchr::memchr_iter(b'\n', &s.as_bytes()[pos..base]).count();
+                pos = base;
+                found.push(CommentMarker{ marker: &s[base + at..base + at + marker.len()],
+                                          text: &s[text_start..text_start + text.len()],
+                                          line, span: base + at..end });
+            }
+            base += piece.len() + 1;
+        }
+    }
+    found
+}
+
+/// The offset and text of the first of `markers` in `s` that is a whole word.
+fn first_marker<'s>(s: &'s str, markers: &[&str]) -> Option<(usize, &'s str)> {
+    let ident = |c: char| c.is_alphanumeric() || c == '_';
+    let mut at = 0;
+    while let Some(start) = s[at..].find(ident) {
+        let start = at + start;
+        let end = s[start..].find(|c| !ident(c)).map_or(s.len(), |len| start + len);
+        if markers.contains(&&s[start..end]) {
+            return Some((start, &s[start..end]));
+        }
+        at = end;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markers_test_1() {
+        let src = "x; /* HACK\n * TODO */ \"FIXME\" // XFIXME FIXME(me) a */ TODO\n//TODO";
+        let found = find_markers(src, &["TODO", "FIXME", "HACK"]);
+        let v: Vec<_> = found.iter()
+            .map(|m| (m.marker, m.text, m.line, &src[m.span.clone()]))
+            .collect();
+        assert_eq!(v, vec![("HACK", "", 1, "HACK"),
+                           ("TODO", "", 2, "TODO"),
+                           ("FIXME", "a */ TODO", 2, "FIXME(me) a */ TODO"),
+                           ("TODO", "", 3, "TODO")]);
+        assert!(find_markers(src, &[]).is_empty());
+
+        // Only a block comment's close is dropped, and lines follow the base.
+        let src = "// TODO fix */
+/* TODO b */";
+        let found = find_markers_with(src, &["TODO"], Config::new().base(crate::Base::Zero));
+        let v: Vec<_> = found.iter().map(|m| (m.text, m.line)).collect();
+        assert_eq!(v, vec![("fix */", 0), ("b", 1)]);
+    }
+}
@@ -0,0 +1,158 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Comment density and other source metrics.
+
+use crate::{Config, Kind, Pretokenizer};
+
+/// Line and byte counts of a source or corpus, from
+/// [metrics()](fn.metrics.html).  Sum a corpus with
+/// [merge()](struct.Metrics.html#method.merge).
+/// ```
+/// let m = pretok::metrics("int x; // x\n\n/* doc\n */\nf(x);\n");
+/// assert_eq!((m.lines, m.code_lines, m.comment_lines, m.blank_lines), (5, 2, 3, 1));
+/// assert_eq!(m.tokens_per_line, [3, 1, 1]);
+/// assert_eq!(m.comment_density(), 0.75);
+/// assert_eq!(m.code_to_comment_ratio(), Some(2.0 / 3.0));
+/// assert_eq!(m.blank_ratio(), 0.2);
+///
+/// let mut corpus = pretok::Metrics::default();
+/// for src in &["a\n", "// b\n"] {
+///     corpus.merge(&pretok::metrics(src));
+/// }
+/// assert_eq!((corpus.lines, corpus.code_lines, corpus.comment_lines), (2, 1, 1));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Metrics {
+    /// Physical lines, counting a final line without a line break.
+    pub lines: usize,
+    /// Lines holding part of a pretoken.
+    pub code_lines: usize,
+    /// Lines holding part of a comment, including those also holding code.
+    pub comment_lines: usize,
+    /// Lines holding nothing but whitespace.
+    pub blank_lines: usize,
+    /// Pretokens.
+    pub tokens: usize,
+    /// Bytes of pretokens.
+    pub code_bytes: usize,
+    /// Bytes of comments.
+    pub comment_bytes: usize,
+    /// Entry `n` is the number of lines on which `n` pretokens start.
+    pub tokens_per_line: Vec<usize>,
+}
+
+impl Metrics {
+    /// Add the counts of `other`, from another source of a corpus.
+    pub fn merge(&mut self, other: &Metrics) {
+        self.lines += other.lines;
+        self.code_lines += other.code_lines;
+        self.comment_lines += other.comment_lines;
+        self.blank_lines += other.blank_lines;
+        self.tokens += other.tokens;
+        self.code_bytes += other.code_bytes;
+        self.comment_bytes += other.comment_bytes;
+        if self.tokens_per_line.len() < other.tokens_per_line.len() {
+            self.tokens_per_line.resize(other.tokens_per_line.len(), 0);
+        }
+        for (n, count) in other.tokens_per_line.iter().enumerate() {
+            self.tokens_per_line[n] += count;
+        }
+    }
+
+    /// The fraction of lines that are not blank that hold a comment, or 0
+    /// if all are blank.
+    pub fn comment_density(&self) -> f64 {
+        ratio(self.comment_lines, self.lines - self.blank_lines).unwrap_or(0.0)
+    }
+
+    /// Lines holding code per line holding a comment, or None if there are
+    /// no comments.
+    pub fn code_to_comment_ratio(&self) -> Option<f64> {
+        ratio(self.code_lines, self.comment_lines)
+    }
+
+    /// The fraction of lines that are blank, or 0 if there are none.
+    pub fn blank_ratio(&self) -> f64 {
+        ratio(self.blank_lines, self.lines).unwrap_or(0.0)
+    }
+}
+
+fn ratio(a: usize, b: usize) -> Option<f64> {
+    if b == 0 {
+        None
+    } else {
+        Some(a as f64 / b as f64)
+    }
+}
+
+/// The [Metrics](struct.Metrics.html) of `s`.
+pub fn metrics(s: &str) -> Metrics {
+    metrics_with(s, Config::default())
+}
+
+/// Like [metrics()](fn.metrics.html), with `cfg`.  Whitespace and comments
+/// are counted even if `cfg` is not lossless.
+pub fn metrics_with(s: &str, cfg: Config) -> Metrics {
+    let lines = s.matches('\n').count() + (!s.is_empty() && !s.ends_with('\n')) as usize;
+    // Per line: pretokens starting on it, and whether it holds code or a
+    // comment.
+    let mut per_line = vec![(0, false, false); lines];
+    let mut m = Metrics{ lines, ..Metrics::default() };
+    let first = cfg.first_line();
+    for tok in Pretokenizer::with_config(s, cfg.lossless(true)) {
+        if tok.kind == Kind::Whitespace || tok.kind == Kind::Newline {
+            continue;
+        }
+        let end = tok.line - first;
+        let start = end - tok.s.matches('\n').count();
+        match tok.kind {
+            Kind::Comment => {
+                m.comment_bytes += tok.s.len();
+                for line in &mut per_line[start..=end] {
+                    line.2 = true;
+                }
+            }
+            _ => {
+                m.tokens += 1;
+                m.code_bytes += tok.s.len();
+                per_line[start].0 += 1;
+                for line in &mut per_line[start..=end] {
+                    line.1 = true;
+                }
+            }
+        }
+    }
+    for (tokens, code, comment) in per_line {
+        if m.tokens_per_line.len() <= tokens {
+            m.tokens_per_line.resize(tokens + 1, 0);
+        }
+        m.tokens_per_line[tokens] += 1;
+        m.code_lines += code as usize;
+        m.comment_lines += comment as usize;
+        m.blank_lines += !(code || comment) as usize;
+    }
+    m
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metrics_test_1() {
+        let src = "a \"b\n c\" /* d\n\n e */ f\n  \ng";
+        let m = metrics(src);
+        assert_eq!((m.lines, m.code_lines, m.comment_lines, m.blank_lines), (6, 4, 3, 1));
+        assert_eq!((m.tokens, m.code_bytes, m.comment_bytes), (4, 9, 11));
+        assert_eq!(m.tokens_per_line, [3, 2, 1]);
+        let mut all = metrics("");
+        assert_eq!(all, Metrics::default());
+        assert_eq!(all.code_to_comment_ratio(), None);
+        assert_eq!((all.comment_density(), all.blank_ratio()), (0.0, 0.0));
+        all.merge(&m);
+        all.merge(&metrics("x y z\r\n"));
+        assert_eq!(all.tokens_per_line, [3, 2, 1, 1]);
+        assert_eq!((all.lines, all.code_lines, all.tokens), (7, 5, 7));
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Removing comments and surplus whitespace.
+
+use crate::{Config, Kind, Pretoken, Pretokenizer};
+
+/// `s` without its comments and with as little whitespace as keeps its
+/// pretokens apart, for embedding or shipping small sources.  The pretokens
+/// of the result are those of `s`, and strings are untouched.
+///
+/// Whitespace and comments between pretokens become a line break if they
+/// held one outside a comment, so line-oriented constructs such as
+/// directives keep working, or else a space, or nothing next to a string,
+/// which delimits itself.
+/// Whitespace and comments at the start and end are removed, except that a
+/// pretoken that would land in the column of a
+/// [Config::column_comment()](struct.Config.html#method.column_comment)
+/// marker it starts with keeps a space before it.  A directive
+/// continued with a backslash onto a line holding a comment may end
+/// differently in the result.
+/// ```
+/// let src = "  int x = 1; // one\n\n/* two */ s = f( \"a  b\" );\n";
+/// assert_eq!(pretok::minify(src), "int x = 1;\ns = f(\"a  b\");");
+/// ```
+pub fn minify(s: &str) -> String {
+    minify_with(s, Config::default())
+}
+
+/// Like [minify()](fn.minify.html), with `cfg`.
+pub fn minify_with(s: &str, cfg: Config) -> String {
+    let trigraphs = cfg.trigraphs;
+    let mut out = String::with_capacity(s.len());
+    let mut last: Option<Pretoken> = None;
+    let mut newline = false;
+    let mut gap = false;
+    for tok in Pretokenizer::with_config(s, cfg.clone().lossless(true)) {
+        match tok.kind {
+            Kind::Newline => newline = true,
+            Kind::Whitespace | Kind::Comment => {}
+            _ => {
+                if let Some(prev) = &last {
+                    if newline {
+                        // A backslash right before the line break would splice
+                        // the lines.
+                        if prev.s.ends_with('\\') || (trigraphs && prev.s.ends_with("??/")) {
+                            out.push(' ');
+                        }
+                        out.push('\n');
+                    } else if gap && !self_delimited(prev, &tok, &cfg) {
+                        out.push(' ');
+                    }
+                }
+                while on_comment_column(&out, &tok, &cfg) {
+                    out.push(' ');
+                }
+                out.push_str(tok.s);
+                last = Some(tok);
+                newline = false;
+                gap = false;
+                continue;
+            }
+        }
+        gap = true;
+    }
+    out
+}
+
+/// True if `tok`, appended to `out`, would start a
+/// `Config::column_comment()` in the marker's column.
+pub(crate) fn on_comment_column(out: &str, tok: &Pretoken, cfg: &Config) -> bool {
+    let mut markers = cfg.column_comments.iter()
+        .filter_map(|&(marker, column)| column.filter(|_| tok.s.starts_with(marker)));
+    let col = || out[out.rfind('\n').map_or(0, |i| i + 1)..].chars().count() + 1;
+    markers.any(|column| column == col())
+}
+
+/// True if `a` followed directly by `b` pretokenizes as the two of them
+/// with `cfg`.
+pub(crate) fn self_delimited(a: &Pretoken, b: &Pretoken, cfg: &Config) -> bool {
+    if a.kind == Kind::Quoted {
+        // An empty string and a string after it could open a triple-quoted
+        // one.
+        let q = a.s.chars().next().unwrap_or('"');
+        return !(cfg.triple_quotes && a.s.chars().eq([q, q]) && b.s.starts_with(q));
+    }
+    // Only a double quote ends a word.  Other quotes, such as the single
+    // quotes of `Config::quote()`, open a string only at the start of a
+    // pretoken.
+    b.kind == Kind::Quoted && b.s.starts_with('"') && a.kind == Kind::Word
+        && !a.s.ends_with(['\\', '?', '/'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testgen::Lcg;
+
+    #[test]
+    fn minify_test_1() {
+        // Random sources keep their pretokens.
+        let pieces = [" ", "\t", "\n", "a", "b c", "\"s t\"", "\"", "/", "*", "/* x */",
+                      "// y", "#if", "\r\n", "(", ")", "\u{e9}", "'", "\\", "??/"];
+        // Directives are tried without splices.  See minify().
+        let configs = [(Config::new(), pieces.len()),
+                       (Config::new().trigraphs(true), pieces.len()),
+                       (Config::new().directives(true), pieces.len() - 2),
+                       (Config::toml(), pieces.len()), (Config::css(), pieces.len()),
+                       (Config::new().column_comment('*', Some(1)).column_comment('(', Some(3))
+                            .column_comment('a', None), pieces.len())];
+        let mut rng = Lcg::new(11);
+        for (cfg, n) in configs.iter() {
+            for _ in 0..2000 {
+                let src = rng.source(&pieces[..*n], 16);
+                let out = minify_with(&src, cfg.clone());
+                let want: Vec<_> = Pretokenizer::with_config(&src, cfg.clone())
+                    .map(|t| (t.kind, t.s)).collect();
+                let got: Vec<_> = Pretokenizer::with_config(&out, cfg.clone())
+                    .map(|t| (t.kind, t.s)).collect();
+                assert_eq!(got, want, "{:?} -> {:?}", src, out);
+            }
+        }
+        assert_eq!(minify("a\"b\" \"c\" d /**/ e"), "a\"b\"\"c\"d e");
+        assert_eq!(minify(" /* */ \n"), "");
+        assert_eq!(minify_with("a 'b' \"c\" '' 'd'", Config::toml()), "a 'b'\"c\"'' 'd'");
+        assert_eq!(minify_with("a 'b'", Config::css()), "a 'b'");
+        let cfg = Config::new().column_comment('*', Some(1)).column_comment('*', Some(2));
+        assert_eq!(minify_with("   *x", cfg.clone()), "  *x");
+        assert_eq!(minify_with("a\n   *b // c\n*d", cfg), "a\n  *b");
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Pretokenizing memory-mapped files.
+
+use crate::{Config, Pretokenizer};
+use memmap2::Mmap;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::str;
+
+/// A file mapped read-only into memory and checked to be UTF-8, so that it
+/// can be pretokenized without copying it into a `String`.  The operating
+/// system pages the file in as it is scanned, so even very large inputs
+/// need little memory.
+///
+/// Opening a mapping is `unsafe`, since the file must not be modified or
+/// truncated while it is mapped.
+/// ```no_run
+/// use pretok::{MappedFile, Pretokenizer};
+/// // SAFETY: Nothing else writes the log while it is pretokenized.
+/// let file = unsafe { MappedFile::open("huge.log")? };
+/// let words = Pretokenizer::from_mmap(&file).count();
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct MappedFile {
+    map: Mmap,
+}
+
+impl MappedFile {
+    /// Map the file at `path`.  The whole file is checked to be UTF-8 up
+    /// front; if it isn't, the error is of kind
+    /// [InvalidData](https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidData).
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated, by this process or any
+    /// other, until the `MappedFile` is dropped.  Otherwise the text that
+    /// was checked to be UTF-8 may change under the pretokenizer, or reads
+    /// past a truncated end may fault.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> io::Result<MappedFile> {
+        let file = File::open(path)?;
+        // SAFETY: The map is read-only, and the caller promises the file
+        // is left alone while it is mapped.
+        let map = unsafe { Mmap::map(&file)? };
+        str::from_utf8(&map).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(MappedFile{ map })
+    }
+
+    /// The contents of the file.
+    pub fn as_str(&self) -> &str {
+        // SAFETY: Checked to be UTF-8 by open(), and unchanged since, as
+        // the caller of open() promised.
+        unsafe { str::from_utf8_unchecked(&self.map) }
+    }
+}
+
+impl<'a> Pretokenizer<'a> {
+    /// Create a pretokenizer over a memory-mapped file.  The pretokenizer
+    /// borrows the mapping, so open the file first with
+    /// [MappedFile::open()](struct.MappedFile.html#method.open).
+    pub fn from_mmap(file: &'a MappedFile) -> Pretokenizer<'a> {
+        Pretokenizer::new(file.as_str())
+    }
+
+    /// Like [Pretokenizer::from_mmap()](struct.Pretokenizer.html#method.from_mmap),
+    /// with `cfg`.
+    pub fn from_mmap_with_config(file: &'a MappedFile, cfg: Config) -> Pretokenizer<'a> {
+        Pretokenizer::with_config(file.as_str(), cfg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn mmap_test_0() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("pretok_mmap_test_{}.c", std::process::id()));
+        let src = "int main() {\n    puts(\"hi\"); // greet\n}\n";
+        fs::write(&path, src).unwrap();
+        // SAFETY: Only this test touches the file, and not while mapped.
+        let open = |path: &std::path::Path| unsafe { MappedFile::open(path) };
+        let file = open(&path).unwrap();
+        assert_eq!(file.as_str(), src);
+        let got: Vec<_> = Pretokenizer::from_mmap(&file).collect();
+        assert_eq!(got, crate::tokenize(src));
+        let cfg = Config::new().lossless(true);
+        assert_eq!(Pretokenizer::from_mmap_with_config(&file, cfg).count(), 15);
+        drop(file);
+
+        fs::write(&path, b"ok \xff").unwrap();
+        let err = open(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        fs::write(&path, "").unwrap();
+        assert_eq!(Pretokenizer::from_mmap(&open(&path).unwrap()).count(), 0);
+        fs::remove_file(&path).unwrap();
+        assert!(open(&path).is_err());
+    }
+}
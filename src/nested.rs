@@ -0,0 +1,135 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Pretokenizing the text inside a pretoken.
+
+use crate::{Kind, PretokError, Pretoken, Pretokenizer};
+
+/// An iterator over the pretokens of the text inside a pretoken, such as
+/// a format string or SQL in a string literal, with offsets and lines in
+/// the outer input, created by
+/// [Pretokenizer::nested()](struct.Pretokenizer.html#method.nested).  The
+/// text of each pretoken still borrows from the outer input.
+/// ```
+/// use pretok::{Config, Pretokenizer};
+/// let src = "db.query(\"SELECT name\n  FROM users -- all\")";
+/// let mut pt = Pretokenizer::with_config(src, Config::new().punctuation("()."));
+/// let sql = pt.find(|t| t.kind == pretok::Kind::Quoted).unwrap();
+/// let v: Vec<_> = pt.nested(&sql, Config::new().line_comment("--"))
+///     .map(|t| (t.s, t.line, t.offset)).collect();
+/// assert_eq!(v, vec![("SELECT", 1, 10), ("name", 1, 17), ("FROM", 2, 24), ("users", 2, 29)]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Nested<'a> {
+    inner: Pretokenizer<'a>,
+    /// The offset of the text in the outer input
+    offset: usize,
+    /// The outer line of the first line of the text
+    line: usize,
+}
+
+impl<'a> Nested<'a> {
+    pub(crate) fn new(inner: Pretokenizer<'a>, offset: usize, line: usize) -> Nested<'a> {
+        Nested{ inner, offset, line }
+    }
+
+    /// The diagnostics recorded so far, with spans and lines in the outer
+    /// input.
+    /// ```
+    /// use pretok::{Config, Pretokenizer, PretokError};
+    /// let src = "sh -c \"echo 'hi\"";
+    /// let mut pt = Pretokenizer::new(src);
+    /// let cmd = pt.nth(2).unwrap();
+    /// let mut inner = pt.nested(&cmd, Config::new().quote('\''));
+    /// inner.by_ref().count();
+    /// assert_eq!(inner.diagnostics(), vec![PretokError::UnterminatedString{ line: 1,
+    ///                                                                      span: 12..15 }]);
+    /// ```
+    pub fn diagnostics(&self) -> Vec<PretokError> {
+        let offsets_only = self.inner.cfg.offsets_only;
+        self.inner.diagnostics().iter().cloned().map(|mut e| {
+            let span = e.span();
+            *e.span_mut() = span.start + self.offset..span.end + self.offset;
+            if !offsets_only {
+                *e.line_mut() = self.outer_line(e.line());
+            }
+            e
+        }).collect()
+    }
+
+    /// The outer line of `line`, a line of the inner pretokenizer.
+    fn outer_line(&self, line: usize) -> usize {
+        line - self.inner.cfg.first_line() + self.line
+    }
+}
+
+impl<'a> Iterator for Nested<'a> {
+    type Item = Pretoken<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut tok = self.inner.next()?;
+        tok.offset += self.offset;
+        if self.inner.cfg.counts_lines() {
+            tok.line = self.outer_line(tok.line);
+        }
+        Some(tok)
+    }
+}
+
+/// The offset in `tok.s` and the text of what is inside `tok`: for a
+/// quoted pretoken, the text between its quotes, and otherwise all of it.
+pub(crate) fn inside<'a>(tok: &Pretoken<'a>) -> (usize, &'a str) {
+    let s = tok.s;
+    let q = match s.chars().next() {
+        Some(q) if tok.kind == Kind::Quoted => q,
+        _ => return (0, s),
+    };
+    // Only a triple-quoted string starts with three quotes.
+    let delim = [q; 3].iter().collect::<String>();
+    let delim = if s.starts_with(delim.as_str()) { &delim[..] } else { &delim[..q.len_utf8()] };
+    let n = delim.len();
+    let end = match s.len() >= 2 * n && s.ends_with(delim) {
+        true => s.len() - n,
+        false => s.len(),
+    };
+    (n, &s[n..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Base, Config};
+
+    #[test]
+    fn nested_test_1() {
+        let src = "f(\"\", \"a\", '''\nb\n'''', \"c\nd\", e)";
+        let cfg = Config::toml().punctuation("(),");
+        let toks: Vec<_> = Pretokenizer::with_config(src, cfg.clone()).collect();
+        let parts: Vec<_> = toks.iter().map(|t| inside(t).1).collect();
+        assert_eq!(parts, vec!["f", "(", "", ",", "a", ",", "\nb\n'", ",", "c\nd", ",", "e",
+                               ")"]);
+        assert_eq!(inside(&Pretokenizer::new("\"x").next().unwrap()), (1, "x"));
+        assert_eq!(inside(&Pretokenizer::new("\"").next().unwrap()), (1, ""));
+
+        let pt = Pretokenizer::with_config(src, cfg.lazy_lines(true));
+        let v: Vec<_> = pt.nested(&toks[6], Config::new()).map(|t| (t.s, t.line, t.offset))
+            .collect();
+        assert_eq!(v, vec![("b", 2, 15), ("'", 3, 17)]);
+        let v: Vec<_> = pt.nested(&toks[8], Config::new().base(Base::Zero))
+            .map(|t| (t.s, t.line, t.offset)).collect();
+        assert_eq!(v, vec![("c", 3, 24), ("d", 4, 26)]);
+        let v: Vec<_> = pt.nested(&toks[8], Config::new().lazy_lines(true))
+            .map(|t| (t.s, t.line)).collect();
+        assert_eq!(v, vec![("c", 0), ("d", 0)]);
+
+        let pt = Pretokenizer::new("x\ny\"z");
+        let word = Pretoken{ s: &pt.source()[2..], line: 2, offset: 2, kind: Kind::Word };
+        let mut inner = pt.nested(&word, Config::new());
+        assert_eq!(inner.by_ref().map(|t| (t.s, t.offset)).collect::<Vec<_>>(),
+                   vec![("y", 2), ("\"z", 3)]);
+        assert_eq!(inner.diagnostics(), vec![PretokError::UnterminatedString{ line: 2,
+                                                                             span: 3..5 }]);
+        let mut inner = pt.nested(&word, Config::new().offsets_only(true));
+        inner.by_ref().count();
+        assert_eq!(inner.diagnostics()[0].line(), 0);
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Parsing pretokens with nom.
+
+use crate::{Kind, Pretoken};
+use nom::error::{ErrorKind, ParseError};
+use nom::{Err, IResult, Input, Needed, Offset};
+use std::iter::Enumerate;
+use std::slice::Iter;
+
+/// A slice of pretokens as [nom](https://docs.rs/nom) parser input, so
+/// combinators can consume pretokens directly.  Each element is a
+/// `&Pretoken`, and errors hold the input where they occurred, whose
+/// [location()](#method.location) and [line()](#method.line) locate them in the
+/// source.
+/// ```
+/// use nom::multi::separated_list1;
+/// use nom::{IResult, Parser};
+/// use pretok::{Kind, Pretoken, Pretokenizer, TokenInput};
+///
+/// fn list<'s, 'a>(i: TokenInput<'s, 'a>) -> IResult<TokenInput<'s, 'a>, Vec<&'s str>> {
+///     let (i, items) = separated_list1(TokenInput::text(","), TokenInput::kind(Kind::Quoted))
+///         .parse_complete(i)?;
+///     Ok((i, items.into_iter().map(|t: &Pretoken| t.s).collect()))
+/// }
+///
+/// let src = "\"a\" , \"b\" , c";
+/// let toks: Vec<_> = Pretokenizer::new(src).collect();
+/// let (rest, items) = list(TokenInput::new(&toks, src.len())).unwrap();
+/// assert_eq!(items, vec!["\"a\"", "\"b\""]);
+/// assert_eq!((rest.location(), rest.line()), (10, 1));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TokenInput<'s, 'a> {
+    toks: &'s [Pretoken<'a>],
+    /// The byte offset and line where the pretokens end
+    end: usize,
+    end_line: usize,
+}
+
+impl<'s, 'a> TokenInput<'s, 'a> {
+    /// Parse `toks`, taken from a source of `len` bytes.  The length places
+    /// errors at the end of input.
+    pub fn new(toks: &'s [Pretoken<'a>], len: usize) -> TokenInput<'s, 'a> {
+        let end_line = toks.last().map_or(0, |t| t.line);
+        TokenInput{ toks, end: len, end_line }
+    }
+
+    /// The pretokens left.
+    pub fn tokens(&self) -> &'s [Pretoken<'a>] {
+        self.toks
+    }
+
+    /// The byte offset of the first pretoken left, or of the end of input
+    /// if there are none.
+    pub fn location(&self) -> usize {
+        self.toks.first().map_or(self.end, |t| t.offset)
+    }
+
+    /// The line of the first pretoken left, or of the last pretoken before
+    /// the end if there are none left, or 0 if there were none at all.
+    pub fn line(&self) -> usize {
+        self.toks.first().map_or(self.end_line, |t| t.line)
+    }
+
+    /// A parser taking one pretoken whose text is `s`.
+    pub fn text<E>(s: &str) -> impl Fn(TokenInput<'s, 'a>) -> IResult<TokenInput<'s, 'a>,
+                                                                       &'s Pretoken<'a>, E> + '_
+    where
+        E: ParseError<TokenInput<'s, 'a>>,
+    {
+        move |i| i.take_one(|t| t.s == s, ErrorKind::Tag)
+    }
+
+    /// A parser taking one pretoken of kind `kind`.
+    pub fn kind<E>(kind: Kind) -> impl Fn(TokenInput<'s, 'a>) -> IResult<TokenInput<'s, 'a>,
+                                                                          &'s Pretoken<'a>, E>
+    where
+        E: ParseError<TokenInput<'s, 'a>>,
+    {
+        move |i| i.take_one(|t| t.kind == kind, ErrorKind::Verify)
+    }
+
+    /// Take the first pretoken if `wanted`, failing with `e` otherwise.
+    fn take_one<E, F>(self, wanted: F, e: ErrorKind)
+        -> IResult<TokenInput<'s, 'a>, &'s Pretoken<'a>, E>
+    where
+        E: ParseError<TokenInput<'s, 'a>>,
+        F: Fn(&Pretoken<'a>) -> bool,
+    {
+        match self.toks.first() {
+            Some(t) if wanted(t) => Ok((self.take_from(1), t)),
+            _ => Err(Err::Error(E::from_error_kind(self, e))),
+        }
+    }
+}
+
+impl<'s, 'a> Input for TokenInput<'s, 'a> {
+    type Item = &'s Pretoken<'a>;
+    type Iter = Iter<'s, Pretoken<'a>>;
+    type IterIndices = Enumerate<Iter<'s, Pretoken<'a>>>;
+
+    fn input_len(&self) -> usize {
+        self.toks.len()
+    }
+
+    fn take(&self, index: usize) -> Self {
+        match self.toks.get(index) {
+            Some(t) => TokenInput{ toks: &self.toks[..index], end: t.offset, end_line: t.line },
+            None => *self,
+        }
+    }
+
+    fn take_from(&self, index: usize) -> Self {
+        TokenInput{ toks: &self.toks[index..], ..*self }
+    }
+
+    fn take_split(&self, index: usize) -> (Self, Self) {
+        (self.take_from(index), self.take(index))
+    }
+
+    fn position<P>(&self, predicate: P) -> Option<usize>
+    where
+        P: Fn(Self::Item) -> bool,
+    {
+        self.toks.iter().position(predicate)
+    }
+
+    fn iter_elements(&self) -> Self::Iter {
+        self.toks.iter()
+    }
+
+    fn iter_indices(&self) -> Self::IterIndices {
+        self.toks.iter().enumerate()
+    }
+
+    fn slice_index(&self, count: usize) -> Result<usize, Needed> {
+        if self.toks.len() >= count {
+            Ok(count)
+        } else {
+            Err(Needed::new(count - self.toks.len()))
+        }
+    }
+}
+
+impl<'s, 'a> Offset for TokenInput<'s, 'a> {
+    /// The number of pretokens from the start of `self` to the start of
+    /// `second`, which must be a later part of the same input.
+    fn offset(&self, second: &Self) -> usize {
+        self.toks.len() - second.toks.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Pretokenizer;
+    use nom::error::Error;
+    use nom::multi::many0;
+    use nom::sequence::{delimited, preceded};
+    use nom::Parser;
+
+    #[test]
+    fn nom_input_test_1() {
+        let src = "let x = ( a b )\nlet";
+        let toks: Vec<_> = Pretokenizer::new(src).collect();
+        let input = TokenInput::new(&toks, src.len());
+        let word = TokenInput::kind::<Error<_>>(Kind::Word);
+        let mut parser = preceded(
+            (TokenInput::text("let"), &word, TokenInput::text("=")),
+            delimited(TokenInput::text("("), many0(TokenInput::text("a")),
+                      TokenInput::text(")")));
+        let e = parser.parse(input).unwrap_err();
+        match e {
+            Err::Error(e) => {
+                assert_eq!((e.input.location(), e.input.line(), e.code), (12, 1, ErrorKind::Tag));
+                assert_eq!(input.offset(&e.input), 5);
+            }
+            e => panic!("{:?}", e),
+        }
+        let (rest, _) = (TokenInput::text::<Error<_>>("let"), &word).parse(input).unwrap();
+        assert_eq!(rest.tokens()[0].s, "=");
+        let end = input.take_from(7);
+        assert_eq!(end.tokens()[0].s, "let");
+        let e = (TokenInput::text::<Error<_>>("let"), &word).parse(end).unwrap_err();
+        assert!(matches!(e, Err::Error(Error{ input, .. })
+                         if input.location() == src.len() && input.line() == 2));
+        let (rest, head) = input.take_split(2);
+        assert_eq!((head.input_len(), head.location(), rest.location()), (2, 0, 6));
+        assert_eq!(head.take_from(2).location(), 6);
+        assert_eq!(input.position(|t| t.s == "("), Some(3));
+    }
+}
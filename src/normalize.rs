@@ -0,0 +1,125 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Canonical forms for comparing sources modulo formatting.
+
+use crate::minify::{on_comment_column, self_delimited};
+use crate::{Config, Kind, Pretoken, Pretokenizer};
+use std::ops::Range;
+
+/// A canonical form of a source, as made by
+/// [normalize()](fn.normalize.html), with the way back to the original.
+#[derive(Clone, Debug, Default)]
+pub struct Normalized {
+    /// The canonical text.
+    pub text: String,
+    // The start of each pretoken in `text` and its span in the original.
+    map: Vec<(usize, Range<usize>)>,
+}
+
+impl Normalized {
+    /// The offset in the original source of `offset` in the canonical text.
+    /// An offset between pretokens maps to the end of the pretoken before
+    /// it, or 0 if there is none.
+    pub fn original(&self, offset: usize) -> usize {
+        let i = self.map.partition_point(|(start, _)| *start <= offset);
+        if i == 0 {
+            return 0;
+        }
+        let (start, span) = &self.map[i - 1];
+        (span.start + offset - start).min(span.end)
+    }
+
+    /// The span in the original source of `range` in the canonical text.
+    pub fn original_span(&self, range: Range<usize>) -> Range<usize> {
+        let start = self.original(range.start);
+        start..self.original(range.end).max(start)
+    }
+}
+
+/// The canonical form of `s`: its pretokens without comments, separated by
+/// a single line break where whitespace or comments between them held one
+/// and otherwise by a single space, or nothing next to a string, with
+/// `\r\n` line ends made `\n`.
+/// Strings are untouched.  Two sources with the same canonical form differ
+/// only in formatting and comments.
+/// ```
+/// let a = pretok::normalize("int  x = 1; // one\r\n\r\n  y = \"a  b\";\r\n");
+/// let b = pretok::normalize("/* two */ int x\t= 1;\ny = \"a  b\";");
+/// assert_eq!(a.text, "int x = 1;\ny =\"a  b\";");
+/// assert_eq!(a.text, b.text);
+/// assert_eq!(a.original(11), 24);
+/// assert_eq!(b.original_span(11..12), 21..22);
+/// ```
+pub fn normalize(s: &str) -> Normalized {
+    normalize_with(s, Config::default())
+}
+
+/// Like [normalize()](fn.normalize.html), with `cfg`.
+pub fn normalize_with(s: &str, cfg: Config) -> Normalized {
+    let mut out = Normalized::default();
+    let mut toks = Pretokenizer::with_config(s, cfg.clone().lossless(true)).peekable();
+    let mut last: Option<Pretoken> = None;
+    let mut newline = false;
+    while let Some(tok) = toks.next() {
+        let mut text = tok.s;
+        match tok.kind {
+            Kind::Newline => newline = true,
+            Kind::Whitespace | Kind::Comment => {}
+            kind => {
+                // A `\r` before a line break is part of the word before it.
+                if kind == Kind::Word
+                    && toks.peek().is_some_and(|next| next.kind == Kind::Newline)
+                {
+                    text = text.strip_suffix('\r').unwrap_or(text);
+                }
+                if text.is_empty() {
+                    continue;
+                }
+                if let Some(prev) = &last {
+                    if newline {
+                        out.text.push('\n');
+                    } else if !self_delimited(prev, &tok, &cfg) {
+                        out.text.push(' ');
+                    }
+                }
+                while on_comment_column(&out.text, &tok, &cfg) {
+                    out.text.push(' ');
+                }
+                let start = tok.offset;
+                out.map.push((out.text.len(), start..start + text.len()));
+                out.text.push_str(text);
+                last = Some(tok);
+                newline = false;
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_test_1() {
+        let src = "\r\n a\r\n\r\n\tb/*\r\n*/c \r\n \"x\r\n y\" \r";
+        let n = normalize(src);
+        assert_eq!(n.text, "a\nb c\n\"x\r\n y\"\r");
+        assert_eq!(n.text, normalize("a\nb c\n\"x\r\n y\"\t\r").text);
+        assert_ne!(n.text, normalize("a b c\n\"x\r\n y\" \r").text);
+        let spans: Vec<_> = n.map.iter().map(|(_, span)| &src[span.clone()]).collect();
+        assert_eq!(spans, ["a", "b", "c", "\"x\r\n y\"", "\r"]);
+        assert_eq!(n.original(0), 3);
+        assert_eq!(n.original(1), 4);
+        assert_eq!(n.original(2), 9);
+        assert_eq!(n.original_span(2..5), 9..17);
+        assert_eq!(n.original(n.text.len()), src.len());
+        assert_eq!(normalize(" // x\n").text, "");
+        assert_eq!(normalize("f( \"a\" , b)").text, normalize("f(\"a\", b)").text);
+        let n = normalize_with("a  'b'\"c\"", Config::toml());
+        assert_eq!(n.text, "a 'b'\"c\"");
+        assert_eq!(normalize_with(&n.text, Config::toml()).text, n.text);
+        let cfg = Config::new().column_comment('*', Some(1));
+        assert_eq!(normalize_with("a\n\t*b *c", cfg).text, "a\n *b *c");
+    }
+}
@@ -0,0 +1,169 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! A reference pretokenizer and checks of the invariants of pretokens.
+
+use crate::{Config, Kind, Pretoken, Pretokenizer};
+
+/// Pretokenize `s` with the default configuration the slow and obvious
+/// way, one character at a time, as a reference to test
+/// [Pretokenizer::new()](struct.Pretokenizer.html#method.new) against.
+/// It is meant to be easy to check by eye, not fast.
+/// ```
+/// let src = "\u{feff}a/b // c\n\"d\\\"\"e /* f */";
+/// assert_eq!(pretok::reference_tokenize(src), pretok::tokenize(src));
+/// ```
+pub fn reference_tokenize(s: &str) -> Vec<Pretoken<'_>> {
+    let chars: Vec<(usize, char)> = s.char_indices().collect();
+    let at = |i: usize| chars.get(i).map(|&(_, c)| c);
+    let offset = |i: usize| chars.get(i).map_or(s.len(), |&(offset, _)| offset);
+
+    let mut toks = Vec::new();
+    // A byte order mark is skipped.
+    let mut i = if at(0) == Some('\u{feff}') { 1 } else { 0 };
+    while let Some(c) = at(i) {
+        let start = i;
+        i += 1;
+        let kind = match (c, at(i)) {
+            (' ', _) | ('\t', _) | ('\n', _) => continue,
+            ('/', Some('/')) => {
+                while at(i).is_some() && at(i) != Some('\n') {
+                    i += 1;
+                }
+                continue;
+            }
+            ('/', Some('*')) => {
+                i += 1;
+                loop {
+                    match (at(i), at(i + 1)) {
+                        (None, _) => break,
+                        (Some('*'), Some('/')) => {
+                            i += 2;
+                            break;
+                        }
+                        _ => i += 1,
+                    }
+                }
+                continue;
+            }
+            ('"', _) => {
+                loop {
+                    match at(i) {
+                        None => break,
+                        Some('"') => {
+                            i += 1;
+                            break;
+                        }
+                        Some('\\') => i = (i + 2).min(chars.len()),
+                        Some(_) => i += 1,
+                    }
+                }
+                Kind::Quoted
+            }
+            _ => {
+                while let Some(c) = at(i) {
+                    let comment = c == '/' && matches!(at(i + 1), Some('/') | Some('*'));
+                    if c == ' ' || c == '\t' || c == '\n' || c == '"' || comment {
+                        break;
+                    }
+                    i += 1;
+                }
+                Kind::Word
+            }
+        };
+        let (start, end) = (offset(start), offset(i));
+        // A pretoken has the line where it ends.
+        let line = 1 + s[..end].matches('\n').count();
+        toks.push(Pretoken{ s: &s[start..end], line, offset: start, kind });
+    }
+    toks
+}
+
+/// Check the pretokens of `s` with the default configuration against
+/// [reference_tokenize()](fn.reference_tokenize.html), and check the
+/// invariants of [verify_with()](fn.verify_with.html).  Panics, naming `s`,
+/// if any check fails, so it can serve as a fuzz target or a property test.
+/// ```
+/// pretok::verify("a \"b\n\\\"c\" /* d */ e//f");
+/// ```
+pub fn verify(s: &str) {
+    let got: Vec<_> = Pretokenizer::new(s).collect();
+    assert_eq!(got, reference_tokenize(s), "pretokens differ from the reference for {:?}", s);
+    verify_with(s, Config::default());
+}
+
+/// Check the invariants of the pretokens of `s` with `cfg`, panicking,
+/// naming `s`, if one fails:
+///
+/// * Each pretoken's text is borrowed from `s` at its offset, and isn't
+///   empty.
+/// * Offsets strictly increase, and pretokens don't overlap.
+/// * Each pretoken's line is the line where it ends, counting newlines
+///   from the start of `s`, except that a newline has the line it ends.
+///   Without line counting, lines are 0.
+/// * In [lossless](struct.Config.html#method.lossless) mode, the
+///   pretokens put together are `s`, unless a limit such as
+///   [Config::max_tokens()](struct.Config.html#method.max_tokens) stops
+///   them early.
+/// ```
+/// use pretok::Config;
+/// pretok::verify_with("#if 0\nx\n#endif\ny", Config::new().skip_region("#if 0", "#endif"));
+/// ```
+pub fn verify_with(s: &str, cfg: Config) {
+    let limited = cfg.max_tokens.is_some() || cfg.max_bytes.is_some();
+    check(s, Pretokenizer::with_config(s, cfg.clone()));
+    if !limited {
+        let text: String = Pretokenizer::with_config(s, cfg.lossless(true)).map(|t| t.s)
+            .collect();
+        assert_eq!(text, s, "lossless pretokens don't make up {:?}", s);
+    }
+}
+
+/// Check the invariants of each pretoken from `pt` over `s`.
+fn check<'a>(s: &'a str, mut pt: Pretokenizer<'a>) {
+    let base = s.as_ptr() as usize;
+    let mut prev: Option<Pretoken<'a>> = None;
+    while let Some(t) = pt.next() {
+        let at = t.s.as_ptr() as usize;
+        assert!(at >= base && at + t.s.len() <= base + s.len() && at - base == t.offset,
+                "{:?} isn't borrowed from {:?} at its offset", t, s);
+        assert!(!t.s.is_empty(), "{:?} is empty in {:?}", t, s);
+        if let Some(p) = prev {
+            assert!(t.offset > p.offset && t.offset >= p.offset + p.s.len(),
+                    "{:?} doesn't follow {:?} in {:?}", t, p, s);
+        }
+        let line = match pt.cfg.counts_lines() {
+            true if t.kind == Kind::Newline => pt.line_at(t.offset),
+            true => pt.line_at(t.offset + t.s.len()),
+            false => 0,
+        };
+        assert_eq!(t.line, line, "{:?} has the wrong line in {:?}", t, s);
+        prev = Some(t);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testgen::Lcg;
+    use crate::{Base, Bom, Shebang};
+
+    #[test]
+    fn oracle_test_1() {
+        let pieces = ["/", "*", "\"", "\\", "\n", " ", "\t", "a", "//", "/*", "*/",
+                      "\u{e9}", "\r", "#", "\u{feff}"];
+        let cfgs = [Config::new().lazy_lines(true), Config::new().base(Base::Zero),
+                    Config::new().directives(true).nested_comments(true),
+                    Config::new().skip_region("#", "*/").bom(Bom::Token),
+                    Config::new().shebang(Shebang::Token).punctuation("*#"),
+                    Config::new().max_tokens(3), Config::makefile(), Config::toml(),
+                    Config::css()];
+        let mut rng = Lcg::new(7);
+        for _ in 0..3000 {
+            let src = rng.source(&pieces, 16);
+            verify(&src);
+            for cfg in &cfgs {
+                verify_with(&src, cfg.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Pretokens that own their text.
+
+use crate::{Kind, Pretoken};
+
+/// A [Pretoken](struct.Pretoken.html) that owns its text, for pretokens that
+/// must outlive their input.
+/// ```
+/// use pretok::{OwnedPretoken, Pretokenizer};
+/// let tok: OwnedPretoken = {
+///     let src = String::from("temporary");
+///     let tok = Pretokenizer::new(&src).next().unwrap();
+///     tok.into()
+/// };
+/// assert_eq!(tok.s, "temporary");
+/// assert_eq!(tok.as_pretoken().offset, 0);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct OwnedPretoken {
+    /// The text.
+    pub s: String,
+    /// Number > 0 of the _last_ line in this pretoken, or 0 if lines aren't
+    /// counted.
+    pub line: usize,
+    /// The byte offset of the first character in the pretoken.
+    pub offset: usize,
+    /// The classification of the pretoken.
+    pub kind: Kind,
+}
+
+impl OwnedPretoken {
+    /// Borrow as a [Pretoken](struct.Pretoken.html).
+    pub fn as_pretoken(&self) -> Pretoken<'_> {
+        Pretoken{ s: &self.s, line: self.line, offset: self.offset, kind: self.kind }
+    }
+}
+
+impl<'a> From<Pretoken<'a>> for OwnedPretoken {
+    fn from(tok: Pretoken<'a>) -> OwnedPretoken {
+        OwnedPretoken{ s: tok.s.to_string(), line: tok.line, offset: tok.offset, kind: tok.kind }
+    }
+}
+
+impl<'a> PartialEq<Pretoken<'a>> for OwnedPretoken {
+    fn eq(&self, other: &Pretoken<'a>) -> bool {
+        self.as_pretoken() == *other
+    }
+}
+
+impl<'a> PartialEq<OwnedPretoken> for Pretoken<'a> {
+    fn eq(&self, other: &OwnedPretoken) -> bool {
+        *self == other.as_pretoken()
+    }
+}
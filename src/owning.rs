@@ -0,0 +1,102 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! A pretokenizer that owns its input.
+
+use crate::{Config, OwnedPretoken, PretokError, Pretokenizer, ScanState};
+
+/// A pretokenizer that takes ownership of its input, so it has no lifetime
+/// and can be kept in a struct or returned from a function.  It returns
+/// [OwnedPretoken](struct.OwnedPretoken.html)s, otherwise the same as the
+/// [Pretokenizer](struct.Pretokenizer.html) over the same input.
+/// ```
+/// use pretok::OwnedPretokenizer;
+/// fn words(name: &str) -> OwnedPretokenizer {
+///     OwnedPretokenizer::new(format!("hello {}", name))
+/// }
+/// let toks: Vec<_> = words("world").map(|t| t.s).collect();
+/// assert_eq!(toks, vec!["hello", "world"]);
+/// ```
+#[derive(Debug)]
+pub struct OwnedPretokenizer {
+    // Declared first so it is dropped before the text it borrows.
+    pt: Pretokenizer<'static>,
+    text: String,
+}
+
+impl OwnedPretokenizer {
+    /// Create a pretokenizer that owns `text`.
+    pub fn new(text: String) -> OwnedPretokenizer {
+        OwnedPretokenizer::with_config(text, Config::default())
+    }
+
+    /// Create a pretokenizer that owns `text`, with `cfg`.
+    pub fn with_config(text: String, cfg: Config) -> OwnedPretokenizer {
+        // SAFETY: The pretokenizer borrows the heap buffer of `text`, which
+        // doesn't move when the `String` does, and is never modified or
+        // freed while the pretokenizer exists.  Only owned copies of the
+        // borrowed pretokens are handed out.
+        let s: &'static str = unsafe { &*(text.as_str() as *const str) };
+        OwnedPretokenizer{ pt: Pretokenizer::with_config(s, cfg), text }
+    }
+
+    /// The input.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The problems found so far.  See
+    /// [Pretokenizer::diagnostics()](struct.Pretokenizer.html#method.diagnostics).
+    pub fn diagnostics(&self) -> &[PretokError] {
+        self.pt.diagnostics()
+    }
+
+    /// The construct left open at the end of input.  See
+    /// [Pretokenizer::state()](struct.Pretokenizer.html#method.state).
+    pub fn state(&self) -> ScanState {
+        self.pt.state()
+    }
+
+    /// Give up the input.
+    pub fn into_text(self) -> String {
+        let OwnedPretokenizer{ pt, text } = self;
+        drop(pt);
+        text
+    }
+}
+
+impl Iterator for OwnedPretokenizer {
+    type Item = OwnedPretoken;
+
+    fn next(&mut self) -> Option<OwnedPretoken> {
+        self.pt.next().map(OwnedPretoken::from)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.pt.size_hint()
+    }
+}
+
+impl std::iter::FusedIterator for OwnedPretokenizer {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn owning_test_0() {
+        let src = "a \"b\"\n/* c";
+        let cfg = Config::new().lossless(true);
+        let mut pt = OwnedPretokenizer::with_config(src.to_string(), cfg.clone());
+        // Moving the pretokenizer leaves the pretokens it borrows in place.
+        let first = pt.next().unwrap();
+        let mut moved = Box::new(pt);
+        let rest: Vec<_> = moved.by_ref().collect();
+        let want = crate::tokenize_with(src, cfg);
+        assert_eq!(first, want[0]);
+        assert_eq!(rest, want[1..]);
+        assert_eq!(moved.diagnostics().len(), 1);
+        assert_eq!(moved.state(), ScanState::BlockComment{ depth: 1 });
+        assert_eq!(moved.text(), src);
+        assert_eq!(moved.into_text(), src);
+    }
+}
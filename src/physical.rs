@@ -0,0 +1,104 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! An iterator that groups pretokens by physical line.
+
+use crate::{Kind, Pretoken, Pretokenizer};
+
+/// An iterator over the physical lines of an input that have pretokens,
+/// each with its line number and pretokens, created by
+/// [Pretokenizer::physical_lines()](struct.Pretokenizer.html#method.physical_lines).
+///
+/// A pretoken belongs to the line where it starts, so a string spanning
+/// lines is grouped with the pretokens before it.  Comments returned by a
+/// [lossless](struct.Config.html#method.lossless) pretokenizer are grouped
+/// like other pretokens, while its whitespace and newlines are dropped.
+/// Lines are numbered as by the pretokenizer, even with
+/// [Config::lazy_lines()](struct.Config.html#method.lazy_lines).
+/// ```
+/// use pretok::Pretokenizer;
+/// let src = "name  qty\n\napple 3 \"red\ngreen\"\npear  5";
+/// let v: Vec<_> = Pretokenizer::new(src).physical_lines()
+///     .map(|(line, toks)| (line, toks.iter().map(|t| t.s).collect::<Vec<_>>()))
+///     .collect();
+/// assert_eq!(v, vec![(1, vec!["name", "qty"]),
+///                    (3, vec!["apple", "3", "\"red\ngreen\""]),
+///                    (5, vec!["pear", "5"])]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct PhysicalLines<'a> {
+    inner: Pretokenizer<'a>,
+    /// The first pretoken of the next line, with its line
+    pending: Option<(usize, Pretoken<'a>)>,
+}
+
+impl<'a> PhysicalLines<'a> {
+    pub(crate) fn new(inner: Pretokenizer<'a>) -> PhysicalLines<'a> {
+        PhysicalLines{ inner, pending: None }
+    }
+
+    /// The underlying pretokenizer.  A pretoken read ahead is lost.
+    pub fn into_inner(self) -> Pretokenizer<'a> {
+        self.inner
+    }
+
+    /// The next pretoken other than whitespace and newlines, with the line
+    /// where it starts.
+    fn read(&mut self) -> Option<(usize, Pretoken<'a>)> {
+        let tok = self.inner.by_ref()
+            .find(|t| !matches!(t.kind, Kind::Whitespace | Kind::Newline))?;
+        let line = match self.inner.cfg.counts_lines() {
+            true => tok.line - tok.s.matches('\n').count(),
+            false => self.inner.line_at(tok.offset),
+        };
+        Some((line, tok))
+    }
+}
+
+impl<'a> Iterator for PhysicalLines<'a> {
+    type Item = (usize, Vec<Pretoken<'a>>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (line, first) = match self.pending.take() {
+            Some(pending) => pending,
+            None => self.read()?,
+        };
+        let mut toks = vec![first];
+        while let Some((next_line, tok)) = self.read() {
+            if next_line != line {
+                self.pending = Some((next_line, tok));
+                break;
+            }
+            toks.push(tok);
+        }
+        Some((line, toks))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Base, Config};
+
+    #[test]
+    fn physical_test_1() {
+        let lines = |src, cfg| -> Vec<_> {
+            Pretokenizer::with_config(src, cfg).physical_lines()
+                .map(|(line, toks)| (line, toks.iter().map(|t| t.s).collect::<Vec<_>>()))
+                .collect()
+        };
+        let src = "a /* b\n */ c\n\t\"d\n\" e // f\n\ng";
+        let want = vec![(1, vec!["a"]), (2, vec!["c"]), (3, vec!["\"d\n\""]), (4, vec!["e"]),
+                        (6, vec!["g"])];
+        assert_eq!(lines(src, Config::new()), want);
+        assert_eq!(lines(src, Config::new().lazy_lines(true)), want);
+        assert_eq!(lines(src, Config::new().lossless(true)),
+                   vec![(1, vec!["a", "/* b\n */"]), (2, vec!["c"]),
+                        (3, vec!["\"d\n\""]), (4, vec!["e", "// f"]), (6, vec!["g"])]);
+        assert_eq!(lines("x\ny", Config::new().base(Base::Zero)),
+                   vec![(0, vec!["x"]), (1, vec!["y"])]);
+        assert_eq!(lines(" \n", Config::new().lossless(true)), vec![]);
+        let mut it = Pretokenizer::new("a\nb c").physical_lines();
+        assert_eq!(it.next().unwrap().0, 1);
+        assert_eq!(it.into_inner().next().unwrap().s, "c");
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! The pretokenizer contract as a trait.
+
+use crate::{Lookahead, Pretoken, Pretokenizer};
+
+/// A source of pretokens with one pretoken of lookahead and position
+/// information.  Write parsers generic over `PreLex` to accept a
+/// [Pretokenizer](struct.Pretokenizer.html), a
+/// [Lookahead](struct.Lookahead.html), or a stub or instrumented source in
+/// tests.
+/// ```
+/// use pretok::{PreLex, Pretokenizer};
+/// fn expect<'a, P: PreLex<'a>>(p: &mut P, s: &str) -> Result<(), String> {
+///     match p.peek() {
+///         Some(t) if t.s == s => { p.next(); Ok(()) }
+///         _ => Err(format!("expected {} at line {}", s, p.line())),
+///     }
+/// }
+/// let mut pt = Pretokenizer::new("( x\n");
+/// assert!(expect(&mut pt, "(").is_ok());
+/// pt.next();
+/// assert_eq!(expect(&mut pt, ")"), Err("expected ) at line 2".to_string()));
+/// ```
+pub trait PreLex<'a>: Iterator<Item = Pretoken<'a>> {
+    /// The next pretoken, without consuming it.
+    fn peek(&mut self) -> Option<&Pretoken<'a>>;
+
+    /// The byte offset of the unconsumed input.
+    fn byte_pos(&self) -> usize;
+
+    /// The line number at `byte_pos()`.
+    fn line(&self) -> usize;
+}
+
+impl<'a> PreLex<'a> for Pretokenizer<'a> {
+    fn peek(&mut self) -> Option<&Pretoken<'a>> {
+        Pretokenizer::peek(self)
+    }
+
+    fn byte_pos(&self) -> usize {
+        Pretokenizer::byte_pos(self)
+    }
+
+    fn line(&self) -> usize {
+        Pretokenizer::line(self)
+    }
+}
+
+/// `K` must be at least 1.
+impl<'a, const K: usize> PreLex<'a> for Lookahead<'a, K> {
+    fn peek(&mut self) -> Option<&Pretoken<'a>> {
+        Lookahead::peek(self)
+    }
+
+    fn byte_pos(&self) -> usize {
+        Lookahead::byte_pos(self)
+    }
+
+    fn line(&self) -> usize {
+        Lookahead::line(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions<'a, P: PreLex<'a>>(mut p: P) -> Vec<(usize, usize)> {
+        let mut v = Vec::new();
+        while p.peek().is_some() {
+            v.push((p.byte_pos(), p.line()));
+            p.next();
+        }
+        v.push((p.byte_pos(), p.line()));
+        v
+    }
+
+    #[test]
+    fn prelex_test_0() {
+        let src = "a\n  b /* c */";
+        let expected = vec![(0, 1), (4, 2), (13, 2)];
+        assert_eq!(positions(Pretokenizer::new(src)), expected);
+        assert_eq!(positions(Pretokenizer::new(src).lookahead::<2>()), expected);
+    }
+}
@@ -0,0 +1,96 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Pretokenizing input supplied in pieces by the caller.
+
+use crate::stream::Chunks;
+use crate::{Config, OwnedPretoken};
+
+/// A pretokenizer fed its input a piece at a time, for input that never
+/// exists as one `&str`, such as text arriving over a network.  Each call to
+/// [feed()](#method.feed) returns the pretokens completed so far, and
+/// [finish()](#method.finish) returns the rest.  Pretokens are the same,
+/// with the same offsets and lines, however the input is split.
+///
+/// Like [StreamingPretokenizer](struct.StreamingPretokenizer.html), a
+/// pretoken is complete once the line break after it has been fed, no
+/// diagnostics are collected, and
+/// [Config::max_tokens()](struct.Config.html#method.max_tokens) and
+/// [Config::max_bytes()](struct.Config.html#method.max_bytes) are ignored.
+/// ```
+/// use pretok::PushPretokenizer;
+/// let mut push = PushPretokenizer::new();
+/// assert_eq!(push.feed("a \"b").count(), 0);
+/// let toks: Vec<_> = push.feed(" c\"\nd").map(|t| t.s).collect();
+/// assert_eq!(toks, vec!["a", "\"b c\""]);
+/// let toks: Vec<_> = push.finish().map(|t| t.s).collect();
+/// assert_eq!(toks, vec!["d"]);
+/// ```
+#[derive(Debug)]
+pub struct PushPretokenizer {
+    chunks: Chunks,
+}
+
+impl PushPretokenizer {
+    /// Create a push pretokenizer with the default configuration.
+    pub fn new() -> PushPretokenizer {
+        PushPretokenizer::with_config(Config::default())
+    }
+
+    /// Create a push pretokenizer with `cfg`.
+    pub fn with_config(cfg: Config) -> PushPretokenizer {
+        PushPretokenizer{ chunks: Chunks::new(cfg) }
+    }
+
+    /// Append `chunk` to the input, returning the pretokens it completes.
+    pub fn feed(&mut self, chunk: &str) -> impl Iterator<Item = OwnedPretoken> + '_ {
+        self.chunks.push_str(chunk);
+        // Only a line break can complete a pretoken.
+        if chunk.contains('\n') {
+            self.chunks.pretokenize();
+        }
+        self.chunks.ready.drain(..)
+    }
+
+    /// End the input, returning the remaining pretokens.
+    pub fn finish(mut self) -> impl Iterator<Item = OwnedPretoken> {
+        // Input from `&str` can't end inside a UTF-8 sequence.
+        let _ = self.chunks.end();
+        self.chunks.pretokenize();
+        self.chunks.ready.into_iter()
+    }
+}
+
+impl Default for PushPretokenizer {
+    fn default() -> PushPretokenizer {
+        PushPretokenizer::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_test_0() {
+        let src = "#!/bin/x\n#define A \"a\\\n b\"\n  x/**/y // z\n\"\u{e9}\u{1F600}\" ??/\n/* \n*/ w\u{e9}";
+        let configs = [Config::new(), Config::new().lossless(true),
+                       Config::new().directives(true).trigraphs(true)];
+        let bounds: Vec<_> = src.char_indices().map(|(i, _)| i).chain(Some(src.len())).collect();
+        for cfg in configs.iter() {
+            let want = crate::tokenize_with(src, cfg.clone());
+            // Split at every pair of character boundaries.
+            for (i, &a) in bounds.iter().enumerate() {
+                for &b in &bounds[i..] {
+                    let mut push = PushPretokenizer::with_config(cfg.clone());
+                    let mut got: Vec<_> = push.feed(&src[..a]).collect();
+                    got.extend(push.feed(&src[a..b]));
+                    got.extend(push.feed(""));
+                    got.extend(push.feed(&src[b..]));
+                    got.extend(push.finish());
+                    assert_eq!(got, want, "{:?} split at {} and {}", cfg, a, b);
+                }
+            }
+        }
+        assert_eq!(PushPretokenizer::default().finish().count(), 0);
+    }
+}
@@ -0,0 +1,79 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Pretokenizing from Python through pyo3.
+
+use crate::{Config, OwnedPretokenizer};
+use pyo3::prelude::*;
+
+/// A Python iterator over the pretokens of a string, yielding
+/// `(text, line, offset, kind)` tuples.  The `offset` counts characters, as
+/// Python string indexes do, so `source[offset:offset + len(text)]` is
+/// `text`, and `kind` is the name of the [Kind](enum.Kind.html), such as
+/// `"Word"`.
+///
+/// Exported to Python as `pretok.Pretokenizer` by the `pretok` module:
+/// ```python
+/// import pretok
+/// for text, line, offset, kind in pretok.Pretokenizer(source, lossless=False):
+///     ...
+/// ```
+#[pyclass(name = "Pretokenizer", unsendable)]
+#[derive(Debug)]
+pub struct PyPretokenizer {
+    pt: OwnedPretokenizer,
+    /// The byte offset and character offset of the last pretoken
+    byte: usize,
+    chars: usize,
+}
+
+#[pymethods]
+impl PyPretokenizer {
+    /// Pretokenize `source`, with whitespace and comments too if `lossless`.
+    #[new]
+    #[pyo3(signature = (source, lossless = false))]
+    pub fn new(source: String, lossless: bool) -> PyPretokenizer {
+        let pt = OwnedPretokenizer::with_config(source, Config::new().lossless(lossless));
+        PyPretokenizer{ pt, byte: 0, chars: 0 }
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(mut slf: PyRefMut<'_, Self>) -> Option<(String, usize, usize, String)> {
+        slf.next_tuple()
+    }
+}
+
+impl PyPretokenizer {
+    /// The next pretoken as a tuple, with its offset in characters.
+    fn next_tuple(&mut self) -> Option<(String, usize, usize, String)> {
+        let tok = self.pt.next()?;
+        self.chars += self.pt.text()[self.byte..tok.offset].chars().count();
+        self.byte = tok.offset;
+        Some((tok.s, tok.line, self.chars, format!("{:?}", tok.kind)))
+    }
+}
+
+/// The `pretok` Python module.
+#[pymodule]
+#[pyo3(name = "pretok")]
+fn pretok_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyPretokenizer>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn python_test_1() {
+        let mut pt = PyPretokenizer::new("\u{e9}\u{1f600} \"a\"\n/* b */ c".to_string(), false);
+        let v: Vec<_> = std::iter::from_fn(|| pt.next_tuple()).collect();
+        assert_eq!(v, vec![("\u{e9}\u{1f600}".to_string(), 1, 0, "Word".to_string()),
+                           ("\"a\"".to_string(), 1, 3, "Quoted".to_string()),
+                           ("c".to_string(), 2, 15, "Word".to_string())]);
+        let mut pt = PyPretokenizer::new("a /* b */".to_string(), true);
+        assert_eq!(std::iter::from_fn(|| pt.next_tuple()).count(), 3);
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Pretokenizing only the part of the input around a byte range.
+
+use crate::{Config, Pretoken, Pretokenizer, ResumePoint};
+use std::ops::Range;
+
+impl<'a> Pretokenizer<'a> {
+    /// The pretokens of `s` that overlap the bytes in `range`, or for an
+    /// empty range, the pretoken containing its start.  The input before the
+    /// range is only scanned for pretoken boundaries, without lines, hooks
+    /// or checks, so this is cheaper than pretokenizing up to the range.
+    /// Offsets and lines are those of the whole input, and the pretokens
+    /// are always those a full scan would find.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let src = "a\n/* b\n c */ d \"e\n f\" g\nh";
+    /// let v: Vec<_> = Pretokenizer::tokens_in_range(src, 10..17).iter()
+    ///     .map(|t| (t.s, t.line)).collect();
+    /// assert_eq!(v, vec![("d", 3), ("\"e\n f\"", 4)]);
+    /// ```
+    pub fn tokens_in_range(s: &'a str, range: Range<usize>) -> Vec<Pretoken<'a>> {
+        Pretokenizer::tokens_in_range_with_config(s, range, Config::default())
+    }
+
+    /// Like
+    /// [Pretokenizer::tokens_in_range()](struct.Pretokenizer.html#method.tokens_in_range),
+    /// with `cfg`.
+    pub fn tokens_in_range_with_config(s: &'a str, range: Range<usize>, cfg: Config)
+        -> Vec<Pretoken<'a>> {
+        let start = crate::floor_char_boundary(s, range.start);
+        let end = crate::floor_char_boundary(s, range.end).max(start);
+        let point = point_before(s, &cfg, start);
+        // An empty range still selects the pretoken containing it.
+        let last = end.max(start + 1);
+        Pretokenizer::resume(s, cfg, point)
+            .take_while(|t| t.offset < last)
+            .filter(|t| start < t.offset + t.s.len())
+            .collect()
+    }
+}
+
+/// The point just after the last pretoken of `s` ending at or before
+/// `offset`, or the start of input if there is none.  Pretokenizing from
+/// there gives what a full scan would, including any whitespace and
+/// comments before `offset`.
+pub(crate) fn point_before(s: &str, cfg: &Config, offset: usize) -> ResumePoint {
+    let mut pt = Pretokenizer::with_config(s, cfg.clone().lossless(false).offsets_only(true));
+    pt.skip_blanks = true;
+    let mut point = pt.resume_point();
+    while let Some(tok) = Pretokenizer::scan(&mut pt) {
+        if tok.offset + tok.s.len() > offset {
+            break;
+        }
+        point = pt.resume_point();
+    }
+    point.line = memchr::memchr_iter(b'\n', &s.as_bytes()[..point.offset]).count()
+        + cfg.first_line();
+    point
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testgen::Lcg;
+
+    #[test]
+    fn range_test_0() {
+        let src = "a /* b\n c */ d \"e\n f\" g\n#if 0\nx y\n#endif\n#define h \\\n i\n\
+                   // off\n \"j\"\n// on\nk /*l*/ m\n\u{e9} n";
+        let configs = [Config::new(), Config::new().lossless(true),
+                       Config::new().directives(true).skip_region("#if 0", "#endif")
+                           .tool_directives("off", "on"),
+                       Config::new().trigraphs(true).nested_comments(true)];
+        for cfg in configs.iter() {
+            let all = crate::tokenize_with(src, cfg.clone());
+            for start in (0..=src.len()).filter(|&i| src.is_char_boundary(i)) {
+                for end in (start..=src.len()).filter(|&i| src.is_char_boundary(i)) {
+                    let want: Vec<_> = all.iter()
+                        .filter(|t| t.offset < end.max(start + 1)
+                                && start < t.offset + t.s.len())
+                        .cloned().collect();
+                    let got = Pretokenizer::tokens_in_range_with_config(src, start..end,
+                                                                        cfg.clone());
+                    assert_eq!(got, want, "{:?} {}..{}", cfg, start, end);
+                }
+            }
+        }
+        assert!(Pretokenizer::tokens_in_range("", 0..0).is_empty());
+        assert!(Pretokenizer::tokens_in_range("a b", 1..2).is_empty());
+
+        // A line inside a string can look like a line comment.
+        let src = "\"\r// off\n)//*#??/\"\"\"";
+        let v: Vec<_> = Pretokenizer::tokens_in_range(src, 18..20).iter().map(|t| t.s).collect();
+        assert_eq!(v, vec!["\"\""]);
+
+        let pieces = ["/", "*", "\"", "\\", "\n", "\r", " ", "a", "#", "??/", "// off",
+                      "// on", "#if 0", "#endif", "\u{e9}"];
+        let mut rng = Lcg::new(19);
+        for cfg in configs.iter() {
+            for _ in 0..100 {
+                let src = rng.source(&pieces, 16);
+                let all = crate::tokenize_with(&src, cfg.clone());
+                let bounds: Vec<_> = (0..=src.len()).filter(|&i| src.is_char_boundary(i))
+                    .collect();
+                for _ in 0..10 {
+                    let a = bounds[rng.below(bounds.len())];
+                    let b = bounds[rng.below(bounds.len())];
+                    let (start, end) = (a.min(b), a.max(b));
+                    let want: Vec<_> = all.iter()
+                        .filter(|t| t.offset < end.max(start + 1)
+                                && start < t.offset + t.s.len())
+                        .cloned().collect();
+                    let got = Pretokenizer::tokens_in_range_with_config(&src, start..end,
+                                                                        cfg.clone());
+                    assert_eq!(got, want, "{:?} {}..{}", src, start, end);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! An iterator over every region of the input.
+
+use crate::{Config, PretokError, Pretoken, Pretokenizer};
+
+/// An iterator over every region of the input, in order: pretokens, runs of
+/// whitespace, newlines and comments, distinguished by their
+/// [Kind](enum.Kind.html).  Concatenating the regions reproduces the input.
+///
+/// [Pretokenizer](struct.Pretokenizer.html) is this iterator with the
+/// [trivia](enum.Kind.html#method.is_trivia) filtered out.  Use this one for
+/// formatters, highlighters and minifiers that need the whole classified
+/// stream.  Pretokenizer hooks, checks and budgets don't apply here, but
+/// problems found while scanning, such as an unterminated string, are
+/// still recorded in `diagnostics()`.
+/// ```
+/// use pretok::{Kind, RawPretokenizer};
+/// let kinds: Vec<_> = RawPretokenizer::new("a /* b */\n").map(|t| t.kind).collect();
+/// assert_eq!(kinds, vec![Kind::Word, Kind::Whitespace, Kind::Comment, Kind::Newline]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct RawPretokenizer<'a> {
+    inner: Pretokenizer<'a>,
+}
+
+impl<'a> RawPretokenizer<'a> {
+    /// Create a raw pretokenizer with the default configuration.
+    pub fn new(s: &'a str) -> RawPretokenizer<'a> {
+        RawPretokenizer::with_config(s, Config::default())
+    }
+
+    /// Create a raw pretokenizer with `cfg`.
+    /// ```
+    /// use pretok::{Config, Kind, RawPretokenizer, Shebang};
+    /// let cfg = Config::new().shebang(Shebang::Skip);
+    /// let mut raw = RawPretokenizer::with_config("#!/bin/sh\nx", cfg);
+    /// assert_eq!(raw.next().unwrap().kind, Kind::Comment);
+    /// ```
+    pub fn with_config(s: &'a str, cfg: Config) -> RawPretokenizer<'a> {
+        RawPretokenizer{ inner: Pretokenizer::with_config(s, cfg) }
+    }
+
+    /// The recoverable problems found so far, in input order.  See
+    /// [Pretokenizer::diagnostics()](struct.Pretokenizer.html#method.diagnostics).
+    pub fn diagnostics(&self) -> &[PretokError] {
+        self.inner.diagnostics()
+    }
+
+    /// The lines collected so far by
+    /// [Config::pragma()](struct.Config.html#method.pragma).  See
+    /// [Pretokenizer::pragmas()](struct.Pretokenizer.html#method.pragmas).
+    pub fn pragmas(&self) -> &[Pretoken<'a>] {
+        self.inner.pragmas()
+    }
+}
+
+impl<'a> Iterator for RawPretokenizer<'a> {
+    type Item = Pretoken<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tok = self.inner.scan_event();
+        if !self.inner.cfg.counts_lines() {
+            self.inner.fix_lines();
+        }
+        tok
+    }
+}
+
+impl<'a> std::iter::FusedIterator for RawPretokenizer<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Kind, Shebang};
+
+    fn concat(s: &str, cfg: Config) -> String {
+        RawPretokenizer::with_config(s, cfg).map(|t| t.s).collect()
+    }
+
+    #[test]
+    fn raw_test_0() {
+        let v: Vec<_> = RawPretokenizer::new("a  \"b\"//c\n\t/* d\n*/ e").collect();
+        assert_eq!(v, vec![
+            Pretoken{s: "a", line: 1, offset: 0, kind: Kind::Word},
+            Pretoken{s: "  ", line: 1, offset: 1, kind: Kind::Whitespace},
+            Pretoken{s: "\"b\"", line: 1, offset: 3, kind: Kind::Quoted},
+            Pretoken{s: "//c", line: 1, offset: 6, kind: Kind::Comment},
+            Pretoken{s: "\n", line: 1, offset: 9, kind: Kind::Newline},
+            Pretoken{s: "\t", line: 2, offset: 10, kind: Kind::Whitespace},
+            Pretoken{s: "/* d\n*/", line: 3, offset: 11, kind: Kind::Comment},
+            Pretoken{s: " ", line: 3, offset: 18, kind: Kind::Whitespace},
+            Pretoken{s: "e", line: 3, offset: 19, kind: Kind::Word},
+        ]);
+    }
+
+    #[test]
+    fn raw_test_1() {
+        // Every region is accounted for
+        let src = "#!/bin/sh\n#define X 1 // one\n  /* open";
+        let cfg = Config::new().shebang(Shebang::Skip).directives(true);
+        assert_eq!(concat(src, cfg), src);
+
+        let src = "a\n// off\nraw  stuff\n// on\nb \"unterminated";
+        let cfg = Config::new().tool_directives("off", "on");
+        assert_eq!(concat(src, cfg.clone()), src);
+        let kinds: Vec<_> = RawPretokenizer::with_config(src, cfg).map(|t| t.kind).collect();
+        assert_eq!(kinds, vec![
+            Kind::Word, Kind::Newline, Kind::Comment, Kind::Newline, Kind::Opaque,
+            Kind::Newline, Kind::Comment, Kind::Newline, Kind::Word, Kind::Whitespace,
+            Kind::Quoted,
+        ]);
+
+        let src = "x\n#if 0\ny\n#endif\nz /";
+        let cfg = Config::new().skip_region("#if 0", "#endif").trigraphs(true);
+        assert_eq!(concat(src, cfg), src);
+        let mut raw = RawPretokenizer::new("a /* b");
+        assert_eq!(raw.by_ref().count(), 3);
+        assert_eq!(raw.diagnostics().len(), 1);
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Rebuilding source text from edited pretokens.
+
+use crate::{Config, OwnedPretoken, Pretokenizer};
+
+/// The source text of `pieces`, the pretokens of `s` in
+/// [lossless](struct.Config.html#method.lossless) mode with the text of
+/// some replaced and some removed, for simple rewriting tools.
+///
+/// The pieces are concatenated, so whitespace and comments are kept as
+/// they are.  Where a replaced piece would run together with a piece next
+/// to it, as a word does with a word or `/` with a comment, a space is put
+/// between them.  A piece is replaced if its text differs from that of `s`
+/// at its offset.
+/// ```
+/// use pretok::{Config, OwnedPretoken, Pretokenizer};
+/// let src = "x = y+1; /* y */\n";
+/// let cfg = Config::new().lossless(true);
+/// let mut pieces: Vec<OwnedPretoken> = Pretokenizer::with_config(src, cfg)
+///     .map(Into::into)
+///     .collect();
+/// for piece in &mut pieces {
+///     if piece.s == "=" {
+///         piece.s = "+=".to_string();
+///     } else if piece.s == "y+1;" {
+///         piece.s = "\"y\" + 1;".to_string();
+///     }
+/// }
+/// assert_eq!(pretok::rebuild(src, &pieces), "x += \"y\" + 1; /* y */\n");
+/// pieces.remove(5);
+/// pieces[4].s = "y/".to_string();
+/// assert_eq!(pretok::rebuild(src, &pieces), "x += y/ /* y */\n");
+/// ```
+pub fn rebuild(s: &str, pieces: &[OwnedPretoken]) -> String {
+    rebuild_with(s, pieces, Config::default())
+}
+
+/// Like [rebuild()](fn.rebuild.html), where the pieces were pretokenized
+/// with `cfg`.
+pub fn rebuild_with(s: &str, pieces: &[OwnedPretoken], cfg: Config) -> String {
+    let cfg = cfg.lossless(true);
+    let changed = |p: &OwnedPretoken| s.get(p.offset..p.offset + p.s.len()) != Some(&p.s);
+    let mut out = String::with_capacity(s.len());
+    let mut last: Option<(&OwnedPretoken, bool)> = None;
+    for piece in pieces {
+        let piece_changed = changed(piece);
+        if let Some((prev, prev_changed)) = last {
+            if (prev_changed || piece_changed) && !prev.s.is_empty() && !piece.s.is_empty()
+                && !separate(&prev.s, &piece.s, &cfg)
+            {
+                out.push(' ');
+            }
+        }
+        out.push_str(&piece.s);
+        if !piece.s.is_empty() {
+            last = Some((piece, piece_changed));
+        }
+    }
+    out
+}
+
+/// True if `a` followed directly by `b` has a pretoken boundary between
+/// them.
+fn separate(a: &str, b: &str, cfg: &Config) -> bool {
+    let joined = [a, b].concat();
+    let mut pt = Pretokenizer::with_config(&joined, cfg.clone());
+    pt.any(|t| t.offset == a.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebuild_test_1() {
+        let src = "f(a)\"b\" c//d\n\"e\"";
+        let cfg = Config::new().lossless(true);
+        let pieces: Vec<OwnedPretoken> = Pretokenizer::with_config(src, cfg).map(Into::into)
+            .collect();
+        assert_eq!(rebuild(src, &pieces), src);
+        let edit = |i: usize, text: &str| {
+            let mut pieces = pieces.clone();
+            pieces[i].s = text.to_string();
+            rebuild(src, &pieces)
+        };
+        assert_eq!(edit(0, "g"), "g\"b\" c//d\n\"e\"");
+        assert_eq!(edit(1, "x"), "f(a) x c//d\n\"e\"");
+        assert_eq!(edit(1, ""), "f(a) c//d\n\"e\"");
+        assert_eq!(edit(4, "/d"), "f(a)\"b\" c /d\n\"e\"");
+        assert_eq!(edit(6, "e"), "f(a)\"b\" c//d\ne");
+    }
+}
@@ -0,0 +1,136 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Finding regions marked by paired comments or directives.
+
+use crate::{Config, Kind, Pretokenizer};
+use std::ops::Range;
+
+/// A region between paired markers such as `#region` and `#endregion`,
+/// found by [find_regions()](fn.find_regions.html), with the regions nested
+/// in it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Region<'a> {
+    /// The text after the opening marker, trimmed.
+    pub name: &'a str,
+    /// From the start of the opening marker's comment or directive to the
+    /// end of the closing one's.
+    pub span: Range<usize>,
+    /// The line of the opening marker, from 1 by default.
+    pub start_line: usize,
+    /// The line of the closing marker.
+    pub end_line: usize,
+    /// The regions directly inside this one, in input order.
+    pub children: Vec<Region<'a>>,
+}
+
+/// The outermost regions of `s` between comments or directives starting
+/// with `open` and `close`, each with the regions nested in it, in input
+/// order.  A comment matches when its text, after the `//` or `/*`, starts
+/// with the marker followed by whitespace or nothing, so `// #region Setup`
+/// opens a region named `Setup` when `open` is `#region`.  A directive
+/// matches when it starts with the marker, with
+/// [Config::directives()](struct.Config.html#method.directives) enabled.
+///
+/// A closing marker closes the innermost open region.  One with no open
+/// region is ignored, as is an opening marker never closed, whose regions
+/// are left to the region around it.
+/// ```
+/// let src = "// #region A\nx;\n  // #region B\n  y;\n  // #endregion\n// #endregion\nz;";
+/// let regions = pretok::find_regions(src, "#region", "#endregion");
+/// assert_eq!(regions.len(), 1);
+/// assert_eq!((regions[0].name, regions[0].start_line, regions[0].end_line), ("A", 1, 6));
+/// let inner = &regions[0].children[0];
+/// assert_eq!(&src[inner.span.clone()], "// #region B\n  y;\n  // #endregion");
+/// ```
+pub fn find_regions<'a>(s: &'a str, open: &str, close: &str) -> Vec<Region<'a>> {
+    find_regions_with(s, open, close, Config::default())
+}
+
+/// Like [find_regions()](fn.find_regions.html), with `cfg`.
+pub fn find_regions_with<'a>(s: &'a str, open: &str, close: &str, cfg: Config)
+    -> Vec<Region<'a>>
+{
+    // The regions open so far, innermost last, each with its children.
+    // The bottom entry collects the outermost regions.
+    let mut stack = vec![Region{ name: "", span: 0..0, start_line: 0, end_line: 0,
+                                 children: Vec::new() }];
+    let (mut pos, mut line) = (0, cfg.first_line());
+    for tok in Pretokenizer::with_config(s, cfg.lossless(true)) {
+        let text = match tok.kind {
+            Kind::Comment => {
+                let text = tok.s.strip_prefix("//").or_else(|| tok.s.strip_prefix("/*"));
+                let text = match text {
+                    Some(text) => text,
+                    None => continue,
+                };
+                text.strip_suffix("*/").unwrap_or(text).trim_start()
+            }
+            Kind::Directive => tok.s,
+            _ => continue,
+        };
+        line += memchr::memchr_iter(b'\n', &s.as_bytes()[pos..tok.offset]).count();
+        pos = tok.offset;
+        let end = tok.offset + tok.s.len();
+        if let Some(name) = after_marker(text, open) {
+            stack.push(Region{ name, span: tok.offset..end, start_line: line, end_line: line,
+                               children: Vec::new() });
+        } else if after_marker(text, close).is_some() && stack.len() > 1 {
+            let mut region = stack.pop().unwrap();
+            region.span.end = end;
+            region.end_line = line + tok.s.matches('\n').count();
+            stack.last_mut().unwrap().children.push(region);
+        }
+    }
+    // Leave the children of regions never closed to the regions around them.
+    while stack.len() > 1 {
+        let unclosed = stack.pop().unwrap();
+        stack.last_mut().unwrap().children.extend(unclosed.children);
+    }
+    let mut outer = stack.pop().unwrap().children;
+    outer.sort_by_key(|r| r.span.start);
+    outer
+}
+
+/// The rest of `text` trimmed, if it starts with `marker` followed by
+/// whitespace or nothing.
+fn after_marker<'a>(text: &'a str, marker: &str) -> Option<&'a str> {
+    let rest = text.strip_prefix(marker)?;
+    if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+        Some(rest.trim())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regions_test_1() {
+        let src = "#pragma region Top\n/* region Inner */ \"// region x\"\n// region Lost\n\
+                   //regional Tight\n/* endregion\n */\n// endregion\n#pragma endregion";
+        let cfg = Config::new().directives(true);
+        let regions = find_regions_with(src, "#pragma region", "#pragma endregion", cfg.clone());
+        assert_eq!(regions.len(), 1);
+        assert_eq!((regions[0].name, regions[0].span.clone()), ("Top", 0..src.len()));
+        assert!(regions[0].children.is_empty());
+
+        let regions = find_regions_with(src, "region", "endregion", cfg);
+        let v: Vec<_> = regions.iter()
+            .map(|r| (r.name, r.start_line, r.end_line, r.children.len()))
+            .collect();
+        assert_eq!(v, vec![("Inner", 2, 7, 1)]);
+        let lost = &regions[0].children[0];
+        assert_eq!((lost.name, lost.start_line, lost.end_line), ("Lost", 3, 6));
+
+        // Unclosed regions leave their children to the region around them.
+        let src = "//#a 1\n//#a 2\n//#a 3\n//#b\n//#b\n";
+        let regions = find_regions(src, "#a", "#b");
+        let v: Vec<_> = regions.iter().map(|r| r.name).collect();
+        assert_eq!(v, vec!["2"]);
+        let src = "//#a 1\n//#a 2\n//#b\n";
+        let v: Vec<_> = find_regions(src, "#a", "#b").iter().map(|r| r.name).collect();
+        assert_eq!(v, vec!["2"]);
+    }
+}
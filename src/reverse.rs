@@ -0,0 +1,131 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Pretokenizing backward from an offset.
+
+use crate::{Config, Kind, PretokError, Pretoken, Pretokenizer};
+
+/// An iterator over the pretokens before an offset, nearest first, created
+/// by [Pretokenizer::before()](struct.Pretokenizer.html#method.before).
+///
+/// The input is pretokenized a line at a time, working backward, so finding
+/// the pretoken before a cursor costs about one line of scanning rather
+/// than the whole prefix.  When a line turns out to start inside a block
+/// comment or a quoted string, earlier lines are included until the
+/// construct is complete.  A string left open at the offset can't be told
+/// from the end of one opened on an earlier line, so it is scanned back to
+/// the start of the input.  A pretoken that straddles the offset is cut
+/// short at the offset.
+/// ```
+/// use pretok::Pretokenizer;
+/// let src = "let x = 1;\nlet y = x + /* one */ 1;";
+/// let v: Vec<_> = Pretokenizer::before(src, 23).map(|t| t.s).take(4).collect();
+/// assert_eq!(v, vec!["+", "x", "=", "y"]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Before<'a> {
+    /// The input up to the offset
+    s: &'a str,
+    cfg: Config,
+    /// Byte offset of the start of the lines already pretokenized
+    end: usize,
+    /// Line number at `end`
+    line: usize,
+    /// Pretokens of the lines last pretokenized, in input order
+    buf: Vec<Pretoken<'a>>,
+}
+
+impl<'a> Before<'a> {
+    pub(crate) fn new(s: &'a str, offset: usize, cfg: Config) -> Before<'a> {
+        let offset = crate::floor_char_boundary(s, offset);
+        let s = &s[..offset];
+        let line = s.matches('\n').count() + cfg.first_line();
+        Before{ s, cfg, end: offset, line, buf: Vec::new() }
+    }
+
+    /// The byte offset of the start of the line before `end`.
+    fn line_start(&self, end: usize) -> usize {
+        let s = &self.s[..end];
+        let s = s.strip_suffix('\n').unwrap_or(s);
+        s.rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    /// Pretokenize the lines from `start` to `self.end` into `self.buf`.
+    /// Returns false if the first of those lines probably starts inside a
+    /// block comment or quoted string.
+    fn scan_lines(&mut self, start: usize, line: usize) -> bool {
+        let s = &self.s[..self.end];
+        let mut pt = Pretokenizer::with_config(s, self.cfg.clone());
+        pt.pos = crate::grapheme_cursor_at(s, start);
+        pt.line = line;
+        self.buf.clear();
+        self.buf.extend(pt.by_ref());
+
+        // A stray "*/" closes a comment opened on an earlier line.  A string
+        // left open may really be the end of a string opened on an earlier
+        // line, whose closing quote was taken for an opening one.
+        let stray_close = self.buf.iter()
+            .any(|t| t.kind == Kind::Word && t.s.contains("*/"));
+        let stray_quote = pt.diagnostics.iter()
+            .any(|e| matches!(e, PretokError::UnterminatedString{..}));
+        !stray_close && !stray_quote
+    }
+}
+
+impl<'a> Iterator for Before<'a> {
+    type Item = Pretoken<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(tok) = self.buf.pop() {
+                return Some(tok);
+            }
+            if self.end == 0 {
+                return None;
+            }
+            let mut start = self.line_start(self.end);
+            let mut line = self.line - self.s[start..self.end].matches('\n').count();
+            while !self.scan_lines(start, line) && start > 0 {
+                start = self.line_start(start);
+                line -= 1;
+            }
+            self.end = start;
+            self.line = line;
+        }
+    }
+}
+
+impl<'a> std::iter::FusedIterator for Before<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reverse_test_0() {
+        assert_eq!(Pretokenizer::before("", 0).next(), None);
+        assert_eq!(Pretokenizer::before("a b", 0).next(), None);
+
+        let src = "a\n\nb \"c\n d\" /* e\n * f */ g\nh";
+        let v: Vec<_> = Pretokenizer::before(src, src.len()).collect();
+        let mut fwd: Vec<_> = Pretokenizer::new(src).collect();
+        fwd.reverse();
+        assert_eq!(v, fwd);
+
+        // Cut at the offset
+        let v: Vec<_> = Pretokenizer::before("abc def", 5).map(|t| t.s).collect();
+        assert_eq!(v, vec!["d", "abc"]);
+        let v: Vec<_> = Pretokenizer::before("x\n\"a\nb\"\n", 8).collect();
+        assert_eq!(v, vec![
+            Pretoken{s: "\"a\nb\"", line: 3, offset: 2, kind: Kind::Quoted},
+            Pretoken{s: "x", line: 1, offset: 0, kind: Kind::Word},
+        ]);
+
+        // At the end of input, on the line with the closing quote
+        for src in &["s = \"a\nb c\" d", "s = \"a\nb\nc\" d", "x\ny \"ab"] {
+            let v: Vec<_> = Pretokenizer::before(src, src.len()).collect();
+            let mut fwd: Vec<_> = Pretokenizer::new(src).collect();
+            fwd.reverse();
+            assert_eq!(v, fwd);
+        }
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! A minimal scanner returning spans.
+
+use crate::{Config, Kind, Pretokenizer};
+
+/// An iterator over the pretokens of the input as `(start, end, kind)`
+/// byte spans.
+///
+/// The scanner runs the same state machine as
+/// [Pretokenizer](struct.Pretokenizer.html) but skips everything layered on
+/// top of it: hooks, filters, checks, budgets and the lookahead cache.
+/// Runs of whitespace and newlines are passed over inside the scan loop, and
+/// lines are only counted when a diagnostic needs one.  Indexing pipelines
+/// that only need offsets get a leaner loop; see `benches/scanner.rs`.
+/// Problems found while scanning are still recorded in `diagnostics()`.
+/// ```
+/// use pretok::{Kind, Scanner};
+/// let spans: Vec<_> = Scanner::new("a \"b\" // c").collect();
+/// assert_eq!(spans, vec![(0, 1, Kind::Word), (2, 5, Kind::Quoted)]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Scanner<'a> {
+    inner: Pretokenizer<'a>,
+}
+
+impl<'a> Scanner<'a> {
+    /// Create a scanner with the default configuration.
+    pub fn new(s: &'a str) -> Scanner<'a> {
+        Scanner::with_config(s, Config::default())
+    }
+
+    /// Create a scanner with `cfg`.  Checks enabled in `cfg` are ignored.
+    /// ```
+    /// use pretok::{Config, Kind, Scanner};
+    /// let mut sc = Scanner::with_config("#define X\nX", Config::new().directives(true));
+    /// assert_eq!(sc.next(), Some((0, 9, Kind::Directive)));
+    /// ```
+    pub fn with_config(s: &'a str, cfg: Config) -> Scanner<'a> {
+        // Spans have no lines, so lines are only found for diagnostics.
+        let lazy = !cfg.offsets_only;
+        let mut inner = Pretokenizer::with_config(s, cfg.lazy_lines(lazy));
+        inner.skip_blanks = !inner.cfg.lossless;
+        Scanner{ inner }
+    }
+
+    /// The recoverable problems found so far, in input order.  See
+    /// [Pretokenizer::diagnostics()](struct.Pretokenizer.html#method.diagnostics).
+    pub fn diagnostics(&self) -> &[crate::PretokError] {
+        self.inner.diagnostics()
+    }
+}
+
+impl<'a> Iterator for Scanner<'a> {
+    type Item = (usize, usize, Kind);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tok = Pretokenizer::scan(&mut self.inner);
+        if self.inner.lines_fixed < self.inner.diagnostics.len() {
+            self.inner.fix_lines();
+        }
+        tok.map(|tok| (tok.offset, tok.offset + tok.s.len(), tok.kind))
+    }
+}
+
+impl<'a> std::iter::FusedIterator for Scanner<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testgen::Lcg;
+
+    #[test]
+    fn scanner_test_0() {
+        let src = "x = \"y\\\"\" /* z */ w;\n#if\nq \"open";
+        let cfg = Config::new().directives(true).max_token_len(1);
+        let spans: Vec<_> = Scanner::with_config(src, cfg.clone()).collect();
+        let toks: Vec<_> = Pretokenizer::with_config(src, cfg).map(|t| {
+            (t.offset, t.offset + t.s.len(), t.kind)
+        }).collect();
+        assert_eq!(spans, toks);
+
+        let mut sc = Scanner::new(src);
+        assert_eq!(sc.by_ref().count(), 7);
+        assert_eq!(sc.diagnostics().len(), 1);
+    }
+
+    #[test]
+    fn scanner_test_1() {
+        // Passing over blanks gives the same spans and diagnostics.
+        let pieces = ["/", "*", "\"", "\n", " ", "\t", "a", "b c", "#", "// off", "// on",
+                      "#pragma x", "/*x*/", "\r\n"];
+        let configs = [
+            Config::new(),
+            Config::new().directives(true).pragma("#pragma"),
+            Config::new().tool_directives("off", "on").skip_region("#", "\n"),
+            Config::new().lossless(true),
+        ];
+        let mut rng = Lcg::new(7);
+        for cfg in configs.iter() {
+            for _ in 0..300 {
+                let src = rng.source(&pieces, 20);
+                let mut sc = Scanner::with_config(&src, cfg.clone());
+                let spans: Vec<_> = sc.by_ref().collect();
+                let mut pt = Pretokenizer::with_config(&src, cfg.clone());
+                let toks: Vec<_> = pt.by_ref().map(|t| (t.offset, t.offset + t.s.len(), t.kind))
+                    .collect();
+                assert_eq!(spans, toks, "{:?}", src);
+                assert_eq!(sc.diagnostics(), pt.diagnostics(), "{:?}", src);
+            }
+        }
+    }
+
+    #[test]
+    fn scanner_test_2() {
+        // Every profile gives the same spans as the Pretokenizer.
+        let pieces = ["\n", "\t", " ", "a", "#", "/*", "*/", "//", "\"", "'", "[b]", ":", "\\",
+                      "$(x)", ";", "\r\n"];
+        let profiles = [Config::new(), Config::jsonc(), Config::toml(), Config::makefile(),
+                        Config::css()];
+        let mut rng = Lcg::new(11);
+        for cfg in profiles.iter() {
+            for _ in 0..300 {
+                let src = rng.source(&pieces, 20);
+                let spans: Vec<_> = Scanner::with_config(&src, cfg.clone()).collect();
+                let toks: Vec<_> = Pretokenizer::with_config(&src, cfg.clone())
+                    .map(|t| (t.offset, t.offset + t.s.len(), t.kind)).collect();
+                assert_eq!(spans, toks, "{:?}", src);
+            }
+            let spans: Vec<_> = Scanner::with_config("\n\t", cfg.clone()).collect();
+            assert_eq!(spans.len(), Pretokenizer::with_config("\n\t", cfg.clone()).count());
+        }
+    }
+}
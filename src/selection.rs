@@ -0,0 +1,139 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Growing a selection through the structure of an input.
+
+use crate::{Config, Kind, Pretokenizer};
+use std::ops::Range;
+
+/// The ranges an editor's "expand selection" steps through from the bytes
+/// of `s` in `range`, each larger than and containing the one before: the
+/// pretokens it touches, those with the whitespace and comments beside
+/// them on the same line, then for each group in brackets around it from
+/// the innermost, the inside of the group and the group with its brackets,
+/// and finally all of `s`.  The whole lines of the selection so far come
+/// next whenever they are smaller than the next of these, and a step that
+/// would partly overlap the one before is merged with it.
+///
+/// An empty `range` is a cursor, and starts from the pretoken containing it.
+/// Brackets in strings and comments are ignored.
+///
+/// # Panics
+///
+/// Panics if `range` is out of order, extends past `s` or doesn't fall on
+/// character boundaries.
+/// ```
+/// let src = "if (f(a, b /* b */)) {\n  return;\n}";
+/// let steps: Vec<_> = pretok::expand_selection(src, 9..9).into_iter()
+///     .map(|r| &src[r])
+///     .collect();
+/// assert_eq!(steps, vec![
+///     "b",
+///     " b /* b */",
+///     "a, b /* b */",
+///     "(a, b /* b */)",
+///     "f(a, b /* b */)",
+///     "(f(a, b /* b */))",
+///     "if (f(a, b /* b */)) {",
+///     src,
+/// ]);
+/// ```
+pub fn expand_selection(s: &str, range: Range<usize>) -> Vec<Range<usize>> {
+    expand_selection_with(s, range, Config::default())
+}
+
+/// Like [expand_selection()](fn.expand_selection.html), with `cfg`.
+pub fn expand_selection_with(s: &str, range: Range<usize>, cfg: Config) -> Vec<Range<usize>> {
+    let toks: Vec<_> = Pretokenizer::with_config(s, cfg.lossless(true)).collect();
+    let end_of = |i: usize| toks[i].offset + toks[i].s.len();
+    let mut steps = Vec::new();
+
+    // The pretokens touched, and the trivia beside them.
+    let last = range.end.max(range.start + 1);
+    let touched: Vec<_> = (0..toks.len())
+        .filter(|&i| toks[i].offset < last && end_of(i) > range.start)
+        .filter(|&i| !toks[i].kind.is_trivia())
+        .collect();
+    if let (Some(&first), Some(&end)) = (touched.first(), touched.last()) {
+        steps.push(toks[first].offset..end_of(end));
+        let beside = |i: usize| matches!(toks[i].kind, Kind::Whitespace | Kind::Comment);
+        let mut first = first;
+        while first > 0 && beside(first - 1) {
+            first -= 1;
+        }
+        let mut end = end;
+        while end + 1 < toks.len() && beside(end + 1) {
+            end += 1;
+        }
+        steps.push(toks[first].offset..end_of(end));
+    }
+
+    // Bracket groups around the range, inside and out.
+    let mut open = Vec::new();
+    for tok in toks.iter().filter(|t| t.kind == Kind::Word) {
+        for (i, c) in tok.s.char_indices() {
+            let at = tok.offset + i;
+            match c {
+                '(' | '[' | '{' => open.push((c, at)),
+                ')' | ']' | '}' => {
+                    let opener = match c { ')' => '(', ']' => '[', _ => '{' };
+                    if let Some(&(o, start)) = open.last() {
+                        if o == opener {
+                            open.pop();
+                            if start < range.start && at >= range.end {
+                                steps.push(start + 1..at);
+                                steps.push(start..at + 1);
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Smallest first, with the lines of the selection so far whenever they
+    // are smaller than the next step, then everything.
+    steps.sort_by_key(|r| r.len());
+    steps.push(0..s.len());
+    let lines = |r: &Range<usize>| {
+        let start = s[..r.start].rfind('\n').map_or(0, |i| i + 1);
+        start..s[r.end..].find('\n').map_or(s.len(), |i| r.end + i)
+    };
+    let mut out: Vec<Range<usize>> = Vec::new();
+    let mut current = range;
+    for step in steps {
+        let hull = current.start.min(step.start)..current.end.max(step.end);
+        let line = lines(&current);
+        if line.len() > current.len() && line.len() < hull.len() {
+            out.push(line.clone());
+            current = line;
+        }
+        let hull = current.start.min(hull.start)..current.end.max(hull.end);
+        if hull.len() > current.len() {
+            out.push(hull.clone());
+            current = hull;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selection_test_1() {
+        let src = "x = [a,\n  \"(b\" ];  y\nz";
+        let steps = |r: Range<usize>| -> Vec<&str> {
+            expand_selection(src, r).into_iter().map(|r| &src[r]).collect()
+        };
+        // The string's bracket is ignored, and the group spans lines.
+        assert_eq!(steps(12..13), vec!["\"(b\"", "  \"(b\" ", "a,\n  \"(b\" ",
+                                       "[a,\n  \"(b\" ]",
+                                       "x = [a,\n  \"(b\" ];  y", src]);
+        // A cursor in whitespace starts from the line.
+        assert_eq!(steps(18..18), vec!["  \"(b\" ];  y", src]);
+        assert_eq!(steps(0..src.len()), Vec::<&str>::new());
+        assert!(expand_selection("", 0..0).is_empty());
+    }
+}
@@ -0,0 +1,174 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Semantic tokens for the Language Server Protocol.
+
+use crate::{Config, Kind, Pretokenizer};
+use std::ops::Range;
+
+/// The token types of [semantic_tokens()](fn.semantic_tokens.html), in the
+/// order of their numbers, for the legend a language server sends.
+pub const SEMANTIC_TOKEN_TYPES: [&str; 3] = ["string", "comment", "keyword"];
+
+/// The token type number of strings in [semantic_tokens()](fn.semantic_tokens.html).
+pub const SEMANTIC_STRING: u32 = 0;
+/// The token type number of comments in [semantic_tokens()](fn.semantic_tokens.html).
+pub const SEMANTIC_COMMENT: u32 = 1;
+/// The token type number of keywords in [semantic_tokens()](fn.semantic_tokens.html).
+pub const SEMANTIC_KEYWORD: u32 = 2;
+
+/// A semantic token as the Language Server Protocol encodes it: its line
+/// and start relative to the token before it, its length, and its type and
+/// modifiers as numbers in the server's legend.  Lines count from 0, and
+/// starts and lengths count UTF-16 code units.  Each token lies on one line.
+///
+/// A response's `data` array holds these five numbers for each token in
+/// order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SemanticToken {
+    /// Lines since the token before, or since the start for the first.
+    pub delta_line: u32,
+    /// The start, relative to the start of the token before if on the same
+    /// line, or else to the start of the line.
+    pub delta_start: u32,
+    /// The length.
+    pub length: u32,
+    /// The token type.
+    pub token_type: u32,
+    /// The token modifiers, one bit each.
+    pub token_modifiers_bitset: u32,
+}
+
+/// The semantic tokens of `s`: its strings, comments and the words in
+/// `keywords`, typed as in [SEMANTIC_TOKEN_TYPES](constant.SEMANTIC_TOKEN_TYPES.html).
+/// Keywords are found among the runs of letters, digits and underscores of
+/// each pretoken, so `if` is found in `if(x)`.
+/// ```
+/// use pretok::{semantic_tokens, SemanticToken, SEMANTIC_KEYWORD, SEMANTIC_STRING};
+/// let toks = semantic_tokens("x = \"\u{e9}\";\nif(x) return;", &["if", "return"]);
+/// let v: Vec<_> = toks.iter()
+///     .map(|t| (t.delta_line, t.delta_start, t.length, t.token_type))
+///     .collect();
+/// assert_eq!(v, vec![(0, 4, 3, SEMANTIC_STRING),
+///                    (1, 0, 2, SEMANTIC_KEYWORD),
+///                    (0, 6, 6, SEMANTIC_KEYWORD)]);
+/// let data: Vec<u32> = toks.iter()
+///     .flat_map(|t| vec![t.delta_line, t.delta_start, t.length, t.token_type,
+///                        t.token_modifiers_bitset])
+///     .collect();
+/// assert_eq!(data.len(), 15);
+/// ```
+pub fn semantic_tokens(s: &str, keywords: &[&str]) -> Vec<SemanticToken> {
+    semantic_tokens_with(s, keywords, Config::default())
+}
+
+/// Like [semantic_tokens()](fn.semantic_tokens.html), with `cfg`.
+pub fn semantic_tokens_with(s: &str, keywords: &[&str], cfg: Config) -> Vec<SemanticToken> {
+    let mut spans = Vec::new();
+    for tok in Pretokenizer::with_config(s, cfg.lossless(true)) {
+        match tok.kind {
+            Kind::Quoted => spans.push((tok.offset..tok.offset + tok.s.len(), SEMANTIC_STRING)),
+            Kind::Comment => spans.push((tok.offset..tok.offset + tok.s.len(), SEMANTIC_COMMENT)),
+            Kind::Word => {
+                let ident = |c: char| c.is_alphanumeric() || c == '_';
+                let mut rest = tok.s;
+                while let Some(start) = rest.find(ident) {
+                    let len = rest[start..].find(|c| !ident(c)).unwrap_or(rest.len() - start);
+                    if keywords.contains(&&rest[start..start + len]) {
+                        let at = tok.offset + (tok.s.len() - rest.len()) + start;
+                        spans.push((at..at + len, SEMANTIC_KEYWORD));
+                    }
+                    rest = &rest[start + len..];
+                }
+            }
+            _ => {}
+        }
+    }
+    semantic_tokens_from_spans(s, spans)
+}
+
+/// Encode the byte `spans` of `s`, each with its token type, as semantic
+/// tokens, for servers that classify text themselves.  A span covering
+/// line breaks becomes a token on each of its lines.  Spans must be in
+/// order and not overlap; one starting before the end of the span before it
+/// is skipped.
+///
+/// # Panics
+///
+/// Panics if a span extends past `s` or doesn't fall on character
+/// boundaries.
+/// ```
+/// let toks = pretok::semantic_tokens_from_spans("/* a\n\u{1f600} */ b", vec![(0..12, 1)]);
+/// let v: Vec<_> = toks.iter().map(|t| (t.delta_line, t.delta_start, t.length)).collect();
+/// assert_eq!(v, vec![(0, 0, 4), (1, 0, 5)]);
+/// ```
+pub fn semantic_tokens_from_spans<I>(s: &str, spans: I) -> Vec<SemanticToken>
+where
+    I: IntoIterator<Item = (Range<usize>, u32)>,
+{
+    let mut toks = Vec::new();
+    // Where scanning has reached, as a byte offset, line and UTF-16 column.
+    let (mut pos, mut line, mut col) = (0, 0, 0);
+    // The line and column of the last token.
+    let (mut last_line, mut last_col) = (0, 0);
+    for (span, token_type) in spans {
+        if span.start < pos {
+            continue;
+        }
+        for c in s[pos..span.start].chars() {
+            if c == '\n' {
+                line += 1;
+                col = 0;
+            } else {
+                col += c.len_utf16() as u32;
+            }
+        }
+        for (i, piece) in s[span.clone()].split('\n').enumerate() {
+            if i > 0 {
+                line += 1;
+                col = 0;
+            }
+            let length = piece.encode_utf16().count() as u32;
+            if length > 0 {
+                let delta_start = if line == last_line { col - last_col } else { col };
+                toks.push(SemanticToken{ delta_line: line - last_line, delta_start, length,
+                                         token_type, token_modifiers_bitset: 0 });
+                last_line = line;
+                last_col = col;
+            }
+            col += length;
+        }
+        pos = span.end;
+    }
+    toks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn semantic_test_1() {
+        let src = "int main() { // \u{1f600} main\r\n  \"a\nb\" /* c */ return_x return; }";
+        let toks = semantic_tokens(src, &["int", "return", "main"]);
+        let v: Vec<_> = toks.iter()
+            .map(|t| (t.delta_line, t.delta_start, t.length, t.token_type))
+            .collect();
+        assert_eq!(v, vec![(0, 0, 3, SEMANTIC_KEYWORD),
+                           (0, 4, 4, SEMANTIC_KEYWORD),
+                           (0, 9, 11, SEMANTIC_COMMENT),
+                           (1, 2, 2, SEMANTIC_STRING),
+                           (1, 0, 2, SEMANTIC_STRING),
+                           (0, 3, 7, SEMANTIC_COMMENT),
+                           (0, 17, 6, SEMANTIC_KEYWORD)]);
+        assert!(semantic_tokens("", &["x"]).is_empty());
+
+        // Out of order spans are skipped.
+        let toks = semantic_tokens_from_spans("abc", vec![(1..2, 0), (0..1, 0), (2..3, 1)]);
+        assert_eq!(toks, vec![
+            SemanticToken{ delta_line: 0, delta_start: 1, length: 1, token_type: 0,
+                           token_modifiers_bitset: 0 },
+            SemanticToken{ delta_line: 0, delta_start: 1, length: 1, token_type: 1,
+                           token_modifiers_bitset: 0 },
+        ]);
+    }
+}
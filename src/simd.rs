@@ -0,0 +1,79 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! SIMD scanning of long whitespace runs and block comment bodies.
+
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+/// Bytes compared per step.
+const LANES: usize = 16;
+
+/// The length of the run of spaces and tabs at the start of `s`.
+pub(crate) fn blank_run(s: &[u8]) -> usize {
+    let mut i = 0;
+    #[cfg(target_arch = "x86_64")]
+    while i + LANES <= s.len() {
+        // SAFETY: SSE2 is part of the x86_64 baseline, and the load reads
+        // the LANES bytes at i, which are in bounds.
+        let mask = unsafe {
+            let v = _mm_loadu_si128(s.as_ptr().add(i) as *const __m128i);
+            let blank = _mm_or_si128(_mm_cmpeq_epi8(v, _mm_set1_epi8(b' ' as i8)),
+                                     _mm_cmpeq_epi8(v, _mm_set1_epi8(b'\t' as i8)));
+            _mm_movemask_epi8(blank) as u32
+        };
+        if mask != 0xffff {
+            return i + (!mask).trailing_zeros() as usize;
+        }
+        i += LANES;
+    }
+    i + s[i..].iter().take_while(|&&b| b == b' ' || b == b'\t').count()
+}
+
+/// The index of the first of `a`, `b` or `c` in `s`.
+pub(crate) fn find3(a: u8, b: u8, c: u8, s: &[u8]) -> Option<usize> {
+    let mut i = 0;
+    #[cfg(target_arch = "x86_64")]
+    while i + LANES <= s.len() {
+        // SAFETY: as in blank_run().
+        let mask = unsafe {
+            let v = _mm_loadu_si128(s.as_ptr().add(i) as *const __m128i);
+            let hit = _mm_or_si128(_mm_cmpeq_epi8(v, _mm_set1_epi8(a as i8)),
+                                   _mm_cmpeq_epi8(v, _mm_set1_epi8(b as i8)));
+            let hit = _mm_or_si128(hit, _mm_cmpeq_epi8(v, _mm_set1_epi8(c as i8)));
+            _mm_movemask_epi8(hit) as u32
+        };
+        if mask != 0 {
+            return Some(i + mask.trailing_zeros() as usize);
+        }
+        i += LANES;
+    }
+    s[i..].iter().position(|&x| x == a || x == b || x == c).map(|n| i + n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simd_test_0() {
+        // Every length and match position, either side of a lane boundary
+        for len in 0..40 {
+            for at in 0..=len {
+                let mut s = vec![b' '; len];
+                s.iter_mut().step_by(3).for_each(|b| *b = b'\t');
+                if at < len {
+                    s[at] = b'x';
+                }
+                assert_eq!(blank_run(&s), at);
+
+                let mut s = vec![b'a'; len];
+                if at < len {
+                    s[at] = b'\n';
+                    s[len - 1] = b'*';
+                }
+                let want = if at < len { Some(at) } else { None };
+                assert_eq!(find3(b'*', b'/', b'\n', &s), want);
+            }
+        }
+    }
+}
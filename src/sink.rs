@@ -0,0 +1,40 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Push-style consumers of pretokens.
+
+use crate::Pretoken;
+
+/// A consumer of everything found in the input, driven by
+/// [Pretokenizer::drive()](struct.Pretokenizer.html#method.drive).
+///
+/// Each callback receives a [Pretoken](struct.Pretoken.html) whose `offset`
+/// and `s.len()` give its span.  Every method has an empty default, so
+/// implement only those of interest.
+/// ```
+/// use pretok::{Pretoken, Pretokenizer, PretokSink};
+/// // Rebuild the input without comments.
+/// struct Strip(String);
+/// impl<'a> PretokSink<'a> for Strip {
+///     fn on_token(&mut self, t: &Pretoken<'a>) { self.0.push_str(t.s); }
+///     fn on_whitespace(&mut self, t: &Pretoken<'a>) { self.0.push_str(t.s); }
+///     fn on_newline(&mut self, t: &Pretoken<'a>) { self.0.push_str(t.s); }
+/// }
+/// let mut strip = Strip(String::new());
+/// Pretokenizer::new("x = 1; // one\ny = 2;").drive(&mut strip);
+/// assert_eq!(strip.0, "x = 1; \ny = 2;");
+/// ```
+pub trait PretokSink<'a> {
+    /// Called for each pretoken that `next()` would return.
+    fn on_token(&mut self, _tok: &Pretoken<'a>) {}
+
+    /// Called for each line or block comment, and for other input skipped
+    /// like a comment.
+    fn on_comment(&mut self, _comment: &Pretoken<'a>) {}
+
+    /// Called for each line break outside pretokens and comments.
+    fn on_newline(&mut self, _newline: &Pretoken<'a>) {}
+
+    /// Called for each run of spaces and tabs outside pretokens and
+    /// comments.
+    fn on_whitespace(&mut self, _ws: &Pretoken<'a>) {}
+}
@@ -0,0 +1,101 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Rendering diagnostics and spans with annotate-snippets.
+
+use crate::{PretokError, Pretoken};
+use annotate_snippets::{Annotation, AnnotationKind, Group, Level, Renderer, Snippet};
+
+impl PretokError {
+    /// An [annotate-snippets](https://docs.rs/annotate-snippets) error
+    /// titled with this diagnostic's message, showing `source`, which is
+    /// the input it was found in, with the offending span marked.
+    /// `path` names the input in the rendered location.  Render a list of
+    /// them with `annotate_snippets::Renderer`, or see
+    /// [render_snippets()](fn.render_snippets.html).
+    /// ```
+    /// use annotate_snippets::Renderer;
+    /// use pretok::Pretokenizer;
+    /// let src = "let s = \"open;\n";
+    /// let mut pt = Pretokenizer::new(src);
+    /// pt.by_ref().for_each(drop);
+    /// let report = [pt.diagnostics()[0].to_group(src, Some("a.c"))];
+    /// let out = Renderer::plain().render(&report);
+    /// assert!(out.starts_with("error: unterminated string literal starting at line 1\n"));
+    /// assert!(out.contains("--> a.c:1:9"));
+    /// ```
+    pub fn to_group<'a>(&self, source: &'a str, path: Option<&'a str>) -> Group<'a> {
+        Level::ERROR.primary_title(self.to_string()).element(
+            Snippet::source(source)
+                .path(path)
+                .annotation(AnnotationKind::Primary.span(self.span())),
+        )
+    }
+}
+
+impl<'a> Pretoken<'a> {
+    /// An [annotate-snippets](https://docs.rs/annotate-snippets) annotation of
+    /// kind `kind` over this pretoken, for marking pretokens in a snippet of
+    /// the input they came from.
+    /// ```
+    /// use annotate_snippets::{AnnotationKind, Level, Renderer, Snippet};
+    /// use pretok::Pretokenizer;
+    /// let src = "x = y y;";
+    /// let toks: Vec<_> = Pretokenizer::new(src).collect();
+    /// let report = [Level::ERROR.primary_title("unexpected word").element(
+    ///     Snippet::source(src)
+    ///         .annotation(toks[3].annotation(AnnotationKind::Primary).label("here"))
+    ///         .annotation(toks[2].annotation(AnnotationKind::Context)),
+    /// )];
+    /// let out = Renderer::plain().render(&report);
+    /// assert!(out.contains("x = y y;"));
+    /// assert!(out.contains("- ^^ here"));
+    /// ```
+    pub fn annotation<'b>(&self, kind: AnnotationKind) -> Annotation<'b> {
+        kind.span(self.offset..self.offset + self.s.len())
+    }
+}
+
+/// Render `diagnostics` found in `source` as plain text in the style of
+/// rustc, one error after another.  `path` names the input in each
+/// location.
+/// ```
+/// use pretok::{Config, Pretokenizer};
+/// let src = "int x;\n/* never closed\n";
+/// let mut pt = Pretokenizer::with_config(src, Config::new());
+/// pt.by_ref().for_each(drop);
+/// print!("{}", pretok::render_snippets(src, Some("x.c"), pt.diagnostics()));
+/// ```
+pub fn render_snippets(source: &str, path: Option<&str>, diagnostics: &[PretokError]) -> String {
+    let report: Vec<_> = diagnostics.iter().map(|d| d.to_group(source, path)).collect();
+    Renderer::plain().render(&report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, Pretokenizer};
+
+    #[test]
+    fn snippets_test_1() {
+        let src = "a \u{202e}b\nc \"d";
+        let mut pt = Pretokenizer::with_config(src, Config::new().detect_bidi(true));
+        pt.by_ref().for_each(drop);
+        let out = render_snippets(src, Some("in.txt"), pt.diagnostics());
+        // The control character itself is not printed.
+        let want = concat!(
+            "error: bidirectional control character U+202E at line 1\n",
+            " --> in.txt:1:3\n",
+            "  |\n",
+            "1 | a \u{fffd}b\n",
+            "  |   ^\n",
+            "  |\n",
+            "error: unterminated string literal starting at line 2\n",
+            " --> in.txt:2:3\n",
+            "  |\n",
+            "2 | c \"d\n",
+            "  |   ^^",
+        );
+        assert_eq!(out, want);
+        assert_eq!(render_snippets(src, None, &[]), "");
+    }
+}
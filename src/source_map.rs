@@ -0,0 +1,357 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! A set of named inputs sharing one offset space.
+
+use crate::{Config, LineIndex, Pretoken, Pretokenizer};
+use std::convert::TryFrom;
+use std::ops::{Index, Range};
+
+/// The id of a file in a [SourceMap](struct.SourceMap.html), in the order
+/// the files were added.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileId(u32);
+
+impl FileId {
+    /// The position of the file in its map, from 0.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A position in a [SourceMap](struct.SourceMap.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Location {
+    /// The file.
+    pub file: FileId,
+    /// The line, from 1.
+    pub line: usize,
+    /// The column in characters, from 1.
+    pub col: usize,
+}
+
+/// A byte span of a file in a [SourceMap](struct.SourceMap.html).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FileSpan {
+    /// The file.
+    pub file: FileId,
+    /// The bytes of the file.
+    pub span: Range<usize>,
+}
+
+/// Where a pretoken came from, as found by
+/// [SourceMap::provenance()](struct.SourceMap.html#method.provenance).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Provenance {
+    /// Where the pretoken takes effect in a file that isn't virtual: its own
+    /// span, or the span of the outermost expansion that produced it.
+    pub at: FileSpan,
+    /// Where the text of the pretoken was written, if known.  For a pretoken
+    /// copied unchanged from another file, this is its span there.
+    pub from: Option<FileSpan>,
+}
+
+/// A named input owned by a [SourceMap](struct.SourceMap.html).
+#[derive(Clone, Debug)]
+pub struct SourceFile {
+    name: String,
+    text: String,
+    base: usize,
+    lines: LineIndex,
+    /// For a virtual file, the span it was expanded from
+    expansion: Option<FileSpan>,
+    /// Spans of a virtual file copied from other files, in the order added
+    origins: Vec<(Range<usize>, FileSpan)>,
+}
+
+impl SourceFile {
+    /// The name the file was added with, usually its path.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The contents of the file.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The global offset of the start of the file.  A pretoken at `offset`
+    /// in the file is at `base() + offset` in the map.
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    /// For a virtual file, the span whose expansion it holds.  See
+    /// [SourceMap::add_virtual()](struct.SourceMap.html#method.add_virtual).
+    pub fn expansion(&self) -> Option<&FileSpan> {
+        self.expansion.as_ref()
+    }
+
+    /// The line, from 1, and the column in characters, from 1, of byte
+    /// `offset` in the file.  An offset past the end is taken as the end.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        self.lines.line_col(offset)
+    }
+
+    /// The index of the file's lines.
+    pub fn lines(&self) -> &LineIndex {
+        &self.lines
+    }
+}
+
+/// Several named inputs, such as the files of a program, each with a
+/// [FileId](struct.FileId.html).  The files share one space of global
+/// offsets, so a single `usize` can say where anything is, and
+/// [location()](#method.location) turns it back into a file, line and
+/// column for messages.
+/// ```
+/// use pretok::{Location, SourceMap};
+/// let mut map = SourceMap::new();
+/// let a = map.add("a.c", "int a;".to_string());
+/// let b = map.add("b.c", "int b;\nint c;".to_string());
+/// let tok = map.pretokenizer(b).last().unwrap();
+/// let global = map[b].base() + tok.offset;
+/// assert_eq!(map.location(global), Some(Location{ file: b, line: 2, col: 5 }));
+/// assert_eq!(map[map.location(global).unwrap().file].name(), "b.c");
+/// assert_eq!(map.location(0).unwrap().file, a);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+    cfg: Config,
+}
+
+impl SourceMap {
+    /// Create an empty map whose pretokenizers use the default
+    /// configuration.
+    pub fn new() -> SourceMap {
+        SourceMap::default()
+    }
+
+    /// Create an empty map whose pretokenizers use `cfg`.
+    pub fn with_config(cfg: Config) -> SourceMap {
+        SourceMap{ files: Vec::new(), cfg }
+    }
+
+    /// Add a file named `name` holding `text`, returning its id.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map already holds `u32::MAX` files.
+    pub fn add(&mut self, name: &str, text: String) -> FileId {
+        let id = FileId(u32::try_from(self.files.len()).expect("too many files"));
+        // Files are a byte apart, so the end of one isn't the start of the
+        // next.
+        let base = self.files.last().map_or(0, |f| f.base + f.text.len() + 1);
+        let lines = LineIndex::new(&text);
+        self.files.push(SourceFile{ name: name.to_string(), text, base, lines,
+                                    expansion: None, origins: Vec::new() });
+        id
+    }
+
+    /// Add a virtual file holding `text`, the expansion of `expansion`,
+    /// such as a macro call, and return its id.  Pieces of the text copied
+    /// from elsewhere, such as a macro body, are registered with
+    /// [add_origin()](#method.add_origin).
+    /// ```
+    /// use pretok::{FileSpan, SourceMap};
+    /// let mut map = SourceMap::new();
+    /// let main = map.add("main.c", "#define TWICE(x) x + x\nTWICE(y);".to_string());
+    /// let call = FileSpan{ file: main, span: 23..31 };
+    /// let exp = map.add_virtual("<TWICE>", "y + y".to_string(), call.clone());
+    /// map.add_origin(exp, 2..3, FileSpan{ file: main, span: 19..20 });
+    ///
+    /// let toks: Vec<_> = map.pretokenizer(exp).collect();
+    /// let plus = map.provenance(exp, &toks[1]);
+    /// assert_eq!(plus.at, call);
+    /// assert_eq!(plus.from, Some(FileSpan{ file: main, span: 19..20 }));
+    /// assert_eq!(map.provenance(exp, &toks[0]).from, None);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map already holds `u32::MAX` files.
+    pub fn add_virtual(&mut self, name: &str, text: String, expansion: FileSpan) -> FileId {
+        let id = self.add(name, text);
+        self.files[id.index()].expansion = Some(expansion);
+        id
+    }
+
+    /// Record that bytes `span` of file `file` were copied from `from`.
+    /// Later records win where they overlap earlier ones.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `file` is not in this map.
+    pub fn add_origin(&mut self, file: FileId, span: Range<usize>, from: FileSpan) {
+        self.files[file.index()].origins.push((span, from));
+    }
+
+    /// Where pretoken `tok` of file `file` came from: where it takes effect,
+    /// following expansions out to a file that isn't virtual, and where its
+    /// text was written, if known.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `file` is not in this map.
+    pub fn provenance(&self, file: FileId, tok: &Pretoken) -> Provenance {
+        let span = tok.offset..tok.offset + tok.s.len();
+        let mut at = FileSpan{ file, span: span.clone() };
+        while let Some(outer) = self[at.file].expansion() {
+            at = outer.clone();
+        }
+        let f = &self[file];
+        let from = match f.expansion {
+            None => Some(FileSpan{ file, span }),
+            Some(_) => f.origins.iter().rev()
+                .find(|(s, _)| s.start <= span.start && span.end <= s.end)
+                .map(|(s, from)| match s.len() == from.span.len() {
+                    // Copied unchanged, so the pretoken's own bytes are known.
+                    true => FileSpan{ file: from.file,
+                                      span: from.span.start + span.start - s.start
+                                          ..from.span.start + span.end - s.start },
+                    false => from.clone(),
+                }),
+        };
+        Provenance{ at, from }
+    }
+
+    /// The file with id `id`, if it is in this map.
+    pub fn get(&self, id: FileId) -> Option<&SourceFile> {
+        self.files.get(id.index())
+    }
+
+    /// The id of every file, in the order they were added.
+    pub fn ids(&self) -> impl Iterator<Item = FileId> {
+        (0..self.files.len() as u32).map(FileId)
+    }
+
+    /// The number of files.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// True if there are no files.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// The file holding global offset `global`, if any.  The end of a file
+    /// belongs to it.
+    pub fn file_at(&self, global: usize) -> Option<FileId> {
+        let i = self.files.partition_point(|f| f.base <= global).checked_sub(1)?;
+        let f = &self.files[i];
+        match global <= f.base + f.text.len() {
+            true => Some(FileId(i as u32)),
+            false => None,
+        }
+    }
+
+    /// The file, line and column of global offset `global`, if it is in a
+    /// file.
+    pub fn location(&self, global: usize) -> Option<Location> {
+        let file = self.file_at(global)?;
+        let (line, col) = self[file].line_col(global - self[file].base);
+        Some(Location{ file, line, col })
+    }
+
+    /// A pretokenizer over file `id`, with the map's configuration.
+    /// Offsets are within the file.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is not in this map.
+    pub fn pretokenizer(&self, id: FileId) -> Pretokenizer<'_> {
+        Pretokenizer::with_config(self[id].text(), self.cfg.clone())
+    }
+
+    /// The pretokens of every file, one file after another in the order
+    /// they were added, each with the id of its file.  Offsets are within
+    /// the file; add its [base()](struct.SourceFile.html#method.base) for a
+    /// global offset.
+    /// ```
+    /// use pretok::SourceMap;
+    /// let mut map = SourceMap::new();
+    /// let a = map.add("a.c", "int a;".to_string());
+    /// let b = map.add("b.c", "b();".to_string());
+    /// let toks: Vec<_> = map.tokens().map(|(file, t)| (file, t.s)).collect();
+    /// assert_eq!(toks, vec![(a, "int"), (a, "a;"), (b, "b();")]);
+    /// ```
+    pub fn tokens(&self) -> impl Iterator<Item = (FileId, Pretoken<'_>)> + '_ {
+        self.ids().flat_map(move |id| self.pretokenizer(id).map(move |tok| (id, tok)))
+    }
+}
+
+impl Index<FileId> for SourceMap {
+    type Output = SourceFile;
+
+    fn index(&self, id: FileId) -> &SourceFile {
+        &self.files[id.index()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_map_test_0() {
+        let mut map = SourceMap::with_config(Config::new().lossless(true));
+        assert!(map.is_empty());
+        let a = map.add("a.c", "x\n\u{e9}t\u{e9} y".to_string());
+        let empty = map.add("empty.c", String::new());
+        let b = map.add("b.c", "z".to_string());
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.ids().collect::<Vec<_>>(), vec![a, empty, b]);
+        assert_eq!((map[a].base(), map[empty].base(), map[b].base()), (0, 10, 11));
+
+        let at = |global| map.location(global).map(|l| (l.file.index(), l.line, l.col));
+        assert_eq!(at(0), Some((0, 1, 1)));
+        assert_eq!(at(8), Some((0, 2, 5)));
+        assert_eq!(at(9), Some((0, 2, 6)));
+        assert_eq!(at(10), Some((1, 1, 1)));
+        assert_eq!(at(11), Some((2, 1, 1)));
+        assert_eq!(at(12), Some((2, 1, 2)));
+        assert_eq!(at(13), None);
+        // Inside a character, the column is the character's.
+        assert_eq!(map[a].line_col(3), (2, 1));
+        assert_eq!(map.pretokenizer(a).count(), 5);
+        assert_eq!(map.get(FileId(3)).map(|f| f.name()), None);
+        assert_eq!(map[b].name(), "b.c");
+
+        let toks: Vec<_> = map.tokens()
+            .filter(|(_, t)| !t.kind.is_trivia())
+            .map(|(file, t)| (file, t.s, map[file].base() + t.offset))
+            .collect();
+        assert_eq!(toks, vec![(a, "x", 0), (a, "\u{e9}t\u{e9}", 2), (a, "y", 8), (b, "z", 11)]);
+        assert_eq!(SourceMap::new().tokens().count(), 0);
+    }
+
+    #[test]
+    fn source_map_test_1() {
+        let mut map = SourceMap::new();
+        let main = map.add("main.c", "#define F(a) g (a) 1\nF(x y)".to_string());
+        let call = FileSpan{ file: main, span: 21..27 };
+        let outer = map.add_virtual("<F>", "g (x y) 1".to_string(), call.clone());
+        map.add_origin(outer, 0..9, FileSpan{ file: main, span: 13..20 });
+        map.add_origin(outer, 2..7, FileSpan{ file: main, span: 22..27 });
+        // A nested expansion takes effect where the outer one does.
+        let inner = map.add_virtual("<G>", "x".to_string(), FileSpan{ file: outer, span: 3..4 });
+        map.add_origin(inner, 0..1, FileSpan{ file: outer, span: 3..4 });
+
+        let toks: Vec<_> = map.pretokenizer(outer).collect();
+        let from: Vec<_> = toks.iter().map(|t| map.provenance(outer, t).from.unwrap()).collect();
+        assert_eq!(from, vec![FileSpan{ file: main, span: 13..20 },
+                              FileSpan{ file: main, span: 22..24 },
+                              FileSpan{ file: main, span: 25..27 },
+                              FileSpan{ file: main, span: 13..20 }]);
+        let x = map.pretokenizer(inner).next().unwrap();
+        assert_eq!(map.provenance(inner, &x),
+                   Provenance{ at: call, from: Some(FileSpan{ file: outer, span: 3..4 }) });
+        assert_eq!(map[inner].expansion().unwrap().file, outer);
+
+        let tok = map.pretokenizer(main).last().unwrap();
+        let own = FileSpan{ file: main, span: 25..27 };
+        assert_eq!(map.provenance(main, &tok), Provenance{ at: own.clone(), from: Some(own) });
+        assert!(map[main].expansion().is_none());
+    }
+}
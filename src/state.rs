@@ -0,0 +1,365 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Starting a pretokenizer inside a comment, string or other construct.
+
+use crate::{cursor_at, Bom, Config, Kind, Pretoken, Pretokenizer, Shebang};
+
+/// A construct still open at the end of some input, so that the input
+/// following it starts inside it.  Editors that pretokenize a line at a time
+/// keep the state at the end of each line, and resume the next line in it
+/// with [Pretokenizer::with_state()](struct.Pretokenizer.html#method.with_state)
+/// or [tokenize_line()](fn.tokenize_line.html).
+/// ```
+/// use pretok::{Config, Pretokenizer, ScanState};
+/// let mut pt = Pretokenizer::with_config("x = \"a", Config::new());
+/// assert_eq!(pt.by_ref().count(), 3);
+/// assert_eq!(pt.state(), ScanState::Quoted{ escape: false });
+///
+/// let mut rest = Pretokenizer::with_state("b\" y", Config::new(), pt.state());
+/// assert_eq!(rest.next().unwrap().s, "b\"");
+/// assert_eq!(rest.next().unwrap().s, "y");
+/// assert_eq!(rest.state(), ScanState::Normal);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum ScanState {
+    /// Outside any construct.  This is the default.
+    #[default]
+    Normal,
+    /// Inside a block comment.
+    BlockComment {
+        /// The nesting depth, which is 1 unless
+        /// [Config::nested_comments()](struct.Config.html#method.nested_comments)
+        /// is set.
+        depth: usize,
+    },
+    /// Inside a quoted string.
+    Quoted {
+        /// True if the input ended with a backslash, escaping whatever
+        /// comes next.
+        escape: bool,
+    },
+    /// In a directive whose line ends with a backslash splice.  See
+    /// [Config::directives()](struct.Config.html#method.directives).
+    Directive,
+    /// Inside a region turned off by
+    /// [Config::tool_directives()](struct.Config.html#method.tool_directives).
+    Opaque,
+    /// Inside a region skipped by
+    /// [Config::skip_region()](struct.Config.html#method.skip_region).
+    SkipRegion {
+        /// The index of the region, in the order the regions were added.
+        region: usize,
+        /// The number of start markers nested inside the region.
+        depth: usize,
+    },
+}
+
+/// Where a pretokenizer is between pretokens, as returned by
+/// [Pretokenizer::resume_point()](struct.Pretokenizer.html#method.resume_point):
+/// how far it has got, the line there, and the construct open there.  A
+/// pretokenizer made with
+/// [Pretokenizer::resume()](struct.Pretokenizer.html#method.resume) picks
+/// up from it.  With the `serde` feature, it can be serialized, so that an
+/// incremental build can keep it between runs.
+/// ```
+/// use pretok::{Pretokenizer, ResumePoint, ScanState};
+/// let mut pt = Pretokenizer::new("a /* b");
+/// pt.by_ref().count();
+/// let state = ScanState::BlockComment{ depth: 1 };
+/// assert_eq!(pt.resume_point(), ResumePoint{ offset: 6, line: 1, state });
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResumePoint {
+    /// The byte offset of the input.
+    pub offset: usize,
+    /// The line number at `offset`.
+    pub line: usize,
+    /// The construct open at `offset`.
+    pub state: ScanState,
+}
+
+impl<'a> Pretokenizer<'a> {
+    /// Create a pretokenizer with `cfg` for input that starts inside
+    /// `state`, as left by the input before `s`.  The first pretoken
+    /// continues the open construct, and whatever follows it on the same
+    /// line is not at the start of a line.  Since `s` follows other input,
+    /// it never starts with a byte order mark or a shebang.
+    /// ```
+    /// use pretok::{Config, Kind, Pretokenizer, ScanState};
+    /// let state = ScanState::BlockComment{ depth: 1 };
+    /// let mut pt = Pretokenizer::with_state("still */ x", Config::new().lossless(true), state);
+    /// assert_eq!(pt.next().unwrap().s, "still */");
+    /// assert_eq!(pt.next().unwrap().kind, Kind::Whitespace);
+    /// ```
+    pub fn with_state(s: &'a str, mut cfg: Config, state: ScanState) -> Pretokenizer<'a> {
+        cfg.bom = Bom::Text;
+        cfg.shebang = Shebang::Text;
+        let mut pt = Pretokenizer::with_config(s, cfg);
+        match state {
+            ScanState::Opaque => pt.off_marker = Some((0, 1)),
+            _ => pt.start = state,
+        }
+        pt
+    }
+
+    /// The construct left open at the end of input.  This is final once
+    /// every pretoken has been returned.
+    pub fn state(&self) -> ScanState {
+        self.open
+    }
+
+    /// Where the pretokenizer is, to pick up from later with
+    /// [resume()](#method.resume), perhaps after more input is added.  The
+    /// point is at `byte_pos()`, so after `peek()` it is before the peeked
+    /// pretoken.
+    /// ```
+    /// use pretok::{Pretokenizer, ScanState};
+    /// let mut pt = Pretokenizer::new("a\n\"b c\" d");
+    /// pt.next();
+    /// pt.peek();
+    /// let point = pt.resume_point();
+    /// assert_eq!((point.offset, point.line, point.state), (2, 2, ScanState::Normal));
+    /// ```
+    pub fn resume_point(&self) -> ResumePoint {
+        let state = match &self.peeked {
+            Some(Some(tok)) if tok.kind == Kind::Opaque => ScanState::Opaque,
+            Some(Some(_)) => ScanState::Normal,
+            _ if self.off_marker.is_some() => ScanState::Opaque,
+            _ => self.open,
+        };
+        ResumePoint{ offset: self.byte_pos(), line: self.line(), state }
+    }
+
+    /// Create a pretokenizer with `cfg` for `s` that picks up at `point`,
+    /// taken from a pretokenizer over `s` or over input that `s` extends,
+    /// with the same configuration.  The pretokens are those the first
+    /// pretokenizer would have returned after `point`, and offsets and
+    /// lines are in the whole of `s`.  If `point` is at the end of input
+    /// left inside a construct, the first pretoken continues it.  Take such
+    /// a point at a line break, as
+    /// [tokenize_line()](fn.tokenize_line.html) does, since a marker split
+    /// across it, such as the `*` and `/` ending a block comment, isn't
+    /// seen.
+    /// ```
+    /// use pretok::{Config, Pretokenizer};
+    /// let cfg = Config::new();
+    /// let mut pt = Pretokenizer::with_config("x = \"a", cfg.clone());
+    /// pt.by_ref().count();
+    /// let point = pt.resume_point();
+    ///
+    /// let mut rest = Pretokenizer::resume("x = \"a\nb\" y", cfg, point);
+    /// assert_eq!(rest.next().unwrap().s, "\nb\"");
+    /// assert_eq!(rest.next().unwrap().offset, 10);
+    /// ```
+    pub fn resume(s: &'a str, cfg: Config, point: ResumePoint) -> Pretokenizer<'a> {
+        let mut pt = Pretokenizer::with_config(s, cfg);
+        pt.pos = cursor_at(s, point.offset);
+        pt.line = point.line;
+        // Only whitespace may come between the start of a line and `point`.
+        let before = s[..pt.pos.byte_pos()].trim_end_matches([' ', '\t']);
+        pt.bol = before.is_empty() || before.ends_with('\n');
+        match point.state {
+            ScanState::Opaque => pt.off_marker = Some((pt.pos.byte_pos(), point.line)),
+            state => pt.start = state,
+        }
+        pt
+    }
+}
+
+/// Pretokenize one line of input with the default configuration, starting
+/// in `state`, the state at the end of the line before.  Returns the
+/// pretokens and the state at the end of this line.  See
+/// [tokenize_line_with()](fn.tokenize_line_with.html).
+/// ```
+/// use pretok::{tokenize_line, ScanState};
+/// let (toks, state) = tokenize_line("a = /* b", ScanState::Normal);
+/// assert_eq!(toks.iter().map(|t| t.s).collect::<Vec<_>>(), vec!["a", "="]);
+/// let (toks, state) = tokenize_line("c */ d;", state);
+/// assert_eq!(toks[0].s, "d;");
+/// assert_eq!(state, ScanState::Normal);
+/// ```
+pub fn tokenize_line(line: &str, state: ScanState) -> (Vec<Pretoken<'_>>, ScanState) {
+    tokenize_line_with(line, Config::default(), state)
+}
+
+/// Pretokenize one line of input with `cfg`, starting in `state`, the state
+/// at the end of the line before.  Returns the pretokens and the state at
+/// the end of this line, so a highlighter only needs to pretokenize a
+/// changed line again, and the lines after it until their starting state is
+/// unchanged.
+///
+/// The line may end with its line break or not, as from `str::lines()`.
+/// Either way the state returned is the one the next line starts in.
+/// Offsets are within the line, and lines are counted from 1 at its start.
+pub fn tokenize_line_with(line: &str, cfg: Config, state: ScanState)
+    -> (Vec<Pretoken<'_>>, ScanState) {
+    // Comments are needed to find an off marker that ends the line.
+    let lossless = cfg.lossless;
+    let off = cfg.tool_directives.as_ref().map(|(off, _)| off.clone());
+    let mut pt = Pretokenizer::with_state(line, cfg.lossless(true), state);
+    let toks: Vec<_> = pt.by_ref().collect();
+    let mut state = pt.state();
+    if !line.ends_with('\n') {
+        // The scanner only sees the line break when it is in the line.
+        match (state, toks.last(), off) {
+            (ScanState::Quoted{ escape: true }, ..) => state = ScanState::Quoted{ escape: false },
+            (ScanState::Normal, Some(last), Some(off))
+                if last.kind == Kind::Comment && last.s.starts_with("//")
+                    && last.s[2..].trim() == off => state = ScanState::Opaque,
+            _ => {}
+        }
+    }
+    let toks = toks.into_iter().filter(|t| lossless || !t.kind.is_trivia()).collect();
+    (toks, state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testgen::Lcg;
+    use crate::Base;
+
+    /// Pretokenize `lines` one at a time, carrying the state across.
+    fn by_lines<'a>(lines: &[&'a str], cfg: &Config) -> (Vec<(&'a str, Kind)>, ScanState) {
+        let mut toks = Vec::new();
+        let mut state = ScanState::Normal;
+        for line in lines {
+            let mut pt = Pretokenizer::with_state(line, cfg.clone(), state);
+            toks.extend(pt.by_ref().map(|t| (t.s, t.kind)));
+            state = pt.state();
+        }
+        (toks, state)
+    }
+
+    #[test]
+    fn state_test_0() {
+        let cfg = Config::new().directives(true).nested_comments(true);
+        let (toks, state) = by_lines(&["a /* /* b\n", "c */\n", "d */ \"e\\\n", "f\" g\n"], &cfg);
+        assert_eq!(toks, vec![("a", Kind::Word), ("\"e\\\n", Kind::Quoted),
+                              ("f\"", Kind::Quoted), ("g", Kind::Word)]);
+        assert_eq!(state, ScanState::Normal);
+
+        let (toks, state) = by_lines(&["#define A \\\n", "  1 \\", "2 /* x"], &cfg);
+        assert_eq!(toks, vec![("#define A \\", Kind::Directive), ("  1 \\", Kind::Directive),
+                              ("2", Kind::Directive)]);
+        assert_eq!(state, ScanState::BlockComment{ depth: 1 });
+
+        let cfg = Config::new().tool_directives("off", "on").skip_region("#if 0", "#endif");
+        let (toks, state) = by_lines(&["x // off\n", "y\n", "z\n", "// on\n", "#if 0\n"], &cfg);
+        assert_eq!(toks, vec![("x", Kind::Word), ("y\n", Kind::Opaque), ("z\n", Kind::Opaque)]);
+        assert_eq!(state, ScanState::SkipRegion{ region: 0, depth: 0 });
+        let (toks, state) = by_lines(&["#if 0\n", "#if 0\n", "#endif\n", "#endif\n", "w"], &cfg);
+        assert_eq!(toks, vec![("w", Kind::Word)]);
+        assert_eq!(state, ScanState::Normal);
+
+        // A state from another configuration is ignored.
+        let pt = Pretokenizer::with_state("v", Config::new(), ScanState::SkipRegion{ region: 3, depth: 0 });
+        assert_eq!(pt.map(|t| t.s).collect::<Vec<_>>(), vec!["v"]);
+    }
+
+    #[test]
+    fn state_test_1() {
+        // Lines with and without their line breaks give the same states.
+        let src = "x = \"a\\\nb\" /* c\n*/ // off\n  y z\n// on\n#define D \\\n  1\n";
+        let cfg = Config::new().directives(true).tool_directives("off", "on");
+        for lines in [src.split_inclusive('\n').collect::<Vec<_>>(), src.lines().collect()] {
+            let mut state = ScanState::Normal;
+            let mut states = Vec::new();
+            let mut toks = Vec::new();
+            for line in lines {
+                let (line_toks, next) = tokenize_line_with(line, cfg.clone(), state);
+                toks.extend(line_toks.iter().map(|t| (t.s.trim_end(), t.kind)));
+                state = next;
+                states.push(state);
+            }
+            assert_eq!(states, vec![ScanState::Quoted{ escape: false }, ScanState::BlockComment{ depth: 1 },
+                                    ScanState::Opaque, ScanState::Opaque, ScanState::Normal,
+                                    ScanState::Directive, ScanState::Normal]);
+            assert_eq!(toks, vec![("x", Kind::Word), ("=", Kind::Word), ("\"a\\", Kind::Quoted),
+                                  ("b\"", Kind::Quoted), ("  y z", Kind::Opaque),
+                                  ("#define D \\", Kind::Directive), ("  1", Kind::Directive)]);
+        }
+    }
+
+    #[test]
+    fn state_test_2() {
+        // Resuming at any point between pretokens gives the pretokens after it.
+        let pieces = ["/*", "*/", "\"", "\\", "\n", " ", "a", "#define", "// off", "// on",
+                      "#if 0", "#endif", "//"];
+        let cfgs = [Config::new(), Config::new().directives(true).lazy_lines(true),
+                    Config::new().tool_directives("off", "on").skip_region("#if 0", "#endif")
+                        .base(Base::Zero)];
+        let mut rng = Lcg::new(5);
+        let mut inside = 0;
+        for _ in 0..500 {
+            let src = rng.source(&pieces, 12);
+            for cfg in &cfgs {
+                let all: Vec<_> = Pretokenizer::with_config(&src, cfg.clone()).collect();
+                let mut pt = Pretokenizer::with_config(&src, cfg.clone());
+                for i in 0..=all.len() {
+                    let point = round_trip(pt.resume_point());
+                    let rest: Vec<_> = Pretokenizer::resume(&src, cfg.clone(), point).collect();
+                    assert_eq!(rest, &all[i..], "{:?} at {}", src, i);
+                    pt.next();
+                }
+
+                // Input that stops at a line break inside a string or
+                // comment, resumed with the rest, gives the rest of the
+                // pretokens.
+                for end in src.match_indices('\n').map(|(i, _)| i + 1) {
+                    let mut head = Pretokenizer::with_config(&src[..end], cfg.clone());
+                    head.by_ref().count();
+                    let point = round_trip(head.resume_point());
+                    if !matches!(point.state, ScanState::Quoted{..} | ScanState::BlockComment{..}) {
+                        continue;
+                    }
+                    let want: Vec<_> = all.iter().filter(|t| t.offset + t.s.len() > end)
+                        .map(|t| (t.s[end.max(t.offset) - t.offset..].to_string(), t.kind))
+                        .collect();
+                    let got: Vec<_> = Pretokenizer::resume(&src, cfg.clone(), point)
+                        .map(|t| (t.s.to_string(), t.kind)).collect();
+                    assert_eq!(got, want, "{:?} at {}", src, end);
+                    inside += 1;
+                }
+            }
+        }
+        assert!(inside > 100, "{}", inside);
+
+        // An opaque region starts on the line after the off marker.
+        let cfg = Config::new().tool_directives("off", "on");
+        let mut pt = Pretokenizer::with_config("a // off\nb\nc", cfg.clone());
+        pt.next();
+        pt.peek();
+        assert_eq!(pt.resume_point(), ResumePoint{ offset: 9, line: 2, state: ScanState::Opaque });
+        // The region is still open at the end of input.
+        pt.next();
+        assert_eq!(pt.resume_point(), ResumePoint{ offset: 12, line: 3, state: ScanState::Opaque });
+        let mut pt = Pretokenizer::with_config("a // off", cfg.clone());
+        pt.by_ref().count();
+        let point = pt.resume_point();
+        assert_eq!(point.state, ScanState::Opaque);
+        let rest = Pretokenizer::resume("a // off\nb\nc", cfg, point);
+        assert_eq!(rest.map(|t| (t.s, t.kind)).collect::<Vec<_>>(), vec![("b\nc", Kind::Opaque)]);
+    }
+
+    /// `point`, serialized and read back when serde is enabled.
+    fn round_trip(point: ResumePoint) -> ResumePoint {
+        #[cfg(feature = "serde")]
+        let point = serde_json::from_str(&serde_json::to_string(&point).unwrap()).unwrap();
+        point
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn state_test_3() {
+        let point = ResumePoint{ offset: 7, line: 3, state: ScanState::SkipRegion{ region: 1,
+                                                                                  depth: 2 } };
+        let json = serde_json::to_string(&point).unwrap();
+        assert_eq!(serde_json::from_str::<ResumePoint>(&json).unwrap(), point);
+        let state: ScanState = serde_json::from_str("{\"Quoted\":{\"escape\":true}}").unwrap();
+        assert_eq!(state, ScanState::Quoted{ escape: true });
+    }
+}
@@ -0,0 +1,96 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Counters collected while pretokenizing.
+
+use crate::{Kind, Pretoken};
+
+/// Counts of what a [Pretokenizer](struct.Pretokenizer.html) has seen so
+/// far, from [Pretokenizer::stats()](struct.Pretokenizer.html#method.stats).
+/// Drain the iterator first for totals over the whole input.
+/// ```
+/// use pretok::Pretokenizer;
+/// let mut pt = Pretokenizer::new("a // one\n\n/* two\n */ b\n");
+/// pt.by_ref().count();
+/// let stats = pt.stats();
+/// assert_eq!(stats.tokens, 2);
+/// assert_eq!((stats.line_comments, stats.block_comments), (1, 1));
+/// assert_eq!((stats.lines, stats.blank_lines), (4, 1));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Stats {
+    /// Pretokens returned.
+    pub tokens: usize,
+    /// Comments skipped that run to the end of the line, such as `//` or
+    /// those of [Config::line_comment()](struct.Config.html#method.line_comment).
+    pub line_comments: usize,
+    /// `/* */` comments skipped.
+    pub block_comments: usize,
+    /// Other input skipped like a comment, such as a
+    /// [Config::skip_region()](struct.Config.html#method.skip_region).
+    pub skipped_regions: usize,
+    /// Physical lines, counting a final line without a line break.
+    pub lines: usize,
+    /// Lines holding nothing but whitespace.
+    pub blank_lines: usize,
+    /// Bytes of whitespace and line breaks outside pretokens and comments.
+    pub whitespace_bytes: usize,
+}
+
+/// How the scanner recognized a comment.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum CommentStyle {
+    /// Runs to the end of the line, like `//`.
+    Line,
+    /// Runs to a closing marker, like `/* */`.
+    Block,
+    /// Anything else skipped, such as a skip region.
+    #[default]
+    Region,
+}
+
+/// Accumulates `Stats` from the regions scanned.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct StatsTracker {
+    pub(crate) stats: Stats,
+    /// True if the current line holds a pretoken or comment
+    content: bool,
+}
+
+impl StatsTracker {
+    /// Count a region of input.  A comment is counted as `style`.
+    pub(crate) fn record(&mut self, tok: &Pretoken, style: CommentStyle) {
+        match tok.kind {
+            Kind::Whitespace => self.stats.whitespace_bytes += tok.s.len(),
+            Kind::Newline => {
+                self.stats.whitespace_bytes += tok.s.len();
+                if !self.content {
+                    self.stats.blank_lines += 1;
+                }
+                self.content = false;
+            }
+            Kind::Terminator => self.content = false,
+            Kind::Comment => {
+                match style {
+                    CommentStyle::Line => self.stats.line_comments += 1,
+                    CommentStyle::Block => self.stats.block_comments += 1,
+                    CommentStyle::Region => self.stats.skipped_regions += 1,
+                }
+                self.content = true;
+            }
+            _ => self.content = true,
+        }
+    }
+
+    /// The counts after consuming `consumed`, the input so far, through
+    /// line `line`.
+    pub(crate) fn stats(&self, consumed: &str, line: usize) -> Stats {
+        let mut stats = self.stats;
+        let partial = !consumed.is_empty() && !consumed.ends_with('\n');
+        stats.lines = line - 1 + partial as usize;
+        if partial && !self.content {
+            stats.blank_lines += 1;
+        }
+        stats
+    }
+}
@@ -0,0 +1,307 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Pretokenizing input read incrementally from an `io::Read`.
+
+use crate::{Bom, Config, Kind, OwnedPretoken, Pretoken, Pretokenizer, Shebang};
+use std::collections::VecDeque;
+use std::io::{self, Read};
+use std::str;
+
+/// Bytes requested from the reader at a time, at least.
+const CHUNK: usize = 64 * 1024;
+
+/// A pretokenizer that reads its input from any `io::Read` as it goes,
+/// holding only the last few lines in memory.  Pretokens are owned, since
+/// the buffer is reused.  A pretoken that straddles two reads is returned
+/// whole, with the same offset and line as
+/// [Pretokenizer](struct.Pretokenizer.html) would give it.  Pretokens are
+/// returned once the line break after them has been read.
+///
+/// Input that isn't UTF-8 gives an error of kind
+/// [InvalidData](https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidData).
+/// After an error, the iterator ends.  No diagnostics are collected, and
+/// [Config::max_tokens()](struct.Config.html#method.max_tokens) and
+/// [Config::max_bytes()](struct.Config.html#method.max_bytes) are ignored.
+/// ```
+/// use pretok::StreamingPretokenizer;
+/// let input = std::io::Cursor::new("a \"b c\"\nd");
+/// let toks: Vec<_> = StreamingPretokenizer::new(input)
+///     .map(|t| t.map(|t| (t.s, t.line)))
+///     .collect::<Result<_, _>>()?;
+/// assert_eq!(toks, vec![("a".to_string(), 1), ("\"b c\"".to_string(), 1),
+///                       ("d".to_string(), 2)]);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct StreamingPretokenizer<R> {
+    reader: R,
+    chunks: Chunks,
+    /// Bytes from the last read
+    scratch: Vec<u8>,
+    /// An error to return once the pretokens before it are returned
+    error: Option<io::Error>,
+    /// True once every pretoken is returned, or after an error
+    done: bool,
+}
+
+impl<R: Read> StreamingPretokenizer<R> {
+    /// Create a streaming pretokenizer reading from `reader`.  Wrapping it
+    /// in a `BufReader` is unnecessary.
+    pub fn new(reader: R) -> StreamingPretokenizer<R> {
+        StreamingPretokenizer::with_config(reader, Config::default())
+    }
+
+    /// Create a streaming pretokenizer reading from `reader`, with `cfg`.
+    pub fn with_config(reader: R, cfg: Config) -> StreamingPretokenizer<R> {
+        StreamingPretokenizer{
+            reader,
+            chunks: Chunks::new(cfg),
+            scratch: Vec::new(),
+            error: None,
+            done: false,
+        }
+    }
+
+    /// Read more input.
+    fn fill(&mut self) -> io::Result<()> {
+        self.scratch.resize(self.chunks.want(), 0);
+        let n = loop {
+            match self.reader.read(&mut self.scratch) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                result => break result?,
+            }
+        };
+        match n {
+            0 => self.chunks.end(),
+            n => self.chunks.push_bytes(&self.scratch[..n]),
+        }
+    }
+}
+
+impl<R: Read> Iterator for StreamingPretokenizer<R> {
+    type Item = io::Result<OwnedPretoken>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(tok) = self.chunks.ready.pop_front() {
+                return Some(Ok(tok));
+            }
+            if let Some(e) = self.error.take() {
+                self.done = true;
+                return Some(Err(e));
+            }
+            if self.done {
+                return None;
+            }
+            if let Err(e) = self.fill() {
+                self.error = Some(e);
+            }
+            self.chunks.pretokenize();
+            self.done = self.chunks.eof;
+        }
+    }
+}
+
+impl<R: Read> std::iter::FusedIterator for StreamingPretokenizer<R> {}
+
+/// Input arriving in pieces, with the pretokens completed so far.  Shared
+/// by the streaming pretokenizers.
+#[derive(Debug)]
+pub(crate) struct Chunks {
+    cfg: Config,
+    /// True to return whitespace and comments too
+    lossless: bool,
+    /// Input not yet pretokenized
+    buf: String,
+    /// An incomplete UTF-8 sequence at the end of the last chunk
+    partial: Vec<u8>,
+    /// Byte offset of `buf` in the whole input
+    base: usize,
+    /// Line number at `base`
+    line: usize,
+    /// True if no pretoken precedes `base` on its line
+    bol: bool,
+    /// A `Config::tool_directives()` off marker just before `base`
+    off_marker: Option<(usize, usize)>,
+    /// Pretokens ready to return
+    pub(crate) ready: VecDeque<OwnedPretoken>,
+    /// True once the input has ended
+    pub(crate) eof: bool,
+}
+
+impl Chunks {
+    pub(crate) fn new(mut cfg: Config) -> Chunks {
+        cfg.max_tokens = None;
+        cfg.max_bytes = None;
+        // Line breaks are needed to tell when pretokens are complete.
+        let lossless = std::mem::replace(&mut cfg.lossless, true);
+        let line = cfg.first_line();
+        Chunks{
+            cfg,
+            lossless,
+            buf: String::new(),
+            partial: Vec::new(),
+            base: 0,
+            line,
+            bol: true,
+            off_marker: None,
+            ready: VecDeque::new(),
+            eof: false,
+        }
+    }
+
+    /// How many bytes to read next: at least as much as is buffered, so a
+    /// line longer than a chunk costs linear time.
+    pub(crate) fn want(&self) -> usize {
+        CHUNK.max(self.buf.len())
+    }
+
+    /// Append `s` to the input.
+    pub(crate) fn push_str(&mut self, s: &str) {
+        self.buf.push_str(s);
+    }
+
+    /// Append `bytes` to the input.  An incomplete UTF-8 sequence at the end
+    /// is finished by the next chunk.
+    pub(crate) fn push_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let mut bytes = match self.partial.is_empty() {
+            true => bytes,
+            false => {
+                self.partial.extend_from_slice(bytes);
+                &self.partial[..]
+            }
+        };
+        let mut result = Ok(());
+        let valid = match str::from_utf8(bytes) {
+            Ok(s) => s.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => {
+                // Keep the input before the error, so its pretokens are
+                // returned first.
+                result = Err(io::Error::new(io::ErrorKind::InvalidData, e));
+                bytes = &bytes[..e.valid_up_to()];
+                bytes.len()
+            }
+        };
+        // SAFETY: `bytes[..valid]` was just checked to be UTF-8.
+        self.buf.push_str(unsafe { str::from_utf8_unchecked(&bytes[..valid]) });
+        let rest = bytes[valid..].to_vec();
+        self.partial = rest;
+        result
+    }
+
+    /// Mark the end of input.
+    pub(crate) fn end(&mut self) -> io::Result<()> {
+        self.eof = true;
+        match self.partial.is_empty() {
+            true => Ok(()),
+            false => Err(io::Error::new(io::ErrorKind::InvalidData,
+                                        "stream did not end on a UTF-8 boundary")),
+        }
+    }
+
+    /// Pretokenize `buf`, keeping the pretokens that more input can't
+    /// change, and drop the input they cover.
+    pub(crate) fn pretokenize(&mut self) {
+        let mut cfg = self.cfg.clone();
+        if self.base > 0 {
+            cfg.bom = Bom::Text;
+            cfg.shebang = Shebang::Text;
+        }
+        let mut pt = Pretokenizer::with_config(&self.buf, cfg);
+        pt.line = self.line;
+        pt.bol = self.bol;
+        pt.off_marker = self.off_marker;
+
+        // Pretokens with the state just after each, until they are known to
+        // be complete.  More input can extend a pretoken, such as a
+        // directive followed by whitespace or a spliced line, but not past a
+        // line break that isn't spliced.  An opaque region ends at a line
+        // break only if the whole next line is the on marker, so the line
+        // break after it doesn't count.
+        let mut pending: VecDeque<(Pretoken, _, _, _)> = VecDeque::new();
+        let mut cut = 0;
+        loop {
+            let tok = pt.next();
+            let complete = match tok {
+                None => self.eof,
+                Some(ref tok) if tok.kind == Kind::Newline => {
+                    let before = &self.buf[..tok.offset];
+                    let spliced = before.ends_with('\\')
+                        || (self.cfg.trigraphs && before.ends_with("??/"));
+                    !spliced && !pending.back().is_some_and(|(last, ..)| last.kind == Kind::Opaque)
+                }
+                Some(_) => false,
+            };
+            if complete {
+                for (done, line, bol, off_marker) in pending.drain(..) {
+                    cut = done.offset + done.s.len();
+                    self.line = line;
+                    self.bol = bol;
+                    self.off_marker = off_marker;
+                    if self.lossless || !done.kind.is_trivia() {
+                        self.ready.push_back(OwnedPretoken{
+                            s: done.s.to_string(), line: done.line,
+                            offset: self.base + done.offset, kind: done.kind});
+                    }
+                }
+            }
+            match tok {
+                Some(tok) => pending.push_back((tok, pt.line, pt.bol, pt.off_marker)),
+                None => break,
+            }
+        }
+        drop(pt);
+        self.buf.drain(..cut);
+        self.base += cut;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads `step` bytes at a time.
+    struct Trickle<'b> {
+        data: &'b [u8],
+        step: usize,
+    }
+
+    impl<'b> Read for Trickle<'b> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.step.min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn stream_test_0() {
+        let src = "#!/bin/x\n#define A \"a\\\n b\"\n  x/**/y // z\n\"\u{e9}\u{1F600}\" ??/\n/* \n*/ w\u{e9}";
+        let configs = [Config::new(), Config::new().lossless(true).shebang(Shebang::Token),
+                       Config::new().directives(true).trigraphs(true),
+                       Config::new().tool_directives("off", "on").max_tokens(2)];
+        for cfg in configs.iter() {
+            // Without the ignored limit
+            let mut full = cfg.clone();
+            full.max_tokens = None;
+            let want = crate::tokenize_with(src, full);
+            for step in 1..8 {
+                let reader = Trickle{ data: src.as_bytes(), step };
+                let got: Vec<_> = StreamingPretokenizer::with_config(reader, cfg.clone())
+                    .collect::<io::Result<_>>().unwrap();
+                assert_eq!(got, want, "{:?} by {}", cfg, step);
+            }
+        }
+
+        let mut bad = StreamingPretokenizer::new(&b"ok\nok \xff ok"[..]);
+        assert_eq!(bad.next().unwrap().unwrap().s, "ok");
+        assert_eq!(bad.next().unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(bad.next().is_none());
+        let mut cut = StreamingPretokenizer::new(&b"ok \xc3"[..]);
+        assert_eq!(cut.next().unwrap().unwrap().s, "ok");
+        assert!(cut.next().unwrap().is_err());
+        assert_eq!(StreamingPretokenizer::new(&b""[..]).count(), 0);
+    }
+}
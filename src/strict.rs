@@ -0,0 +1,68 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! An iterator that reports recoverable problems as errors.
+
+use crate::{PretokError, Pretoken, Pretokenizer};
+
+/// An iterator over `Result<Pretoken, PretokError>` created by
+/// [Pretokenizer::strict()](struct.Pretokenizer.html#method.strict).
+///
+/// Each error is returned before the pretoken that was being scanned when the
+/// problem was found.  That pretoken, if any, follows the error, so a caller
+/// may stop at the first error or keep going.
+/// ```
+/// use pretok::{Pretokenizer, PretokError};
+/// let mut strict = Pretokenizer::new("a /* b").strict();
+/// assert_eq!(strict.next().unwrap().unwrap().s, "a");
+/// assert_eq!(strict.next(),
+///            Some(Err(PretokError::UnterminatedBlockComment{line: 1, span: 2..6})));
+/// assert_eq!(strict.next(), None);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Strict<'a> {
+    inner: Pretokenizer<'a>,
+    /// Number of diagnostics already returned as errors
+    reported: usize,
+    /// A pretoken waiting behind errors
+    pending: Option<Pretoken<'a>>,
+    done: bool,
+}
+
+impl<'a> Strict<'a> {
+    pub(crate) fn new(inner: Pretokenizer<'a>) -> Strict<'a> {
+        Strict{ inner, reported: 0, pending: None, done: false }
+    }
+
+    /// The underlying pretokenizer.
+    /// ```
+    /// use pretok::Pretokenizer;
+    /// let strict = Pretokenizer::new("x").strict();
+    /// assert_eq!(strict.into_inner().next().unwrap().s, "x");
+    /// ```
+    pub fn into_inner(self) -> Pretokenizer<'a> {
+        self.inner
+    }
+}
+
+impl<'a> Iterator for Strict<'a> {
+    type Item = Result<Pretoken<'a>, PretokError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(e) = self.inner.diagnostics.get(self.reported) {
+                self.reported += 1;
+                return Some(Err(e.clone()));
+            }
+            if let Some(tok) = self.pending.take() {
+                return Some(Ok(tok));
+            }
+            if self.done {
+                return None;
+            }
+            match self.inner.next() {
+                Some(tok) => self.pending = Some(tok),
+                None => self.done = true,
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Removing comments while keeping the layout of the input.
+
+use crate::{Config, Kind, Pretokenizer};
+
+/// `s` with every byte of its comments replaced by a space, except line
+/// breaks and carriage returns, so that byte offsets, lines and byte
+/// columns into the result are those of `s`.  Comment markers in strings are
+/// left alone.  A multibyte character in a comment becomes as many spaces as
+/// it has bytes.
+/// ```
+/// let src = "x = 1; /* one\n */ s = \"/* no */\"; // \u{e9}\n";
+/// let out = pretok::strip_comments(src);
+/// assert_eq!(out, "x = 1;       \n    s = \"/* no */\";      \n");
+/// assert_eq!(out.len(), src.len());
+/// ```
+pub fn strip_comments(s: &str) -> String {
+    strip_comments_with(s, Config::default())
+}
+
+/// Like [strip_comments()](fn.strip_comments.html), with `cfg`.  Whatever
+/// `cfg` pretokenizes as a comment is replaced, such as skipped regions and
+/// a skipped byte order mark.
+pub fn strip_comments_with(s: &str, cfg: Config) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut end = 0;
+    for tok in Pretokenizer::with_config(s, cfg.lossless(true)) {
+        if tok.kind == Kind::Comment {
+            out.push_str(&s[end..tok.offset]);
+            out.extend(tok.s.bytes()
+                .map(|b| if b == b'\n' || b == b'\r' { b as char } else { ' ' }));
+            end = tok.offset + tok.s.len();
+        }
+    }
+    out.push_str(&s[end..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_test_1() {
+        let src = "\u{feff}a/*\r\n\u{1f600}*/b \"//\" //x\r\n#if 0\n\"\n#endif\n'/*'";
+        let cfg = Config::new().skip_region("#if 0", "#endif");
+        let out = strip_comments_with(src, cfg);
+        assert_eq!(out, "   a  \r\n      b \"//\"    \r\n     \n \n      \n'   ");
+        assert_eq!(out.len(), src.len());
+        assert_eq!(strip_comments(""), "");
+    }
+}
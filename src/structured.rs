@@ -0,0 +1,216 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Structured inputs for fuzzing, with the pretokens they should give.
+
+use crate::Kind;
+use arbitrary::{Arbitrary, Unstructured};
+use std::ops::Range;
+
+// Characters for each kind of piece, picked to collide with the scanner's
+// own: slashes and stars near comments, quotes and backslashes in strings,
+// and some wider characters.
+const WORD: &[char] = &['a', 'x', '_', '0', '(', ')', ';', ',', '.', '+', '*', '/', '#', '!',
+                        '\'', '?', '=', '{', '}', '\u{e9}', '\u{3bb}', '\u{20ac}', '\u{1f600}'];
+const SPACE: &[char] = &[' ', '\t'];
+const QUOTED: &[&[char]] = &[WORD, SPACE, &['\n', '"', '\\']];
+const LINE_COMMENT: &[&[char]] = &[WORD, SPACE, &['"']];
+const BLOCK_COMMENT: &[&[char]] = &[WORD, SPACE, &['\n', '"', '\\']];
+
+/// One piece of a [FuzzSource](struct.FuzzSource.html).  Each renders to
+/// source text that pretokenizes as the piece says, whatever is around it:
+/// characters a piece doesn't allow are dropped as it is rendered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FuzzPiece {
+    /// Ordinary text.  It joins any word next to it.  A slash that could
+    /// start a comment is dropped.
+    Word(String),
+    /// The contents of a quoted string, with quotes and backslashes escaped
+    /// as it is rendered.
+    Quoted(String),
+    /// The text of a line comment, rendered with its line break.
+    LineComment(String),
+    /// The text of a block comment.  A slash that would end it is dropped.
+    BlockComment(String),
+    /// Spaces and tabs.  An empty run renders as a space.
+    Whitespace(String),
+    /// A line break.
+    Newline,
+}
+
+/// An input for fuzzing described as a list of pieces, such as words,
+/// strings and comments, rather than as bytes, so the fuzzer can explore
+/// inputs that mean something and check what they pretokenize to, not
+/// just that pretokenizing them returns.  With the `arbitrary` feature, a
+/// fuzz target can take one of these in place of bytes.
+///
+/// [text()](#method.text) renders the input as source, and
+/// [expected()](#method.expected) gives its pretokens in
+/// [lossless](struct.Config.html#method.lossless) mode with the default
+/// configuration.
+/// ```
+/// use pretok::{Config, FuzzPiece, FuzzSource, Kind, Pretokenizer};
+/// let src = FuzzSource(vec![
+///     FuzzPiece::Word("x/".to_string()),
+///     FuzzPiece::BlockComment("*/".to_string()),
+///     FuzzPiece::Quoted("a\"x".to_string()),
+/// ]);
+/// assert_eq!(src.text(), r#"x/***/"a\"x""#);
+/// let got: Vec<_> = Pretokenizer::with_config(&src.text(), Config::new().lossless(true))
+///     .map(|t| (t.kind, t.s.to_string()))
+///     .collect();
+/// assert_eq!(got, src.expected());
+/// assert_eq!(got[1], (Kind::Comment, "/***/".to_string()));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FuzzSource(pub Vec<FuzzPiece>);
+
+impl FuzzSource {
+    /// The source text.
+    pub fn text(&self) -> String {
+        self.render().0
+    }
+
+    /// The kind and text of each pretoken of [text()](#method.text) in
+    /// lossless mode with the default configuration.
+    pub fn expected(&self) -> Vec<(Kind, String)> {
+        let (text, toks) = self.render();
+        toks.into_iter().map(|(kind, span)| (kind, text[span].to_string())).collect()
+    }
+
+    /// Render the source along with the kind and span of each pretoken.
+    fn render(&self) -> (String, Vec<(Kind, Range<usize>)>) {
+        let mut text = String::new();
+        let mut toks: Vec<(Kind, Range<usize>)> = Vec::new();
+        for piece in &self.0 {
+            let start = text.len();
+            let kind = match piece {
+                FuzzPiece::Word(s) => {
+                    // A slash must not start a comment, here or in what follows.
+                    let mut chars = s.chars().filter(|c| WORD.contains(c)).peekable();
+                    while let Some(c) = chars.next() {
+                        if c != '/' || matches!(chars.peek(), Some(c) if *c != '/' && *c != '*') {
+                            text.push(c);
+                        }
+                    }
+                    Kind::Word
+                }
+                FuzzPiece::Quoted(s) => {
+                    text.push('"');
+                    for c in s.chars().filter(|c| QUOTED.iter().any(|a| a.contains(c))) {
+                        if c == '"' || c == '\\' {
+                            text.push('\\');
+                        }
+                        text.push(c);
+                    }
+                    text.push('"');
+                    Kind::Quoted
+                }
+                FuzzPiece::LineComment(s) => {
+                    text.push_str("//");
+                    text.extend(s.chars().filter(|c| LINE_COMMENT.iter().any(|a| a.contains(c))));
+                    toks.push((Kind::Comment, start..text.len()));
+                    text.push('\n');
+                    toks.push((Kind::Newline, text.len() - 1..text.len()));
+                    continue;
+                }
+                FuzzPiece::BlockComment(s) => {
+                    text.push_str("/*");
+                    // A slash after a star, including the opening one, would end it.
+                    for c in s.chars().filter(|c| BLOCK_COMMENT.iter().any(|a| a.contains(c))) {
+                        if c != '/' || !text.ends_with('*') {
+                            text.push(c);
+                        }
+                    }
+                    text.push_str("*/");
+                    Kind::Comment
+                }
+                FuzzPiece::Whitespace(s) => {
+                    text.extend(s.chars().filter(|c| SPACE.contains(c)));
+                    if text.len() == start {
+                        text.push(' ');
+                    }
+                    Kind::Whitespace
+                }
+                FuzzPiece::Newline => {
+                    text.push('\n');
+                    Kind::Newline
+                }
+            };
+            let end = text.len();
+            match toks.last_mut() {
+                _ if start == end => {}
+                Some((last, span)) if *last == kind && span.end == start
+                    && (kind == Kind::Word || kind == Kind::Whitespace) => span.end = end,
+                _ => toks.push((kind, start..end)),
+            }
+        }
+        (text, toks)
+    }
+}
+
+/// Up to 16 characters taken from `sets`.
+fn arbitrary_text(u: &mut Unstructured<'_>, sets: &[&[char]]) -> arbitrary::Result<String> {
+    let len = u.int_in_range(0..=16)?;
+    (0..len).map(|_| {
+        let set = *u.choose(sets)?;
+        u.choose(set).copied()
+    }).collect()
+}
+
+impl<'a> Arbitrary<'a> for FuzzPiece {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<FuzzPiece> {
+        Ok(match u.int_in_range(0..=5)? {
+            0 => FuzzPiece::Word(arbitrary_text(u, &[WORD])?),
+            1 => FuzzPiece::Quoted(arbitrary_text(u, QUOTED)?),
+            2 => FuzzPiece::LineComment(arbitrary_text(u, LINE_COMMENT)?),
+            3 => FuzzPiece::BlockComment(arbitrary_text(u, BLOCK_COMMENT)?),
+            4 => FuzzPiece::Whitespace(arbitrary_text(u, &[SPACE])?),
+            _ => FuzzPiece::Newline,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for FuzzSource {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<FuzzSource> {
+        Ok(FuzzSource(u.arbitrary()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testgen::Lcg;
+    use crate::{Config, Pretokenizer};
+
+    #[test]
+    fn structured_test_1() {
+        // Sources built from any bytes pretokenize as expected.
+        let mut rng = Lcg::new(7);
+        for _ in 0..500 {
+            let bytes: Vec<u8> = (0..200).map(|_| rng.below(256) as u8).collect();
+            let src = FuzzSource::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+            let text = src.text();
+            let got: Vec<_> = Pretokenizer::with_config(&text, Config::new().lossless(true))
+                .map(|t| (t.kind, t.s.to_string()))
+                .collect();
+            assert_eq!(got, src.expected(), "{:?}", text);
+        }
+
+        let src = FuzzSource(vec![
+            FuzzPiece::Word("a//x/".to_string()),
+            FuzzPiece::Word("/*0".to_string()),
+            FuzzPiece::Whitespace("\n".to_string()),
+            FuzzPiece::LineComment("x\n;".to_string()),
+            FuzzPiece::BlockComment("/*/".to_string()),
+            FuzzPiece::Word(String::new()),
+        ]);
+        assert_eq!(src.text(), "a/x*0 //x;\n/***/");
+        assert_eq!(src.expected(), vec![
+            (Kind::Word, "a/x*0".to_string()),
+            (Kind::Whitespace, " ".to_string()),
+            (Kind::Comment, "//x;".to_string()),
+            (Kind::Newline, "\n".to_string()),
+            (Kind::Comment, "/***/".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Running a logos lexer over each pretoken.
+
+use crate::{Pretoken, Pretokenizer};
+use logos::{Lexer, Logos};
+use std::ops::Range;
+
+/// An iterator that runs a [logos](https://docs.rs/logos) lexer over the
+/// text of each pretoken in turn, yielding each token the lexer returns with
+/// its byte span in the whole input rather than in the pretoken.  Created by
+/// [Pretokenizer::logos()](struct.Pretokenizer.html#method.logos) or, for
+/// pretokens from elsewhere, [SubLexer::new()](#method.new).
+///
+/// Tokens never span pretokens, so whitespace and comments the
+/// [Pretokenizer](struct.Pretokenizer.html) dropped stay dropped, and
+/// quoted strings reach the lexer whole.
+/// ```
+/// use logos::Logos;
+/// use pretok::Pretokenizer;
+///
+/// #[derive(Logos, Debug, PartialEq)]
+/// enum Tok {
+///     #[regex("[a-z]+")]
+///     Ident,
+///     #[token("+")]
+///     Plus,
+///     #[regex("\"[^\"]*\"")]
+///     Str,
+/// }
+///
+/// let v: Vec<_> = Pretokenizer::new("x+y \"a b\"").logos::<Tok>().collect();
+/// assert_eq!(v, vec![(Ok(Tok::Ident), 0..1), (Ok(Tok::Plus), 1..2), (Ok(Tok::Ident), 2..3),
+///                    (Ok(Tok::Str), 4..9)]);
+/// ```
+pub struct SubLexer<'a, T: Logos<'a>, I> {
+    pretokens: I,
+    /// The lexer over the current pretoken, and the pretoken's offset
+    lexer: Option<(Lexer<'a, T>, usize)>,
+}
+
+impl<'a, T, I> SubLexer<'a, T, I>
+where
+    T: Logos<'a, Source = str>,
+    T::Extras: Default,
+    I: Iterator<Item = Pretoken<'a>>,
+{
+    /// Lex each of `pretokens` with `T`.
+    /// ```
+    /// # use logos::Logos;
+    /// # #[derive(Logos, Debug, PartialEq)]
+    /// # enum Tok { #[regex("[0-9]+")] Num, #[token(",")] Comma }
+    /// use pretok::SubLexer;
+    /// let list: pretok::TokenList = pretok::tokenize("1,2 3").into_iter().collect();
+    /// let spans: Vec<_> = SubLexer::<Tok, _>::new(list.as_slice().iter().cloned())
+    ///     .map(|(_, span)| span).collect();
+    /// assert_eq!(spans, vec![0..1, 1..2, 2..3, 4..5]);
+    /// ```
+    pub fn new<P: IntoIterator<IntoIter = I>>(pretokens: P) -> SubLexer<'a, T, I> {
+        SubLexer{ pretokens: pretokens.into_iter(), lexer: None }
+    }
+}
+
+impl<'a, T, I> Iterator for SubLexer<'a, T, I>
+where
+    T: Logos<'a, Source = str>,
+    T::Extras: Default,
+    I: Iterator<Item = Pretoken<'a>>,
+{
+    type Item = (Result<T, T::Error>, Range<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((lexer, offset)) = &mut self.lexer {
+                if let Some(token) = lexer.next() {
+                    let span = lexer.span();
+                    return Some((token, *offset + span.start..*offset + span.end));
+                }
+            }
+            let tok = self.pretokens.next()?;
+            self.lexer = Some((T::lexer(tok.s), tok.offset));
+        }
+    }
+}
+
+impl<'a> Pretokenizer<'a> {
+    /// Run the [logos](https://docs.rs/logos) lexer `T` over each pretoken,
+    /// with spans in the whole input.  See [SubLexer](struct.SubLexer.html).
+    pub fn logos<T>(self) -> SubLexer<'a, T, Self>
+    where
+        T: Logos<'a, Source = str>,
+        T::Extras: Default,
+    {
+        SubLexer::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    #[derive(Logos, Clone, Debug, PartialEq)]
+    enum Tok {
+        #[regex("[a-z]+")]
+        Ident,
+        #[regex("[0-9]+")]
+        Num,
+        #[token("(")]
+        Open,
+        #[token(")")]
+        Close,
+    }
+
+    #[test]
+    fn sublex_test_1() {
+        let src = "f(1)\n  /* x */ g(x2) # \u{e9}";
+        let v: Vec<_> = Pretokenizer::new(src).logos::<Tok>()
+            .map(|(t, span)| (t, &src[span])).collect();
+        assert_eq!(v, vec![(Ok(Tok::Ident), "f"), (Ok(Tok::Open), "("), (Ok(Tok::Num), "1"),
+                           (Ok(Tok::Close), ")"), (Ok(Tok::Ident), "g"), (Ok(Tok::Open), "("),
+                           (Ok(Tok::Ident), "x"), (Ok(Tok::Num), "2"), (Ok(Tok::Close), ")"),
+                           (Err(()), "#"), (Err(()), "\u{e9}")]);
+        let pt = Pretokenizer::with_config("a /**/", Config::new().lossless(true));
+        assert_eq!(pt.logos::<Tok>().filter(|(t, _)| t.is_err()).count(), 5);
+    }
+}
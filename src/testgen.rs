@@ -0,0 +1,28 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Repeatable random inputs for tests.
+
+/// A linear congruential generator, so that randomized tests see the same
+/// inputs on every run.
+pub(crate) struct Lcg(u32);
+
+impl Lcg {
+    pub(crate) fn new(seed: u32) -> Lcg {
+        Lcg(seed)
+    }
+
+    /// A number below `n`.
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        self.0 = self.0.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        (self.0 >> 16) as usize % n
+    }
+
+    /// A source of fewer than `max` pieces, each picked from `pieces`.
+    pub(crate) fn source(&mut self, pieces: &[&str], max: usize) -> String {
+        let mut src = String::new();
+        for _ in 0..self.below(max) {
+            src.push_str(pieces[self.below(pieces.len())]);
+        }
+        src
+    }
+}
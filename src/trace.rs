@@ -0,0 +1,74 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Recording the scanner's state changes to see why input split as it did.
+
+use crate::Pretokenizer;
+use std::fmt;
+
+/// A change of scanner state, recorded when
+/// [Config::trace()](struct.Config.html#method.trace) is enabled.  The
+/// state names are those of the scanner's internal states, such as `WS`,
+/// `MaybeComment` or `QuotedTok`, and may change between releases, so they
+/// are for reading rather than matching.
+/// ```
+/// use pretok::{Config, Pretokenizer};
+/// let mut pt = Pretokenizer::with_config("a /*b*/", Config::new().trace(true));
+/// pt.by_ref().count();
+/// let v: Vec<_> = pt.trace().iter().map(|t| t.to_string()).collect();
+/// assert_eq!(v[0], "0 line 1: WS -> StartTok");
+/// assert_eq!(v[3], "4 line 1: MaybeComment -> BlockComment");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Transition {
+    /// The state left.
+    pub from: &'static str,
+    /// The state entered.
+    pub to: &'static str,
+    /// The byte offset scanning had reached.
+    pub offset: usize,
+    /// The line scanning had reached, or 0 if lines aren't counted.
+    pub line: usize,
+}
+
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} line {}: {} -> {}", self.offset, self.line, self.from, self.to)
+    }
+}
+
+impl<'a> Pretokenizer<'a> {
+    /// The state changes made so far, if
+    /// [Config::trace()](struct.Config.html#method.trace) is enabled.  A
+    /// scan of each pretoken starts from the state the last one left
+    /// off in, so the changes made while scanning a pretoken are those
+    /// recorded by the call to `next()` returning it.
+    pub fn trace(&self) -> &[Transition] {
+        &self.transitions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    #[test]
+    fn trace_test_1() {
+        let cfg = Config::new().trace(true);
+        let mut pt = Pretokenizer::with_config("\"a\\\"\n\" // c\nd", cfg);
+        let mut steps = Vec::new();
+        while pt.next().is_some() {
+            steps.push(pt.trace().len());
+        }
+        let v: Vec<_> = pt.trace().iter().map(|t| (t.from, t.to, t.offset, t.line)).collect();
+        assert_eq!(v, vec![("WS", "StartTok", 0, 1), ("StartTok", "QuotedTok", 1, 1),
+                           ("QuotedTok", "EscapeChar", 3, 1), ("EscapeChar", "QuotedTok", 4, 1),
+                           ("WS", "MaybeComment", 8, 2), ("MaybeComment", "LineComment", 9, 2),
+                           ("WS", "StartTok", 12, 3),
+                           ("StartTok", "NormalTok", 13, 3)]);
+        assert_eq!(steps, vec![4, 8]);
+        let mut pt = Pretokenizer::new("a b");
+        pt.by_ref().count();
+        assert!(pt.trace().is_empty());
+    }
+}
@@ -0,0 +1,366 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Pretokenizing every file in a directory tree.
+
+use crate::{Config, Kind, PretokError, Pretoken, Pretokenizer};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+
+/// What [TreeWalk](struct.TreeWalk.html) does when a directory or file
+/// can't be read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Yield the error in place of the file and carry on.
+    Report,
+    /// Leave the file out and carry on.
+    Skip,
+    /// Yield the error, then end the walk.
+    Stop,
+}
+
+/// A directory or file that couldn't be read.
+#[derive(Debug)]
+pub struct WalkError {
+    /// The directory or file.
+    pub path: PathBuf,
+    /// The cause.  A file that isn't UTF-8 gives an error of kind
+    /// [InvalidData](https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidData).
+    pub error: io::Error,
+}
+
+impl fmt::Display for WalkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.error)
+    }
+}
+
+impl Error for WalkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// A pretoken stored without a borrow of its file.
+#[derive(Clone, Copy, Debug)]
+struct Span {
+    offset: usize,
+    len: usize,
+    line: usize,
+    kind: Kind,
+}
+
+/// The text of a file with its pretokens and diagnostics, from
+/// [TreeWalk](struct.TreeWalk.html).
+#[derive(Clone, Debug)]
+pub struct FileTokens {
+    path: PathBuf,
+    text: String,
+    spans: Vec<Span>,
+    diagnostics: Vec<PretokError>,
+}
+
+impl FileTokens {
+    fn read(path: PathBuf, cfg: Config) -> Result<FileTokens, WalkError> {
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(error) => return Err(WalkError{ path, error }),
+        };
+        let mut pt = Pretokenizer::with_config(&text, cfg);
+        let spans = pt.by_ref()
+            .map(|t| Span{ offset: t.offset, len: t.s.len(), line: t.line, kind: t.kind })
+            .collect();
+        let diagnostics = pt.diagnostics().to_vec();
+        drop(pt);
+        Ok(FileTokens{ path, text, spans, diagnostics })
+    }
+
+    /// The path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The contents of the file.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The pretokens of the file, in input order.
+    pub fn tokens(&self) -> impl Iterator<Item = Pretoken<'_>> + '_ {
+        self.spans.iter().map(move |sp| Pretoken{
+            s: &self.text[sp.offset..sp.offset + sp.len],
+            line: sp.line,
+            offset: sp.offset,
+            kind: sp.kind,
+        })
+    }
+
+    /// The number of pretokens.
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    /// True if the file has no pretokens.
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// The problems found while pretokenizing the file.
+    pub fn diagnostics(&self) -> &[PretokError] {
+        &self.diagnostics
+    }
+}
+
+/// Pretokenizes the files under a directory on several threads.  Files are
+/// yielded in the order of their paths whatever order they finish in, so
+/// results are reproducible.  Symbolic links to directories are not
+/// followed.
+/// ```no_run
+/// use pretok::{ErrorPolicy, TreeWalk};
+/// let walk = TreeWalk::new("src").extension("c").extension("h")
+///     .on_error(ErrorPolicy::Skip);
+/// for file in walk.run() {
+///     let file = file?;
+///     println!("{}: {} pretokens", file.path().display(), file.len());
+/// }
+/// # Ok::<(), pretok::WalkError>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct TreeWalk {
+    root: PathBuf,
+    extensions: Vec<String>,
+    cfg: Config,
+    threads: usize,
+    policy: ErrorPolicy,
+}
+
+impl TreeWalk {
+    /// Walk the tree under `root`, pretokenizing every file with the default
+    /// configuration on one thread per CPU, and reporting errors.
+    pub fn new<P: Into<PathBuf>>(root: P) -> TreeWalk {
+        TreeWalk{
+            root: root.into(),
+            extensions: Vec::new(),
+            cfg: Config::default(),
+            threads: 0,
+            policy: ErrorPolicy::Report,
+        }
+    }
+
+    /// Only pretokenize files with extension `ext`, given without the dot.
+    /// May be called more than once to accept several extensions.
+    pub fn extension(mut self, ext: &str) -> TreeWalk {
+        self.extensions.push(ext.to_string());
+        self
+    }
+
+    /// Pretokenize with `cfg`.
+    pub fn config(mut self, cfg: Config) -> TreeWalk {
+        self.cfg = cfg;
+        self
+    }
+
+    /// Use `n` threads, or one per CPU if `n` is 0.
+    pub fn threads(mut self, n: usize) -> TreeWalk {
+        self.threads = n;
+        self
+    }
+
+    /// Handle unreadable directories and files according to `policy`.
+    pub fn on_error(mut self, policy: ErrorPolicy) -> TreeWalk {
+        self.policy = policy;
+        self
+    }
+
+    /// Start the walk.  The tree is listed before this returns; the files
+    /// are read and pretokenized in the background.
+    pub fn run(self) -> TreeFiles {
+        let mut jobs = Vec::new();
+        self.list(&self.root, &mut jobs);
+        let jobs = Arc::new(jobs);
+        let next = Arc::new(AtomicUsize::new(0));
+        let stop = Arc::new(AtomicBool::new(false));
+        let threads = match self.threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..threads.min(jobs.len()) {
+            let (jobs, next, stop, tx) = (jobs.clone(), next.clone(), stop.clone(), tx.clone());
+            let cfg = self.cfg.clone();
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let result = match jobs.get(i) {
+                        Some(Ok(path)) => FileTokens::read(path.clone(), cfg.clone()),
+                        Some(Err((path, kind, msg))) => Err(WalkError{
+                            path: path.clone(), error: io::Error::new(*kind, msg.clone())}),
+                        None => break,
+                    };
+                    if tx.send((i, result)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        TreeFiles{ rx, pending: BTreeMap::new(), next: 0, total: jobs.len(), stop,
+                   policy: self.policy }
+    }
+
+    /// The files the walk would pretokenize, in path order, without reading
+    /// them, and an error for each directory that can't be listed.  The
+    /// error policy is not applied.
+    /// ```no_run
+    /// let paths = pretok::TreeWalk::new("src").extension("rs").paths();
+    /// assert!(paths.iter().all(|p| p.is_ok()));
+    /// ```
+    pub fn paths(&self) -> Vec<Result<PathBuf, WalkError>> {
+        let mut jobs = Vec::new();
+        self.list(&self.root, &mut jobs);
+        jobs.into_iter()
+            .map(|job| job.map_err(|(path, kind, msg)| WalkError{
+                path, error: io::Error::new(kind, msg)}))
+            .collect()
+    }
+
+    /// Append the files under `dir` to `jobs` in path order, with an error
+    /// for each directory that can't be listed.
+    fn list(&self, dir: &Path, jobs: &mut Vec<Job>) {
+        let entries = fs::read_dir(dir)
+            .and_then(|rd| rd.map(|e| e.map(|e| e.path())).collect::<io::Result<Vec<_>>>());
+        let mut paths = match entries {
+            Ok(paths) => paths,
+            Err(e) => {
+                jobs.push(Err((dir.to_path_buf(), e.kind(), e.to_string())));
+                return;
+            }
+        };
+        paths.sort();
+        for path in paths {
+            let is_link = fs::symlink_metadata(&path).is_ok_and(|m| m.file_type().is_symlink());
+            if path.is_dir() {
+                if !is_link {
+                    self.list(&path, jobs);
+                }
+            } else if self.wanted(&path) {
+                jobs.push(Ok(path));
+            }
+        }
+    }
+
+    fn wanted(&self, path: &Path) -> bool {
+        self.extensions.is_empty() || path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| self.extensions.iter().any(|e| e == ext))
+    }
+}
+
+/// A file to read, or an error listing a directory.  `io::Error` can't be
+/// cloned, so the error is kept in parts.
+type Job = Result<PathBuf, (PathBuf, io::ErrorKind, String)>;
+
+/// An iterator over the results of a [TreeWalk](struct.TreeWalk.html), in
+/// path order.  Dropping it stops the walk.
+#[derive(Debug)]
+pub struct TreeFiles {
+    rx: Receiver<(usize, Result<FileTokens, WalkError>)>,
+    /// Results that finished ahead of their turn
+    pending: BTreeMap<usize, Result<FileTokens, WalkError>>,
+    /// The index of the next result to yield
+    next: usize,
+    total: usize,
+    stop: Arc<AtomicBool>,
+    policy: ErrorPolicy,
+}
+
+impl Iterator for TreeFiles {
+    type Item = Result<FileTokens, WalkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.next < self.total {
+            let result = match self.pending.remove(&self.next) {
+                Some(result) => result,
+                None => {
+                    let (i, result) = self.rx.recv().ok()?;
+                    self.pending.insert(i, result);
+                    continue;
+                }
+            };
+            self.next += 1;
+            match (result, self.policy) {
+                (Ok(file), _) => return Some(Ok(file)),
+                (Err(_), ErrorPolicy::Skip) => {}
+                (Err(e), ErrorPolicy::Report) => return Some(Err(e)),
+                (Err(e), ErrorPolicy::Stop) => {
+                    self.next = self.total;
+                    self.stop.store(true, Ordering::Relaxed);
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Drop for TreeFiles {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walk_test_0() {
+        let root = std::env::temp_dir().join(format!("pretok_walk_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("sub/deeper")).unwrap();
+        fs::write(root.join("a.c"), "int a;").unwrap();
+        fs::write(root.join("b.txt"), "not code").unwrap();
+        fs::write(root.join("sub/c.h"), "#define C \"c\n").unwrap();
+        fs::write(root.join("sub/deeper/d.c"), b"bad \xff").unwrap();
+        fs::write(root.join("sub/e.c"), "/* e */ e(\n);").unwrap();
+
+        let walk = TreeWalk::new(&root).extension("c").extension("h").threads(3);
+        let results: Vec<_> = walk.clone().run().collect();
+        assert_eq!(results.len(), 4);
+        let a = results[0].as_ref().unwrap();
+        assert_eq!(a.path(), root.join("a.c"));
+        assert_eq!(a.text(), "int a;");
+        assert_eq!(a.tokens().map(|t| t.s).collect::<Vec<_>>(), vec!["int", "a;"]);
+        let c = results[1].as_ref().unwrap();
+        assert_eq!(c.len(), 3);
+        assert_eq!(c.diagnostics().len(), 1);
+        let d = results[2].as_ref().unwrap_err();
+        assert_eq!(d.path, root.join("sub/deeper/d.c"));
+        assert_eq!(d.error.kind(), io::ErrorKind::InvalidData);
+        let e = results[3].as_ref().unwrap();
+        assert_eq!(e.tokens().last().unwrap(), Pretoken{s: ");", line: 2, offset: 11, kind: Kind::Word});
+
+        let names = |policy| -> Vec<_> {
+            walk.clone().on_error(policy).run()
+                .map(|r| r.map_or_else(|e| e.path, |f| f.path))
+                .map(|p| p.file_name().unwrap().to_owned())
+                .collect()
+        };
+        assert_eq!(names(ErrorPolicy::Skip), vec!["a.c", "c.h", "e.c"]);
+        assert_eq!(names(ErrorPolicy::Stop), vec!["a.c", "c.h", "d.c"]);
+        assert_eq!(TreeWalk::new(&root).threads(1).run().count(), 5);
+        let paths: Vec<_> = walk.paths().into_iter().map(Result::unwrap).collect();
+        assert_eq!(paths[3], root.join("sub/e.c"));
+
+        let missing = TreeWalk::new(root.join("missing")).run().next().unwrap().unwrap_err();
+        assert_eq!(missing.error.kind(), io::ErrorKind::NotFound);
+        assert!(missing.to_string().contains("missing"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
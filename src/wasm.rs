@@ -0,0 +1,61 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Pretokenizing JavaScript strings in the browser through wasm-bindgen.
+
+use crate::{Config, Kind, Pretokenizer};
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsValue;
+
+/// Pretokenize `source`, returning an array of `{text, line, offset, kind}`
+/// objects, with whitespace and comments too if `lossless`.  The `offset`
+/// counts UTF-16 code units, as JavaScript string indexes do, so
+/// `source.slice(t.offset, t.offset + t.text.length)` is `t.text`.  `kind`
+/// is the name of the [Kind](enum.Kind.html), such as `"Word"`.
+///
+/// Exported to JavaScript as `tokenize()`.  Rules are those of
+/// [tokenize()](fn.tokenize.html), so a browser playground splits input
+/// exactly as the Rust side does.
+#[wasm_bindgen(js_name = tokenize)]
+pub fn tokenize_js(source: &str, lossless: Option<bool>) -> Array {
+    let cfg = Config::new().lossless(lossless.unwrap_or(false));
+    utf16_tokens(source, cfg)
+        .map(|(text, line, offset, kind)| {
+            let tok = Object::new();
+            let set = |key: &str, value: JsValue| {
+                Reflect::set(&tok, &JsValue::from_str(key), &value).expect("set on new object");
+            };
+            set("text", JsValue::from_str(text));
+            set("line", JsValue::from_f64(line as f64));
+            set("offset", JsValue::from_f64(offset as f64));
+            set("kind", JsValue::from_str(&format!("{:?}", kind)));
+            JsValue::from(tok)
+        })
+        .collect()
+}
+
+/// The text, line, offset in UTF-16 code units and kind of each pretoken of
+/// `source`.
+fn utf16_tokens(source: &str, cfg: Config) -> impl Iterator<Item = (&str, usize, usize, Kind)> {
+    let (mut byte, mut unit) = (0, 0);
+    Pretokenizer::with_config(source, cfg).map(move |tok| {
+        unit += source[byte..tok.offset].encode_utf16().count();
+        byte = tok.offset;
+        (tok.s, tok.line, unit, tok.kind)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wasm_test_1() {
+        let src = "\u{e9}\u{1f600} \"a\u{1f600}\" b";
+        let v: Vec<_> = utf16_tokens(src, Config::new()).collect();
+        assert_eq!(v, vec![("\u{e9}\u{1f600}", 1, 0, Kind::Word),
+                           ("\"a\u{1f600}\"", 1, 4, Kind::Quoted),
+                           ("b", 1, 10, Kind::Word)]);
+        assert_eq!(utf16_tokens("a /* b */", Config::new().lossless(true)).count(), 3);
+    }
+}
@@ -0,0 +1,52 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Display columns for terminals.
+
+use unicode_width::UnicodeWidthChar;
+
+/// The number of terminal columns `s` takes: two for each wide character,
+/// such as most CJK characters, none for combining marks and one for all
+/// others, including tabs and other control characters.
+/// ```
+/// assert_eq!(pretok::display_width("x = \"漢字\";"), 11);
+/// assert_eq!(pretok::display_width("e\u{301}"), 1);
+/// ```
+pub fn display_width(s: &str) -> usize {
+    s.chars().map(|c| c.width().unwrap_or(1)).sum()
+}
+
+/// The line and the display column, both from 1, of byte `offset` of `s`,
+/// with columns counted as by [display_width()](fn.display_width.html), so
+/// that a caret printed under a line of CJK text in a terminal lines up.
+/// An offset inside a character is taken as the start of the character, and
+/// an offset past the end as the end.
+/// ```
+/// let s = "x;\ns = \"漢字\" +;";
+/// assert_eq!(pretok::display_line_col(s, 14), (2, 10));
+/// assert_eq!(pretok::LineIndex::new(s).line_col(14), (2, 8));
+/// ```
+pub fn display_line_col(s: &str, offset: usize) -> (usize, usize) {
+    let mut offset = offset.min(s.len());
+    while !s.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let start = s.as_bytes()[..offset].iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+    let line = 1 + memchr::memchr_iter(b'\n', &s.as_bytes()[..start]).count();
+    (line, display_width(&s[start..offset]) + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn width_test_1() {
+        let s = "a\n\t漢e\u{301}ｘ\u{0}b\n";
+        let cols: Vec<_> = (0..=s.len()).map(|i| display_line_col(s, i)).collect();
+        assert_eq!(cols, vec![(1, 1), (1, 2), (2, 1), (2, 2), (2, 2), (2, 2), (2, 4), (2, 5),
+                              (2, 5), (2, 5), (2, 5), (2, 5), (2, 7), (2, 8), (2, 9), (3, 1)]);
+        assert_eq!(display_line_col(s, 100), (3, 1));
+        assert_eq!(display_line_col("", 0), (1, 1));
+        assert_eq!(display_width(""), 0);
+    }
+}
@@ -0,0 +1,28 @@
+// Copyright 2020, Steve King
+// See LICENSE.txt.
+//! Splitting words between identifier characters and others.
+
+/// The byte offset in word `s` where a run of XID_Continue characters
+/// meets a run of other characters, if any.
+pub(crate) fn word_end(s: &str) -> Option<usize> {
+    crate::run_end(s, unicode_ident::is_xid_continue)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, Pretokenizer};
+
+    #[test]
+    fn xid_test_1() {
+        assert_eq!(word_end("foo+bar"), Some(3));
+        assert_eq!(word_end("+=x"), Some(2));
+        assert_eq!(word_end("\u{e9}t\u{e9}_1"), None);
+        assert_eq!(word_end(""), None);
+        let src = "a->b(c1,\u{3b1}\u{3b2})/x\"s+t\"y+=//z\n#!";
+        let v: Vec<_> = Pretokenizer::with_config(src, Config::new().xid_words(true))
+            .map(|t| t.s).collect();
+        assert_eq!(v, vec!["a", "->", "b", "(", "c1", ",", "\u{3b1}\u{3b2}", ")/", "x",
+                           "\"s+t\"", "y", "+=", "#!"]);
+    }
+}